@@ -9,6 +9,7 @@ const alpha_ledger_name = "Bitcoin";
 const alpha_ledger_network = "regtest";
 
 const beta_ledger_name = "Ethereum";
+const beta_ledger_chain_id = 17;
 
 const alpha_asset_name = "Bitcoin";
 const alpha_asset_reasonable_quantity = "100000000";
@@ -111,6 +112,7 @@ describe("RFC003 HTTP API", () => {
                 },
                 beta_ledger: {
                     name: beta_ledger_name,
+                    chain_id: beta_ledger_chain_id,
                 },
                 alpha_asset: {
                     name: alpha_asset_name,
@@ -123,6 +125,7 @@ describe("RFC003 HTTP API", () => {
                 alpha_ledger_refund_identity: null,
                 beta_ledger_redeem_identity: alice_final_address,
                 alpha_ledger_lock_duration: alpha_ledger_lock_duration,
+                peer: "bob",
             })
             .then(res => {
                 res.error.should.equal(false);
@@ -145,6 +148,7 @@ describe("RFC003 HTTP API", () => {
                 },
                 beta_ledger: {
                     name: beta_ledger_name,
+                    chain_id: beta_ledger_chain_id,
                 },
                 alpha_asset: {
                     name: alpha_asset_name,
@@ -157,6 +161,7 @@ describe("RFC003 HTTP API", () => {
                 alpha_ledger_refund_identity: null,
                 beta_ledger_redeem_identity: alice_final_address,
                 alpha_ledger_lock_duration: alpha_ledger_lock_duration,
+                peer: "bob",
             })
             .then(res => {
                 res.error.should.equal(false);
@@ -182,6 +187,7 @@ describe("RFC003 HTTP API", () => {
                 swap.alpha_ledger.name.should.equal(alpha_ledger_name);
                 swap.alpha_ledger.network.should.equal(alpha_ledger_network);
                 swap.beta_ledger.name.should.equal(beta_ledger_name);
+                swap.beta_ledger.chain_id.should.equal(beta_ledger_chain_id);
                 swap.alpha_asset.name.should.equal(alpha_asset_name);
                 swap.alpha_asset.quantity.should.equal(alpha_asset_reasonable_quantity);
                 swap.beta_asset.name.should.equal(beta_asset_name);
@@ -266,6 +272,7 @@ describe("RFC003 HTTP API", () => {
                 swap.alpha_ledger.name.should.equal(alpha_ledger_name);
                 swap.alpha_ledger.network.should.equal(alpha_ledger_network);
                 swap.beta_ledger.name.should.equal(beta_ledger_name);
+                swap.beta_ledger.chain_id.should.equal(beta_ledger_chain_id);
                 swap.alpha_asset.name.should.equal(alpha_asset_name);
                 swap.alpha_asset.quantity.should.equal("100");
                 swap.beta_asset.name.should.equal(beta_asset_name);
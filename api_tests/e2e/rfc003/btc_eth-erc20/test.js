@@ -74,6 +74,7 @@ describe("RFC003: Bitcoin for ERC20", () => {
                 },
                 beta_ledger: {
                     name: "Ethereum",
+                    chain_id: 17,
                 },
                 alpha_asset: {
                     name: "Bitcoin",
@@ -87,6 +88,7 @@ describe("RFC003: Bitcoin for ERC20", () => {
                 alpha_ledger_refund_identity: null,
                 beta_ledger_redeem_identity: alice_final_address,
                 alpha_ledger_lock_duration: 144,
+                peer: "bob",
             });
 
         res.should.have.status(201);
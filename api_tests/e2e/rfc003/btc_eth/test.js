@@ -126,6 +126,7 @@ describe("RFC003: Bitcoin for Ether", () => {
                 },
                 beta_ledger: {
                     name: "Ethereum",
+                    chain_id: 17,
                 },
                 alpha_asset: {
                     name: "Bitcoin",
@@ -138,6 +139,7 @@ describe("RFC003: Bitcoin for Ether", () => {
                 alpha_ledger_refund_identity: null,
                 beta_ledger_redeem_identity: alice_final_address,
                 alpha_ledger_lock_duration: 144,
+                peer: "bob",
             })
             .then(res => {
                 res.should.have.status(201);
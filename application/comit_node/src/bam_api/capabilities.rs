@@ -0,0 +1,135 @@
+use crate::{
+    bam_api::rfc003,
+    swap_protocols::{AssetKind, LedgerKind, SwapProtocols},
+};
+use bam::{json, Hello};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+
+/// What a comit node announces in the hello it sends when a connection is
+/// set up
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Capabilities {
+    pub version: String,
+    #[serde(deserialize_with = "known")]
+    pub swap_protocols: Vec<SwapProtocols>,
+    #[serde(deserialize_with = "known")]
+    pub swaps: Vec<SwapKind>,
+}
+
+/// The ledgers and assets of a swap
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SwapKind {
+    pub alpha_ledger: LedgerKind,
+    pub beta_ledger: LedgerKind,
+    pub alpha_asset: AssetKind,
+    pub beta_asset: AssetKind,
+}
+
+impl SwapKind {
+    pub fn new(
+        alpha_ledger: LedgerKind,
+        beta_ledger: LedgerKind,
+        alpha_asset: AssetKind,
+        beta_asset: AssetKind,
+    ) -> Self {
+        SwapKind {
+            alpha_ledger,
+            beta_ledger,
+            alpha_asset,
+            beta_asset,
+        }
+    }
+}
+
+impl Capabilities {
+    pub fn of_this_node() -> Self {
+        Capabilities {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            swap_protocols: vec![SwapProtocols::Rfc003],
+            swaps: rfc003::supported_swaps(),
+        }
+    }
+
+    pub fn supports(&self, swap_protocol: &SwapProtocols, swap: &SwapKind) -> bool {
+        self.swap_protocols.contains(swap_protocol) && self.swaps.contains(swap)
+    }
+}
+
+impl Hello<json::Frame> for Capabilities {
+    fn into_hello_frame(self) -> json::Frame {
+        json::Frame::hello(&self)
+    }
+
+    fn from_hello_frame(frame: json::Frame) -> Result<Self, bam::Error> {
+        frame.into_hello()
+    }
+}
+
+/// Nodes of newer versions may announce protocols, ledgers and assets this
+/// node doesn't know, those are left out instead of failing the hello
+fn known<'de, D: Deserializer<'de>, T: DeserializeOwned>(
+    deserializer: D,
+) -> Result<Vec<T>, D::Error> {
+    Ok(Vec::<serde_json::Value>::deserialize(deserializer)?
+        .into_iter()
+        .filter_map(|value| serde_json::from_value(value).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn unknown_protocols_and_swaps_are_left_out() {
+        let capabilities = serde_json::from_value::<Capabilities>(json!({
+            "version": "0.2.0",
+            "swap_protocols": ["COMIT-RFC-003", "COMIT-RFC-999"],
+            "swaps": [
+                {
+                    "alpha_ledger": "Bitcoin",
+                    "beta_ledger": "Ethereum",
+                    "alpha_asset": "Bitcoin",
+                    "beta_asset": "Ether"
+                },
+                {
+                    "alpha_ledger": "Monero",
+                    "beta_ledger": "Ethereum",
+                    "alpha_asset": "Monero",
+                    "beta_asset": "Ether"
+                }
+            ]
+        }))
+        .unwrap();
+
+        assert_that(&capabilities.swap_protocols).is_equal_to(vec![SwapProtocols::Rfc003]);
+        assert_that(&capabilities.swaps).has_length(1);
+    }
+
+    #[test]
+    fn supports_only_announced_swaps() {
+        let capabilities = Capabilities::of_this_node();
+
+        assert_that(&capabilities.supports(
+            &SwapProtocols::Rfc003,
+            &SwapKind::new(
+                LedgerKind::Bitcoin,
+                LedgerKind::Ethereum,
+                AssetKind::Bitcoin,
+                AssetKind::Erc20,
+            ),
+        ))
+        .is_true();
+        assert_that(&capabilities.supports(
+            &SwapProtocols::Rfc003,
+            &SwapKind::new(
+                LedgerKind::Bitcoin,
+                LedgerKind::Bitcoin,
+                AssetKind::Bitcoin,
+                AssetKind::Bitcoin,
+            ),
+        ))
+        .is_false();
+    }
+}
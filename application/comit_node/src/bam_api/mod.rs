@@ -2,10 +2,21 @@ use crate::{
     bam_api::header::{Error, FromBamHeader, Header, ToBamHeader},
     swap_protocols::SwapProtocols,
 };
+use bam::connection::Keepalive;
+use std::time::Duration;
 
+pub mod capabilities;
 pub mod header;
 pub mod rfc003;
 
+/// How connections to other comit nodes are checked to be alive
+pub fn keepalive() -> Keepalive {
+    Keepalive {
+        interval: Duration::from_secs(30),
+        timeout: Duration::from_secs(90),
+    }
+}
+
 mod ledger_impls {
     use crate::{
         bam_api::header::{Error, FromBamHeader, Header, ToBamHeader},
@@ -29,16 +40,18 @@ mod ledger_impls {
     }
 
     impl FromBamHeader for Ethereum {
-        fn from_bam_header(header: Header) -> Result<Self, Error> {
+        fn from_bam_header(mut header: Header) -> Result<Self, Error> {
             header.has_value("Ethereum")?;
 
-            Ok(Ethereum {})
+            Ok(Ethereum {
+                chain_id: header.parameter("chain_id")?,
+            })
         }
     }
 
     impl ToBamHeader for Ethereum {
         fn to_bam_header(&self) -> Result<Header, Error> {
-            Ok(Header::with_value("Ethereum"))
+            Ok(Header::with_value("Ethereum").with_parameter("chain_id", self.chain_id)?)
         }
     }
 }
@@ -120,8 +133,11 @@ impl ToBamHeader for SwapProtocols {
 #[cfg(test)]
 mod tests {
 
-    use crate::bam_api::header::{Error, Header, ToBamHeader};
-    use ethereum_support::{Address, Erc20Quantity, U256};
+    use crate::{
+        bam_api::header::{Error, FromBamHeader, Header, ToBamHeader},
+        swap_protocols::ledger::Ethereum,
+    };
+    use ethereum_support::{Address, ChainId, Erc20Quantity, U256};
 
     #[test]
     fn erc20_quantity_to_bam_header() -> Result<(), Error> {
@@ -138,4 +154,18 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn ethereum_ledger_round_trips_through_bam_header() -> Result<(), Error> {
+        let ethereum = Ethereum::new(ChainId::ropsten());
+        let header = ethereum.to_bam_header()?;
+
+        assert_eq!(
+            header,
+            Header::with_value("Ethereum").with_parameter("chain_id", 3)?
+        );
+        assert_eq!(Ethereum::from_bam_header(header)?, ethereum);
+
+        Ok(())
+    }
+
 }
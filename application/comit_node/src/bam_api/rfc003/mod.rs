@@ -1,3 +1,3 @@
 mod swap;
 
-pub use self::swap::swap_config;
+pub use self::swap::{supported_swaps, swap_config};
@@ -1,11 +1,15 @@
 use crate::{
-    bam_api::header::FromBamHeader,
-    comit_client::{self, rfc003::RequestBody, SwapReject},
+    bam_api::{
+        capabilities::SwapKind,
+        header::{FromBamHeader, Header},
+    },
+    comit_client::{self, bam::Reason, rfc003::RequestBody, SwapReject, SwapRejectReason},
+    peers::Counterparty,
     swap_protocols::{
         asset::Asset,
-        ledger::{Bitcoin, Ethereum},
+        ledger::{Bitcoin, Ethereum, Networks},
         rfc003::{self, state_machine::StateMachineResponse, Ledger},
-        SwapId, SwapProtocols,
+        AssetKind, LedgerKind, SwapId, SwapProtocols,
     },
 };
 use bam::{
@@ -17,13 +21,17 @@ use futures::{
     future::Future,
     sync::{mpsc, oneshot},
 };
+use std::time::SystemTime;
 
 pub fn swap_config(
     sender: mpsc::UnboundedSender<(
         SwapId,
+        Counterparty,
         rfc003::bob::SwapRequestKind,
         oneshot::Sender<rfc003::bob::SwapResponseKind>,
     )>,
+    counterparty: Counterparty,
+    networks: Networks,
 ) -> Config<Request, Response> {
     Config::default().on_request(
         "SWAP",
@@ -37,53 +45,121 @@ pub fn swap_config(
         move |request: Request| {
             let swap_protocol = header!(request.get_header("swap_protocol"));
 
-            match SwapProtocols::from_bam_header(swap_protocol).unwrap() {
+            let swap_protocol = match SwapProtocols::from_bam_header(swap_protocol) {
+                Ok(swap_protocol) => swap_protocol,
+                Err(e) => {
+                    warn!(
+                        "Rejecting swap request {:?} from {} because its swap protocol is not supported: {:?}",
+                        request, counterparty, e
+                    );
+                    return Box::new(futures::future::ok(rejection(Some(
+                        SwapRejectReason::UnsupportedSwap,
+                    ))));
+                }
+            };
+
+            match swap_protocol {
                 SwapProtocols::Rfc003 => {
                     let swap_id = SwapId::default();
                     let (response_sender, response_receiver) = oneshot::channel();
 
-                    if let Ok(swap_request) = decode_request(&request) {
-                        let request_kind =
-                            rfc003::bob::SwapRequestKind::BitcoinEthereumBitcoinQuantityEtherQuantity(
-                                swap_request,
+                    let request_kind = match decode_swap_request(&request) {
+                        Some(request_kind) => request_kind,
+                        None => {
+                            let reason = undecodable_request_reason(&request);
+                            warn!(
+                                "Rejecting swap request {:?} from {} because it can't be decoded: {:?}",
+                                request, counterparty, reason
                             );
-                        sender.unbounded_send((swap_id, request_kind, response_sender)).unwrap();
-
-                        Box::new(response_receiver.then(move |result| {
-                            match result {
-                                Ok(rfc003::bob::SwapResponseKind::BitcoinEthereum(response)) => Ok(to_bam_response::<Bitcoin, Ethereum>(response)),
-                                Err(_) => {
-                                    warn!("Failed to receive from oneshot channel for swap {}", swap_id);
-                                    Ok(Response::new(Status::SE(0)))
-                                }
-                            }
-                        }))
-                    } else if let Ok(swap_request) = decode_request(&request) {
-                        let request_kind =
-                            rfc003::bob::SwapRequestKind::BitcoinEthereumBitcoinQuantityErc20Quantity(
-                                swap_request,
-                            );
-                        sender.unbounded_send((swap_id, request_kind, response_sender)).unwrap();
-
-                        Box::new(response_receiver.then(move |result| {
-                            match result {
-                                Ok(rfc003::bob::SwapResponseKind::BitcoinEthereum(response)) => Ok(to_bam_response::<Bitcoin, Ethereum>(response)),
-                                Err(_) => {
-                                    warn!("Failed to receive from oneshot channel for swap {}", swap_id);
-                                    Ok(Response::new(Status::SE(0)))
-                                }
-                            }
-                        }))
+                            return Box::new(futures::future::ok(rejection(Some(reason))));
+                        }
+                    };
+
+                    if !request_kind.is_on(&networks) {
+                        warn!(
+                            "Rejecting swap request {:?} from {} because it is not on the networks of this node ({:?})",
+                            request_kind, counterparty, networks
+                        );
+                        return Box::new(futures::future::ok(rejection(Some(
+                            SwapRejectReason::UnsupportedNetwork,
+                        ))));
                     }
-                    else {
-                        unimplemented!()
+
+                    if request_kind.is_expired(SystemTime::now()) {
+                        warn!(
+                            "Rejecting swap request {:?} from {} because it has already expired",
+                            request_kind, counterparty
+                        );
+                        return Box::new(futures::future::ok(rejection(Some(
+                            SwapRejectReason::Expired,
+                        ))));
                     }
+
+                    sender
+                        .unbounded_send((swap_id, counterparty, request_kind, response_sender))
+                        .unwrap();
+
+                    Box::new(response_receiver.then(move |result| match result {
+                        Ok(rfc003::bob::SwapResponseKind::BitcoinEthereum(response)) => {
+                            Ok(to_bam_response::<Bitcoin, Ethereum>(response))
+                        }
+                        Ok(rfc003::bob::SwapResponseKind::EthereumBitcoin(response)) => {
+                            Ok(to_bam_response::<Ethereum, Bitcoin>(response))
+                        }
+                        Ok(rfc003::bob::SwapResponseKind::EthereumEthereum(response)) => {
+                            Ok(to_bam_response::<Ethereum, Ethereum>(response))
+                        }
+                        Err(_) => {
+                            warn!(
+                                "Failed to receive from oneshot channel for swap {}",
+                                swap_id
+                            );
+                            Ok(rejection(Some(SwapRejectReason::Unavailable)))
+                        }
+                    }))
                 }
             }
         },
     )
 }
 
+/// Lists every swap `swap_config` can decode once, so that the swaps this node
+/// announces are always the ones it can take part in
+macro_rules! supported_swaps {
+    ($($variant:ident => ($alpha_ledger:ident, $beta_ledger:ident, $alpha_asset:ident, $beta_asset:ident),)+) => {
+        /// The ledgers and assets of the swaps this node can take part in
+        pub fn supported_swaps() -> Vec<SwapKind> {
+            vec![$(
+                SwapKind::new(
+                    LedgerKind::$alpha_ledger,
+                    LedgerKind::$beta_ledger,
+                    AssetKind::$alpha_asset,
+                    AssetKind::$beta_asset,
+                ),
+            )+]
+        }
+
+        fn decode_swap_request(request: &Request) -> Option<rfc003::bob::SwapRequestKind> {
+            $(
+                if let Ok(swap_request) = decode_request(request) {
+                    return Some(rfc003::bob::SwapRequestKind::$variant(swap_request));
+                }
+            )+
+            None
+        }
+    };
+}
+
+supported_swaps! {
+    BitcoinEthereumBitcoinQuantityEtherQuantity => (Bitcoin, Ethereum, Bitcoin, Ether),
+    BitcoinEthereumBitcoinQuantityErc20Quantity => (Bitcoin, Ethereum, Bitcoin, Erc20),
+    EthereumBitcoinEtherQuantityBitcoinQuantity => (Ethereum, Bitcoin, Ether, Bitcoin),
+    EthereumBitcoinErc20QuantityBitcoinQuantity => (Ethereum, Bitcoin, Erc20, Bitcoin),
+    EthereumEthereumEtherQuantityErc20Quantity => (Ethereum, Ethereum, Ether, Erc20),
+    EthereumEthereumErc20QuantityEtherQuantity => (Ethereum, Ethereum, Erc20, Ether),
+    EthereumEthereumErc20QuantityErc20Quantity => (Ethereum, Ethereum, Erc20, Erc20),
+}
+
 #[allow(clippy::type_complexity)]
 fn to_bam_response<AL: Ledger, BL: Ledger>(
     result: Result<
@@ -102,11 +178,58 @@ fn to_bam_response<AL: Ledger, BL: Ledger>(
                 beta_ledger_lock_duration: response.beta_ledger_lock_duration,
             })
         }
-        Err(SwapReject::Declined { reason: None }) => Response::new(Status::SE(20)),
+        Err(SwapReject::Declined { reason: None, .. }) => Response::new(Status::SE(20)),
         Err(SwapReject::Declined {
             reason: Some(reason),
-        }) => Response::new(Status::SE(20)).with_header("REASON", reason),
-        Err(_) => Response::new(Status::RE(0)),
+            details,
+        }) => Response::new(Status::SE(20)).with_header("REASON", Reason::new(reason, details)),
+        Err(SwapReject::CounterOffered(offer)) => Response::new(Status::SE(21)).with_body(offer),
+        Err(SwapReject::Rejected { reason }) => rejection(reason),
+    }
+}
+
+/// Requests for the ledgers and assets of a supported swap are malformed,
+/// any other request is for a swap this node doesn't support
+fn undecodable_request_reason(request: &Request) -> SwapRejectReason {
+    let header_value = |name| {
+        request
+            .get_header::<Header>(name)
+            .and_then(Result::ok)
+            .map(|header| header.value().to_string())
+    };
+    let ledger_kind = |name| header_value(name)?.parse::<LedgerKind>().ok();
+    let asset_kind = |name| match header_value(name)?.as_str() {
+        "Bitcoin" => Some(AssetKind::Bitcoin),
+        "Ether" => Some(AssetKind::Ether),
+        "ERC20" => Some(AssetKind::Erc20),
+        _ => None,
+    };
+
+    let swap_kind = (|| {
+        Some(SwapKind::new(
+            ledger_kind("alpha_ledger")?,
+            ledger_kind("beta_ledger")?,
+            asset_kind("alpha_asset")?,
+            asset_kind("beta_asset")?,
+        ))
+    })();
+
+    match swap_kind {
+        Some(ref swap_kind) if supported_swaps().contains(swap_kind) => {
+            SwapRejectReason::MalformedRequest
+        }
+        _ => SwapRejectReason::UnsupportedSwap,
+    }
+}
+
+/// Every request this node can't process is answered with `SE(0)`, `RE` is
+/// only used for internal errors
+fn rejection(reason: Option<SwapRejectReason>) -> Response {
+    match reason {
+        Some(reason) => {
+            Response::new(Status::SE(0)).with_header("REASON", Reason::new(reason, None))
+        }
+        None => Response::new(Status::SE(0)),
     }
 }
 
@@ -151,6 +274,7 @@ fn decode_request<AL: Ledger, BL: Ledger, AA: Asset, BA: Asset>(
         beta_ledger_redeem_identity: request_body.beta_ledger_redeem_identity,
         alpha_ledger_lock_duration: request_body.alpha_ledger_lock_duration,
         secret_hash: request_body.secret_hash,
+        expires_at: request_body.expires_at,
     })
 }
 
@@ -158,3 +282,62 @@ enum Error {
     Missing,
     Invalid,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    fn swap_request(alpha_asset: serde_json::Value, body: serde_json::Value) -> Request {
+        Request::new(
+            String::from("SWAP"),
+            hashmap! {
+                String::from("alpha_ledger") => json!({
+                    "value": "Bitcoin",
+                    "parameters": { "network": "regtest" },
+                }),
+                String::from("beta_ledger") => json!({
+                    "value": "Ethereum",
+                    "parameters": { "chain_id": 17 },
+                }),
+                String::from("alpha_asset") => alpha_asset,
+                String::from("beta_asset") => json!({
+                    "value": "Ether",
+                    "parameters": { "quantity": "10000000000000000000" },
+                }),
+                String::from("swap_protocol") => json!({ "value": "COMIT-RFC-003" }),
+            },
+            body,
+        )
+    }
+
+    #[test]
+    fn request_for_a_supported_swap_with_invalid_body_is_malformed() {
+        let request = swap_request(
+            json!({
+                "value": "Bitcoin",
+                "parameters": { "quantity": "100000000" },
+            }),
+            json!({ "secret_hash": "not a secret hash" }),
+        );
+
+        assert_that(&decode_swap_request(&request)).is_none();
+        assert_that(&undecodable_request_reason(&request))
+            .is_equal_to(SwapRejectReason::MalformedRequest);
+    }
+
+    #[test]
+    fn request_for_unknown_asset_is_for_an_unsupported_swap() {
+        let request = swap_request(
+            json!({
+                "value": "Dogecoin",
+                "parameters": { "quantity": "100000000" },
+            }),
+            json!({}),
+        );
+
+        assert_that(&decode_swap_request(&request)).is_none();
+        assert_that(&undecodable_request_reason(&request))
+            .is_equal_to(SwapRejectReason::UnsupportedSwap);
+    }
+}
@@ -10,17 +10,24 @@ use comit_node::{
     http_api::route_factory,
     ledger_query_service::DefaultLedgerQueryServiceApiClient,
     logging,
+    peers::{self, Counterparty, Peer, PeerRegistry, SqlitePeerStore},
+    price_feed::{HttpPriceFeed, RateCheck},
     seed::Seed,
-    settings::ComitNodeSettings,
+    settings::{AutoAccept, ComitNodeSettings},
     swap_protocols::{
-        rfc003::{self, state_store::InMemoryStateStore},
-        InMemoryMetadataStore, SwapId,
+        ledger::Networks,
+        rfc003::{self, state_store::SqliteStateStore},
+        SqliteMetadataStore, SwapId,
     },
+    webhooks::{self, Deliveries},
 };
 use ethereum_support::*;
-use futures::sync::{
-    mpsc::{self, UnboundedSender},
-    oneshot,
+use futures::{
+    future,
+    sync::{
+        mpsc::{self, UnboundedSender},
+        oneshot,
+    },
 };
 use std::{env::var, marker::PhantomData, net::SocketAddr, sync::Arc, time::Duration};
 
@@ -33,18 +40,48 @@ fn main() -> Result<(), failure::Error> {
     info!("Starting up with {:#?}", settings);
 
     let seed = settings.comit.secret_seed;
-    let metadata_store = Arc::new(InMemoryMetadataStore::default());
-    let state_store = Arc::new(InMemoryStateStore::default());
+    let metadata_store = Arc::new(SqliteMetadataStore::open(&settings.database.path)?);
+    let state_store = Arc::new(SqliteStateStore::open(
+        &settings.database.path,
+        Arc::new(seed),
+    )?);
     let ledger_query_service_api_client = create_ledger_query_service_api_client(&settings);
 
+    let webhook_deliveries = Arc::new(Deliveries::default());
+    let peers = Arc::new(create_peer_registry(&settings)?);
+
     let mut runtime = tokio::runtime::Runtime::new()?;
 
+    // Has to subscribe to the state store before any swap gets resumed
+    spawn_webhook_notifications(
+        &settings,
+        Arc::clone(&metadata_store),
+        Arc::clone(&state_store),
+        Arc::clone(&webhook_deliveries),
+        &mut runtime,
+    );
+
+    spawn_bitcoin_block_height_tracking(
+        &settings,
+        Arc::clone(&ledger_query_service_api_client),
+        &mut runtime,
+    );
+
+    spawn_swap_recovery(
+        &settings,
+        Arc::clone(&metadata_store),
+        Arc::clone(&state_store),
+        Arc::clone(&ledger_query_service_api_client),
+        &mut runtime,
+    );
+
     let sender = spawn_alice_swap_request_handler_for_rfc003(
         &settings,
         Arc::clone(&metadata_store),
         Arc::clone(&state_store),
         seed,
         Arc::clone(&ledger_query_service_api_client),
+        Arc::clone(&peers),
         settings.ledger_query_service.bitcoin.poll_interval_secs,
         settings.ledger_query_service.ethereum.poll_interval_secs,
         &mut runtime,
@@ -56,6 +93,8 @@ fn main() -> Result<(), failure::Error> {
         Arc::clone(&state_store),
         sender,
         seed,
+        Arc::clone(&peers),
+        webhook_deliveries,
         &mut runtime,
     );
 
@@ -66,10 +105,13 @@ fn main() -> Result<(), failure::Error> {
         seed,
         settings.ledger_query_service.bitcoin.poll_interval_secs,
         settings.ledger_query_service.ethereum.poll_interval_secs,
+        settings.comit.lock_duration_margin_secs,
+        settings.auto_accept.clone(),
+        create_rate_check(&settings),
         &mut runtime,
     );
 
-    spawn_comit_server(&settings, sender, &mut runtime);
+    spawn_comit_server(&settings, sender, seed, peers, &mut runtime);
 
     // Block the current thread.
     ::std::thread::park();
@@ -94,15 +136,107 @@ fn create_ledger_query_service_api_client(
     ))
 }
 
+fn create_peer_registry(settings: &ComitNodeSettings) -> Result<PeerRegistry, peers::Error> {
+    PeerRegistry::open(
+        SqlitePeerStore::open(&settings.database.path)?,
+        settings
+            .peers
+            .iter()
+            .map(|(name, peer)| {
+                Peer::new(name.clone(), peer.addresses.clone(), peer.notes.clone())
+                    .with_identity(peer.identity)
+            })
+            .collect(),
+    )
+}
+
+fn create_rate_check(settings: &ComitNodeSettings) -> Option<RateCheck> {
+    settings
+        .auto_accept
+        .price_feed
+        .as_ref()
+        .map(|price_feed| RateCheck {
+            feed: Arc::new(HttpPriceFeed::new(price_feed.url.clone())),
+            spread: price_feed.spread,
+        })
+}
+
+fn spawn_bitcoin_block_height_tracking(
+    settings: &ComitNodeSettings,
+    lqs_api_client: Arc<DefaultLedgerQueryServiceApiClient>,
+    runtime: &mut tokio::runtime::Runtime,
+) {
+    runtime.spawn(rfc003::bitcoin::track_latest_block_height(
+        lqs_api_client,
+        settings.ledger_query_service.bitcoin.poll_interval_secs,
+    ));
+}
+
+fn spawn_swap_recovery(
+    settings: &ComitNodeSettings,
+    metadata_store: Arc<SqliteMetadataStore>,
+    state_store: Arc<SqliteStateStore>,
+    lqs_api_client: Arc<DefaultLedgerQueryServiceApiClient>,
+    runtime: &mut tokio::runtime::Runtime,
+) {
+    let bitcoin_poll_interval = settings.ledger_query_service.bitcoin.poll_interval_secs;
+    let ethereum_poll_interval = settings.ledger_query_service.ethereum.poll_interval_secs;
+
+    runtime.spawn(future::lazy(move || {
+        rfc003::recovery::resume_swaps(
+            state_store.as_ref(),
+            &metadata_store,
+            &lqs_api_client,
+            bitcoin_poll_interval,
+            ethereum_poll_interval,
+        )
+        .map_err(|e| error!("Failed to resume swaps: {:?}", e))
+    }));
+}
+
+fn spawn_webhook_notifications(
+    settings: &ComitNodeSettings,
+    metadata_store: Arc<SqliteMetadataStore>,
+    state_store: Arc<SqliteStateStore>,
+    deliveries: Arc<Deliveries>,
+    runtime: &mut tokio::runtime::Runtime,
+) {
+    if settings.webhooks.is_empty() {
+        return;
+    }
+
+    runtime.spawn(webhooks::notify(
+        settings.webhooks.clone(),
+        metadata_store,
+        state_store,
+        deliveries,
+    ));
+}
+
+#[allow(clippy::too_many_arguments)]
 fn spawn_warp_instance(
     settings: &ComitNodeSettings,
-    metadata_store: Arc<InMemoryMetadataStore<SwapId>>,
-    state_store: Arc<InMemoryStateStore<SwapId>>,
-    sender: UnboundedSender<(SwapId, rfc003::alice::SwapRequestKind)>,
+    metadata_store: Arc<SqliteMetadataStore>,
+    state_store: Arc<SqliteStateStore>,
+    sender: UnboundedSender<(SwapId, Peer, rfc003::alice::SwapRequestKind)>,
     seed: Seed,
+    peers: Arc<PeerRegistry>,
+    webhook_deliveries: Arc<Deliveries>,
     runtime: &mut tokio::runtime::Runtime,
 ) {
-    let routes = route_factory::create(metadata_store, state_store, sender, seed);
+    let networks = Networks {
+        ethereum_chain_id: settings.ethereum.chain_id,
+    };
+    let routes = route_factory::create(
+        metadata_store,
+        state_store,
+        sender,
+        seed,
+        networks,
+        peers,
+        settings.comit.lock_duration_margin_secs,
+        webhook_deliveries,
+    );
 
     let http_socket_address = SocketAddr::new(settings.http_api.address, settings.http_api.port);
 
@@ -114,16 +248,19 @@ fn spawn_warp_instance(
 #[allow(clippy::too_many_arguments)]
 fn spawn_alice_swap_request_handler_for_rfc003(
     settings: &ComitNodeSettings,
-    metadata_store: Arc<InMemoryMetadataStore<SwapId>>,
-    state_store: Arc<InMemoryStateStore<SwapId>>,
+    metadata_store: Arc<SqliteMetadataStore>,
+    state_store: Arc<SqliteStateStore>,
     seed: Seed,
     lqs_api_client: Arc<DefaultLedgerQueryServiceApiClient>,
+    peers: Arc<PeerRegistry>,
     bitcoin_poll_interval: Duration,
     ethereum_poll_interval: Duration,
     runtime: &mut tokio::runtime::Runtime,
-) -> UnboundedSender<(SwapId, rfc003::alice::SwapRequestKind)> {
-    let client_factory = Arc::new(comit_client::bam::BamClientPool::default());
-    let comit_node_addr = settings.comit.remote_comit_node_url;
+) -> UnboundedSender<(SwapId, Peer, rfc003::alice::SwapRequestKind)> {
+    let client_factory = Arc::new(comit_client::bam::BamClientPool::new(
+        peers,
+        seed.node_identity(),
+    ));
 
     let (sender, receiver) = mpsc::unbounded();
 
@@ -133,9 +270,10 @@ fn spawn_alice_swap_request_handler_for_rfc003(
         seed,
         state_store,
         client_factory,
-        comit_node_addr,
         bitcoin_poll_interval,
         ethereum_poll_interval,
+        lock_duration_margin: settings.comit.lock_duration_margin_secs,
+        response_timeout: settings.comit.response_timeout_secs,
         lqs_api_client,
         phantom_data: PhantomData,
     };
@@ -147,15 +285,19 @@ fn spawn_alice_swap_request_handler_for_rfc003(
 
 #[allow(clippy::too_many_arguments)]
 fn spawn_bob_swap_request_handler_for_rfc003(
-    metadata_store: Arc<InMemoryMetadataStore<SwapId>>,
-    state_store: Arc<InMemoryStateStore<SwapId>>,
+    metadata_store: Arc<SqliteMetadataStore>,
+    state_store: Arc<SqliteStateStore>,
     lqs_api_client: Arc<DefaultLedgerQueryServiceApiClient>,
     seed: Seed,
     bitcoin_poll_interval: Duration,
     ethereum_poll_interval: Duration,
+    lock_duration_margin: Duration,
+    auto_accept: AutoAccept,
+    rate_check: Option<RateCheck>,
     runtime: &mut tokio::runtime::Runtime,
 ) -> UnboundedSender<(
     SwapId,
+    Counterparty,
     rfc003::bob::SwapRequestKind,
     oneshot::Sender<rfc003::bob::SwapResponseKind>,
 )> {
@@ -169,6 +311,9 @@ fn spawn_bob_swap_request_handler_for_rfc003(
         seed,
         bitcoin_poll_interval,
         ethereum_poll_interval,
+        lock_duration_margin,
+        auto_accept: Arc::new(auto_accept),
+        rate_check,
     };
 
     runtime.spawn(bob_swap_request_handler.start());
@@ -180,13 +325,24 @@ fn spawn_comit_server(
     settings: &ComitNodeSettings,
     sender: UnboundedSender<(
         SwapId,
+        Counterparty,
         rfc003::bob::SwapRequestKind,
         oneshot::Sender<rfc003::bob::SwapResponseKind>,
     )>,
-
+    seed: Seed,
+    peers: Arc<PeerRegistry>,
     runtime: &mut tokio::runtime::Runtime,
 ) {
-    let server = ComitServer::new(sender);
+    let networks = Networks {
+        ethereum_chain_id: settings.ethereum.chain_id,
+    };
+    let server = ComitServer::new(
+        sender,
+        networks,
+        seed.node_identity(),
+        peers,
+        settings.comit.refuse_unknown_peers,
+    );
 
     runtime.spawn(server.listen(settings.comit.comit_listen).map_err(|e| {
         error!("ComitServer shutdown: {:?}", e);
@@ -1,41 +1,115 @@
 use crate::{
-    bam_api::{self, header::ToBamHeader},
+    bam_api::{
+        self,
+        capabilities::{Capabilities, SwapKind},
+        header::ToBamHeader,
+    },
     comit_client::{
-        rfc003, Client, ClientFactory, ClientFactoryError, SwapDeclineReason, SwapReject,
-        SwapResponseError,
+        rfc003, Client, ClientFactory, ClientFactoryError, CounterOffer, DeclineDetails,
+        SwapDeclineReason, SwapReject, SwapRejectReason, SwapResponseError,
     },
+    noise::{self, NoiseCodec},
+    peers::{Counterparty, Peer, PeerRegistry},
     swap_protocols::{self, asset::Asset, SwapProtocols},
 };
-use bam::{self, config::Config, connection::Connection, json, Status};
-use futures::Future;
+use bam::{
+    self,
+    config::Config,
+    connection::{Connection, ConnectionLoop},
+    json, Status,
+};
+use chrono::Utc;
+use futures::{
+    future::{self, Loop, Shared},
+    Future,
+};
+use secp256k1_support::{KeyPair, PublicKey};
 use std::{
     collections::HashMap,
     net::SocketAddr,
-    sync::{Arc, Mutex, RwLock},
+    sync::{Arc, Mutex, RwLock, Weak},
+    time::{Duration, Instant},
+};
+use tokio::{
+    self,
+    net::TcpStream,
+    timer::{Delay, Timeout},
 };
-use tokio::{self, net::TcpStream};
 
 #[derive(Debug)]
 pub struct BamClient {
     comit_node_socket_addr: SocketAddr,
+    identity: PublicKey,
+    /// Replaced once the node is connected to again, swaps holding on to the
+    /// client keep sending their requests over a live connection
+    connection: RwLock<BamConnection>,
+}
+
+#[derive(Clone, Debug)]
+struct BamConnection {
+    capabilities: Capabilities,
     bam_client: Arc<Mutex<bam::client::Client<json::Frame, json::Request, json::Response>>>,
 }
 
 impl BamClient {
     pub fn new(
         comit_node_socket_addr: SocketAddr,
+        identity: PublicKey,
+        capabilities: Capabilities,
         bam_client: bam::client::Client<json::Frame, json::Request, json::Response>,
     ) -> Self {
         BamClient {
             comit_node_socket_addr,
-            bam_client: Arc::new(Mutex::new(bam_client)),
+            identity,
+            connection: RwLock::new(BamConnection {
+                capabilities,
+                bam_client: Arc::new(Mutex::new(bam_client)),
+            }),
         }
     }
+
+    /// The identity the other node proved to own
+    pub fn identity(&self) -> PublicKey {
+        self.identity
+    }
+
+    /// What the other node announced when the connection was set up
+    pub fn capabilities(&self) -> Capabilities {
+        self.connection.read().unwrap().capabilities.clone()
+    }
+
+    /// Sends the requests of the client over the connection of `other`, which
+    /// was made to the same node
+    fn use_connection_of(&self, other: &BamClient) {
+        let connection = other.connection.read().unwrap().clone();
+        *self.connection.write().unwrap() = connection;
+    }
+
+    fn bam_client(
+        &self,
+    ) -> Arc<Mutex<bam::client::Client<json::Frame, json::Request, json::Response>>> {
+        Arc::clone(&self.connection.read().unwrap().bam_client)
+    }
+}
+
+/// The `REASON` header of declined and rejected swap requests
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Reason<R> {
+    pub value: R,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<DeclineDetails>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct Reason {
-    pub value: SwapDeclineReason,
+impl<R> Reason<R> {
+    pub fn new(value: R, parameters: Option<DeclineDetails>) -> Self {
+        Reason { value, parameters }
+    }
+
+    /// A header without parameters is received with empty ones
+    fn details(self) -> Option<DeclineDetails> {
+        self.parameters
+            .filter(|details| *details != DeclineDetails::default())
+    }
 }
 
 impl Client for BamClient {
@@ -53,10 +127,27 @@ impl Client for BamClient {
                 Error = SwapResponseError,
             > + Send,
     > {
+        let swap = SwapKind::new(
+            request.alpha_ledger.clone().into(),
+            request.beta_ledger.clone().into(),
+            request.alpha_asset.clone().into(),
+            request.beta_asset.clone().into(),
+        );
+        if !self.capabilities().supports(&SwapProtocols::Rfc003, &swap) {
+            warn!(
+                "Not sending swap request to {} because it doesn't support {:?}",
+                self.comit_node_socket_addr, swap
+            );
+            return Box::new(future::ok(Err(SwapReject::Rejected {
+                reason: Some(SwapRejectReason::UnsupportedSwap),
+            })));
+        }
+
         let alpha_ledger_refund_identity = request.alpha_ledger_refund_identity;
         let beta_ledger_redeem_identity = request.beta_ledger_redeem_identity;
         let alpha_ledger_lock_duration = request.alpha_ledger_lock_duration;
         let secret_hash = request.secret_hash;
+        let expires_at = request.expires_at;
 
         let request = json::Request::new(
             "SWAP".into(),
@@ -76,6 +167,7 @@ impl Client for BamClient {
                 beta_ledger_redeem_identity,
                 alpha_ledger_lock_duration,
                 secret_hash,
+                expires_at,
             })
             .expect("should not fail to serialize"),
         );
@@ -84,7 +176,8 @@ impl Client for BamClient {
             "Making swap request to {}: {:?}",
             &self.comit_node_socket_addr, request,
         );
-        let mut bam_client = self.bam_client.lock().unwrap();
+        let bam_client = self.bam_client();
+        let mut bam_client = bam_client.lock().unwrap();
 
         let socket_addr = self.comit_node_socket_addr;
 
@@ -103,7 +196,7 @@ impl Client for BamClient {
                         info!("{} declined swap request: {:?}", socket_addr, response);
                         Ok(Err({
                             let reason = response
-                                .get_header::<Reason>("REASON")
+                                .get_header::<Reason<SwapDeclineReason>>("REASON")
                                 .map_or(Ok(None), |x| x.map(Some))
                                 .map_err(|e| {
                                     error!(
@@ -111,15 +204,50 @@ impl Client for BamClient {
                                         response, e,
                                     );
                                     SwapResponseError::InvalidResponse
-                                })?
-                                .map(|reason| reason.value);
+                                })?;
 
-                            SwapReject::Declined { reason }
+                            match reason {
+                                Some(reason) => SwapReject::Declined {
+                                    reason: Some(reason.value),
+                                    details: reason.details(),
+                                },
+                                None => SwapReject::Declined {
+                                    reason: None,
+                                    details: None,
+                                },
+                            }
                         }))
                     }
-                    Status::SE(_) => {
+                    Status::SE(21) => {
+                        info!(
+                            "{} counter-offered swap request: {:?}",
+                            socket_addr, response
+                        );
+                        serde_json::from_value::<rfc003::CounterOfferBody<AL, BL, BA>>(
+                            response.get_body().clone(),
+                        )
+                        .map(|offer| Err(SwapReject::CounterOffered(CounterOffer::new(offer))))
+                        .map_err(|e| {
+                            error!("Could not deserialize counter-offer {:?}: {}", response, e);
+                            SwapResponseError::InvalidResponse
+                        })
+                    }
+                    Status::SE(0) => {
                         info!("{} rejected swap request: {:?}", socket_addr, response);
-                        Ok(Err(SwapReject::Rejected))
+                        // Reasons this node doesn't know yet don't make the response invalid
+                        let reason = response
+                            .get_header::<Reason<SwapRejectReason>>("REASON")
+                            .and_then(Result::ok)
+                            .map(|reason| reason.value);
+
+                        Ok(Err(SwapReject::Rejected { reason }))
+                    }
+                    Status::SE(_) => {
+                        error!(
+                            "{} answered swap request with an unknown status: {:?}",
+                            socket_addr, response
+                        );
+                        Err(SwapResponseError::InvalidResponse)
                     }
                     Status::RE(_) => {
                         error!(
@@ -150,57 +278,613 @@ fn to_json_value(
     serde_json::to_value(header).expect("converting bam-header to json must not fail")
 }
 
-#[derive(Default, Debug)]
+const MAX_RECONNECT_ATTEMPTS: u32 = 5;
+const INITIAL_BACKOFF_SECS: u64 = 1;
+/// How long connecting, the handshake and the hello may take together
+const CONNECT_TIMEOUT_SECS: u64 = 30;
+
+type Clients = Arc<RwLock<HashMap<SocketAddr, Arc<BamClient>>>>;
+type ConnectFuture = Box<
+    dyn Future<Item = (ConnectionLoop<json::Error>, BamClient), Error = ClientFactoryError> + Send,
+>;
+type PooledConnectFuture =
+    Box<dyn Future<Item = Arc<BamClient>, Error = Arc<ClientFactoryError>> + Send>;
+type Connecting = Arc<Mutex<HashMap<SocketAddr, Shared<PooledConnectFuture>>>>;
+type HandedOut = Arc<Mutex<HashMap<SocketAddr, Vec<Weak<BamClient>>>>>;
+
+#[derive(Clone, DebugStub)]
 pub struct BamClientPool {
-    clients: RwLock<HashMap<SocketAddr, Arc<BamClient>>>,
+    clients: Clients,
+    /// The connections being set up, shared by everyone asking for a client
+    /// of the same address in the meantime
+    #[debug_stub = "Connecting"]
+    connecting: Connecting,
+    /// The clients given to swaps, which hold on to them until they are done
+    handed_out: HandedOut,
+    peers: Arc<PeerRegistry>,
+    #[debug_stub = "KeyPair"]
+    identity: KeyPair,
 }
 
-impl ClientFactory<BamClient> for BamClientPool {
-    fn client_for(
+impl BamClientPool {
+    pub fn new(peers: Arc<PeerRegistry>, identity: KeyPair) -> Self {
+        BamClientPool {
+            clients: Clients::default(),
+            connecting: Connecting::default(),
+            handed_out: HandedOut::default(),
+            peers,
+            identity,
+        }
+    }
+
+    /// Only a client of a node that proved to own `expected_identity` is
+    /// returned
+    fn client_for_socket_addr(
+        &self,
+        comit_node_socket_addr: SocketAddr,
+        expected_identity: Option<PublicKey>,
+    ) -> impl Future<Item = Arc<BamClient>, Error = ClientFactoryError> + Send {
+        let handed_out = Arc::clone(&self.handed_out);
+
+        self.connection(comit_node_socket_addr)
+            .and_then(move |client| {
+                check_identity(expected_identity, &client)?;
+                Ok(client)
+            })
+            .map(move |client| {
+                handed_out
+                    .lock()
+                    .unwrap()
+                    .entry(comit_node_socket_addr)
+                    .or_insert_with(Vec::new)
+                    .push(Arc::downgrade(&client));
+                client
+            })
+    }
+
+    /// The pooled client of the address, otherwise the one of the connection
+    /// that is being set up to it or else of a new connection
+    fn connection(
         &self,
         comit_node_socket_addr: SocketAddr,
-        // TODO: Return a future and ensure no duplicate connections
-    ) -> Result<Arc<BamClient>, ClientFactoryError> {
+    ) -> impl Future<Item = Arc<BamClient>, Error = ClientFactoryError> + Send {
         debug!("Trying to get client for {}", comit_node_socket_addr);
+        // Held while looking into the pool, so that a connection is either
+        // still being set up or already pooled
+        let mut connecting = self.connecting.lock().unwrap();
+
         let existing_client = self
             .clients
             .read()
             .unwrap()
             .get(&comit_node_socket_addr)
             .cloned();
+        if let Some(client) = existing_client {
+            debug!("Retrieved existing client for {}", comit_node_socket_addr);
+            return future::Either::A(future::ok(client));
+        }
 
-        match existing_client {
-            None => {
+        let connection = connecting
+            .entry(comit_node_socket_addr)
+            .or_insert_with(|| {
                 info!(
                     "No existing connection to {}. Trying to connect.",
                     comit_node_socket_addr
                 );
-                let socket = TcpStream::connect(&comit_node_socket_addr).wait()?;
-                info!("Connection to {} established", comit_node_socket_addr);
-                let codec = json::JsonFrameCodec::default();
-                let config = Config::<json::Request, json::Response>::default();
-                let connection = Connection::new(config, codec, socket);
-                let (connection_future, client) = connection.start::<json::JsonFrameHandler>();
-                let socket_addr = comit_node_socket_addr;
-                tokio::spawn(connection_future.map_err(move |e| {
-                    error!(
-                        "Connection to {:?} prematurely closed: {:?}",
-                        socket_addr, e
-                    )
-                }));
-                let client = Arc::new(BamClient::new(comit_node_socket_addr, client));
-                let mut clients = self.clients.write().unwrap();
-                clients.insert(comit_node_socket_addr, client.clone());
-                debug!(
-                    "Client for {} created by making a new connection",
+                self.pooled_connect(comit_node_socket_addr).shared()
+            })
+            .clone();
+
+        future::Either::B(connection.then(|result| match result {
+            Ok(client) => Ok(Arc::clone(&*client)),
+            Err(e) => Err(ClientFactoryError::SharedConnection(Arc::clone(&*e))),
+        }))
+    }
+
+    fn pooled_connect(&self, comit_node_socket_addr: SocketAddr) -> PooledConnectFuture {
+        let pool = self.clone();
+
+        Box::new(
+            connect(comit_node_socket_addr, self.identity).then(move |result| {
+                let result = result.map(|(connection_loop, client)| {
+                    let client = Arc::new(client);
+                    pool.spawn_connection(connection_loop, Arc::clone(&client));
+                    debug!(
+                        "Client for {} created by making a new connection",
+                        comit_node_socket_addr
+                    );
+                    client
+                });
+                let _ = pool
+                    .connecting
+                    .lock()
+                    .unwrap()
+                    .remove(&comit_node_socket_addr);
+
+                result.map_err(Arc::new)
+            }),
+        )
+    }
+
+    /// Keeps the client in the pool until its connection is closed, then
+    /// reconnects if a swap with the node is still active
+    fn spawn_connection(
+        &self,
+        connection_loop: ConnectionLoop<json::Error>,
+        client: Arc<BamClient>,
+    ) {
+        let socket_addr = client.comit_node_socket_addr;
+        self.clients
+            .write()
+            .unwrap()
+            .insert(socket_addr, Arc::clone(&client));
+        self.hand_over(&client);
+
+        let pool = self.clone();
+        tokio::spawn(connection_loop.then(move |result| {
+            match result {
+                Ok(()) => info!("Connection to {} closed", socket_addr),
+                Err(e) => error!(
+                    "Connection to {:?} prematurely closed: {:?}",
+                    socket_addr, e
+                ),
+            }
+            let was_current = evict(&pool.clients, &client);
+            drop(client);
+            if was_current && pool.has_active_swap(socket_addr) {
+                pool.reconnect(socket_addr);
+            }
+            Ok(())
+        }));
+    }
+
+    /// Moves the swaps still holding a client of an earlier connection to the
+    /// node over to the connection of `client`
+    fn hand_over(&self, client: &Arc<BamClient>) {
+        let socket_addr = client.comit_node_socket_addr;
+        let handed_out = self.handed_out.lock().unwrap();
+
+        for earlier in handed_out
+            .get(&socket_addr)
+            .into_iter()
+            .flatten()
+            .filter_map(Weak::upgrade)
+            .filter(|earlier| !Arc::ptr_eq(earlier, client))
+        {
+            if earlier.identity() != client.identity() {
+                warn!(
+                    "Not moving swaps with {} over to {}, which proved to own {}",
+                    earlier.identity(),
+                    socket_addr,
+                    client.identity()
+                );
+                continue;
+            }
+            earlier.use_connection_of(client);
+            debug!("Moved swaps with {} to new connection", socket_addr);
+        }
+    }
+
+    fn has_active_swap(&self, socket_addr: SocketAddr) -> bool {
+        let mut handed_out = self.handed_out.lock().unwrap();
+
+        let active = match handed_out.get_mut(&socket_addr) {
+            Some(clients) => {
+                clients.retain(|client| client.upgrade().is_some());
+                !clients.is_empty()
+            }
+            None => false,
+        };
+        if !active {
+            let _ = handed_out.remove(&socket_addr);
+        }
+
+        active
+    }
+
+    fn reconnect(&self, socket_addr: SocketAddr) {
+        let pool = self.clone();
+
+        let reconnection = future::loop_fn(0, move |attempt| {
+            let backoff = Duration::from_secs(INITIAL_BACKOFF_SECS << attempt);
+            let pool = pool.clone();
+
+            Delay::new(Instant::now() + backoff).then(move |_| {
+                if !pool.has_active_swap(socket_addr) {
+                    debug!("Not reconnecting to {} without active swaps", socket_addr);
+                    return future::Either::A(future::ok(Loop::Break(())));
+                }
+
+                future::Either::B(
+                    pool.connection(socket_addr)
+                        .then(move |result| match result {
+                            Ok(_) => {
+                                info!("Reconnected to {}", socket_addr);
+                                Ok(Loop::Break(()))
+                            }
+                            Err(e) => {
+                                if attempt + 1 == MAX_RECONNECT_ATTEMPTS {
+                                    warn!("Giving up reconnecting to {}: {:?}", socket_addr, e);
+                                    Ok(Loop::Break(()))
+                                } else {
+                                    debug!(
+                                        "Failed to reconnect to {}, retrying: {:?}",
+                                        socket_addr, e
+                                    );
+                                    Ok(Loop::Continue(attempt + 1))
+                                }
+                            }
+                        }),
+                )
+            })
+        });
+
+        tokio::spawn(reconnection);
+    }
+}
+
+fn check_identity(
+    expected_identity: Option<PublicKey>,
+    client: &BamClient,
+) -> Result<(), ClientFactoryError> {
+    match expected_identity {
+        Some(expected) if expected != client.identity() => {
+            Err(ClientFactoryError::UnexpectedIdentity {
+                expected,
+                actual: client.identity(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+fn connect(comit_node_socket_addr: SocketAddr, identity: KeyPair) -> ConnectFuture {
+    let connection = TcpStream::connect(&comit_node_socket_addr)
+        .from_err()
+        .and_then(move |socket| noise::initiate(socket, identity).from_err())
+        .and_then(move |(socket, session)| {
+            let identity = session.remote_identity;
+            info!(
+                "Connection to {} with identity {} established",
+                comit_node_socket_addr, identity
+            );
+            let codec = NoiseCodec::new(json::JsonFrameCodec::default(), session);
+            let config = Config::<json::Request, json::Response>::default();
+
+            Connection::new(config, codec, socket)
+                .with_keepalive(bam_api::keepalive())
+                .start_with_hello::<json::JsonFrameHandler, _>(Capabilities::of_this_node())
+                .map_err(ClientFactoryError::Hello)
+                .map(move |(connection_loop, client, capabilities)| {
+                    debug!(
+                        "{} announced capabilities {:?}",
+                        comit_node_socket_addr, capabilities
+                    );
+                    let client =
+                        BamClient::new(comit_node_socket_addr, identity, capabilities, client);
+                    (connection_loop, client)
+                })
+        });
+
+    Box::new(
+        Timeout::new(connection, Duration::from_secs(CONNECT_TIMEOUT_SECS)).map_err(move |e| {
+            if e.is_elapsed() {
+                warn!(
+                    "Setting up the connection to {} timed out",
                     comit_node_socket_addr
                 );
-                Ok(client)
+                return ClientFactoryError::TimedOut;
             }
-            Some(client) => {
-                debug!("Retrieved existing client for {}", comit_node_socket_addr);
-                Ok(client.clone())
+            e.into_inner().unwrap_or(ClientFactoryError::TimerError)
+        }),
+    )
+}
+
+/// Removes the client unless it was already replaced by a newer one, returns
+/// whether it was removed
+fn evict(clients: &Clients, client: &Arc<BamClient>) -> bool {
+    let mut clients = clients.write().unwrap();
+    let socket_addr = client.comit_node_socket_addr;
+
+    let is_current = clients
+        .get(&socket_addr)
+        .map_or(false, |current| Arc::ptr_eq(current, client));
+    if is_current {
+        let _ = clients.remove(&socket_addr);
+        debug!("Evicted client for {}", socket_addr);
+    }
+
+    is_current
+}
+
+impl ClientFactory<BamClient> for BamClientPool {
+    fn client_for(
+        &self,
+        peer: &Peer,
+    ) -> Box<dyn Future<Item = (Counterparty, Arc<BamClient>), Error = ClientFactoryError> + Send>
+    {
+        let mut socket_addrs = Vec::new();
+        let mut resolve_error = None;
+
+        for address in &peer.addresses {
+            match address.resolve() {
+                Ok(resolved) => socket_addrs.extend(resolved),
+                Err(e) => {
+                    warn!("Failed to resolve {} of peer {}: {}", address, peer.name, e);
+                    resolve_error = Some(ClientFactoryError::Connection(e));
+                }
             }
         }
+
+        let pool = self.clone();
+        let peer = peer.clone();
+
+        // The addresses are tried one after the other, in the peer's order
+        Box::new(future::loop_fn(
+            (socket_addrs.into_iter(), resolve_error),
+            move |(mut socket_addrs, last_error)| {
+                let socket_addr = match socket_addrs.next() {
+                    Some(socket_addr) => socket_addr,
+                    None => {
+                        return future::Either::A(future::err(
+                            last_error.unwrap_or(ClientFactoryError::NoAddress),
+                        ))
+                    }
+                };
+                let pool = pool.clone();
+                let peer = peer.clone();
+
+                future::Either::B(
+                    pool.client_for_socket_addr(socket_addr, peer.identity)
+                        .then(move |result| match result {
+                            Ok(client) => {
+                                if let Err(e) = pool.peers.mark_seen(
+                                    &peer.name,
+                                    Utc::now(),
+                                    client.capabilities(),
+                                ) {
+                                    error!("Failed to record that {} was seen: {}", peer.name, e);
+                                }
+                                let counterparty = Counterparty {
+                                    address: socket_addr,
+                                    identity: Some(client.identity()),
+                                };
+                                Ok(Loop::Break((counterparty, client)))
+                            }
+                            Err(e) => {
+                                warn!(
+                                    "Failed to connect to {} of peer {}: {:?}",
+                                    socket_addr, peer.name, e
+                                );
+                                Ok(Loop::Continue((socket_addrs, Some(e))))
+                            }
+                        }),
+                )
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comit_client::Decimal;
+    use bam::FrameHandler;
+    use futures::Stream;
+    use spectral::prelude::*;
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+    };
+    use tokio::net::TcpListener;
+
+    fn client() -> Arc<BamClient> {
+        let (_frame_handler, response_source) = json::JsonFrameHandler::create(Config::default());
+        let (client, _requests) = bam::client::Client::create(response_source);
+        let identity = KeyPair::from_secret_key_slice(&[1u8; 32])
+            .unwrap()
+            .public_key();
+
+        Arc::new(BamClient::new(
+            "127.0.0.1:8011".parse().unwrap(),
+            identity,
+            Capabilities::of_this_node(),
+            client,
+        ))
+    }
+
+    #[test]
+    fn closed_connections_only_evict_their_own_client() {
+        let clients = Clients::default();
+        let closed = client();
+        let reconnected = client();
+        let socket_addr = reconnected.comit_node_socket_addr;
+        clients
+            .write()
+            .unwrap()
+            .insert(socket_addr, Arc::clone(&reconnected));
+
+        evict(&clients, &closed);
+        assert_that(&clients.read().unwrap().contains_key(&socket_addr)).is_true();
+
+        evict(&clients, &reconnected);
+        assert_that(&clients.read().unwrap().contains_key(&socket_addr)).is_false();
+    }
+
+    #[test]
+    fn clients_of_another_identity_are_refused() {
+        let client = client();
+        let other_identity = KeyPair::from_secret_key_slice(&[2u8; 32])
+            .unwrap()
+            .public_key();
+
+        assert_that(&check_identity(None, &client)).is_ok();
+        assert_that(&check_identity(Some(client.identity()), &client)).is_ok();
+        assert_that(&check_identity(Some(other_identity), &client)).is_err();
+    }
+
+    fn pool() -> BamClientPool {
+        BamClientPool::new(
+            Arc::new(PeerRegistry::default()),
+            KeyPair::from_secret_key_slice(&[3u8; 32]).unwrap(),
+        )
+    }
+
+    #[test]
+    fn swaps_are_active_while_they_hold_a_client() {
+        let pool = pool();
+        let client = client();
+        let socket_addr = client.comit_node_socket_addr;
+        pool.handed_out
+            .lock()
+            .unwrap()
+            .insert(socket_addr, vec![Arc::downgrade(&client)]);
+
+        assert_that(&pool.has_active_swap(socket_addr)).is_true();
+        drop(client);
+        assert_that(&pool.has_active_swap(socket_addr)).is_false();
+    }
+
+    #[test]
+    fn one_connection_is_set_up_per_address() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let socket_addr = listener.local_addr().unwrap();
+        let pool = pool();
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+
+        let connecting = runtime
+            .block_on(future::lazy(move || {
+                let _first = pool.connection(socket_addr);
+                let _second = pool.connection(socket_addr);
+
+                Ok::<_, ()>(pool.connecting.lock().unwrap().len())
+            }))
+            .unwrap();
+
+        assert_that(&connecting).is_equal_to(1);
+    }
+
+    /// Accepts connections like another node would, but drops the first one
+    /// right after the hello
+    fn serve_dropping_first_connection(identity: KeyPair) -> SocketAddr {
+        let listener = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let socket_addr = listener.local_addr().unwrap();
+        let accepted = Arc::new(AtomicUsize::new(0));
+
+        tokio::spawn(
+            listener
+                .incoming()
+                .map_err(|e| panic!("failed to accept connection: {:?}", e))
+                .for_each(move |socket| {
+                    let is_first = accepted.fetch_add(1, Ordering::SeqCst) == 0;
+                    let connection = noise::respond(socket, identity)
+                        .map_err(|e| panic!("handshake failed: {:?}", e))
+                        .and_then(|(socket, session)| {
+                            let codec = NoiseCodec::new(json::JsonFrameCodec::default(), session);
+
+                            Connection::new(
+                                Config::<json::Request, json::Response>::default(),
+                                codec,
+                                socket,
+                            )
+                            .start_with_hello::<json::JsonFrameHandler, _>(
+                                Capabilities::of_this_node(),
+                            )
+                            .map_err(|e| panic!("hello failed: {:?}", e))
+                        })
+                        .and_then(move |(connection_loop, _client, _capabilities)| {
+                            if is_first {
+                                future::Either::A(future::ok(()))
+                            } else {
+                                future::Either::B(connection_loop.map_err(|_| ()))
+                            }
+                        });
+
+                    tokio::spawn(connection);
+                    Ok(())
+                }),
+        );
+
+        socket_addr
+    }
+
+    #[test]
+    fn swaps_use_the_new_connection_after_reconnecting() {
+        let pool = pool();
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let identity = KeyPair::from_secret_key_slice(&[4u8; 32]).unwrap();
+
+        let socket_addr = runtime
+            .block_on(future::lazy(move || {
+                Ok::<_, ()>(serve_dropping_first_connection(identity))
+            }))
+            .unwrap();
+        let swap_client = runtime
+            .block_on(pool.client_for_socket_addr(socket_addr, Some(identity.public_key())))
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let reconnected = loop {
+            let current = pool.clients.read().unwrap().get(&socket_addr).cloned();
+            match current {
+                Some(current) if !Arc::ptr_eq(&current, &swap_client) => break current,
+                _ if Instant::now() > deadline => panic!("did not reconnect in time"),
+                _ => thread::sleep(Duration::from_millis(100)),
+            }
+        };
+
+        assert_that(&Arc::ptr_eq(
+            &swap_client.bam_client(),
+            &reconnected.bam_client(),
+        ))
+        .is_true();
+        let request = json::Request::new("UNKNOWN".into(), HashMap::new(), serde_json::Value::Null);
+        let response = swap_client
+            .bam_client()
+            .lock()
+            .unwrap()
+            .send_request(request);
+        assert_that(&runtime.block_on(response)).is_ok();
+    }
+
+    fn received(response: json::Response) -> json::Response {
+        serde_json::from_value(serde_json::to_value(response).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn reason_without_details_is_sent_as_plain_value() {
+        let response = json::Response::new(Status::SE(20))
+            .with_header("REASON", Reason::new(SwapDeclineReason::BadRate, None));
+
+        let header = serde_json::to_value(&response).unwrap()["headers"]["REASON"].clone();
+        assert_that(&header).is_equal_to(json!("BadRate"));
+
+        let reason = received(response)
+            .get_header::<Reason<SwapDeclineReason>>("REASON")
+            .unwrap()
+            .unwrap();
+        assert_that(&reason.value).is_equal_to(SwapDeclineReason::BadRate);
+        assert_that(&reason.details()).is_none();
+    }
+
+    #[test]
+    fn reason_details_are_sent_as_parameters() {
+        let details = DeclineDetails {
+            max_alpha_quantity: Some(Decimal::from(2.0)),
+            ..DeclineDetails::default()
+        };
+        let response = json::Response::new(Status::SE(20)).with_header(
+            "REASON",
+            Reason::new(SwapDeclineReason::AmountTooHigh, Some(details.clone())),
+        );
+
+        let reason = received(response)
+            .get_header::<Reason<SwapDeclineReason>>("REASON")
+            .unwrap()
+            .unwrap();
+        assert_that(&reason.value).is_equal_to(SwapDeclineReason::AmountTooHigh);
+        assert_that(&reason.details())
+            .is_some()
+            .is_equal_to(details);
     }
 }
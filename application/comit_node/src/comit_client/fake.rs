@@ -2,16 +2,17 @@ use crate::{
     comit_client::{
         rfc003, Client, ClientFactory, ClientFactoryError, SwapReject, SwapResponseError,
     },
+    peers::{Counterparty, Peer},
     swap_protocols::{self, asset::Asset},
 };
 use futures::{
+    future,
     sync::oneshot::{self, Sender},
     Future,
 };
 use std::{
     any::{Any, TypeId},
     collections::HashMap,
-    net::SocketAddr,
     sync::{Arc, Mutex},
 };
 
@@ -88,8 +89,13 @@ impl FakeClientFactory {
 impl ClientFactory<FakeClient> for FakeClientFactory {
     fn client_for(
         &self,
-        _comit_node_socket_addr: SocketAddr,
-    ) -> Result<Arc<FakeClient>, ClientFactoryError> {
-        Ok(self.fake_client.clone())
+        _peer: &Peer,
+    ) -> Box<dyn Future<Item = (Counterparty, Arc<FakeClient>), Error = ClientFactoryError> + Send>
+    {
+        let counterparty = Counterparty {
+            address: ([127, 0, 0, 1], 0).into(),
+            identity: None,
+        };
+        Box::new(future::ok((counterparty, self.fake_client.clone())))
     }
 }
@@ -2,9 +2,24 @@ pub mod bam;
 pub mod fake;
 pub mod rfc003;
 
-use crate::swap_protocols::{self, asset::Asset};
+use crate::{
+    noise,
+    peers::{Counterparty, Peer},
+    swap_protocols::{self, asset::Asset},
+};
+use bam::{connection::ClosedReason, json};
+use bigdecimal::BigDecimal;
 use futures::Future;
-use std::{fmt::Debug, io, net::SocketAddr, panic::RefUnwindSafe, sync::Arc};
+use secp256k1_support::PublicKey;
+use serde::{de, Deserialize, Deserializer};
+use std::{
+    fmt::Debug,
+    io,
+    panic::RefUnwindSafe,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 pub trait Client: Send + Sync + 'static {
     fn send_swap_request<
@@ -24,21 +39,207 @@ pub trait Client: Send + Sync + 'static {
 }
 
 pub trait ClientFactory<C: Client>: Send + Sync + RefUnwindSafe + Debug {
-    fn client_for(&self, comit_node_socket_addr: SocketAddr) -> Result<Arc<C>, ClientFactoryError>;
+    /// Resolves to a client for the first address of the peer that can be
+    /// connected to, together with that address and the identity the peer
+    /// proved to own
+    fn client_for(
+        &self,
+        peer: &Peer,
+    ) -> Box<dyn Future<Item = (Counterparty, Arc<C>), Error = ClientFactoryError> + Send>;
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum SwapReject {
-    Declined { reason: Option<SwapDeclineReason> },
-    Rejected,
+    Declined {
+        reason: Option<SwapDeclineReason>,
+        #[serde(default)]
+        details: Option<DeclineDetails>,
+    },
+    /// The counterparty could not process the request
+    Rejected {
+        #[serde(default)]
+        reason: Option<SwapRejectReason>,
+    },
+    /// Bob declined but is willing to do the swap on different terms
+    CounterOffered(CounterOffer),
+}
+
+/// The terms of a counter-offer, in the format of the
+/// `rfc003::CounterOfferBody` of the swap's ledgers and assets
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CounterOffer {
+    pub beta_asset: serde_json::Value,
+    pub alpha_ledger_lock_duration: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beta_ledger_lock_duration: Option<serde_json::Value>,
+    /// Seconds since the unix epoch
+    pub expires_at: u64,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+impl CounterOffer {
+    pub fn new<
+        AL: swap_protocols::rfc003::Ledger,
+        BL: swap_protocols::rfc003::Ledger,
+        BA: Asset,
+    >(
+        body: rfc003::CounterOfferBody<AL, BL, BA>,
+    ) -> Self {
+        CounterOffer {
+            beta_asset: serde_json::to_value(body.beta_asset)
+                .expect("should not fail to serialize"),
+            alpha_ledger_lock_duration: serde_json::to_value(body.alpha_ledger_lock_duration)
+                .expect("should not fail to serialize"),
+            beta_ledger_lock_duration: body.beta_ledger_lock_duration.map(|lock_duration| {
+                serde_json::to_value(lock_duration).expect("should not fail to serialize")
+            }),
+            expires_at: body.expires_at,
+        }
+    }
+
+    pub fn terms<
+        AL: swap_protocols::rfc003::Ledger,
+        BL: swap_protocols::rfc003::Ledger,
+        BA: Asset,
+    >(
+        &self,
+    ) -> Result<rfc003::CounterOfferBody<AL, BL, BA>, serde_json::Error> {
+        let beta_ledger_lock_duration = match self.beta_ledger_lock_duration {
+            Some(ref lock_duration) => Some(serde_json::from_value(lock_duration.clone())?),
+            None => None,
+        };
+
+        Ok(rfc003::CounterOfferBody {
+            beta_asset: serde_json::from_value(self.beta_asset.clone())?,
+            alpha_ledger_lock_duration: serde_json::from_value(
+                self.alpha_ledger_lock_duration.clone(),
+            )?,
+            beta_ledger_lock_duration,
+            expires_at: self.expires_at,
+        })
+    }
+
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        has_expired(self.expires_at, now)
+    }
+}
+
+/// Whether `now` is past a deadline given in seconds since the unix epoch
+pub fn has_expired(expires_at: u64, now: SystemTime) -> bool {
+    now >= UNIX_EPOCH + Duration::from_secs(expires_at)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SwapDeclineReason {
     BadRate,
+    /// The beta lock doesn't expire early enough before the alpha lock
+    UnsafeLockDurations,
+    UnsupportedPair,
+    AmountTooHigh,
+    AmountTooLow,
+    LockDurationTooShort,
+    LockDurationTooLong,
+    InsufficientLiquidity,
+    RateLimited,
+}
+
+/// A non-negative number in decimal notation, e.g. `"0.1"`. Unlike a float
+/// it keeps every digit of large ERC20 quantities and of rates.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Decimal(String);
+
+#[derive(Debug, Fail, PartialEq)]
+#[fail(display = "Not a decimal number: {}", _0)]
+pub struct InvalidDecimal(String);
+
+impl Decimal {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<f64> for Decimal {
+    fn from(value: f64) -> Self {
+        // The `Display` of floats never uses an exponent
+        Decimal(value.to_string())
+    }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// Trailing zeros of the fraction are left out, `value` must not be negative
+impl<'a> From<&'a BigDecimal> for Decimal {
+    fn from(value: &'a BigDecimal) -> Self {
+        let value = value.to_string();
+        let value = if value.contains('.') {
+            value.trim_end_matches('0').trim_end_matches('.')
+        } else {
+            &value
+        };
+
+        Decimal(value.to_string())
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = InvalidDecimal;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let is_digits =
+            |digits: &str| !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit());
+        let mut parts = s.splitn(2, '.');
+        let integer = parts.next().unwrap_or("");
+        let fraction = parts.next();
+
+        if is_digits(integer) && fraction.map_or(true, is_digits) {
+            Ok(Decimal(s.to_string()))
+        } else {
+            Err(InvalidDecimal(s.to_string()))
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Decimal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// What the declining side would have accepted instead, quantities are in
+/// bitcoin, ether or the smallest unit of an ERC20 token
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeclineDetails {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_rate: Option<Decimal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_alpha_quantity: Option<Decimal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_alpha_quantity: Option<Decimal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_alpha_lock_duration_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_alpha_lock_duration_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub available_beta_quantity: Option<Decimal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SwapRejectReason {
+    /// The request is for ledgers on networks the counterparty isn't on
+    UnsupportedNetwork,
+    /// The counterparty's node dropped the request before answering it
+    Unavailable,
+    /// The request arrived after the expiry Alice set on it
+    Expired,
+    /// The counterparty doesn't support the swap protocol, ledgers or assets.
+    /// If it announced that, the request was never sent.
+    UnsupportedSwap,
+    /// The counterparty couldn't decode the request
+    MalformedRequest,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum SwapResponseError {
     /// The counterparty had an internal error while processing the request
     InternalError,
@@ -46,11 +247,28 @@ pub enum SwapResponseError {
     TransportError,
     /// The counterparty produced an invalid response to the request
     InvalidResponse,
+    /// The counterparty did not respond before the deadline
+    TimedOut,
 }
 
 #[derive(Debug)]
 pub enum ClientFactoryError {
     Connection(io::Error),
+    Handshake(noise::Error),
+    /// The peer didn't announce its capabilities
+    Hello(ClosedReason<json::Error>),
+    /// The peer proved to own another identity than the configured one
+    UnexpectedIdentity {
+        expected: PublicKey,
+        actual: PublicKey,
+    },
+    /// The peer has no addresses to connect to
+    NoAddress,
+    /// The connection was not set up in time
+    TimedOut,
+    TimerError,
+    /// Setting up the connection failed for everyone who waited for it
+    SharedConnection(Arc<ClientFactoryError>),
 }
 
 impl From<io::Error> for ClientFactoryError {
@@ -58,3 +276,71 @@ impl From<io::Error> for ClientFactoryError {
         ClientFactoryError::Connection(e)
     }
 }
+
+impl From<noise::Error> for ClientFactoryError {
+    fn from(e: noise::Error) -> Self {
+        ClientFactoryError::Handshake(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swap_protocols::{
+        ledger::{Bitcoin, Ethereum},
+        rfc003::{bitcoin::Timelock, ethereum::Seconds},
+    };
+    use bitcoin_support::{BitcoinQuantity, Blocks};
+    use spectral::prelude::*;
+
+    #[test]
+    fn counter_offer_keeps_terms_of_swap() {
+        let body = rfc003::CounterOfferBody::<Ethereum, Bitcoin, BitcoinQuantity> {
+            beta_asset: BitcoinQuantity::from_bitcoin(0.9),
+            alpha_ledger_lock_duration: Seconds(86_400),
+            beta_ledger_lock_duration: Some(Timelock::from(Blocks::from(72))),
+            expires_at: 1_500_000_000,
+        };
+
+        let offer = CounterOffer::new(body.clone());
+
+        assert_that(&offer.terms::<Ethereum, Bitcoin, BitcoinQuantity>()).is_ok_containing(body);
+    }
+
+    #[test]
+    fn counter_offer_expires_at_expiry() {
+        let offer = CounterOffer {
+            beta_asset: json!("1"),
+            alpha_ledger_lock_duration: json!(86_400),
+            beta_ledger_lock_duration: None,
+            expires_at: 1_500_000_000,
+        };
+        let expiry = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+
+        assert_that(&offer.is_expired(expiry - Duration::from_secs(1))).is_false();
+        assert_that(&offer.is_expired(expiry)).is_true();
+    }
+
+    #[test]
+    fn decimal_keeps_every_digit() {
+        let decimal =
+            "115792089237316195423570985008687907853269984665640564039457.584007913129639935";
+
+        assert_that(&serde_json::from_value::<Decimal>(json!(decimal)).map(|d| d.0))
+            .is_ok_containing(decimal.to_string());
+    }
+
+    #[test]
+    fn decimal_rejects_other_notations() {
+        for not_decimal in &["", "1e5", "-1", ".5", "5.", "0x10", "1.2.3"] {
+            assert_that(&not_decimal.parse::<Decimal>())
+                .is_err_containing(InvalidDecimal(not_decimal.to_string()));
+        }
+    }
+
+    #[test]
+    fn decimal_of_float_has_no_exponent() {
+        assert_that(&Decimal::from(0.1).as_str()).is_equal_to("0.1");
+        assert_that(&Decimal::from(1e21).as_str()).is_equal_to("1000000000000000000000");
+    }
+}
@@ -10,6 +10,9 @@ pub struct Request<AL: Ledger, BL: Ledger, AA, BA> {
     pub beta_ledger_redeem_identity: BL::Identity,
     pub alpha_ledger_lock_duration: AL::LockDuration,
     pub secret_hash: SecretHash,
+    /// Seconds since the unix epoch after which the request must not be
+    /// accepted anymore
+    pub expires_at: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -19,10 +22,25 @@ pub struct AcceptResponseBody<AL: Ledger, BL: Ledger> {
     pub beta_ledger_lock_duration: BL::LockDuration,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CounterOfferBody<AL: Ledger, BL: Ledger, BA> {
+    pub beta_asset: BA,
+    pub alpha_ledger_lock_duration: AL::LockDuration,
+    /// The beta lock Bob would respond with, if it differs from what he
+    /// would choose anyway
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beta_ledger_lock_duration: Option<BL::LockDuration>,
+    /// Seconds since the unix epoch after which the offer no longer stands
+    pub expires_at: u64,
+}
+
 #[derive(Debug, Deserialize, PartialEq, Serialize)]
 pub struct RequestBody<AL: Ledger, BL: Ledger> {
     pub alpha_ledger_refund_identity: AL::Identity,
     pub beta_ledger_redeem_identity: BL::Identity,
     pub alpha_ledger_lock_duration: AL::LockDuration,
     pub secret_hash: SecretHash,
+    /// Seconds since the unix epoch after which the request must not be
+    /// accepted anymore
+    pub expires_at: Option<u64>,
 }
@@ -1,47 +1,121 @@
 use crate::{
-    bam_api::rfc003::swap_config,
-    swap_protocols::{rfc003, SwapId},
+    bam_api::{self, capabilities::Capabilities, rfc003::swap_config},
+    noise::{self, NoiseCodec},
+    peers::{Counterparty, PeerRegistry},
+    swap_protocols::{ledger::Networks, rfc003, SwapId},
 };
 use bam::{connection::Connection, json};
+use chrono::Utc;
 use futures::{
     sync::{mpsc, oneshot},
     Future, Stream,
 };
-use std::{io, net::SocketAddr};
-use tokio::{self, net::TcpListener};
+use secp256k1_support::KeyPair;
+use std::{io, net::SocketAddr, sync::Arc, time::Duration};
+use tokio::{self, net::TcpListener, timer::Timeout};
+
+/// How long the handshake and the hello may take each
+const SETUP_TIMEOUT_SECS: u64 = 30;
 
 impl ComitServer {
     pub fn new(
         sender: mpsc::UnboundedSender<(
             SwapId,
+            Counterparty,
             rfc003::bob::SwapRequestKind,
             oneshot::Sender<rfc003::bob::SwapResponseKind>,
         )>,
+        networks: Networks,
+        identity: KeyPair,
+        peers: Arc<PeerRegistry>,
+        refuse_unknown_peers: bool,
     ) -> Self {
-        Self { sender }
+        Self {
+            sender,
+            networks,
+            identity,
+            peers,
+            refuse_unknown_peers,
+        }
     }
 
     pub fn listen(self, addr: SocketAddr) -> impl Future<Item = (), Error = io::Error> {
-        info!("ComitServer listening at {:?}", addr);
+        info!(
+            "ComitServer listening at {:?} with identity {}",
+            addr,
+            self.identity.public_key()
+        );
         let socket = TcpListener::bind(&addr).unwrap();
 
         socket.incoming().for_each(move |connection| {
-            let peer_addr = connection.peer_addr();
-            let codec = json::JsonFrameCodec::default();
+            let peer_addr = match connection.peer_addr() {
+                Ok(peer_addr) => peer_addr,
+                Err(e) => {
+                    warn!("Dropping connection without peer address: {:?}", e);
+                    return Ok(());
+                }
+            };
+            let sender = self.sender.clone();
+            let networks = self.networks;
+            let peers = Arc::clone(&self.peers);
+            let refuse_unknown_peers = self.refuse_unknown_peers;
 
-            let config = swap_config(self.sender.clone());
+            let handshake = Timeout::new(
+                noise::respond(connection, self.identity),
+                Duration::from_secs(SETUP_TIMEOUT_SECS),
+            );
 
-            let connection = Connection::new(config, codec, connection);
-            let (close_future, _client) = connection.start::<json::JsonFrameHandler>();
+            tokio::spawn(handshake.then(move |result| {
+                let (connection, session) = match result {
+                    Ok(handshake) => handshake,
+                    Err(e) => {
+                        warn!("Handshake with {:?} failed: {:?}", peer_addr, e);
+                        return Ok(());
+                    }
+                };
+                let counterparty = Counterparty {
+                    address: peer_addr,
+                    identity: Some(session.remote_identity),
+                };
 
-            tokio::spawn(close_future.then(move |result| {
-                match result {
-                    Ok(()) => info!("Connection with {:?} closed", peer_addr),
-                    Err(e) => error!(
-                        "Unexpected error in connection with {:?}: {:?}",
-                        peer_addr, e
-                    ),
+                let known_peer = peers.find_by_identity(&session.remote_identity);
+                if known_peer.is_none() && refuse_unknown_peers {
+                    warn!("Refusing connection from unknown peer {}", counterparty);
+                    return Ok(());
                 }
+
+                let codec = NoiseCodec::new(json::JsonFrameCodec::default(), session);
+
+                let config = swap_config(sender, counterparty, networks);
+
+                let hello = Connection::new(config, codec, connection)
+                    .with_keepalive(bam_api::keepalive())
+                    .start_with_hello::<json::JsonFrameHandler, _>(Capabilities::of_this_node());
+
+                let connection = Timeout::new(hello, Duration::from_secs(SETUP_TIMEOUT_SECS))
+                    .map_err(move |e| {
+                        warn!("Hello with {} failed: {:?}", counterparty, e);
+                    })
+                    .and_then(move |(close_future, _client, capabilities)| {
+                        debug!("{} announced capabilities {:?}", counterparty, capabilities);
+                        if let Some(peer) = known_peer {
+                            if let Err(e) = peers.mark_seen(&peer.name, Utc::now(), capabilities) {
+                                error!("Failed to record that {} was seen: {}", peer.name, e);
+                            }
+                        }
+                        close_future.then(move |result| {
+                            match result {
+                                Ok(()) => info!("Connection with {} closed", counterparty),
+                                Err(e) => error!(
+                                    "Unexpected error in connection with {}: {:?}",
+                                    counterparty, e
+                                ),
+                            }
+                            Ok(())
+                        })
+                    });
+
+                tokio::spawn(connection);
                 Ok(())
             }));
             Ok(())
@@ -49,11 +123,18 @@ impl ComitServer {
     }
 }
 
-#[derive(Debug)]
+#[derive(DebugStub)]
 pub struct ComitServer {
     sender: mpsc::UnboundedSender<(
         SwapId,
+        Counterparty,
         rfc003::bob::SwapRequestKind,
         oneshot::Sender<rfc003::bob::SwapResponseKind>,
     )>,
+    networks: Networks,
+    #[debug_stub = "KeyPair"]
+    identity: KeyPair,
+    peers: Arc<PeerRegistry>,
+    /// Only accept connections from nodes with an identity in the registry
+    refuse_unknown_peers: bool,
 }
@@ -1,5 +1,7 @@
+pub mod peers;
 pub mod rfc003;
 pub mod route_factory;
+pub mod webhooks;
 
 #[macro_use]
 pub mod ledger;
@@ -21,7 +23,7 @@ mod ledger_impls {
     use crate::swap_protocols::ledger::{Bitcoin, Ethereum};
 
     impl_http_ledger!(Bitcoin { network });
-    impl_http_ledger!(Ethereum);
+    impl_http_ledger!(Ethereum { chain_id });
 
 }
 
@@ -59,11 +61,28 @@ mod asset_impls {
 
 mod lock_duration_impls {
     use super::lock_duration::{Error, HttpLockDuration, ToHttpLockDuration};
-    use crate::swap_protocols::rfc003::ethereum::Seconds;
+    use crate::swap_protocols::rfc003::{
+        bitcoin::{AbsoluteTimelock, Timelock},
+        ethereum::Seconds,
+    };
     use bitcoin_support::Blocks;
 
     impl_to_http_lock_duration!(Blocks);
     impl_to_http_lock_duration!(Seconds);
+
+    impl ToHttpLockDuration for Timelock {
+        fn to_http_lock_duration(&self) -> Result<HttpLockDuration, Error> {
+            match self {
+                Timelock::Relative(blocks) => blocks.to_http_lock_duration(),
+                Timelock::Absolute(AbsoluteTimelock::BlockHeight(height)) => Ok(
+                    HttpLockDuration::with_lock_duration("block_height", u64::from(*height)),
+                ),
+                Timelock::Absolute(AbsoluteTimelock::Timestamp(timestamp)) => Ok(
+                    HttpLockDuration::with_lock_duration("timestamp", u64::from(*timestamp)),
+                ),
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -74,7 +93,7 @@ mod tests {
         swap_protocols::ledger::{Bitcoin, Ethereum},
     };
     use bitcoin_support::{BitcoinQuantity, Network};
-    use ethereum_support::{Address, Erc20Quantity, EtherQuantity, U256};
+    use ethereum_support::{Address, ChainId, Erc20Quantity, EtherQuantity, U256};
 
     #[test]
     fn http_asset_serializes_correctly_to_json() {
@@ -109,7 +128,9 @@ mod tests {
         let bitcoin = Bitcoin {
             network: Network::Regtest,
         };
-        let ethereum = Ethereum {};
+        let ethereum = Ethereum {
+            chain_id: ChainId::new(3),
+        };
 
         let bitcoin = bitcoin.to_http_ledger().unwrap();
         let ethereum = ethereum.to_http_ledger().unwrap();
@@ -121,7 +142,7 @@ mod tests {
             &bitcoin_serialized,
             r#"{"name":"Bitcoin","network":"regtest"}"#
         );
-        assert_eq!(&ethereum_serialized, r#"{"name":"Ethereum"}"#);
+        assert_eq!(&ethereum_serialized, r#"{"name":"Ethereum","chain_id":3}"#);
     }
 
 }
@@ -0,0 +1,138 @@
+use crate::{
+    http_api::problem::{self, HttpApiProblemStdError},
+    peers::{Peer, PeerAddress, PeerRegistry},
+};
+use http_api_problem::HttpApiProblem;
+use hyper::{header, Body, Response, StatusCode};
+use secp256k1_support::PublicKey;
+use std::sync::Arc;
+use warp::{self, Rejection, Reply};
+
+pub const PATH: &str = "peers";
+
+#[derive(Debug, Serialize)]
+struct PeersResource {
+    peers: Vec<Peer>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PutPeerBody {
+    addresses: Vec<PeerAddress>,
+    #[serde(default)]
+    notes: Option<String>,
+    #[serde(default)]
+    identity: Option<PublicKey>,
+}
+
+#[allow(clippy::needless_pass_by_value)]
+pub fn get_peers(peers: Arc<PeerRegistry>) -> impl Reply {
+    warp::reply::json(&PeersResource { peers: peers.all() })
+}
+
+#[allow(clippy::needless_pass_by_value)]
+pub fn get_peer(peers: Arc<PeerRegistry>, name: String) -> Result<impl Reply, Rejection> {
+    peers
+        .get(&name)
+        .map(|peer| warp::reply::json(&peer))
+        .ok_or_else(|| warp::reject::custom(HttpApiProblemStdError::new(problem::peer_not_found())))
+}
+
+#[allow(clippy::needless_pass_by_value)]
+pub fn put_peer(
+    peers: Arc<PeerRegistry>,
+    name: String,
+    body: PutPeerBody,
+) -> Result<impl Reply, Rejection> {
+    handle_put_peer(&peers, name, body)
+        .map_err(|e| warp::reject::custom(HttpApiProblemStdError::new(e)))
+}
+
+fn handle_put_peer(
+    peers: &PeerRegistry,
+    name: String,
+    body: PutPeerBody,
+) -> Result<Response<Body>, HttpApiProblem> {
+    if body.addresses.is_empty() {
+        return Err(problem::no_peer_addresses());
+    }
+
+    let peer = Peer::new(name.clone(), body.addresses, body.notes).with_identity(body.identity);
+    let status = if peers.insert(peer).map_err(|e| {
+        error!("Failed to save peer {}: {}", name, e);
+        HttpApiProblem::from(e)
+    })? {
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    };
+    let peer = peers.get(&name).ok_or_else(|| {
+        error!("Peer {} was removed while being added", name);
+        HttpApiProblem::with_title_and_type_from_status(500)
+    })?;
+    let body = serde_json::to_string(&peer).expect("should not fail to serialize");
+
+    Ok(Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .expect("should not fail to build response"))
+}
+
+#[allow(clippy::needless_pass_by_value)]
+pub fn delete_peer(peers: Arc<PeerRegistry>, name: String) -> Result<impl Reply, Rejection> {
+    let removed = peers.remove(&name).map_err(|e| {
+        error!("Failed to remove peer {}: {}", name, e);
+        warp::reject::custom(HttpApiProblemStdError::new(e))
+    })?;
+
+    removed
+        .map(|_| warp::reply::with_status(warp::reply(), StatusCode::NO_CONTENT))
+        .ok_or_else(|| warp::reject::custom(HttpApiProblemStdError::new(problem::peer_not_found())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    fn body(addresses: Vec<&str>) -> PutPeerBody {
+        PutPeerBody {
+            addresses: addresses
+                .into_iter()
+                .map(|address| address.parse().unwrap())
+                .collect(),
+            notes: None,
+            identity: None,
+        }
+    }
+
+    #[test]
+    fn putting_a_peer_adds_or_replaces_it() {
+        let peers = PeerRegistry::default();
+
+        let added = handle_put_peer(
+            &peers,
+            String::from("bob"),
+            body(vec!["bob.example.com:8011"]),
+        );
+        let replaced = handle_put_peer(&peers, String::from("bob"), body(vec!["127.0.0.1:8011"]));
+
+        assert_that(&added.map(|response| response.status())).is_ok_containing(StatusCode::CREATED);
+        assert_that(&replaced.map(|response| response.status())).is_ok_containing(StatusCode::OK);
+        assert_that(&peers.get("bob"))
+            .is_some()
+            .map(|peer| &peer.addresses)
+            .is_equal_to(&vec!["127.0.0.1:8011".parse::<PeerAddress>().unwrap()]);
+    }
+
+    #[test]
+    fn peer_without_addresses_is_rejected() {
+        let peers = PeerRegistry::default();
+
+        let response = handle_put_peer(&peers, String::from("bob"), body(vec![]));
+
+        assert_that(&response.map_err(|problem| problem.status.map(|status| status.to_u16())))
+            .is_err_containing(Some(400));
+        assert_that(&peers.get("bob")).is_none();
+    }
+}
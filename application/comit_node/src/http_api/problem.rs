@@ -1,6 +1,9 @@
-use crate::swap_protocols::{
-    metadata_store,
-    rfc003::{self, state_store},
+use crate::{
+    peers,
+    swap_protocols::{
+        metadata_store,
+        rfc003::{self, state_store, UnsafeLockDurations},
+    },
 };
 use http::StatusCode;
 use http_api_problem::{HttpApiProblem, HttpStatusCode};
@@ -50,6 +53,28 @@ pub fn unsupported() -> HttpApiProblem {
     HttpApiProblem::new("swap-not-supported").set_status(400)
 }
 
+pub fn unsupported_network() -> HttpApiProblem {
+    HttpApiProblem::new("network-not-supported")
+        .set_status(400)
+        .set_detail("The swap involves a ledger on a network this node is not connected to.")
+}
+
+pub fn unknown_peer(peer: &str) -> HttpApiProblem {
+    HttpApiProblem::new("unknown-peer")
+        .set_status(400)
+        .set_detail(format!("{} is neither an address nor a known peer", peer))
+}
+
+pub fn peer_not_found() -> HttpApiProblem {
+    HttpApiProblem::new("peer-not-found").set_status(404)
+}
+
+pub fn no_peer_addresses() -> HttpApiProblem {
+    HttpApiProblem::new("no-peer-addresses")
+        .set_status(400)
+        .set_detail("A peer needs at least one address.")
+}
+
 pub fn serde(_e: &serde_json::Error) -> HttpApiProblem {
     // FIXME: Use error to give more detail to the user
     HttpApiProblem::new("invalid-body")
@@ -66,6 +91,24 @@ pub fn action_already_taken() -> HttpApiProblem {
     HttpApiProblem::new("action-already-taken").set_status(400)
 }
 
+pub fn request_expired() -> HttpApiProblem {
+    HttpApiProblem::new("request-expired")
+        .set_status(400)
+        .set_detail("The swap request can no longer be accepted")
+}
+
+pub fn response_no_longer_awaited() -> HttpApiProblem {
+    HttpApiProblem::new("response-no-longer-awaited")
+        .set_status(410)
+        .set_detail("The swap no longer waits for a response to its request")
+}
+
+pub fn unsafe_lock_durations(e: &UnsafeLockDurations) -> HttpApiProblem {
+    HttpApiProblem::new("unsafe-lock-durations")
+        .set_status(400)
+        .set_detail(format!("{}", e))
+}
+
 impl From<state_store::Error> for HttpApiProblem {
     fn from(_e: state_store::Error) -> Self {
         HttpApiProblem::with_title_and_type_from_status(500).set_detail("Storage layer failure")
@@ -78,6 +121,12 @@ impl From<metadata_store::Error> for HttpApiProblem {
     }
 }
 
+impl From<peers::Error> for HttpApiProblem {
+    fn from(_e: peers::Error) -> Self {
+        HttpApiProblem::with_title_and_type_from_status(500).set_detail("Storage layer failure")
+    }
+}
+
 impl From<rfc003::state_machine::Error> for HttpApiProblem {
     fn from(e: rfc003::state_machine::Error) -> Self {
         error!("Protocol execution error: {:?}", e);
@@ -1,26 +1,37 @@
 use crate::{
-    comit_client::SwapDeclineReason,
-    http_api::{problem, HttpApiProblemStdError},
+    comit_client::{rfc003::CounterOfferBody, CounterOffer, DeclineDetails, SwapDeclineReason},
+    http_api::{problem, rfc003::swap::swap_created, HttpApiProblemStdError},
+    peers::{Peer, PeerAddress},
     swap_protocols::{
+        asset::Asset,
         ledger::{Bitcoin, Ethereum},
-        metadata_store::Metadata,
+        metadata_store::{HistoryEvent, Metadata},
         rfc003::{
             actions::{
-                bob::{Accept, Decline},
+                alice::AcceptCounterOffer,
+                bob::{Accept, AcceptError, Decline, DeclineError},
                 ActionKind, Actions,
             },
+            alice::{SwapRequest, SwapRequestKind},
             bitcoin, ethereum,
             state_machine::StateMachineResponse,
             state_store::StateStore,
-            Ledger, SecretSource,
+            validate_lock_durations, Ledger, SecretSource,
         },
         AssetKind, LedgerKind, MetadataStore, RoleKind, SwapId,
     },
+    with_swap_types,
 };
 use bitcoin_support::{self, serialize::serialize_hex, BitcoinQuantity};
 use ethereum_support::{self, Erc20Quantity, EtherQuantity};
+use futures::sync::mpsc::UnboundedSender;
 use http_api_problem::HttpApiProblem;
-use std::{str::FromStr, sync::Arc};
+use hyper::{Body, Response};
+use std::{
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 use warp::{self, Rejection, Reply};
 
 #[derive(Clone, Copy, Debug)]
@@ -29,6 +40,15 @@ pub enum PostAction {
     Decline,
 }
 
+impl PostAction {
+    pub fn name(self) -> String {
+        match self {
+            PostAction::Accept => String::from("accept"),
+            PostAction::Decline => String::from("decline"),
+        }
+    }
+}
+
 impl FromStr for PostAction {
     type Err = ();
 
@@ -42,12 +62,16 @@ impl FromStr for PostAction {
 }
 
 trait ExecuteAccept<AL: Ledger, BL: Ledger> {
-    fn execute(
+    /// Returns the id of the swap that was started by taking the action
+    fn execute<M: MetadataStore<SwapId>>(
         &self,
-        body: AcceptSwapRequestHttpBody<AL, BL>,
+        body: serde_json::Value,
         secret_source: &dyn SecretSource,
         id: SwapId,
-    ) -> Result<(), HttpApiProblem>;
+        lock_duration_margin: Duration,
+        metadata_store: &M,
+        sender: &UnboundedSender<(SwapId, Peer, SwapRequestKind)>,
+    ) -> Result<Option<SwapId>, HttpApiProblem>;
 }
 
 impl<AL: Ledger, BL: Ledger> ExecuteAccept<AL, BL> for Accept<AL, BL>
@@ -55,18 +79,111 @@ where
     StateMachineResponse<AL::HtlcIdentity, BL::HtlcIdentity, BL::LockDuration>:
         FromAcceptSwapRequestHttpBody<AL, BL>,
 {
-    fn execute(
+    fn execute<M: MetadataStore<SwapId>>(
         &self,
-        body: AcceptSwapRequestHttpBody<AL, BL>,
+        body: serde_json::Value,
         secret_source: &dyn SecretSource,
         id: SwapId,
-    ) -> Result<(), HttpApiProblem> {
-        self.accept(StateMachineResponse::from_accept_swap_request_http_body(
-            body,
-            id,
-            secret_source,
-        )?)
-        .map_err(|_| problem::action_already_taken())
+        lock_duration_margin: Duration,
+        _metadata_store: &M,
+        _sender: &UnboundedSender<(SwapId, Peer, SwapRequestKind)>,
+    ) -> Result<Option<SwapId>, HttpApiProblem> {
+        let body =
+            serde_json::from_value::<AcceptSwapRequestHttpBody<AL, BL>>(body).map_err(|e| {
+                error!(
+                    "Failed to deserialize body of accept response for swap {}: {:?}",
+                    id, e
+                );
+                problem::serde(&e)
+            })?;
+
+        self.accept(
+            StateMachineResponse::from_accept_swap_request_http_body(body, id, secret_source)?,
+            lock_duration_margin,
+        )
+        .map(|_| None)
+        .map_err(|e| match e {
+            AcceptError::AlreadyResponded => problem::action_already_taken(),
+            AcceptError::NoLongerAwaited => {
+                warn!(
+                    "Cannot accept swap {}, it no longer waits for a response",
+                    id
+                );
+                problem::response_no_longer_awaited()
+            }
+            AcceptError::RequestExpired => {
+                warn!("Refusing to accept swap {} after its request expired", id);
+                problem::request_expired()
+            }
+            AcceptError::UnsafeLockDurations(e) => {
+                warn!("Refusing to accept swap {}: {}", id, e);
+                problem::unsafe_lock_durations(&e)
+            }
+        })
+    }
+}
+
+impl<AL: Ledger, BL: Ledger, AA: Asset, BA: Asset> ExecuteAccept<AL, BL>
+    for AcceptCounterOffer<AL, BL, AA, BA>
+where
+    SwapRequestKind: From<SwapRequest<AL, BL, AA, BA>>,
+{
+    fn execute<M: MetadataStore<SwapId>>(
+        &self,
+        _body: serde_json::Value,
+        secret_source: &dyn SecretSource,
+        id: SwapId,
+        lock_duration_margin: Duration,
+        metadata_store: &M,
+        sender: &UnboundedSender<(SwapId, Peer, SwapRequestKind)>,
+    ) -> Result<Option<SwapId>, HttpApiProblem> {
+        if let Some(ref beta_ledger_lock_duration) = self.beta_ledger_lock_duration {
+            validate_lock_durations(
+                &self.request.alpha_ledger_lock_duration,
+                beta_ledger_lock_duration,
+                lock_duration_margin,
+            )
+            .map_err(|e| {
+                warn!("Refusing to accept counter-offer for swap {}: {}", id, e);
+                problem::unsafe_lock_durations(&e)
+            })?;
+        }
+
+        // The counter-offer is only accepted from the node that made it
+        let peer = metadata_store
+            .counterparty(&id)?
+            .map(|counterparty| {
+                Peer::from(PeerAddress::from(counterparty.address))
+                    .with_identity(counterparty.identity)
+            })
+            .ok_or_else(|| {
+                error!("Counterparty of swap {} is unknown", id);
+                HttpApiProblem::with_title_from_status(500)
+            })?;
+
+        // Reserving the counter-offer before the request is sent makes sure
+        // that concurrent accepts start one swap
+        let new_id = SwapId::default();
+        let accepted_id = metadata_store.accept_counter_offer(&id, new_id)?;
+        if accepted_id != new_id {
+            return Ok(Some(accepted_id));
+        }
+
+        let request = self.request.clone().for_swap(new_id, secret_source);
+        if let Err(e) = sender.unbounded_send((new_id, peer, request.into())) {
+            error!(
+                "Swap request {:?} for id {} could not dispatched.",
+                e.into_inner(),
+                new_id
+            );
+            return Err(HttpApiProblem::with_title_from_status(500));
+        }
+
+        info!(
+            "Requested swap {} on the terms counter-offered for swap {}",
+            new_id, id
+        );
+        Ok(Some(new_id))
     }
 }
 
@@ -105,30 +222,103 @@ impl FromAcceptSwapRequestHttpBody<Bitcoin, Ethereum>
     }
 }
 
-trait ExecuteDecline {
-    fn execute(&self, reason: Option<SwapDeclineReason>) -> Result<(), HttpApiProblem>;
+impl FromAcceptSwapRequestHttpBody<Ethereum, Bitcoin>
+    for StateMachineResponse<
+        ethereum_support::Address,
+        secp256k1_support::KeyPair,
+        bitcoin::Timelock,
+    >
+{
+    fn from_accept_swap_request_http_body(
+        body: AcceptSwapRequestHttpBody<Ethereum, Bitcoin>,
+        id: SwapId,
+        secret_source: &dyn SecretSource,
+    ) -> Result<Self, HttpApiProblem> {
+        match body {
+            AcceptSwapRequestHttpBody::OnlyRefund { .. } | AcceptSwapRequestHttpBody::RefundAndRedeem { .. } => Err(HttpApiProblem::with_title_and_type_from_status(400).set_detail("The refund identity for swaps where Bitcoin is the BetaLedger has to be provided on-demand, i.e. when the refund action is executed.")),
+            AcceptSwapRequestHttpBody::None { .. } => Err(HttpApiProblem::with_title_and_type_from_status(400).set_detail("Missing alpha_ledger_redeem_identity")),
+            AcceptSwapRequestHttpBody::OnlyRedeem { alpha_ledger_redeem_identity, beta_ledger_lock_duration } => Ok(StateMachineResponse {
+                alpha_ledger_redeem_identity,
+                beta_ledger_lock_duration,
+                beta_ledger_refund_identity: secret_source.new_secp256k1_refund(id),
+            }),
+        }
+    }
 }
 
-impl<AL: Ledger, BL: Ledger> ExecuteDecline for Decline<AL, BL> {
-    fn execute(&self, reason: Option<SwapDeclineReason>) -> Result<(), HttpApiProblem> {
-        self.decline(reason)
-            .map_err(|_| problem::action_already_taken())
+impl FromAcceptSwapRequestHttpBody<Ethereum, Ethereum>
+    for StateMachineResponse<
+        ethereum_support::Address,
+        ethereum_support::Address,
+        ethereum::Seconds,
+    >
+{
+    fn from_accept_swap_request_http_body(
+        body: AcceptSwapRequestHttpBody<Ethereum, Ethereum>,
+        _id: SwapId,
+        _secret_source: &dyn SecretSource,
+    ) -> Result<Self, HttpApiProblem> {
+        match body {
+            AcceptSwapRequestHttpBody::OnlyRedeem { .. } => {
+                Err(HttpApiProblem::with_title_and_type_from_status(400)
+                    .set_detail("Missing beta_ledger_refund_identity"))
+            }
+            AcceptSwapRequestHttpBody::OnlyRefund { .. } => {
+                Err(HttpApiProblem::with_title_and_type_from_status(400)
+                    .set_detail("Missing alpha_ledger_redeem_identity"))
+            }
+            AcceptSwapRequestHttpBody::None { .. } => Err(
+                HttpApiProblem::with_title_and_type_from_status(400).set_detail(
+                    "Missing alpha_ledger_redeem_identity and beta_ledger_refund_identity",
+                ),
+            ),
+            AcceptSwapRequestHttpBody::RefundAndRedeem {
+                alpha_ledger_redeem_identity,
+                beta_ledger_refund_identity,
+                beta_ledger_lock_duration,
+            } => Ok(StateMachineResponse {
+                alpha_ledger_redeem_identity,
+                beta_ledger_refund_identity,
+                beta_ledger_lock_duration,
+            }),
+        }
     }
 }
 
-impl<AL: Ledger, BL: Ledger> ExecuteAccept<AL, BL> for () {
+trait ExecuteDecline {
     fn execute(
         &self,
-        _body: AcceptSwapRequestHttpBody<AL, BL>,
-        _secret_source: &dyn SecretSource,
-        _id: SwapId,
+        reason: Option<SwapDeclineReason>,
+        details: Option<DeclineDetails>,
+        counter_offer: Option<CounterOffer>,
+    ) -> Result<(), HttpApiProblem>;
+}
+
+impl<AL: Ledger, BL: Ledger> ExecuteDecline for Decline<AL, BL> {
+    fn execute(
+        &self,
+        reason: Option<SwapDeclineReason>,
+        details: Option<DeclineDetails>,
+        counter_offer: Option<CounterOffer>,
     ) -> Result<(), HttpApiProblem> {
-        unreachable!("FIXME: Alice will never return this action so we shouldn't have to deal with this case")
+        match counter_offer {
+            Some(counter_offer) => self.counter_offer(counter_offer),
+            None => self.decline(reason, details),
+        }
+        .map_err(|e| match e {
+            DeclineError::AlreadyResponded => problem::action_already_taken(),
+            DeclineError::NoLongerAwaited => problem::response_no_longer_awaited(),
+        })
     }
 }
 
 impl ExecuteDecline for () {
-    fn execute(&self, _reason: Option<SwapDeclineReason>) -> Result<(), HttpApiProblem> {
+    fn execute(
+        &self,
+        _reason: Option<SwapDeclineReason>,
+        _details: Option<DeclineDetails>,
+        _counter_offer: Option<CounterOffer>,
+    ) -> Result<(), HttpApiProblem> {
         unreachable!("FIXME: Alice will never return this action so we shouldn't have to deal with this case")
     }
 }
@@ -158,6 +348,7 @@ pub enum ActionResponseBody {
         data: ethereum_support::Bytes,
         value: EtherQuantity,
         gas_limit: ethereum_support::U256,
+        chain_id: ethereum_support::ChainId,
     },
 }
 
@@ -261,6 +452,7 @@ impl IntoResponseBody for ethereum::ContractDeploy {
             data,
             value,
             gas_limit,
+            chain_id,
         } = self;
         match query_params {
             GetActionQueryParams::None {} => Ok(ActionResponseBody::SendEthereumTransaction {
@@ -268,6 +460,7 @@ impl IntoResponseBody for ethereum::ContractDeploy {
                 data,
                 value,
                 gas_limit,
+                chain_id,
             }),
             _ => {
                 error!("Unexpected GET parameters for an ethereum::ContractDeploy action type. Expected: None.");
@@ -288,6 +481,7 @@ impl IntoResponseBody for ethereum::SendTransaction {
             data,
             value,
             gas_limit,
+            chain_id,
         } = self;
         match query_params {
             GetActionQueryParams::None {} => Ok(ActionResponseBody::SendEthereumTransaction {
@@ -295,6 +489,7 @@ impl IntoResponseBody for ethereum::SendTransaction {
                 data,
                 value,
                 gas_limit,
+                chain_id,
             }),
             _ => {
                 error!("Unexpected GET parameters for an ethereum::SendTransaction action. Expected: None.");
@@ -363,8 +558,37 @@ enum AcceptSwapRequestHttpBody<AL: Ledger, BL: Ledger> {
 }
 
 #[derive(Deserialize)]
-struct DeclineSwapRequestHttpBody {
+#[serde(bound = "")]
+struct DeclineSwapRequestHttpBody<AL: Ledger, BL: Ledger, BA: Asset> {
     reason: Option<SwapDeclineReason>,
+    details: Option<DeclineDetails>,
+    counter_offer: Option<CounterOfferHttpBody<AL, BL, BA>>,
+}
+
+#[derive(Deserialize)]
+#[serde(bound = "")]
+struct CounterOfferHttpBody<AL: Ledger, BL: Ledger, BA: Asset> {
+    beta_asset: BA,
+    alpha_ledger_lock_duration: AL::LockDuration,
+    #[serde(default)]
+    beta_ledger_lock_duration: Option<BL::LockDuration>,
+    expires_in_secs: u64,
+}
+
+impl<AL: Ledger, BL: Ledger, BA: Asset> CounterOfferHttpBody<AL, BL, BA> {
+    fn into_counter_offer(self, now: SystemTime) -> CounterOffer {
+        let expires_at = (now + Duration::from_secs(self.expires_in_secs))
+            .duration_since(UNIX_EPOCH)
+            .expect("the counter-offer should expire after the unix epoch")
+            .as_secs();
+
+        CounterOffer::new(CounterOfferBody::<AL, BL, BA> {
+            beta_asset: self.beta_asset,
+            alpha_ledger_lock_duration: self.alpha_ledger_lock_duration,
+            beta_ledger_lock_duration: self.beta_ledger_lock_duration,
+            expires_at,
+        })
+    }
 }
 
 #[allow(clippy::needless_pass_by_value)]
@@ -372,6 +596,8 @@ pub fn post<T: MetadataStore<SwapId>, S: StateStore<SwapId>>(
     metadata_store: Arc<T>,
     state_store: Arc<S>,
     secret_source: Arc<dyn SecretSource>,
+    lock_duration_margin: Duration,
+    sender: UnboundedSender<(SwapId, Peer, SwapRequestKind)>,
     id: SwapId,
     action: PostAction,
     body: serde_json::Value,
@@ -380,11 +606,16 @@ pub fn post<T: MetadataStore<SwapId>, S: StateStore<SwapId>>(
         metadata_store.as_ref(),
         state_store.as_ref(),
         secret_source.as_ref(),
+        lock_duration_margin,
+        &sender,
         id,
         action,
         body,
     )
-    .map(|_| warp::reply())
+    .map(|new_swap| match new_swap {
+        Some(new_id) => swap_created(new_id),
+        None => Response::new(Body::empty()),
+    })
     .map_err(HttpApiProblemStdError::from)
     .map_err(warp::reject::custom)
 }
@@ -394,49 +625,59 @@ pub fn handle_post<T: MetadataStore<SwapId>, S: StateStore<SwapId>>(
     metadata_store: &T,
     state_store: &S,
     secret_source: &dyn SecretSource,
+    lock_duration_margin: Duration,
+    sender: &UnboundedSender<(SwapId, Peer, SwapRequestKind)>,
     id: SwapId,
     action: PostAction,
     body: serde_json::Value,
-) -> Result<(), HttpApiProblem> {
+) -> Result<Option<SwapId>, HttpApiProblem> {
     use crate::swap_protocols::{AssetKind, LedgerKind, Metadata, RoleKind};
     trace!("accept action requested on {:?}", id);
     let metadata = metadata_store
         .get(&id)?
         .ok_or_else(problem::swap_not_found)?;
 
-    with_swap_types!(
+    // A counter-offer is only accepted once, asking again tells about the
+    // swap that was requested the first time
+    if let PostAction::Accept = action {
+        if let Some(swap_id) = accepted_counter_offer(metadata_store, &id)? {
+            return Ok(Some(swap_id));
+        }
+    }
+
+    let new_swap = with_swap_types!(
         &metadata,
         (|| match action {
-            PostAction::Accept => serde_json::from_value::<AcceptSwapRequestHttpBody<AL, BL>>(body)
-                .map_err(|e| {
-                    error!(
-                        "Failed to deserialize body of accept response for swap {}: {:?}",
-                        id, e
-                    );
-                    problem::serde(&e)
-                })
-                .and_then(|accept_body| {
-                    let state = state_store
-                        .get::<Role>(&id)?
-                        .ok_or_else(problem::state_store)?;
-
-                    let accept_action = {
-                        state
-                            .actions()
-                            .into_iter()
-                            .find_map(move |action| match action {
-                                ActionKind::Accept(accept) => Some(Ok(accept)),
-                                _ => None,
-                            })
-                            .unwrap_or_else(|| {
-                                Err(HttpApiProblem::with_title_and_type_from_status(404))
-                            })?
-                    };
-
-                    ExecuteAccept::execute(&accept_action, accept_body, secret_source, id)
-                }),
+            PostAction::Accept => {
+                let state = state_store
+                    .get::<Role>(&id)?
+                    .ok_or_else(problem::state_store)?;
+
+                let accept_action = {
+                    state
+                        .actions()
+                        .into_iter()
+                        .find_map(move |action| match action {
+                            ActionKind::Accept(accept) => Some(Ok(accept)),
+                            _ => None,
+                        })
+                        .unwrap_or_else(|| {
+                            Err(HttpApiProblem::with_title_and_type_from_status(404))
+                        })?
+                };
+
+                ExecuteAccept::<AL, BL>::execute(
+                    &accept_action,
+                    body,
+                    secret_source,
+                    id,
+                    lock_duration_margin,
+                    metadata_store,
+                    sender,
+                )
+            }
             PostAction::Decline => {
-                serde_json::from_value::<DeclineSwapRequestHttpBody>(body.clone())
+                serde_json::from_value::<DeclineSwapRequestHttpBody<AL, BL, BA>>(body.clone())
                     .map_err(|e| {
                         error!(
                             "Failed to deserialize body of decline response for swap {}: {:?}",
@@ -463,12 +704,42 @@ pub fn handle_post<T: MetadataStore<SwapId>, S: StateStore<SwapId>>(
                         };
 
                         let reason = decline_body.reason;
+                        let details = decline_body.details;
+                        let counter_offer = decline_body.counter_offer.map(|counter_offer| {
+                            counter_offer.into_counter_offer(SystemTime::now())
+                        });
 
-                        ExecuteDecline::execute(&decline_action, reason)
+                        ExecuteDecline::execute(&decline_action, reason, details, counter_offer)
+                            .map(|_| None)
                     })
             }
         })
-    )
+    )?;
+
+    // Accepting a counter-offer is recorded when it is reserved
+    if new_swap.is_none() {
+        metadata_store.append_history(
+            &id,
+            HistoryEvent::ActionExecuted {
+                action: action.name(),
+            },
+        )?;
+    }
+
+    Ok(new_swap)
+}
+
+fn accepted_counter_offer<T: MetadataStore<SwapId>>(
+    metadata_store: &T,
+    id: &SwapId,
+) -> Result<Option<SwapId>, HttpApiProblem> {
+    Ok(metadata_store
+        .history(id)?
+        .into_iter()
+        .find_map(|entry| match entry.event {
+            HistoryEvent::CounterOfferAccepted { swap_id } => Some(swap_id),
+            _ => None,
+        }))
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -575,6 +846,20 @@ fn handle_get<T: MetadataStore<SwapId>, S: StateStore<SwapId>>(
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::{
+        peers::Counterparty,
+        seed::Seed,
+        swap_protocols::{
+            rfc003::{alice::SwapRequestIdentities, roles::test::Bobisha},
+            InMemoryMetadataStore,
+        },
+    };
+    use futures::{
+        sync::mpsc::{self, UnboundedReceiver},
+        Stream,
+    };
+    use spectral::prelude::*;
+    use std::thread;
 
     #[test]
     fn given_no_query_parameters_deserialize_to_none() {
@@ -597,4 +882,175 @@ mod test {
             })
         );
     }
+
+    fn metadata_store_with_swap(id: SwapId) -> InMemoryMetadataStore<SwapId> {
+        let metadata_store = InMemoryMetadataStore::default();
+        metadata_store
+            .insert(
+                id,
+                Metadata {
+                    alpha_ledger: LedgerKind::Bitcoin,
+                    beta_ledger: LedgerKind::Ethereum,
+                    alpha_asset: AssetKind::Bitcoin,
+                    beta_asset: AssetKind::Ether,
+                    role: RoleKind::Alice,
+                },
+                Counterparty {
+                    address: "127.0.0.1:8011".parse().unwrap(),
+                    identity: None,
+                },
+            )
+            .unwrap();
+        metadata_store
+    }
+
+    fn accept_counter_offer(
+        secret_source: &dyn SecretSource,
+        id: SwapId,
+    ) -> AcceptCounterOffer<Bitcoin, Ethereum, BitcoinQuantity, EtherQuantity> {
+        AcceptCounterOffer {
+            request: SwapRequest {
+                alpha_asset: BitcoinQuantity::from_bitcoin(1.0),
+                beta_asset: EtherQuantity::from_eth(9.0),
+                alpha_ledger: Bitcoin::default(),
+                beta_ledger: Ethereum::default(),
+                alpha_ledger_lock_duration: bitcoin_support::Blocks::from(144).into(),
+                identities: SwapRequestIdentities {
+                    alpha_ledger_refund_identity: secret_source.new_secp256k1_refund(id),
+                    beta_ledger_redeem_identity: "8457037fcd80a8650c4692d7fcfc1d0a96b92867"
+                        .parse()
+                        .unwrap(),
+                },
+            },
+            beta_ledger_lock_duration: None,
+        }
+    }
+
+    fn requested_swaps(
+        receiver: UnboundedReceiver<(SwapId, Peer, SwapRequestKind)>,
+    ) -> Vec<(SwapId, SwapRequestKind)> {
+        receiver
+            .wait()
+            .map(Result::unwrap)
+            .map(|(id, _, request)| (id, request))
+            .collect()
+    }
+
+    #[test]
+    fn accepted_counter_offer_is_the_first_swap_requested_for_it() {
+        let id = SwapId::default();
+        let first_swap = SwapId::default();
+        let metadata_store = metadata_store_with_swap(id);
+
+        assert_eq!(accepted_counter_offer(&metadata_store, &id).unwrap(), None);
+
+        for swap_id in vec![first_swap, SwapId::default()] {
+            metadata_store
+                .append_history(&id, HistoryEvent::CounterOfferAccepted { swap_id })
+                .unwrap();
+        }
+
+        assert_eq!(
+            accepted_counter_offer(&metadata_store, &id).unwrap(),
+            Some(first_swap)
+        );
+    }
+
+    #[test]
+    fn accepting_after_the_swap_stopped_waiting_is_gone() {
+        let seed = Seed::from(*b"hello world, you are beautiful!!");
+        let id = SwapId::default();
+        let (sender, _receiver) = mpsc::unbounded();
+        let (bobisha, response_future) = Bobisha::create(None);
+        drop(response_future);
+
+        let problem = ExecuteAccept::<Bitcoin, Ethereum>::execute(
+            &bobisha.accept_action(bitcoin_support::Blocks::from(144).into()),
+            json!({
+                "beta_ledger_refund_identity": "0x71b9f69dcabb340a3fe229c3f94f1662ad85e5e8",
+                "beta_ledger_lock_duration": 43200,
+            }),
+            &seed,
+            id,
+            Duration::from_secs(6 * 60 * 60),
+            &metadata_store_with_swap(id),
+            &sender,
+        )
+        .unwrap_err();
+
+        assert_that(&problem.status.map(|status| status.to_u16())).is_equal_to(Some(410));
+    }
+
+    #[test]
+    fn swap_of_counter_offer_uses_keys_of_its_own_id() {
+        let seed = Seed::from(*b"hello world, you are beautiful!!");
+        let id = SwapId::default();
+        let metadata_store = metadata_store_with_swap(id);
+        let (sender, receiver) = mpsc::unbounded();
+
+        let new_id = ExecuteAccept::<Bitcoin, Ethereum>::execute(
+            &accept_counter_offer(&seed, id),
+            json!({}),
+            &seed,
+            id,
+            Duration::from_secs(0),
+            &metadata_store,
+            &sender,
+        )
+        .unwrap()
+        .unwrap();
+        drop(sender);
+
+        match requested_swaps(receiver).as_slice() {
+            [(
+                requested_id,
+                SwapRequestKind::BitcoinEthereumBitcoinQuantityEtherQuantity(request),
+            )] => {
+                assert_that(requested_id).is_equal_to(&new_id);
+                assert_that(&request.identities.alpha_ledger_refund_identity)
+                    .is_equal_to(seed.new_secp256k1_refund(new_id));
+            }
+            requests => panic!("expected one Bitcoin/Ether request but got {:?}", requests),
+        }
+    }
+
+    #[test]
+    fn concurrent_accepts_of_a_counter_offer_request_one_swap() {
+        let seed = Seed::from(*b"hello world, you are beautiful!!");
+        let id = SwapId::default();
+        let metadata_store = Arc::new(metadata_store_with_swap(id));
+        let (sender, receiver) = mpsc::unbounded();
+
+        let accepts: Vec<_> = (0..8)
+            .map(|_| {
+                let metadata_store = Arc::clone(&metadata_store);
+                let sender = sender.clone();
+                thread::spawn(move || {
+                    ExecuteAccept::<Bitcoin, Ethereum>::execute(
+                        &accept_counter_offer(&seed, id),
+                        json!({}),
+                        &seed,
+                        id,
+                        Duration::from_secs(0),
+                        metadata_store.as_ref(),
+                        &sender,
+                    )
+                    .unwrap()
+                    .unwrap()
+                })
+            })
+            .collect();
+        let new_ids: Vec<SwapId> = accepts
+            .into_iter()
+            .map(|accept| accept.join().unwrap())
+            .collect();
+        drop(sender);
+
+        let requested_ids: Vec<SwapId> = requested_swaps(receiver)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_that(&requested_ids).has_length(1);
+        assert_that(&new_ids.iter().all(|new_id| *new_id == requested_ids[0])).is_true();
+    }
 }
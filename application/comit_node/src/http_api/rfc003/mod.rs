@@ -1,4 +1,3 @@
-#[macro_use]
-mod with_swap_types;
 pub mod action;
+pub mod stream;
 pub mod swap;
@@ -0,0 +1,160 @@
+use crate::{
+    http_api::problem::{self, HttpApiProblemStdError},
+    swap_protocols::{
+        ledger::{Bitcoin, Ethereum},
+        rfc003::{
+            actions::{ActionKind, Actions},
+            roles::Role,
+            state_machine::SwapStates,
+            state_store::{StateStore, StateUpdate},
+        },
+        AssetKind, LedgerKind, Metadata, MetadataStore, RoleKind, SwapId,
+    },
+    with_swap_types,
+};
+use ethereum_support::Erc20Quantity;
+use futures::{stream, Future, Sink, Stream};
+use http_api_problem::HttpApiProblem;
+use std::sync::Arc;
+use warp::{
+    ws::{Message, WebSocket, Ws2},
+    Rejection, Reply,
+};
+
+/// What clients get to know about a state. It leaves out the secret and
+/// the keys, so it is safe to send to WebSocket clients and webhooks.
+#[derive(Debug, Serialize)]
+pub struct SwapStateUpdate {
+    pub id: SwapId,
+    pub state: String,
+    #[serde(rename = "final")]
+    pub is_final: bool,
+    pub actions: Vec<String>,
+}
+
+impl SwapStateUpdate {
+    fn new<R: Role, Accept, Decline, Deploy, Fund, Redeem, Refund>(
+        id: SwapId,
+        state: &SwapStates<R>,
+    ) -> Self
+    where
+        SwapStates<R>:
+            Actions<ActionKind = ActionKind<Accept, Decline, Deploy, Fund, Redeem, Refund>>,
+    {
+        SwapStateUpdate {
+            id,
+            state: state.name(),
+            is_final: state.is_final(),
+            actions: state.actions().iter().map(ActionKind::name).collect(),
+        }
+    }
+}
+
+pub fn stream_swap<T: MetadataStore<SwapId>, S: StateStore<SwapId>>(
+    metadata_store: Arc<T>,
+    state_store: Arc<S>,
+    id: SwapId,
+    ws: Ws2,
+) -> Result<impl Reply, Rejection> {
+    // Subscribing before looking at the current state makes sure that no
+    // state saved in between is missed
+    let saved_states = state_store
+        .subscribe()
+        .filter(move |update| update.key == id);
+
+    let current = swap_state_update(metadata_store.as_ref(), state_store.as_ref(), id)
+        .and_then(|update| update.ok_or_else(problem::state_store))
+        .map_err(|e| warp::reject::custom(HttpApiProblemStdError::new(e)))?;
+    let updates =
+        stream::once(Ok(current)).chain(saved_swap_state_updates(metadata_store, saved_states));
+
+    Ok(ws.on_upgrade(move |websocket| send_updates(websocket, updates)))
+}
+
+pub fn stream_swaps<T: MetadataStore<SwapId>, S: StateStore<SwapId>>(
+    metadata_store: Arc<T>,
+    state_store: Arc<S>,
+    ws: Ws2,
+) -> impl Reply {
+    let updates = saved_swap_state_updates(metadata_store, state_store.subscribe());
+
+    ws.on_upgrade(move |websocket| send_updates(websocket, updates))
+}
+
+fn send_updates<U: Stream<Item = SwapStateUpdate, Error = ()> + Send + 'static>(
+    websocket: WebSocket,
+    updates: U,
+) -> impl Future<Item = (), Error = ()> {
+    let (sink, _) = websocket.split();
+
+    updates
+        .map(|update| {
+            Message::text(serde_json::to_string(&update).expect("update should serialize"))
+        })
+        .forward(sink.sink_map_err(|e| debug!("Stopped streaming swap states: {:?}", e)))
+        .map(|_| ())
+}
+
+/// Turns every saved state into what clients get to see of it
+pub fn saved_swap_state_updates<
+    T: MetadataStore<SwapId>,
+    U: Stream<Item = StateUpdate<SwapId>, Error = ()>,
+>(
+    metadata_store: Arc<T>,
+    saved_states: U,
+) -> impl Stream<Item = SwapStateUpdate, Error = ()> {
+    saved_states.filter_map(move |saved_state| {
+        match saved_swap_state_update(metadata_store.as_ref(), &saved_state) {
+            Ok(update) => Some(update),
+            Err(e) => {
+                error!(
+                    "Failed to look at the saved state of swap {}: {:?}",
+                    saved_state.key, e
+                );
+                None
+            }
+        }
+    })
+}
+
+fn saved_swap_state_update<T: MetadataStore<SwapId>>(
+    metadata_store: &T,
+    saved_state: &StateUpdate<SwapId>,
+) -> Result<SwapStateUpdate, HttpApiProblem> {
+    let id = saved_state.key;
+    let metadata = metadata_store
+        .get(&id)?
+        .ok_or_else(problem::swap_not_found)?;
+
+    with_swap_types!(
+        &metadata,
+        (|| {
+            let state = saved_state
+                .state::<Role>()
+                .ok_or_else(problem::state_store)?;
+
+            Ok(SwapStateUpdate::new(id, state))
+        })
+    )
+}
+
+/// The state the store has for swap `id` right now or `None` if it has
+/// none
+pub fn swap_state_update<T: MetadataStore<SwapId>, S: StateStore<SwapId>>(
+    metadata_store: &T,
+    state_store: &S,
+    id: SwapId,
+) -> Result<Option<SwapStateUpdate>, HttpApiProblem> {
+    let metadata = metadata_store
+        .get(&id)?
+        .ok_or_else(problem::swap_not_found)?;
+
+    with_swap_types!(
+        &metadata,
+        (|| {
+            let state = state_store.get::<Role>(&id)?;
+
+            Ok(state.map(|state| SwapStateUpdate::new(id, &state)))
+        })
+    )
+}
@@ -1,14 +1,18 @@
 use crate::{
+    comit_client::{CounterOffer, DeclineDetails, SwapDeclineReason, SwapReject, SwapRejectReason},
     http_api::{
         self,
         asset::{HttpAsset, ToHttpAsset},
         ledger::{HttpLedger, ToHttpLedger},
         lock_duration::{HttpLockDuration, ToHttpLockDuration},
         problem::{self, HttpApiProblemStdError},
+        rfc003::stream::swap_state_update,
     },
+    peers::{Peer, PeerRegistry},
     swap_protocols::{
         asset::Asset,
-        ledger::{Bitcoin, Ethereum},
+        ledger::{Bitcoin, Ethereum, Networks},
+        metadata_store::{self, Cursor, HistoryEvent, Order},
         rfc003::{
             self,
             actions::{ActionKind, Actions},
@@ -16,19 +20,24 @@ use crate::{
             state_store::StateStore,
             Ledger, SecretSource,
         },
-        AssetKind, LedgerKind, Metadata, MetadataStore, RoleKind, SwapId,
+        AssetKind, LedgerKind, Metadata, MetadataStore, Query, RoleKind, SwapId,
     },
+    with_swap_types,
 };
 use bitcoin_support::{self, BitcoinQuantity};
+use chrono::{DateTime, Utc};
 use ethereum_support::{self, Erc20Quantity, EtherQuantity};
 use futures::sync::mpsc::UnboundedSender;
 use http_api_problem::HttpApiProblem;
-use hyper::header;
+use hyper::{header, Body, Response, StatusCode};
 use rustic_hal::HalResource;
-use std::sync::Arc;
+use std::{str::FromStr, sync::Arc};
+use url::form_urlencoded;
 use warp::{self, Rejection, Reply};
 
 pub const PROTOCOL_NAME: &str = "rfc003";
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 1000;
 
 #[derive(Clone, Debug, Deserialize, PartialEq)]
 #[serde(untagged)]
@@ -39,11 +48,56 @@ pub enum SwapRequestBodyKind {
     BitcoinEthereumBitcoinQuantityErc20Quantity(
         SwapRequestBody<Bitcoin, Ethereum, BitcoinQuantity, Erc20Quantity>,
     ),
+    EthereumBitcoinEtherQuantityBitcoinQuantity(
+        SwapRequestBody<Ethereum, Bitcoin, EtherQuantity, BitcoinQuantity>,
+    ),
+    EthereumBitcoinErc20QuantityBitcoinQuantity(
+        SwapRequestBody<Ethereum, Bitcoin, Erc20Quantity, BitcoinQuantity>,
+    ),
+    EthereumEthereumEtherQuantityErc20Quantity(
+        SwapRequestBody<Ethereum, Ethereum, EtherQuantity, Erc20Quantity>,
+    ),
+    EthereumEthereumErc20QuantityEtherQuantity(
+        SwapRequestBody<Ethereum, Ethereum, Erc20Quantity, EtherQuantity>,
+    ),
+    EthereumEthereumErc20QuantityErc20Quantity(
+        SwapRequestBody<Ethereum, Ethereum, Erc20Quantity, Erc20Quantity>,
+    ),
     // It is important that these two come last because untagged enums are tried in order
     UnsupportedCombination(Box<UnsupportedSwapRequestBody>),
     MalformedRequest(serde_json::Value),
 }
 
+impl SwapRequestBodyKind {
+    fn peer(&self) -> Option<&str> {
+        match *self {
+            SwapRequestBodyKind::BitcoinEthereumBitcoinQuantityEtherQuantity(ref body) => {
+                Some(&body.peer)
+            }
+            SwapRequestBodyKind::BitcoinEthereumBitcoinQuantityErc20Quantity(ref body) => {
+                Some(&body.peer)
+            }
+            SwapRequestBodyKind::EthereumBitcoinEtherQuantityBitcoinQuantity(ref body) => {
+                Some(&body.peer)
+            }
+            SwapRequestBodyKind::EthereumBitcoinErc20QuantityBitcoinQuantity(ref body) => {
+                Some(&body.peer)
+            }
+            SwapRequestBodyKind::EthereumEthereumEtherQuantityErc20Quantity(ref body) => {
+                Some(&body.peer)
+            }
+            SwapRequestBodyKind::EthereumEthereumErc20QuantityEtherQuantity(ref body) => {
+                Some(&body.peer)
+            }
+            SwapRequestBodyKind::EthereumEthereumErc20QuantityErc20Quantity(ref body) => {
+                Some(&body.peer)
+            }
+            SwapRequestBodyKind::UnsupportedCombination(_)
+            | SwapRequestBodyKind::MalformedRequest(_) => None,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct SwapRequestBody<AL: Ledger, BL: Ledger, AA: Asset, BA: Asset> {
     #[serde(with = "http_api::asset::serde")]
@@ -57,6 +111,8 @@ pub struct SwapRequestBody<AL: Ledger, BL: Ledger, AA: Asset, BA: Asset> {
     alpha_ledger_lock_duration: AL::LockDuration,
     #[serde(flatten)]
     identities: SwapRequestBodyIdentities<AL::Identity, BL::Identity>,
+    /// The address or the name in the address book of the counterparty
+    peer: String,
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq)]
@@ -113,6 +169,58 @@ impl FromSwapRequestBodyIdentities<Bitcoin, Ethereum>
     }
 }
 
+impl FromSwapRequestBodyIdentities<Ethereum, Bitcoin>
+    for rfc003::alice::SwapRequestIdentities<Ethereum, Bitcoin>
+{
+    fn from_swap_request_body_identities(
+        identities: SwapRequestBodyIdentities<
+            ethereum_support::Address,
+            bitcoin_support::PubkeyHash,
+        >,
+        id: SwapId,
+        secret_source: &dyn SecretSource,
+    ) -> Result<Self, HttpApiProblem> {
+        match identities {
+            SwapRequestBodyIdentities::RefundAndRedeem { .. }
+            | SwapRequestBodyIdentities::OnlyRedeem { .. }
+            | SwapRequestBodyIdentities::None {} => {
+                Err(HttpApiProblem::with_title_and_type_from_status(400))
+            }
+            SwapRequestBodyIdentities::OnlyRefund {
+                alpha_ledger_refund_identity,
+            } => Ok(rfc003::alice::SwapRequestIdentities {
+                alpha_ledger_refund_identity,
+                beta_ledger_redeem_identity: secret_source.new_secp256k1_redeem(id),
+            }),
+        }
+    }
+}
+
+impl FromSwapRequestBodyIdentities<Ethereum, Ethereum>
+    for rfc003::alice::SwapRequestIdentities<Ethereum, Ethereum>
+{
+    fn from_swap_request_body_identities(
+        identities: SwapRequestBodyIdentities<ethereum_support::Address, ethereum_support::Address>,
+        _id: SwapId,
+        _secret_source: &dyn SecretSource,
+    ) -> Result<Self, HttpApiProblem> {
+        match identities {
+            SwapRequestBodyIdentities::OnlyRedeem { .. }
+            | SwapRequestBodyIdentities::OnlyRefund { .. }
+            | SwapRequestBodyIdentities::None {} => {
+                Err(HttpApiProblem::with_title_and_type_from_status(400))
+            }
+            SwapRequestBodyIdentities::RefundAndRedeem {
+                alpha_ledger_refund_identity,
+                beta_ledger_redeem_identity,
+            } => Ok(rfc003::alice::SwapRequestIdentities {
+                alpha_ledger_refund_identity,
+                beta_ledger_redeem_identity,
+            }),
+        }
+    }
+}
+
 trait FromSwapRequestBody<AL: Ledger, BL: Ledger, AA: Asset, BA: Asset>
 where
     Self: Sized,
@@ -121,6 +229,7 @@ where
         body: SwapRequestBody<AL, BL, AA, BA>,
         id: SwapId,
         secret_source: &dyn SecretSource,
+        networks: &Networks,
     ) -> Result<Self, HttpApiProblem>;
 }
 
@@ -133,7 +242,16 @@ where
         body: SwapRequestBody<AL, BL, AA, BA>,
         id: SwapId,
         secret_source: &dyn SecretSource,
+        networks: &Networks,
     ) -> Result<Self, HttpApiProblem> {
+        if !body.alpha_ledger.is_on(networks) || !body.beta_ledger.is_on(networks) {
+            error!(
+                "Swap between {:?} and {:?} is not on the networks of this node ({:?})",
+                body.alpha_ledger, body.beta_ledger, networks
+            );
+            return Err(problem::unsupported_network());
+        }
+
         Ok(rfc003::alice::SwapRequest {
             alpha_asset: body.alpha_asset,
             beta_asset: body.beta_asset,
@@ -169,38 +287,118 @@ fn swap_path(id: SwapId) -> String {
     format!("/{}/{}/{}", http_api::PATH, PROTOCOL_NAME, id)
 }
 
+pub fn swap_created(id: SwapId) -> Response<Body> {
+    let body = serde_json::to_string(&SwapCreated { id }).expect("should not fail to serialize");
+
+    Response::builder()
+        .status(StatusCode::CREATED)
+        .header(header::LOCATION, swap_path(id))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .expect("should not fail to build response")
+}
+
 #[allow(clippy::needless_pass_by_value)]
 pub fn post_swap(
     secret_source: Arc<dyn SecretSource>,
-    sender: UnboundedSender<(SwapId, rfc003::alice::SwapRequestKind)>,
+    sender: UnboundedSender<(SwapId, Peer, rfc003::alice::SwapRequestKind)>,
+    networks: Networks,
+    peers: Arc<PeerRegistry>,
     request_body_kind: SwapRequestBodyKind,
 ) -> Result<impl Reply, Rejection> {
-    handle_post_swap(secret_source.as_ref(), &sender, request_body_kind)
-        .map(|swap_created| {
-            let body = warp::reply::json(&swap_created);
-            let response =
-                warp::reply::with_header(body, header::LOCATION, swap_path(swap_created.id));
-            warp::reply::with_status(response, warp::http::StatusCode::CREATED)
-        })
-        .map_err(|problem| warp::reject::custom(HttpApiProblemStdError::from(problem)))
+    handle_post_swap(
+        secret_source.as_ref(),
+        &sender,
+        networks,
+        peers.as_ref(),
+        request_body_kind,
+    )
+    .map(|SwapCreated { id }| swap_created(id))
+    .map_err(|problem| warp::reject::custom(HttpApiProblemStdError::from(problem)))
 }
 
 fn handle_post_swap(
     secret_source: &dyn SecretSource,
-    sender: &UnboundedSender<(SwapId, rfc003::alice::SwapRequestKind)>,
+    sender: &UnboundedSender<(SwapId, Peer, rfc003::alice::SwapRequestKind)>,
+    networks: Networks,
+    peers: &PeerRegistry,
     request_body_kind: SwapRequestBodyKind,
 ) -> Result<SwapCreated, HttpApiProblem> {
     let id = SwapId::default();
+    let peer = request_body_kind
+        .peer()
+        .map(String::from)
+        .unwrap_or_default();
 
     let request_kind = match request_body_kind {
         SwapRequestBodyKind::BitcoinEthereumBitcoinQuantityEtherQuantity(body) => {
             rfc003::alice::SwapRequestKind::BitcoinEthereumBitcoinQuantityEtherQuantity(
-                rfc003::alice::SwapRequest::from_swap_request_body(body, id, secret_source)?,
+                rfc003::alice::SwapRequest::from_swap_request_body(
+                    body,
+                    id,
+                    secret_source,
+                    &networks,
+                )?,
             )
         }
         SwapRequestBodyKind::BitcoinEthereumBitcoinQuantityErc20Quantity(body) => {
             rfc003::alice::SwapRequestKind::BitcoinEthereumBitcoinQuantityErc20Quantity(
-                rfc003::alice::SwapRequest::from_swap_request_body(body, id, secret_source)?,
+                rfc003::alice::SwapRequest::from_swap_request_body(
+                    body,
+                    id,
+                    secret_source,
+                    &networks,
+                )?,
+            )
+        }
+        SwapRequestBodyKind::EthereumBitcoinEtherQuantityBitcoinQuantity(body) => {
+            rfc003::alice::SwapRequestKind::EthereumBitcoinEtherQuantityBitcoinQuantity(
+                rfc003::alice::SwapRequest::from_swap_request_body(
+                    body,
+                    id,
+                    secret_source,
+                    &networks,
+                )?,
+            )
+        }
+        SwapRequestBodyKind::EthereumBitcoinErc20QuantityBitcoinQuantity(body) => {
+            rfc003::alice::SwapRequestKind::EthereumBitcoinErc20QuantityBitcoinQuantity(
+                rfc003::alice::SwapRequest::from_swap_request_body(
+                    body,
+                    id,
+                    secret_source,
+                    &networks,
+                )?,
+            )
+        }
+        SwapRequestBodyKind::EthereumEthereumEtherQuantityErc20Quantity(body) => {
+            rfc003::alice::SwapRequestKind::EthereumEthereumEtherQuantityErc20Quantity(
+                rfc003::alice::SwapRequest::from_swap_request_body(
+                    body,
+                    id,
+                    secret_source,
+                    &networks,
+                )?,
+            )
+        }
+        SwapRequestBodyKind::EthereumEthereumErc20QuantityEtherQuantity(body) => {
+            rfc003::alice::SwapRequestKind::EthereumEthereumErc20QuantityEtherQuantity(
+                rfc003::alice::SwapRequest::from_swap_request_body(
+                    body,
+                    id,
+                    secret_source,
+                    &networks,
+                )?,
+            )
+        }
+        SwapRequestBodyKind::EthereumEthereumErc20QuantityErc20Quantity(body) => {
+            rfc003::alice::SwapRequestKind::EthereumEthereumErc20QuantityErc20Quantity(
+                rfc003::alice::SwapRequest::from_swap_request_body(
+                    body,
+                    id,
+                    secret_source,
+                    &networks,
+                )?,
             )
         }
         SwapRequestBodyKind::UnsupportedCombination(body) => {
@@ -221,7 +419,12 @@ fn handle_post_swap(
         }
     };
 
-    if let Err(e) = sender.unbounded_send((id, request_kind)) {
+    let peer = peers.resolve(&peer).ok_or_else(|| {
+        error!("Peer {} is neither an address nor a known peer", peer);
+        problem::unknown_peer(&peer)
+    })?;
+
+    if let Err(e) = sender.unbounded_send((id, peer, request_kind)) {
         error!(
             "Swap request {:?} for id {} could not dispatched.",
             e.into_inner(),
@@ -244,11 +447,45 @@ pub struct SwapDescription {
     beta_lock_duration: Option<HttpLockDuration>,
 }
 
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RejectionResource {
+    Declined {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reason: Option<SwapDeclineReason>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        details: Option<DeclineDetails>,
+    },
+    Rejected {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reason: Option<SwapRejectReason>,
+    },
+    CounterOffered {
+        counter_offer: CounterOffer,
+    },
+}
+
+impl From<SwapReject> for RejectionResource {
+    fn from(rejection: SwapReject) -> Self {
+        match rejection {
+            SwapReject::Declined { reason, details } => {
+                RejectionResource::Declined { reason, details }
+            }
+            SwapReject::Rejected { reason } => RejectionResource::Rejected { reason },
+            SwapReject::CounterOffered(counter_offer) => {
+                RejectionResource::CounterOffered { counter_offer }
+            }
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 struct GetSwapResource {
     pub swap: SwapDescription,
     pub role: String,
     pub state: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rejection: Option<RejectionResource>,
 }
 
 #[allow(clippy::needless_pass_by_value)]
@@ -312,6 +549,7 @@ fn handle_get_swap<T: MetadataStore<SwapId>, S: StateStore<SwapId>>(
                             .map(|lock| lock.to_http_lock_duration().unwrap()),
                     },
                     role: format!("{}", metadata.role),
+                    rejection: state.rejection().cloned().map(RejectionResource::from),
                 },
                 actions,
             )))
@@ -319,21 +557,225 @@ fn handle_get_swap<T: MetadataStore<SwapId>, S: StateStore<SwapId>>(
     )
 }
 
+#[derive(Debug, Serialize)]
+struct HistoryEntryResource {
+    timestamp: String,
+    #[serde(flatten)]
+    event: HistoryEvent,
+}
+
+#[derive(Debug, Serialize)]
+struct SwapHistoryResource {
+    history: Vec<HistoryEntryResource>,
+}
+
+#[allow(clippy::needless_pass_by_value)]
+pub fn get_swap_history<T: MetadataStore<SwapId>>(
+    metadata_store: Arc<T>,
+    id: SwapId,
+) -> Result<impl Reply, Rejection> {
+    match handle_get_swap_history(metadata_store.as_ref(), &id) {
+        Ok(history) => {
+            let mut response = HalResource::new(history);
+            response.with_link("swap", swap_path(id));
+            Ok(warp::reply::json(&response))
+        }
+        Err(e) => Err(warp::reject::custom(HttpApiProblemStdError::new(e))),
+    }
+}
+
+fn handle_get_swap_history<T: MetadataStore<SwapId>>(
+    metadata_store: &T,
+    id: &SwapId,
+) -> Result<SwapHistoryResource, HttpApiProblem> {
+    let history = match metadata_store.history(id) {
+        Ok(history) => history,
+        Err(metadata_store::Error::NotFound) => return Err(problem::swap_not_found()),
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(SwapHistoryResource {
+        history: history
+            .into_iter()
+            .map(|entry| HistoryEntryResource {
+                timestamp: entry.timestamp.to_rfc3339(),
+                event: entry.event,
+            })
+            .collect(),
+    })
+}
+
 #[derive(Serialize, Debug)]
 pub struct EmbeddedSwapResource {
     state: String,
     protocol: String,
 }
 
+/// The filters and paging of `GET /swaps`, as given in the query string
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct GetSwapsQueryParams {
+    role: Option<String>,
+    alpha_ledger: Option<String>,
+    beta_ledger: Option<String>,
+    alpha_asset: Option<String>,
+    beta_asset: Option<String>,
+    state: Option<String>,
+    has_pending_action: Option<bool>,
+    created_after: Option<String>,
+    created_before: Option<String>,
+    order: Option<String>,
+    limit: Option<usize>,
+    after: Option<String>,
+    before: Option<String>,
+}
+
+impl GetSwapsQueryParams {
+    fn to_query(&self) -> Result<Query, HttpApiProblem> {
+        let order = match self.order.as_ref().map(String::as_str) {
+            None | Some("asc") => Order::OldestFirst,
+            Some("desc") => Order::NewestFirst,
+            Some(order) => return Err(invalid_query_param("order", order)),
+        };
+        let limit = match self.limit {
+            Some(0) => return Err(invalid_query_param("limit", "0")),
+            Some(limit) => limit.min(MAX_PAGE_SIZE),
+            None => DEFAULT_PAGE_SIZE,
+        };
+
+        Ok(Query {
+            role: parse_query_param("role", &self.role)?,
+            alpha_ledger: parse_query_param("alpha_ledger", &self.alpha_ledger)?,
+            beta_ledger: parse_query_param("beta_ledger", &self.beta_ledger)?,
+            alpha_asset: parse_query_param("alpha_asset", &self.alpha_asset)?,
+            beta_asset: parse_query_param("beta_asset", &self.beta_asset)?,
+            state: self.state.clone(),
+            created_after: parse_query_param("created_after", &self.created_after)?,
+            created_before: parse_query_param("created_before", &self.created_before)?,
+            order,
+            after: parse_cursor_query_param("after", &self.after)?,
+            before: parse_cursor_query_param("before", &self.before)?,
+            limit: Some(limit),
+        })
+    }
+
+    /// The same listing, moved to the swaps after or before the given cursor
+    fn link(&self, after: Option<&Cursor>, before: Option<&Cursor>) -> String {
+        let mut serializer = form_urlencoded::Serializer::new(String::new());
+
+        let filters = vec![
+            ("role", &self.role),
+            ("alpha_ledger", &self.alpha_ledger),
+            ("beta_ledger", &self.beta_ledger),
+            ("alpha_asset", &self.alpha_asset),
+            ("beta_asset", &self.beta_asset),
+            ("state", &self.state),
+            ("created_after", &self.created_after),
+            ("created_before", &self.created_before),
+            ("order", &self.order),
+        ];
+        for (name, value) in filters {
+            if let Some(value) = value {
+                serializer.append_pair(name, value);
+            }
+        }
+        if let Some(has_pending_action) = self.has_pending_action {
+            serializer.append_pair("has_pending_action", &has_pending_action.to_string());
+        }
+        if let Some(limit) = self.limit {
+            serializer.append_pair("limit", &limit.to_string());
+        }
+        if let Some(after) = after {
+            serializer.append_pair("after", &encode_cursor(after));
+        }
+        if let Some(before) = before {
+            serializer.append_pair("before", &encode_cursor(before));
+        }
+
+        let query = serializer.finish();
+        if query.is_empty() {
+            format!("/{}", http_api::PATH)
+        } else {
+            format!("/{}?{}", http_api::PATH, query)
+        }
+    }
+}
+
+fn invalid_query_param(name: &str, value: &str) -> HttpApiProblem {
+    HttpApiProblem::new("invalid-query-parameter")
+        .set_status(400)
+        .set_detail(format!("{} is not a valid value for {}", value, name))
+}
+
+fn parse_query_param<T: FromStr>(
+    name: &str,
+    value: &Option<String>,
+) -> Result<Option<T>, HttpApiProblem> {
+    match value {
+        None => Ok(None),
+        Some(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| invalid_query_param(name, value)),
+    }
+}
+
+fn parse_cursor_query_param(
+    name: &str,
+    value: &Option<String>,
+) -> Result<Option<Cursor>, HttpApiProblem> {
+    match value {
+        None => Ok(None),
+        Some(value) => decode_cursor(value)
+            .map(Some)
+            .ok_or_else(|| invalid_query_param(name, value)),
+    }
+}
+
+/// Cursors are opaque to clients, they just pass on what they got in a link
+fn encode_cursor(cursor: &Cursor) -> String {
+    base64::encode_config(
+        &format!("{}|{}", cursor.created_at.to_rfc3339(), cursor.key),
+        base64::URL_SAFE,
+    )
+}
+
+fn decode_cursor(cursor: &str) -> Option<Cursor> {
+    let bytes = base64::decode_config(cursor, base64::URL_SAFE).ok()?;
+    let cursor = String::from_utf8(bytes).ok()?;
+    let mut parts = cursor.splitn(2, '|');
+    let created_at = DateTime::parse_from_rfc3339(parts.next()?).ok()?;
+    let key = parts.next()?;
+
+    Some(Cursor {
+        created_at: created_at.with_timezone(&Utc),
+        key: key.to_string(),
+    })
+}
+
+#[derive(Debug)]
+struct SwapsPage {
+    swaps: Vec<HalResource>,
+    next: Option<Cursor>,
+    prev: Option<Cursor>,
+}
+
 #[allow(clippy::needless_pass_by_value)]
 pub fn get_swaps<T: MetadataStore<SwapId>, S: StateStore<SwapId>>(
     metadata_store: Arc<T>,
     state_store: Arc<S>,
+    params: GetSwapsQueryParams,
 ) -> Result<impl Reply, Rejection> {
-    match handle_get_swaps(metadata_store.as_ref(), state_store.as_ref()) {
-        Ok(swaps) => {
+    match handle_get_swaps(metadata_store.as_ref(), state_store.as_ref(), &params) {
+        Ok(page) => {
             let mut response = HalResource::new("");
-            response.with_resources("swaps", swaps);
+            response.with_resources("swaps", page.swaps);
+            response.with_link("self", params.link(None, None));
+            if let Some(next) = page.next {
+                response.with_link("next", params.link(Some(&next), None));
+            }
+            if let Some(prev) = page.prev {
+                response.with_link("prev", params.link(None, Some(&prev)));
+            }
             Ok(warp::reply::json(&response))
         }
         Err(e) => {
@@ -347,39 +789,86 @@ pub fn get_swaps<T: MetadataStore<SwapId>, S: StateStore<SwapId>>(
 fn handle_get_swaps<T: MetadataStore<SwapId>, S: StateStore<SwapId>>(
     metadata_store: &T,
     state_store: &S,
-) -> Result<Vec<HalResource>, HttpApiProblem> {
-    let mut resources = vec![];
-    for (id, metadata) in metadata_store.all()?.into_iter() {
-        with_swap_types!(
-            &metadata,
-            (|| -> Result<(), HttpApiProblem> {
-                let state = state_store.get::<Role>(&id)?;
-
-                match state {
-                    Some(state) => {
-                        let swap = EmbeddedSwapResource {
-                            state: state.name(),
-                            protocol: PROTOCOL_NAME.into(),
-                        };
-
-                        let mut hal_resource = HalResource::new(swap);
-                        hal_resource.with_link("self", swap_path(id));
-                        resources.push(hal_resource);
+    params: &GetSwapsQueryParams,
+) -> Result<SwapsPage, HttpApiProblem> {
+    let query = params.to_query()?;
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+
+    // One more than asked for tells whether there is another page
+    let query = Query {
+        limit: Some(limit.saturating_add(1)),
+        ..query
+    };
+
+    let records = match params.has_pending_action {
+        None => metadata_store.query(&query)?,
+        // Actions are only known to the state store, so the limit can only
+        // be applied once they have been looked at
+        Some(has_pending_action) => {
+            let mut records = Vec::new();
+            for (id, record) in metadata_store.query(&Query {
+                limit: None,
+                ..query.clone()
+            })? {
+                match swap_state_update(metadata_store, state_store, id)? {
+                    Some(update) => {
+                        if update.actions.is_empty() != has_pending_action {
+                            records.push((id, record));
+                        }
                     }
-                    None => error!("Couldn't find state for {} despite having the metadata", id),
-                };
-                Ok(())
-            })
-        )?;
-    }
+                    None => warn!("Swap {} has no state, leaving it out", id),
+                }
+            }
+            query.page(records)
+        }
+    };
+
+    let has_more = records.len() > limit;
+    let surplus = records.len().saturating_sub(limit);
+    let records: Vec<_> = if query.pages_backwards() {
+        records.into_iter().skip(surplus).collect()
+    } else {
+        records.into_iter().take(limit).collect()
+    };
+
+    let (has_next, has_prev) = if query.pages_backwards() {
+        (true, has_more)
+    } else {
+        (has_more || query.before.is_some(), query.after.is_some())
+    };
+    let next = records
+        .last()
+        .filter(|_| has_next)
+        .map(|(id, record)| Cursor::of(id, record));
+    let prev = records
+        .first()
+        .filter(|_| has_prev)
+        .map(|(id, record)| Cursor::of(id, record));
+
+    let swaps = records
+        .into_iter()
+        .map(|(id, record)| {
+            let swap = EmbeddedSwapResource {
+                state: record.state,
+                protocol: PROTOCOL_NAME.into(),
+            };
+
+            let mut hal_resource = HalResource::new(swap);
+            hal_resource.with_link("self", swap_path(id));
+            hal_resource
+        })
+        .collect();
 
-    Ok(resources)
+    Ok(SwapsPage { swaps, next, prev })
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use crate::seed::Seed;
+    use ethereum_support::ChainId;
+    use futures::{Future, Stream};
     use spectral::prelude::*;
 
     #[test]
@@ -390,7 +879,8 @@ mod tests {
                     "network": "regtest"
                 },
                 "beta_ledger": {
-                    "name": "Ethereum"
+                    "name": "Ethereum",
+                    "chain_id": 17
                 },
                 "alpha_asset": {
                     "name": "Bitcoin",
@@ -402,7 +892,8 @@ mod tests {
                 },
                 "alpha_ledger_refund_identity": null,
                 "beta_ledger_redeem_identity": "0x00a329c0648769a73afac7f9381e08fb43dbea72",
-                "alpha_ledger_lock_duration": 144
+                "alpha_ledger_lock_duration": 144,
+                "peer": "127.0.0.1:8011"
             }"#;
 
         let body = serde_json::from_str(body);
@@ -412,13 +903,266 @@ mod tests {
             beta_asset: EtherQuantity::from_eth(10.0),
             alpha_ledger: Bitcoin::regtest(),
             beta_ledger: Ethereum::default(),
-            alpha_ledger_lock_duration: bitcoin_support::Blocks::new(144),
+            alpha_ledger_lock_duration: bitcoin_support::Blocks::new(144).into(),
             identities: SwapRequestBodyIdentities::OnlyRedeem {
                 beta_ledger_redeem_identity: ethereum_support::Address::from(
                     "0x00a329c0648769a73afac7f9381e08fb43dbea72",
                 ),
             },
+            peer: String::from("127.0.0.1:8011"),
         })
     }
 
+    #[test]
+    fn can_deserialize_ethereum_bitcoin_swap_request_body() {
+        let body = r#"{
+                "alpha_ledger": {
+                    "name": "Ethereum",
+                    "chain_id": 17
+                },
+                "beta_ledger": {
+                    "name": "Bitcoin",
+                    "network": "regtest"
+                },
+                "alpha_asset": {
+                    "name": "Ether",
+                    "quantity": "10000000000000000000"
+                },
+                "beta_asset": {
+                    "name": "Bitcoin",
+                    "quantity": "100000000"
+                },
+                "alpha_ledger_refund_identity": "0x00a329c0648769a73afac7f9381e08fb43dbea72",
+                "alpha_ledger_lock_duration": 86400,
+                "peer": "127.0.0.1:8011"
+            }"#;
+
+        let body = serde_json::from_str(body);
+
+        assert_that(&body).is_ok_containing(
+            SwapRequestBodyKind::EthereumBitcoinEtherQuantityBitcoinQuantity(SwapRequestBody {
+                alpha_asset: EtherQuantity::from_eth(10.0),
+                beta_asset: BitcoinQuantity::from_bitcoin(1.0),
+                alpha_ledger: Ethereum::default(),
+                beta_ledger: Bitcoin::regtest(),
+                alpha_ledger_lock_duration: rfc003::ethereum::Seconds(86400),
+                identities: SwapRequestBodyIdentities::OnlyRefund {
+                    alpha_ledger_refund_identity: ethereum_support::Address::from(
+                        "0x00a329c0648769a73afac7f9381e08fb43dbea72",
+                    ),
+                },
+                peer: String::from("127.0.0.1:8011"),
+            }),
+        )
+    }
+
+    #[test]
+    fn can_deserialize_ether_erc20_swap_request_body() {
+        let body = r#"{
+                "alpha_ledger": {
+                    "name": "Ethereum",
+                    "chain_id": 17
+                },
+                "beta_ledger": {
+                    "name": "Ethereum",
+                    "chain_id": 17
+                },
+                "alpha_asset": {
+                    "name": "Ether",
+                    "quantity": "10000000000000000000"
+                },
+                "beta_asset": {
+                    "name": "ERC20",
+                    "quantity": "1000",
+                    "token_contract": "0xb97048628db6b661d4c2aa833e95dbe1a905b280"
+                },
+                "alpha_ledger_refund_identity": "0x00a329c0648769a73afac7f9381e08fb43dbea72",
+                "beta_ledger_redeem_identity": "0x00a329c0648769a73afac7f9381e08fb43dbea73",
+                "alpha_ledger_lock_duration": 86400,
+                "peer": "127.0.0.1:8011"
+            }"#;
+
+        let body = serde_json::from_str(body);
+
+        assert_that(&body).is_ok_containing(
+            SwapRequestBodyKind::EthereumEthereumEtherQuantityErc20Quantity(SwapRequestBody {
+                alpha_asset: EtherQuantity::from_eth(10.0),
+                beta_asset: Erc20Quantity::new(
+                    ethereum_support::Address::from("0xb97048628db6b661d4c2aa833e95dbe1a905b280"),
+                    ethereum_support::U256::from(1000),
+                ),
+                alpha_ledger: Ethereum::default(),
+                beta_ledger: Ethereum::default(),
+                alpha_ledger_lock_duration: rfc003::ethereum::Seconds(86400),
+                identities: SwapRequestBodyIdentities::RefundAndRedeem {
+                    alpha_ledger_refund_identity: ethereum_support::Address::from(
+                        "0x00a329c0648769a73afac7f9381e08fb43dbea72",
+                    ),
+                    beta_ledger_redeem_identity: ethereum_support::Address::from(
+                        "0x00a329c0648769a73afac7f9381e08fb43dbea73",
+                    ),
+                },
+                peer: String::from("127.0.0.1:8011"),
+            }),
+        )
+    }
+
+    #[test]
+    fn rejects_swap_request_on_other_ethereum_chain() {
+        let body = r#"{
+                "alpha_ledger": {
+                    "name": "Bitcoin",
+                    "network": "regtest"
+                },
+                "beta_ledger": {
+                    "name": "Ethereum",
+                    "chain_id": 1
+                },
+                "alpha_asset": {
+                    "name": "Bitcoin",
+                    "quantity": "100000000"
+                },
+                "beta_asset": {
+                    "name": "Ether",
+                    "quantity": "10000000000000000000"
+                },
+                "beta_ledger_redeem_identity": "0x00a329c0648769a73afac7f9381e08fb43dbea72",
+                "alpha_ledger_lock_duration": 144,
+                "peer": "127.0.0.1:8011"
+            }"#;
+        let (sender, _receiver) = futures::sync::mpsc::unbounded();
+        let seed = Seed::from(*b"hello world, you are beautiful!!");
+        let networks = Networks {
+            ethereum_chain_id: ChainId::regtest(),
+        };
+
+        let result = handle_post_swap(
+            &seed,
+            &sender,
+            networks,
+            &PeerRegistry::default(),
+            serde_json::from_str(body).unwrap(),
+        );
+
+        assert_that(&result.map(|_| ()).map_err(|problem| problem.title))
+            .is_err_containing(String::from("network-not-supported"));
+    }
+
+    #[test]
+    fn swap_request_is_sent_to_peer_from_registry() {
+        let body = |peer: &str| {
+            json!({
+                "alpha_ledger": {
+                    "name": "Bitcoin",
+                    "network": "regtest"
+                },
+                "beta_ledger": {
+                    "name": "Ethereum",
+                    "chain_id": 17
+                },
+                "alpha_asset": {
+                    "name": "Bitcoin",
+                    "quantity": "100000000"
+                },
+                "beta_asset": {
+                    "name": "Ether",
+                    "quantity": "10000000000000000000"
+                },
+                "beta_ledger_redeem_identity": "0x00a329c0648769a73afac7f9381e08fb43dbea72",
+                "alpha_ledger_lock_duration": 144,
+                "peer": peer
+            })
+        };
+        let (sender, receiver) = futures::sync::mpsc::unbounded();
+        let seed = Seed::from(*b"hello world, you are beautiful!!");
+        let networks = Networks {
+            ethereum_chain_id: ChainId::regtest(),
+        };
+        let bob = Peer::new(
+            String::from("bob"),
+            vec!["127.0.0.1:8011".parse().unwrap()],
+            None,
+        );
+        let peers = PeerRegistry::new(vec![bob.clone()]);
+
+        let unknown = handle_post_swap(
+            &seed,
+            &sender,
+            networks,
+            &peers,
+            serde_json::from_value(body("carol")).unwrap(),
+        );
+        assert_that(&unknown.map(|_| ()).map_err(|problem| problem.title))
+            .is_err_containing(String::from("unknown-peer"));
+
+        let SwapCreated { id } = handle_post_swap(
+            &seed,
+            &sender,
+            networks,
+            &peers,
+            serde_json::from_value(body("bob")).unwrap(),
+        )
+        .unwrap();
+        drop(sender);
+        let requests: Vec<_> = receiver
+            .map(|(id, peer, _)| (id, peer))
+            .collect()
+            .wait()
+            .unwrap();
+        assert_that(&requests).is_equal_to(vec![(id, bob)]);
+    }
+
+    #[test]
+    fn get_swaps_query_params_are_parsed_into_query() {
+        let params: GetSwapsQueryParams = serde_urlencoded::from_str(
+            "role=Bob&alpha_ledger=Bitcoin&beta_asset=Erc20&created_after=2019-01-01T00:00:00Z&order=desc&limit=10",
+        )
+        .unwrap();
+
+        let query = params.to_query().unwrap();
+
+        assert_that(&query).is_equal_to(Query {
+            role: Some(RoleKind::Bob),
+            alpha_ledger: Some(LedgerKind::Bitcoin),
+            beta_asset: Some(AssetKind::Erc20),
+            created_after: Some("2019-01-01T00:00:00Z".parse().unwrap()),
+            order: Order::NewestFirst,
+            limit: Some(10),
+            ..Query::default()
+        });
+    }
+
+    #[test]
+    fn get_swaps_limit_is_capped() {
+        let params: GetSwapsQueryParams =
+            serde_urlencoded::from_str(&format!("limit={}", usize::max_value())).unwrap();
+
+        assert_that(&params.to_query().unwrap().limit).is_equal_to(Some(MAX_PAGE_SIZE));
+    }
+
+    #[test]
+    fn get_swaps_rejects_unknown_role() {
+        let params: GetSwapsQueryParams = serde_urlencoded::from_str("role=Carol").unwrap();
+
+        assert_that(&params.to_query().map_err(|problem| problem.title))
+            .is_err_containing(String::from("invalid-query-parameter"));
+    }
+
+    #[test]
+    fn next_link_keeps_filters_and_points_after_cursor() {
+        let params: GetSwapsQueryParams =
+            serde_urlencoded::from_str("state=Accepted&limit=2").unwrap();
+        let cursor = Cursor {
+            created_at: "2019-01-01T00:00:00.123Z".parse().unwrap(),
+            key: SwapId::default().to_string(),
+        };
+
+        let link = params.link(Some(&cursor), None);
+        let next: GetSwapsQueryParams =
+            serde_urlencoded::from_str(link.trim_start_matches("/swaps?")).unwrap();
+
+        assert_that(&next.state).is_equal_to(Some(String::from("Accepted")));
+        assert_that(&next.limit).is_equal_to(Some(2));
+        assert_that(&next.to_query().unwrap().after).is_equal_to(Some(cursor));
+    }
 }
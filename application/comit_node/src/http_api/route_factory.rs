@@ -1,20 +1,28 @@
 use crate::{
     http_api::{self, rfc003::action::GetActionQueryParams},
+    peers::{Peer, PeerRegistry},
     seed::Seed,
     swap_protocols::{
+        ledger::Networks,
         rfc003::{self, state_store, SecretSource},
         MetadataStore, SwapId,
     },
+    webhooks::Deliveries,
 };
 use futures::sync::mpsc::UnboundedSender;
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 use warp::{self, filters::BoxedFilter, Filter, Reply};
 
+#[allow(clippy::too_many_arguments)]
 pub fn create<T: MetadataStore<SwapId>, S: state_store::StateStore<SwapId>>(
     metadata_store: Arc<T>,
     state_store: Arc<S>,
-    sender: UnboundedSender<(SwapId, rfc003::alice::SwapRequestKind)>,
+    sender: UnboundedSender<(SwapId, Peer, rfc003::alice::SwapRequestKind)>,
     seed: Seed,
+    networks: Networks,
+    peers: Arc<PeerRegistry>,
+    lock_duration_margin: Duration,
+    webhook_deliveries: Arc<Deliveries>,
 ) -> BoxedFilter<(impl Reply,)> {
     let seed = Arc::new(seed);
     let path = warp::path(http_api::PATH);
@@ -23,13 +31,19 @@ pub fn create<T: MetadataStore<SwapId>, S: state_store::StateStore<SwapId>>(
     let rfc003_secret_gen = warp::any().map(move || seed.clone() as Arc<dyn SecretSource>);
     let state_store = warp::any().map(move || state_store.clone());
     let sender = warp::any().map(move || sender.clone());
+    let networks = warp::any().map(move || networks);
+    let peers = warp::any().map(move || peers.clone());
+    let lock_duration_margin = warp::any().map(move || lock_duration_margin);
     let empty_json_body = warp::any().map(|| json!({}));
+    let webhook_deliveries = warp::any().map(move || webhook_deliveries.clone());
 
     let rfc003_post_swap = rfc003
         .and(warp::path::end())
         .and(warp::post2())
         .and(rfc003_secret_gen.clone())
-        .and(sender)
+        .and(sender.clone())
+        .and(networks)
+        .and(peers.clone())
         .and(warp::body::json())
         .and_then(http_api::rfc003::swap::post_swap);
 
@@ -41,17 +55,51 @@ pub fn create<T: MetadataStore<SwapId>, S: state_store::StateStore<SwapId>>(
         .and(warp::path::end())
         .and_then(http_api::rfc003::swap::get_swap);
 
+    let rfc003_get_swap_history = rfc003
+        .and(warp::get2())
+        .and(metadata_store.clone())
+        .and(warp::path::param::<SwapId>())
+        .and(warp::path("history"))
+        .and(warp::path::end())
+        .and_then(http_api::rfc003::swap::get_swap_history);
+
+    let rfc003_stream_swap = rfc003
+        .and(warp::get2())
+        .and(metadata_store.clone())
+        .and(state_store.clone())
+        .and(warp::path::param::<SwapId>())
+        .and(warp::path("stream"))
+        .and(warp::path::end())
+        .and(warp::ws2())
+        .and_then(http_api::rfc003::stream::stream_swap);
+
+    let stream_swaps = path
+        .and(warp::get2())
+        .and(warp::path("stream"))
+        .and(warp::path::end())
+        .and(metadata_store.clone())
+        .and(state_store.clone())
+        .and(warp::ws2())
+        .map(http_api::rfc003::stream::stream_swaps);
+
     let get_swaps = path
         .and(warp::get2())
         .and(warp::path::end())
         .and(metadata_store.clone())
         .and(state_store.clone())
+        .and(
+            warp::query::<http_api::rfc003::swap::GetSwapsQueryParams>()
+                .or(warp::any().map(http_api::rfc003::swap::GetSwapsQueryParams::default))
+                .unify(),
+        )
         .and_then(http_api::rfc003::swap::get_swaps);
 
     let rfc003_post_action = rfc003
         .and(metadata_store.clone())
         .and(state_store.clone())
         .and(rfc003_secret_gen.clone())
+        .and(lock_duration_margin)
+        .and(sender)
         .and(warp::path::param::<SwapId>())
         .and(warp::path::param::<http_api::rfc003::action::PostAction>())
         .and(warp::post2())
@@ -69,11 +117,56 @@ pub fn create<T: MetadataStore<SwapId>, S: state_store::StateStore<SwapId>>(
         .and(warp::path::end())
         .and_then(http_api::rfc003::action::get);
 
+    let get_webhook_deliveries = warp::path(http_api::webhooks::PATH)
+        .and(warp::path("deliveries"))
+        .and(warp::path::end())
+        .and(warp::get2())
+        .and(webhook_deliveries)
+        .map(http_api::webhooks::get_deliveries);
+
+    let peers_path = warp::path(http_api::peers::PATH);
+
+    let get_peers = peers_path
+        .and(warp::path::end())
+        .and(warp::get2())
+        .and(peers.clone())
+        .map(http_api::peers::get_peers);
+
+    let get_peer = peers_path
+        .and(warp::get2())
+        .and(peers.clone())
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and_then(http_api::peers::get_peer);
+
+    let put_peer = peers_path
+        .and(warp::put2())
+        .and(peers.clone())
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and_then(http_api::peers::put_peer);
+
+    let delete_peer = peers_path
+        .and(warp::delete2())
+        .and(peers)
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and_then(http_api::peers::delete_peer);
+
     rfc003_get_swap
         .or(rfc003_post_swap)
         .or(rfc003_post_action)
         .or(rfc003_get_action)
+        .or(rfc003_get_swap_history)
+        .or(rfc003_stream_swap)
         .or(get_swaps)
+        .or(stream_swaps)
+        .or(get_webhook_deliveries)
+        .or(get_peers)
+        .or(get_peer)
+        .or(put_peer)
+        .or(delete_peer)
         .with(warp::log("http"))
         .recover(http_api::unpack_problem)
         .boxed()
@@ -0,0 +1,17 @@
+use crate::webhooks::{Deliveries, Delivery};
+use std::sync::Arc;
+use warp::{self, Reply};
+
+pub const PATH: &str = "webhooks";
+
+#[derive(Debug, Serialize)]
+struct DeliveriesResource {
+    deliveries: Vec<Delivery>,
+}
+
+#[allow(clippy::needless_pass_by_value)]
+pub fn get_deliveries(deliveries: Arc<Deliveries>) -> impl Reply {
+    warp::reply::json(&DeliveriesResource {
+        deliveries: deliveries.all(),
+    })
+}
@@ -11,6 +11,8 @@ pub enum BitcoinQuery {
     },
     Block {
         min_height: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        min_timestamp_secs: Option<u32>,
     },
 }
 
@@ -61,11 +63,25 @@ mod tests {
     fn given_a_bitcoin_block_query_with_min_height_it_serializes_ok() {
         let query = BitcoinQuery::Block {
             min_height: Some(42),
+            min_timestamp_secs: None,
         };
         let query = serde_json::to_string(&query).unwrap();
         assert_eq!(query, r#"{"min_height":42}"#)
     }
 
+    #[test]
+    fn given_a_bitcoin_block_query_with_min_timestamp_it_serializes_ok() {
+        let query = BitcoinQuery::Block {
+            min_height: None,
+            min_timestamp_secs: Some(1_550_000_000),
+        };
+        let query = serde_json::to_string(&query).unwrap();
+        assert_eq!(
+            query,
+            r#"{"min_height":null,"min_timestamp_secs":1550000000}"#
+        )
+    }
+
     #[test]
     fn given_a_bitcoin_transaction_query_with_from_outpoint_it_serializes_ok() {
         let to_address = None;
@@ -1,7 +1,8 @@
 use crate::{
     ledger_query_service::{
         bitcoin::BitcoinQuery, ethereum::EthereumQuery, CreateQuery, Error, FetchFullQueryResults,
-        FetchQueryResults, LedgerQueryServiceApiClient, Query, QueryId,
+        FetchInvalidatedQueryResults, FetchLatestBlockHeight, FetchQueryResults,
+        LedgerQueryServiceApiClient, Query, QueryId,
     },
     swap_protocols::ledger::{Bitcoin, Ethereum, Ledger},
 };
@@ -17,6 +18,7 @@ pub struct DefaultLedgerQueryServiceApiClient {
     create_bitcoin_block_query_endpoint: Url,
     create_ethereum_transaction_query_endpoint: Url,
     create_ethereum_block_query_endpoint: Url,
+    latest_bitcoin_block_endpoint: Url,
 }
 
 #[derive(Debug, Deserialize)]
@@ -24,6 +26,17 @@ pub struct QueryResponse<T> {
     matches: Vec<T>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct LatestBlockResponse {
+    height: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InvalidatedQueryResponse<T> {
+    #[serde(default)]
+    invalidated_matches: Vec<T>,
+}
+
 impl DefaultLedgerQueryServiceApiClient {
     pub fn new(endpoint: &Url) -> Self {
         DefaultLedgerQueryServiceApiClient {
@@ -40,6 +53,9 @@ impl DefaultLedgerQueryServiceApiClient {
             create_ethereum_block_query_endpoint: endpoint
                 .join("queries/ethereum/blocks")
                 .expect("invalid url"),
+            latest_bitcoin_block_endpoint: endpoint
+                .join("blocks/bitcoin/latest")
+                .expect("invalid url"),
         }
     }
 
@@ -126,6 +142,27 @@ impl DefaultLedgerQueryServiceApiClient {
         Box::new(transactions)
     }
 
+    fn fetch_invalidated_results<L: Ledger>(
+        &self,
+        query: &QueryId<L>,
+    ) -> Box<dyn Future<Item = Vec<L::TxId>, Error = Error> + Send> {
+        let url = query.as_ref().clone();
+        let transactions = self
+            .client
+            .get(url.clone())
+            .send()
+            .and_then(|mut response| response.json::<InvalidatedQueryResponse<L::TxId>>())
+            .map_err(move |e| {
+                Error::FailedRequest(format!(
+                    "Failed to fetch invalidated results for {:?} because {:?}",
+                    url, e
+                ))
+            })
+            .map(|response| response.invalidated_matches);
+
+        Box::new(transactions)
+    }
+
     fn fetch_full_results<L: Ledger>(
         &self,
         query: &QueryId<L>,
@@ -189,6 +226,15 @@ impl FetchQueryResults<Bitcoin> for DefaultLedgerQueryServiceApiClient {
     }
 }
 
+impl FetchInvalidatedQueryResults<Bitcoin> for DefaultLedgerQueryServiceApiClient {
+    fn fetch_invalidated_query_results(
+        &self,
+        query: &QueryId<Bitcoin>,
+    ) -> Box<dyn Future<Item = Vec<<Bitcoin as Ledger>::TxId>, Error = Error> + Send> {
+        self.fetch_invalidated_results(query)
+    }
+}
+
 impl FetchFullQueryResults<Bitcoin> for DefaultLedgerQueryServiceApiClient {
     fn fetch_full_query_results(
         &self,
@@ -198,6 +244,35 @@ impl FetchFullQueryResults<Bitcoin> for DefaultLedgerQueryServiceApiClient {
     }
 }
 
+impl FetchLatestBlockHeight<Bitcoin> for DefaultLedgerQueryServiceApiClient {
+    fn fetch_latest_block_height(
+        &self,
+    ) -> Box<dyn Future<Item = Option<u32>, Error = Error> + Send> {
+        let url = self.latest_bitcoin_block_endpoint.clone();
+        let height = self
+            .client
+            .get(url.clone())
+            .send()
+            .and_then(|mut response| {
+                // Before the first block there is nothing to report, which is not an error
+                if response.status() == StatusCode::NOT_FOUND {
+                    None
+                } else {
+                    Some(response.json::<LatestBlockResponse>())
+                }
+            })
+            .map_err(move |e| {
+                Error::FailedRequest(format!(
+                    "Failed to fetch latest block from {:?} because {:?}",
+                    url, e
+                ))
+            })
+            .map(|latest_block| latest_block.map(|latest_block| latest_block.height));
+
+        Box::new(height)
+    }
+}
+
 impl LedgerQueryServiceApiClient<Bitcoin, BitcoinQuery> for DefaultLedgerQueryServiceApiClient {
     fn delete(&self, query: &QueryId<Bitcoin>) -> Box<dyn Future<Item = (), Error = Error> + Send> {
         self._delete(&query)
@@ -228,6 +303,15 @@ impl FetchQueryResults<Ethereum> for DefaultLedgerQueryServiceApiClient {
     }
 }
 
+impl FetchInvalidatedQueryResults<Ethereum> for DefaultLedgerQueryServiceApiClient {
+    fn fetch_invalidated_query_results(
+        &self,
+        query: &QueryId<Ethereum>,
+    ) -> Box<dyn Future<Item = Vec<<Ethereum as Ledger>::TxId>, Error = Error> + Send> {
+        self.fetch_invalidated_results(query)
+    }
+}
+
 impl FetchFullQueryResults<Ethereum> for DefaultLedgerQueryServiceApiClient {
     fn fetch_full_query_results(
         &self,
@@ -257,4 +341,13 @@ mod test {
 
         let _: QueryResponse<TransactionId> = serde_json::from_str(json).unwrap();
     }
+
+    #[test]
+    fn json_deserialize_without_invalidated_matches() {
+        let json = r#"{"query":{"to_address":"bcrt1qtfd0gvmdhx2uz267a8a3rpm4v55t8nuzgka2f5xzm4e06tg2d2dqxugdz7","confirmations_needed":1},"matches":[]}"#;
+
+        let response: InvalidatedQueryResponse<TransactionId> = serde_json::from_str(json).unwrap();
+
+        assert!(response.invalidated_matches.is_empty());
+    }
 }
@@ -31,7 +31,7 @@ pub trait FetchTransactionStream<L: Ledger> {
 
 impl<L: Ledger, C> FetchTransactionIdStream<L> for Arc<C>
 where
-    C: FetchQueryResults<L>,
+    C: FetchQueryResults<L> + ?Sized,
 {
     fn fetch_transaction_id_stream<
         I,
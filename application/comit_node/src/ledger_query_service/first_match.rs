@@ -1,6 +1,7 @@
 use crate::{
     ledger_query_service::{
-        fetch_transaction_stream::FetchTransactionStream, FetchFullQueryResults, QueryId,
+        fetch_transaction_stream::{FetchTransactionIdStream, FetchTransactionStream},
+        FetchFullQueryResults, QueryId,
     },
     swap_protocols::ledger::Ledger,
 };
@@ -41,4 +42,110 @@ impl<L: Ledger> FirstMatch<L> {
             .map(|(txid, _)| txid.expect("ticker stream should never terminate"))
             .map_err(|(e, _)| panic!("timer should never error but {:?}", e))
     }
+
+    /// Resolves once the query matched `n` different results.
+    ///
+    /// The results are not expanded, which makes this usable for queries
+    /// whose results are not transactions, like block queries.
+    pub fn nth_match_id_of<E>(
+        &self,
+        query_id: QueryId<L>,
+        n: u32,
+    ) -> impl Future<Item = L::TxId, Error = E> {
+        self.fetch_results
+            .fetch_transaction_id_stream(
+                Interval::new(Instant::now(), self.poll_interval),
+                query_id,
+            )
+            .skip(u64::from(n.saturating_sub(1)))
+            .take(1)
+            .into_future()
+            .map(|(id, _)| id.expect("ticker stream should never terminate"))
+            .map_err(|(e, _)| panic!("timer should never error but {:?}", e))
+    }
+
+    /// Resolves once all results the query matched got invalidated by a
+    /// chain reorganisation, i.e. once what the query is looking for is no
+    /// longer part of the ledger.
+    pub fn invalidated_match_id_of<E>(
+        &self,
+        query_id: QueryId<L>,
+    ) -> impl Future<Item = L::TxId, Error = E> {
+        self.results_of(query_id)
+            .filter_map(|(matches, invalidated)| {
+                if matches.is_empty() {
+                    invalidated.into_iter().next()
+                } else {
+                    None
+                }
+            })
+            .take(1)
+            .into_future()
+            .map(|(id, _)| id.expect("ticker stream should never terminate"))
+            .map_err(|(e, _)| e)
+    }
+
+    /// Reports every time all results the query matched got invalidated by a
+    /// chain reorganisation and what happened afterwards.
+    pub fn reverts_of<E>(&self, query_id: QueryId<L>) -> impl Stream<Item = Revert<L>, Error = E> {
+        let mut invalidated_match = None;
+
+        self.results_of(query_id)
+            .filter_map(
+                move |(matches, invalidated)| match invalidated_match.take() {
+                    None => {
+                        if !matches.is_empty() {
+                            return None;
+                        }
+                        let id = invalidated.into_iter().next()?;
+                        invalidated_match = Some(id.clone());
+
+                        Some(Revert::Invalidated(id))
+                    }
+                    Some(id) => {
+                        if matches.contains(&id) {
+                            Some(Revert::Reconfirmed(id))
+                        } else if !matches.is_empty() {
+                            Some(Revert::Replaced(id))
+                        } else {
+                            invalidated_match = Some(id);
+                            None
+                        }
+                    }
+                },
+            )
+    }
+
+    fn results_of<E>(
+        &self,
+        query_id: QueryId<L>,
+    ) -> impl Stream<Item = (Vec<L::TxId>, Vec<L::TxId>), Error = E> {
+        let fetch_results = Arc::clone(&self.fetch_results);
+
+        Interval::new(Instant::now(), self.poll_interval)
+            .map_err(|e| panic!("timer should never error but {:?}", e))
+            .and_then(move |_| {
+                fetch_results
+                    .fetch_query_results(&query_id)
+                    .join(fetch_results.fetch_invalidated_query_results(&query_id))
+                    .map(Some)
+                    .or_else(|e| {
+                        warn!("Skipping check for invalidated results because {:?}", e);
+                        Ok(None)
+                    })
+            })
+            .filter_map(|results| results)
+    }
+}
+
+/// What happened to the results of a query because of a chain reorganisation
+#[derive(Clone, Debug, PartialEq)]
+pub enum Revert<L: Ledger> {
+    /// All results got invalidated, the id being one of them
+    Invalidated(L::TxId),
+    /// The invalidated result is part of the ledger again
+    Reconfirmed(L::TxId),
+    /// The query matches something else than the invalidated result now, so
+    /// the invalidated one is not coming back
+    Replaced(L::TxId),
 }
@@ -34,7 +34,7 @@ impl<L: Ledger> QueryId<L> {
     }
 }
 
-#[derive(Fail, Debug, PartialEq, Clone)]
+#[derive(Fail, Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Error {
     #[fail(display = "The request failed to send.")]
     FailedRequest(String),
@@ -64,7 +64,26 @@ pub trait FetchQueryResults<L: Ledger>: 'static + Send + Sync {
     ) -> Box<dyn Future<Item = Vec<L::TxId>, Error = Error> + Send>;
 }
 
-pub trait FetchFullQueryResults<L: Ledger>: 'static + Send + Sync + Debug {
+/// Results the query matched before but which are no longer part of the
+/// ledger because of a chain reorganisation
+pub trait FetchInvalidatedQueryResults<L: Ledger>: 'static + Send + Sync {
+    fn fetch_invalidated_query_results(
+        &self,
+        query: &QueryId<L>,
+    ) -> Box<dyn Future<Item = Vec<L::TxId>, Error = Error> + Send>;
+}
+
+/// The height of the block the ledger query service processed last or `None`
+/// if it hasn't seen one yet
+pub trait FetchLatestBlockHeight<L: Ledger>: 'static + Send + Sync {
+    fn fetch_latest_block_height(
+        &self,
+    ) -> Box<dyn Future<Item = Option<u32>, Error = Error> + Send>;
+}
+
+pub trait FetchFullQueryResults<L: Ledger>:
+    'static + Send + Sync + Debug + FetchQueryResults<L> + FetchInvalidatedQueryResults<L>
+{
     fn fetch_full_query_results(
         &self,
         query: &QueryId<L>,
@@ -31,6 +31,10 @@ pub mod http_api;
 pub mod item_cache;
 pub mod ledger_query_service;
 pub mod logging;
+pub mod noise;
+pub mod peers;
+pub mod price_feed;
 pub mod seed;
 pub mod settings;
 pub mod swap_protocols;
+pub mod webhooks;
@@ -0,0 +1,154 @@
+use crate::noise::{
+    read_length, symmetric_state::TAG_LENGTH, write_length, CipherState, Session,
+    MAX_MESSAGE_LENGTH,
+};
+use bytes::BytesMut;
+use std::io;
+use tokio_codec::{Decoder, Encoder};
+
+const MAX_PLAINTEXT_LENGTH: usize = MAX_MESSAGE_LENGTH - TAG_LENGTH;
+
+/// Encrypts what the inner codec encodes and decrypts what it decodes
+///
+/// The encoded bytes are sent as length-prefixed messages of at most
+/// `MAX_MESSAGE_LENGTH` bytes, a frame of the inner codec can span several
+/// messages.
+#[derive(Debug)]
+pub struct NoiseCodec<C> {
+    inner: C,
+    sender: CipherState,
+    receiver: CipherState,
+    plaintext: BytesMut,
+}
+
+impl<C> NoiseCodec<C> {
+    pub fn new(inner: C, session: Session) -> Self {
+        NoiseCodec {
+            inner,
+            sender: session.sender,
+            receiver: session.receiver,
+            plaintext: BytesMut::new(),
+        }
+    }
+}
+
+impl<C: Encoder> Encoder for NoiseCodec<C>
+where
+    C::Error: From<io::Error>,
+{
+    type Item = C::Item;
+    type Error = C::Error;
+
+    fn encode(&mut self, item: C::Item, dst: &mut BytesMut) -> Result<(), C::Error> {
+        let mut plaintext = BytesMut::new();
+        self.inner.encode(item, &mut plaintext)?;
+
+        for chunk in plaintext.chunks(MAX_PLAINTEXT_LENGTH) {
+            let message = self
+                .sender
+                .encrypt_with_ad(&[], chunk)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+
+            dst.extend_from_slice(&write_length(message.len()));
+            dst.extend_from_slice(&message);
+        }
+
+        Ok(())
+    }
+}
+
+impl<C: Decoder> Decoder for NoiseCodec<C>
+where
+    C::Error: From<io::Error>,
+{
+    type Item = C::Item;
+    type Error = C::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<C::Item>, C::Error> {
+        loop {
+            if let Some(item) = self.inner.decode(&mut self.plaintext)? {
+                return Ok(Some(item));
+            }
+
+            if src.len() < 2 {
+                return Ok(None);
+            }
+            let length = read_length([src[0], src[1]]);
+            if src.len() < 2 + length {
+                return Ok(None);
+            }
+
+            src.advance(2);
+            let message = src.split_to(length);
+            let plaintext = self
+                .receiver
+                .decrypt_with_ad(&[], &message)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+            self.plaintext.extend_from_slice(&plaintext);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::noise::Handshake;
+    use bam::json::{Frame, JsonFrameCodec};
+    use secp256k1_support::KeyPair;
+    use spectral::prelude::*;
+
+    fn codecs() -> (NoiseCodec<JsonFrameCodec>, NoiseCodec<JsonFrameCodec>) {
+        let key_pair = |byte| KeyPair::from_secret_key_slice(&[byte; 32]).unwrap();
+        let mut alice = Handshake::initiator(key_pair(1), key_pair(2));
+        let mut bob = Handshake::responder(key_pair(3), key_pair(4));
+
+        bob.read_first_message(&alice.first_message().unwrap())
+            .unwrap();
+        alice
+            .read_second_message(&bob.second_message().unwrap())
+            .unwrap();
+        bob.read_third_message(&alice.third_message().unwrap())
+            .unwrap();
+
+        (
+            NoiseCodec::new(JsonFrameCodec::default(), alice.into_session().unwrap()),
+            NoiseCodec::new(JsonFrameCodec::default(), bob.into_session().unwrap()),
+        )
+    }
+
+    fn frame(payload: serde_json::Value) -> Frame {
+        Frame::new("REQUEST".into(), 0, payload)
+    }
+
+    #[test]
+    fn frames_are_sent_encrypted() {
+        let (mut alice, mut bob) = codecs();
+
+        let mut bytes = BytesMut::new();
+        alice.encode(frame(json!("SWAP")), &mut bytes).unwrap();
+
+        assert_that(&bytes.windows(4).any(|window| window == b"SWAP")).is_false();
+        assert_that(&bob.decode(&mut bytes))
+            .is_ok()
+            .is_some()
+            .is_equal_to(&frame(json!("SWAP")));
+    }
+
+    #[test]
+    fn frames_larger_than_a_message_are_split() {
+        let (mut alice, mut bob) = codecs();
+        let payload = json!("a".repeat(2 * MAX_MESSAGE_LENGTH));
+
+        let mut bytes = BytesMut::new();
+        alice.encode(frame(payload.clone()), &mut bytes).unwrap();
+        let mut first_message = bytes.split_to(2 + MAX_MESSAGE_LENGTH);
+
+        assert_that(&bob.decode(&mut first_message))
+            .is_ok()
+            .is_none();
+        assert_that(&bob.decode(&mut bytes))
+            .is_ok()
+            .is_some()
+            .is_equal_to(&frame(payload));
+    }
+}
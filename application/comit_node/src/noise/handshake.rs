@@ -0,0 +1,278 @@
+use crate::noise::{
+    symmetric_state::{CipherState, SymmetricState, TAG_LENGTH},
+    Error,
+};
+use secp256k1_support::{KeyPair, PublicKey, PUBLIC_KEY_SIZE};
+
+const PROTOCOL_NAME: &str = "Noise_XX_secp256k1_ChaChaPoly_SHA256";
+const ENCRYPTED_PUBLIC_KEY_SIZE: usize = PUBLIC_KEY_SIZE + TAG_LENGTH;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Role {
+    Initiator,
+    Responder,
+}
+
+/// An encrypted channel to a peer that proved to own `remote_identity`
+#[derive(Debug)]
+pub struct Session {
+    pub remote_identity: PublicKey,
+    pub sender: CipherState,
+    pub receiver: CipherState,
+}
+
+/// The XX handshake pattern, both parties send their identity encrypted:
+///
+/// ```text
+/// -> e
+/// <- e, ee, s, es
+/// -> s, se
+/// ```
+///
+/// The payloads of all messages are empty.
+#[derive(DebugStub)]
+pub struct Handshake {
+    role: Role,
+    symmetric_state: SymmetricState,
+    #[debug_stub = "KeyPair"]
+    identity: KeyPair,
+    #[debug_stub = "KeyPair"]
+    ephemeral: KeyPair,
+    remote_ephemeral: Option<PublicKey>,
+    remote_identity: Option<PublicKey>,
+}
+
+impl Handshake {
+    pub fn initiator(identity: KeyPair, ephemeral: KeyPair) -> Self {
+        Self::new(Role::Initiator, identity, ephemeral)
+    }
+
+    pub fn responder(identity: KeyPair, ephemeral: KeyPair) -> Self {
+        Self::new(Role::Responder, identity, ephemeral)
+    }
+
+    fn new(role: Role, identity: KeyPair, ephemeral: KeyPair) -> Self {
+        let mut symmetric_state = SymmetricState::new(PROTOCOL_NAME);
+        // Empty prologue
+        symmetric_state.mix_hash(&[]);
+
+        Handshake {
+            role,
+            symmetric_state,
+            identity,
+            ephemeral,
+            remote_ephemeral: None,
+            remote_identity: None,
+        }
+    }
+
+    /// -> e
+    pub fn first_message(&mut self) -> Result<Vec<u8>, Error> {
+        let mut message = self.write_ephemeral();
+        message.extend(self.symmetric_state.encrypt_and_hash(&[])?);
+        Ok(message)
+    }
+
+    pub fn read_first_message(&mut self, message: &[u8]) -> Result<(), Error> {
+        expect_length(message, PUBLIC_KEY_SIZE)?;
+
+        self.read_ephemeral(message)?;
+        self.symmetric_state
+            .decrypt_and_hash(&message[PUBLIC_KEY_SIZE..])?;
+        Ok(())
+    }
+
+    /// <- e, ee, s, es
+    pub fn second_message(&mut self) -> Result<Vec<u8>, Error> {
+        let remote_ephemeral = self.remote_ephemeral.ok_or(Error::UnexpectedMessage)?;
+
+        let mut message = self.write_ephemeral();
+        self.symmetric_state
+            .mix_key(&self.ephemeral.ecdh(&remote_ephemeral));
+        message.extend(self.write_identity()?);
+        self.symmetric_state
+            .mix_key(&self.identity.ecdh(&remote_ephemeral));
+        message.extend(self.symmetric_state.encrypt_and_hash(&[])?);
+        Ok(message)
+    }
+
+    pub fn read_second_message(&mut self, message: &[u8]) -> Result<(), Error> {
+        expect_length(
+            message,
+            PUBLIC_KEY_SIZE + ENCRYPTED_PUBLIC_KEY_SIZE + TAG_LENGTH,
+        )?;
+
+        let remote_ephemeral = self.read_ephemeral(message)?;
+        self.symmetric_state
+            .mix_key(&self.ephemeral.ecdh(&remote_ephemeral));
+        let remote_identity = self.read_identity(&message[PUBLIC_KEY_SIZE..])?;
+        self.symmetric_state
+            .mix_key(&self.ephemeral.ecdh(&remote_identity));
+        self.symmetric_state
+            .decrypt_and_hash(&message[PUBLIC_KEY_SIZE + ENCRYPTED_PUBLIC_KEY_SIZE..])?;
+        Ok(())
+    }
+
+    /// -> s, se
+    pub fn third_message(&mut self) -> Result<Vec<u8>, Error> {
+        let remote_ephemeral = self.remote_ephemeral.ok_or(Error::UnexpectedMessage)?;
+
+        let mut message = self.write_identity()?;
+        self.symmetric_state
+            .mix_key(&self.identity.ecdh(&remote_ephemeral));
+        message.extend(self.symmetric_state.encrypt_and_hash(&[])?);
+        Ok(message)
+    }
+
+    pub fn read_third_message(&mut self, message: &[u8]) -> Result<(), Error> {
+        expect_length(message, ENCRYPTED_PUBLIC_KEY_SIZE + TAG_LENGTH)?;
+
+        let remote_identity = self.read_identity(message)?;
+        self.symmetric_state
+            .mix_key(&self.ephemeral.ecdh(&remote_identity));
+        self.symmetric_state
+            .decrypt_and_hash(&message[ENCRYPTED_PUBLIC_KEY_SIZE..])?;
+        Ok(())
+    }
+
+    pub fn into_session(self) -> Result<Session, Error> {
+        let remote_identity = self.remote_identity.ok_or(Error::UnexpectedMessage)?;
+        let (initiator, responder) = self.symmetric_state.split();
+
+        let (sender, receiver) = match self.role {
+            Role::Initiator => (initiator, responder),
+            Role::Responder => (responder, initiator),
+        };
+
+        Ok(Session {
+            remote_identity,
+            sender,
+            receiver,
+        })
+    }
+
+    fn write_ephemeral(&mut self) -> Vec<u8> {
+        let ephemeral = self.ephemeral.public_key().serialize();
+        self.symmetric_state.mix_hash(&ephemeral);
+        ephemeral.to_vec()
+    }
+
+    fn read_ephemeral(&mut self, message: &[u8]) -> Result<PublicKey, Error> {
+        let remote_ephemeral = &message[..PUBLIC_KEY_SIZE];
+        self.symmetric_state.mix_hash(remote_ephemeral);
+
+        let remote_ephemeral =
+            PublicKey::from_slice(remote_ephemeral).map_err(|_| Error::InvalidPublicKey)?;
+        self.remote_ephemeral = Some(remote_ephemeral);
+        Ok(remote_ephemeral)
+    }
+
+    fn write_identity(&mut self) -> Result<Vec<u8>, Error> {
+        self.symmetric_state
+            .encrypt_and_hash(&self.identity.public_key().serialize())
+    }
+
+    fn read_identity(&mut self, message: &[u8]) -> Result<PublicKey, Error> {
+        let remote_identity = self
+            .symmetric_state
+            .decrypt_and_hash(&message[..ENCRYPTED_PUBLIC_KEY_SIZE])?;
+
+        let remote_identity =
+            PublicKey::from_slice(&remote_identity).map_err(|_| Error::InvalidPublicKey)?;
+        self.remote_identity = Some(remote_identity);
+        Ok(remote_identity)
+    }
+}
+
+fn expect_length(message: &[u8], length: usize) -> Result<(), Error> {
+    if message.len() == length {
+        Ok(())
+    } else {
+        Err(Error::UnexpectedMessage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    fn key_pair(byte: u8) -> KeyPair {
+        KeyPair::from_secret_key_slice(&[byte; 32]).unwrap()
+    }
+
+    fn handshake(initiator: &mut Handshake, responder: &mut Handshake) -> Result<(), Error> {
+        let first = initiator.first_message()?;
+        responder.read_first_message(&first)?;
+        let second = responder.second_message()?;
+        initiator.read_second_message(&second)?;
+        let third = initiator.third_message()?;
+        responder.read_third_message(&third)
+    }
+
+    #[test]
+    fn both_parties_learn_the_identity_of_the_other_and_share_keys() {
+        let mut alice = Handshake::initiator(key_pair(1), key_pair(2));
+        let mut bob = Handshake::responder(key_pair(3), key_pair(4));
+
+        assert_that(&handshake(&mut alice, &mut bob)).is_ok();
+
+        let mut alice = alice.into_session().unwrap();
+        let mut bob = bob.into_session().unwrap();
+
+        assert_that(&alice.remote_identity).is_equal_to(key_pair(3).public_key());
+        assert_that(&bob.remote_identity).is_equal_to(key_pair(1).public_key());
+
+        let ciphertext = alice.sender.encrypt_with_ad(&[], b"SWAP").unwrap();
+        assert_that(&bob.receiver.decrypt_with_ad(&[], &ciphertext))
+            .is_ok_containing(b"SWAP".to_vec());
+        let ciphertext = bob.sender.encrypt_with_ad(&[], b"OK").unwrap();
+        assert_that(&alice.receiver.decrypt_with_ad(&[], &ciphertext))
+            .is_ok_containing(b"OK".to_vec());
+    }
+
+    // Worked out separately from the Noise specification, these are the bytes
+    // on the wire with fixed keys
+    #[test]
+    fn messages_match_the_specification() {
+        let mut alice = Handshake::initiator(key_pair(1), key_pair(2));
+        let mut bob = Handshake::responder(key_pair(3), key_pair(4));
+
+        let first = alice.first_message().unwrap();
+        assert_that(&hex::encode(&first)).is_equal_to(
+            "024d4b6cd1361032ca9bd2aeb9d900aa4d45d9ead80ac9423374c451a7254d0766".to_string(),
+        );
+
+        bob.read_first_message(&first).unwrap();
+        let second = bob.second_message().unwrap();
+        assert_that(&hex::encode(&second)).is_equal_to(
+            "03462779ad4aad39514614751a71085f2f10e1c7a593e4e030efb5b8721ce55b0b\
+             33ebcc3d96a597476b63ec31b79e754cc245b341035bb37d2f2c469c22d428d4dc\
+             00093706998c735a075c798620ba3ba4b72164020d64327922ed89b231cd2efe"
+                .to_string(),
+        );
+
+        alice.read_second_message(&second).unwrap();
+        let third = alice.third_message().unwrap();
+        assert_that(&hex::encode(&third)).is_equal_to(
+            "9e0a1bcf0d056805751e97cc314f99f2142366ba47b00c84cad64a8619b1ed7054\
+             e0de51ee0d3ca17b17fd30626c9b9a169108eae114830422bc9186c65237f0e2"
+                .to_string(),
+        );
+
+        assert_that(&bob.read_third_message(&third)).is_ok();
+    }
+
+    #[test]
+    fn tampered_identity_fails_the_handshake() {
+        let mut alice = Handshake::initiator(key_pair(1), key_pair(2));
+        let mut bob = Handshake::responder(key_pair(3), key_pair(4));
+
+        let first = alice.first_message().unwrap();
+        bob.read_first_message(&first).unwrap();
+        let mut second = bob.second_message().unwrap();
+        second[PUBLIC_KEY_SIZE] ^= 1;
+
+        assert_that(&alice.read_second_message(&second)).is_err();
+    }
+}
@@ -0,0 +1,154 @@
+//! An authenticated and encrypted transport between comit nodes
+//!
+//! Nodes prove their identity with a secp256k1 key during a handshake
+//! following the XX pattern of the Noise protocol framework, after which
+//! every message is encrypted with ChaCha20-Poly1305.
+
+mod codec;
+mod handshake;
+mod symmetric_state;
+
+pub use self::{
+    codec::NoiseCodec,
+    handshake::{Handshake, Session},
+    symmetric_state::CipherState,
+};
+
+use futures::{future, Future};
+use rand::{thread_rng, Rng};
+use secp256k1_support::{KeyPair, SECRET_KEY_SIZE};
+use std::io;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+pub const MAX_MESSAGE_LENGTH: usize = 65535;
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "Connection failed during the handshake: {}", _0)]
+    Io(io::Error),
+    #[fail(display = "Message could not be decrypted")]
+    Decryption,
+    #[fail(display = "Received an invalid public key")]
+    InvalidPublicKey,
+    #[fail(display = "Received a message that is not part of the handshake")]
+    UnexpectedMessage,
+    #[fail(display = "Sent or received too many messages over the connection")]
+    NoncesExhausted,
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+pub type HandshakeFuture<S> = Box<dyn Future<Item = (S, Session), Error = Error> + Send>;
+
+/// Performs the handshake as the node that opened the connection
+pub fn initiate<S: AsyncRead + AsyncWrite + Send + 'static>(
+    socket: S,
+    identity: KeyPair,
+) -> HandshakeFuture<S> {
+    let mut handshake = Handshake::initiator(identity, ephemeral_key_pair());
+    let first_message = handshake.first_message();
+
+    Box::new(
+        future::result(first_message)
+            .and_then(|first_message| write_message(socket, first_message))
+            .and_then(read_message)
+            .and_then(move |(socket, second_message)| {
+                handshake.read_second_message(&second_message)?;
+                let third_message = handshake.third_message()?;
+                Ok((socket, handshake, third_message))
+            })
+            .and_then(|(socket, handshake, third_message)| {
+                write_message(socket, third_message)
+                    .and_then(move |socket| Ok((socket, handshake.into_session()?)))
+            }),
+    )
+}
+
+/// Performs the handshake as the node that accepted the connection
+pub fn respond<S: AsyncRead + AsyncWrite + Send + 'static>(
+    socket: S,
+    identity: KeyPair,
+) -> HandshakeFuture<S> {
+    let mut handshake = Handshake::responder(identity, ephemeral_key_pair());
+
+    Box::new(
+        read_message(socket)
+            .and_then(move |(socket, first_message)| {
+                handshake.read_first_message(&first_message)?;
+                let second_message = handshake.second_message()?;
+                Ok((socket, handshake, second_message))
+            })
+            .and_then(|(socket, handshake, second_message)| {
+                write_message(socket, second_message).map(move |socket| (socket, handshake))
+            })
+            .and_then(|(socket, mut handshake)| {
+                read_message(socket).and_then(move |(socket, third_message)| {
+                    handshake.read_third_message(&third_message)?;
+                    Ok((socket, handshake.into_session()?))
+                })
+            }),
+    )
+}
+
+fn ephemeral_key_pair() -> KeyPair {
+    let mut rng = thread_rng();
+    let mut secret_key = [0u8; SECRET_KEY_SIZE];
+
+    loop {
+        rng.fill_bytes(&mut secret_key);
+        if let Ok(key_pair) = KeyPair::from_secret_key_slice(&secret_key) {
+            return key_pair;
+        }
+    }
+}
+
+fn write_length(length: usize) -> [u8; 2] {
+    [(length >> 8) as u8, length as u8]
+}
+
+fn read_length(prefix: [u8; 2]) -> usize {
+    (usize::from(prefix[0]) << 8) | usize::from(prefix[1])
+}
+
+fn write_message<S: AsyncWrite>(
+    socket: S,
+    message: Vec<u8>,
+) -> impl Future<Item = S, Error = Error> {
+    let mut bytes = write_length(message.len()).to_vec();
+    bytes.extend(message);
+
+    tokio::io::write_all(socket, bytes)
+        .map(|(socket, _)| socket)
+        .map_err(Error::Io)
+}
+
+fn read_message<S: AsyncRead>(socket: S) -> impl Future<Item = (S, Vec<u8>), Error = Error> {
+    tokio::io::read_exact(socket, [0u8; 2])
+        .and_then(|(socket, prefix)| tokio::io::read_exact(socket, vec![0u8; read_length(prefix)]))
+        .map_err(Error::Io)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn handshake_over_a_connection_authenticates_both_nodes() {
+        let alice = KeyPair::from_secret_key_slice(&[1u8; 32]).unwrap();
+        let bob = KeyPair::from_secret_key_slice(&[2u8; 32]).unwrap();
+        let (alice_socket, bob_socket) = memsocket::unbounded();
+
+        let ((_, alice_session), (_, bob_session)) = initiate(alice_socket, alice)
+            .join(respond(bob_socket, bob))
+            .wait()
+            .unwrap();
+
+        assert_that(&alice_session.remote_identity).is_equal_to(bob.public_key());
+        assert_that(&bob_session.remote_identity).is_equal_to(alice.public_key());
+    }
+}
@@ -0,0 +1,407 @@
+use crate::noise::Error;
+use crypto::{
+    chacha20::ChaCha20,
+    digest::Digest,
+    hkdf::{hkdf_expand, hkdf_extract},
+    mac::{Mac, MacResult},
+    poly1305::Poly1305,
+    sha2::Sha256,
+    symmetriccipher::SynchronousStreamCipher,
+};
+
+pub const TAG_LENGTH: usize = 16;
+const HASH_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 12;
+
+/// Encrypts or decrypts the messages going in one direction
+#[derive(DebugStub)]
+pub struct CipherState {
+    #[debug_stub = "Key"]
+    key: Option<[u8; 32]>,
+    nonce: u64,
+}
+
+impl CipherState {
+    fn new(key: [u8; 32]) -> Self {
+        CipherState {
+            key: Some(key),
+            nonce: 0,
+        }
+    }
+
+    fn empty() -> Self {
+        CipherState {
+            key: None,
+            nonce: 0,
+        }
+    }
+
+    /// Without a key yet the plaintext is returned as is
+    pub fn encrypt_with_ad(&mut self, ad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let key = match self.key {
+            Some(key) => key,
+            None => return Ok(plaintext.to_vec()),
+        };
+
+        Ok(seal(&key, &self.next_nonce()?, ad, plaintext))
+    }
+
+    pub fn decrypt_with_ad(&mut self, ad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        let key = match self.key {
+            Some(key) => key,
+            None => return Ok(ciphertext.to_vec()),
+        };
+
+        open(&key, &self.next_nonce()?, ad, ciphertext)
+    }
+
+    /// 32 bits of zeros followed by the little-endian counter, as Noise
+    /// specifies for ChaChaPoly. The counter must never wrap around and its
+    /// largest value is reserved, so the cipher state is used up before it.
+    fn next_nonce(&mut self) -> Result<[u8; NONCE_LENGTH], Error> {
+        if self.nonce == u64::max_value() {
+            return Err(Error::NoncesExhausted);
+        }
+
+        let mut nonce = [0u8; NONCE_LENGTH];
+        nonce[4..].copy_from_slice(&le_bytes(self.nonce));
+        self.nonce += 1;
+        Ok(nonce)
+    }
+}
+
+/// The chaining key and handshake hash every handshake message is mixed into
+#[derive(DebugStub)]
+pub struct SymmetricState {
+    cipher_state: CipherState,
+    #[debug_stub = "ChainingKey"]
+    chaining_key: [u8; HASH_LENGTH],
+    handshake_hash: [u8; HASH_LENGTH],
+}
+
+impl SymmetricState {
+    pub fn new(protocol_name: &str) -> Self {
+        let protocol_name = protocol_name.as_bytes();
+        let handshake_hash = if protocol_name.len() <= HASH_LENGTH {
+            let mut padded = [0u8; HASH_LENGTH];
+            padded[..protocol_name.len()].copy_from_slice(protocol_name);
+            padded
+        } else {
+            sha256(&[protocol_name])
+        };
+
+        SymmetricState {
+            cipher_state: CipherState::empty(),
+            chaining_key: handshake_hash,
+            handshake_hash,
+        }
+    }
+
+    pub fn mix_key(&mut self, input_key_material: &[u8]) {
+        let (chaining_key, key) = hkdf(&self.chaining_key, input_key_material);
+
+        self.chaining_key = chaining_key;
+        self.cipher_state = CipherState::new(key);
+    }
+
+    pub fn mix_hash(&mut self, data: &[u8]) {
+        self.handshake_hash = sha256(&[&self.handshake_hash, data]);
+    }
+
+    pub fn encrypt_and_hash(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let ciphertext = self
+            .cipher_state
+            .encrypt_with_ad(&self.handshake_hash, plaintext)?;
+        self.mix_hash(&ciphertext);
+        Ok(ciphertext)
+    }
+
+    pub fn decrypt_and_hash(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        let plaintext = self
+            .cipher_state
+            .decrypt_with_ad(&self.handshake_hash, ciphertext)?;
+        self.mix_hash(ciphertext);
+        Ok(plaintext)
+    }
+
+    /// The cipher states of the messages sent by the initiator and of those
+    /// sent by the responder
+    pub fn split(&self) -> (CipherState, CipherState) {
+        let (initiator_key, responder_key) = hkdf(&self.chaining_key, &[]);
+
+        (
+            CipherState::new(initiator_key),
+            CipherState::new(responder_key),
+        )
+    }
+}
+
+/// The ChaCha20-Poly1305 AEAD of RFC 7539 with its 96-bit nonce, the one of
+/// rust-crypto takes a 64-bit nonce and authenticates differently
+fn seal(key: &[u8; 32], nonce: &[u8; NONCE_LENGTH], ad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    let mut chacha20 = ChaCha20::new(key, nonce);
+    let poly1305_key = poly1305_key(&mut chacha20);
+
+    let mut ciphertext = vec![0u8; plaintext.len()];
+    chacha20.process(plaintext, &mut ciphertext);
+    let tag = poly1305(&poly1305_key, ad, &ciphertext);
+    ciphertext.extend_from_slice(tag.code());
+
+    ciphertext
+}
+
+fn open(
+    key: &[u8; 32],
+    nonce: &[u8; NONCE_LENGTH],
+    ad: &[u8],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, Error> {
+    if ciphertext.len() < TAG_LENGTH {
+        return Err(Error::Decryption);
+    }
+    let (input, tag) = ciphertext.split_at(ciphertext.len() - TAG_LENGTH);
+
+    let mut chacha20 = ChaCha20::new(key, nonce);
+    let poly1305_key = poly1305_key(&mut chacha20);
+
+    // MacResult compares in constant time
+    if poly1305(&poly1305_key, ad, input) != MacResult::new(tag) {
+        return Err(Error::Decryption);
+    }
+
+    let mut plaintext = vec![0u8; input.len()];
+    chacha20.process(input, &mut plaintext);
+
+    Ok(plaintext)
+}
+
+/// The first block of the key stream is the one-time Poly1305 key, the
+/// message is encrypted with the blocks after it
+fn poly1305_key(chacha20: &mut ChaCha20) -> [u8; 32] {
+    let mut block = [0u8; 64];
+    chacha20.process(&[0u8; 64], &mut block);
+
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&block[..32]);
+    key
+}
+
+fn poly1305(key: &[u8; 32], ad: &[u8], ciphertext: &[u8]) -> MacResult {
+    let mut poly1305 = Poly1305::new(key);
+    poly1305.input(ad);
+    poly1305.input(padding(ad.len()));
+    poly1305.input(ciphertext);
+    poly1305.input(padding(ciphertext.len()));
+    poly1305.input(&le_bytes(ad.len() as u64));
+    poly1305.input(&le_bytes(ciphertext.len() as u64));
+    poly1305.result()
+}
+
+static ZEROS: [u8; 16] = [0u8; 16];
+
+/// Pads data of the given length to a multiple of 16 bytes
+fn padding(length: usize) -> &'static [u8] {
+    &ZEROS[..(16 - length % 16) % 16]
+}
+
+fn le_bytes(value: u64) -> [u8; 8] {
+    let mut bytes = [0u8; 8];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = (value >> (8 * i)) as u8;
+    }
+    bytes
+}
+
+fn sha256(slices: &[&[u8]]) -> [u8; HASH_LENGTH] {
+    let mut sha = Sha256::new();
+    for slice in slices {
+        sha.input(slice);
+    }
+    let mut result = [0u8; HASH_LENGTH];
+    sha.result(&mut result);
+    result
+}
+
+fn hkdf(
+    chaining_key: &[u8; HASH_LENGTH],
+    input_key_material: &[u8],
+) -> ([u8; HASH_LENGTH], [u8; HASH_LENGTH]) {
+    let mut pseudo_random_key = [0u8; HASH_LENGTH];
+    hkdf_extract(
+        Sha256::new(),
+        chaining_key,
+        input_key_material,
+        &mut pseudo_random_key,
+    );
+
+    let mut output = [0u8; 2 * HASH_LENGTH];
+    hkdf_expand(Sha256::new(), &pseudo_random_key, &[], &mut output);
+
+    let mut first = [0u8; HASH_LENGTH];
+    let mut second = [0u8; HASH_LENGTH];
+    first.copy_from_slice(&output[..HASH_LENGTH]);
+    second.copy_from_slice(&output[HASH_LENGTH..]);
+    (first, second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1_support::{KeyPair, PublicKey};
+    use spectral::prelude::*;
+
+    fn key_pair(byte: u8) -> KeyPair {
+        KeyPair::from_secret_key_slice(&[byte; 32]).unwrap()
+    }
+
+    // RFC 7539, section 2.8.2
+    #[test]
+    fn aead_matches_the_rfc_7539_test_vector() {
+        let key = [
+            0x80, 0x81, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x8a, 0x8b, 0x8c, 0x8d,
+            0x8e, 0x8f, 0x90, 0x91, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0x9b,
+            0x9c, 0x9d, 0x9e, 0x9f,
+        ];
+        let nonce = [
+            0x07, 0x00, 0x00, 0x00, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47,
+        ];
+        let ad = hex::decode("50515253c0c1c2c3c4c5c6c7").unwrap();
+        let plaintext: &[u8] = b"Ladies and Gentlemen of the class of '99: If I could offer you \
+                                 only one tip for the future, sunscreen would be it.";
+        let ciphertext = hex::decode(
+            "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6\
+             3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36\
+             92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc\
+             3ff4def08e4b7a9de576d26586cec64b6116\
+             1ae10b594f09e26a7e902ecbd0600691",
+        )
+        .unwrap();
+
+        assert_that(&seal(&key, &nonce, &ad, plaintext)).is_equal_to(&ciphertext);
+        assert_that(&open(&key, &nonce, &ad, &ciphertext)).is_ok_containing(plaintext.to_vec());
+    }
+
+    #[test]
+    fn nonce_is_the_counter_after_four_zero_bytes() {
+        let mut sender = CipherState::new([1u8; 32]);
+
+        let _ = sender.encrypt_with_ad(b"ad", b"SWAP");
+
+        assert_that(&sender.encrypt_with_ad(b"ad", b"SWAP"))
+            .is_ok_containing(hex::decode("1e6d6a43ed3c181d589d0a06aa99d440e6c6f74f").unwrap());
+    }
+
+    #[test]
+    fn cipher_state_is_used_up_before_the_nonce_wraps_around() {
+        let mut sender = CipherState::new([1u8; 32]);
+        let mut receiver = CipherState::new([1u8; 32]);
+        sender.nonce = u64::max_value() - 1;
+        receiver.nonce = u64::max_value() - 1;
+
+        let ciphertext = sender.encrypt_with_ad(&[], b"SWAP").unwrap();
+
+        assert_that(&receiver.decrypt_with_ad(&[], &ciphertext)).is_ok();
+        assert_that(&sender.encrypt_with_ad(&[], b"SWAP")).is_err();
+        assert_that(&receiver.decrypt_with_ad(&[], &ciphertext)).is_err();
+    }
+
+    // There are no published Noise test vectors for secp256k1. The handshake of
+    // Lightning (BOLT 8, Appendix A) is Noise_XK_secp256k1_ChaChaPoly_SHA256,
+    // which only differs from ours in the message pattern, so going through it
+    // with the initiator's state checks everything the patterns share.
+    #[test]
+    fn symmetric_state_matches_the_bolt_8_test_vector() {
+        let static_key = key_pair(0x11);
+        let ephemeral = key_pair(0x12);
+        let remote_static = PublicKey::from_slice(
+            &hex::decode("028d7500dd4c12685d1f568b4c2b5048e8534b873319f3a8daa612b469132ec7f7")
+                .unwrap(),
+        )
+        .unwrap();
+        let remote_ephemeral = key_pair(0x22).public_key();
+
+        let mut state = SymmetricState::new("Noise_XK_secp256k1_ChaChaPoly_SHA256");
+        state.mix_hash(b"lightning");
+        state.mix_hash(&remote_static.serialize());
+
+        // -> e, es
+        let mut act_one = ephemeral.public_key().serialize().to_vec();
+        state.mix_hash(&act_one);
+        state.mix_key(&ephemeral.ecdh(&remote_static));
+        act_one.extend(state.encrypt_and_hash(&[]).unwrap());
+
+        assert_that(&hex::encode(act_one)).is_equal_to(
+            "036360e856310ce5d294e8be33fc807077dc56ac80d95d9cd4ddbd21325eff73f7\
+             0df6086551151f58b8afe6c195782c6a"
+                .to_string(),
+        );
+
+        // <- e, ee
+        let act_two = hex::decode(
+            "02466d7fcae563e5cb09a0d1870bb580344804617879a14949cf22285f1bae3f27\
+             6e2470b93aac583c9ef6eafca3f730ae",
+        )
+        .unwrap();
+        state.mix_hash(&act_two[..33]);
+        state.mix_key(&ephemeral.ecdh(&remote_ephemeral));
+        assert_that(&state.decrypt_and_hash(&act_two[33..])).is_ok();
+
+        // -> s, se
+        let mut act_three = state
+            .encrypt_and_hash(&static_key.public_key().serialize())
+            .unwrap();
+        state.mix_key(&static_key.ecdh(&remote_ephemeral));
+        act_three.extend(state.encrypt_and_hash(&[]).unwrap());
+
+        assert_that(&hex::encode(act_three)).is_equal_to(
+            "b9e3a702e93e3a9948c2ed6e5fd7590a6e1c3a0344cfc9d5b57357049aa22355\
+             361aa02e55a8fc28fef5bd6d71ad0c38228dc68b1c466263b47fdf31e560e139ba"
+                .to_string(),
+        );
+
+        let (sender, receiver) = state.split();
+        assert_that(&sender.key.map(hex::encode)).is_equal_to(Some(
+            "969ab31b4d288cedf6218839b27a3e2140827047f2c0f01bf5c04435d43511a9".to_string(),
+        ));
+        assert_that(&receiver.key.map(hex::encode)).is_equal_to(Some(
+            "bb9020b8965f4df047e07f955f3c4b88418984aadc5cdb35096b9ea8fa5c3442".to_string(),
+        ));
+    }
+
+    #[test]
+    fn short_protocol_names_are_padded_instead_of_hashed() {
+        let mut padded = [0u8; HASH_LENGTH];
+        padded[..11].copy_from_slice(b"Noise_short");
+
+        assert_that(&SymmetricState::new("Noise_short").handshake_hash).is_equal_to(padded);
+        assert_that(&SymmetricState::new(&"Noise_long".repeat(4)).handshake_hash)
+            .is_equal_to(sha256(&["Noise_long".repeat(4).as_bytes()]));
+    }
+
+    #[test]
+    fn tampered_ciphertext_is_rejected() {
+        let mut sender = CipherState::new([1u8; 32]);
+        let mut receiver = CipherState::new([1u8; 32]);
+
+        let mut ciphertext = sender.encrypt_with_ad(&[], b"SWAP").unwrap();
+        ciphertext[0] ^= 1;
+
+        assert_that(&receiver.decrypt_with_ad(&[], &ciphertext)).is_err();
+    }
+
+    #[test]
+    fn messages_are_decrypted_in_the_order_they_were_encrypted() {
+        let mut sender = CipherState::new([1u8; 32]);
+        let mut receiver = CipherState::new([1u8; 32]);
+
+        let first = sender.encrypt_with_ad(&[], b"first").unwrap();
+        let second = sender.encrypt_with_ad(&[], b"second").unwrap();
+
+        assert_that(&first).is_not_equal_to(&sender.encrypt_with_ad(&[], b"first").unwrap());
+        assert_that(&receiver.decrypt_with_ad(&[], &second)).is_err();
+
+        let mut receiver = CipherState::new([1u8; 32]);
+        assert_that(&receiver.decrypt_with_ad(&[], &first)).is_ok_containing(b"first".to_vec());
+        assert_that(&receiver.decrypt_with_ad(&[], &second)).is_ok_containing(b"second".to_vec());
+    }
+}
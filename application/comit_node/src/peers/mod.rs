@@ -0,0 +1,399 @@
+mod sqlite;
+
+pub use self::sqlite::SqlitePeerStore;
+
+use crate::bam_api::capabilities::Capabilities;
+use chrono::{DateTime, Utc};
+use secp256k1_support::PublicKey;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::BTreeMap,
+    fmt, io,
+    net::{SocketAddr, ToSocketAddrs},
+    str::FromStr,
+    sync::RwLock,
+};
+
+/// The address of a comit node, its host can be a DNS name
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PeerAddress {
+    host: String,
+    port: u16,
+}
+
+#[derive(Debug, Fail, PartialEq)]
+#[fail(display = "Not a host and port: {}", _0)]
+pub struct InvalidPeerAddress(String);
+
+impl PeerAddress {
+    pub fn resolve(&self) -> io::Result<Vec<SocketAddr>> {
+        (self.host.as_str(), self.port)
+            .to_socket_addrs()
+            .map(Iterator::collect)
+    }
+}
+
+impl FromStr for PeerAddress {
+    type Err = InvalidPeerAddress;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidPeerAddress(s.to_string());
+
+        let mut parts = s.rsplitn(2, ':');
+        let port = parts
+            .next()
+            .and_then(|port| port.parse().ok())
+            .ok_or_else(invalid)?;
+        let host = parts
+            .next()
+            .map(|host| host.trim_start_matches('[').trim_end_matches(']'))
+            .filter(|host| !host.is_empty())
+            .ok_or_else(invalid)?;
+
+        Ok(PeerAddress {
+            host: host.to_string(),
+            port,
+        })
+    }
+}
+
+impl fmt::Display for PeerAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
+    }
+}
+
+impl From<SocketAddr> for PeerAddress {
+    fn from(socket_addr: SocketAddr) -> Self {
+        PeerAddress {
+            host: socket_addr.ip().to_string(),
+            port: socket_addr.port(),
+        }
+    }
+}
+
+impl Serialize for PeerAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for PeerAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Peer {
+    pub name: String,
+    /// Dialled in this order until a connection succeeds
+    pub addresses: Vec<PeerAddress>,
+    pub notes: Option<String>,
+    /// The identity key the peer has to prove to own, any if not given
+    pub identity: Option<PublicKey>,
+    /// When a connection to the peer last succeeded
+    pub last_seen: Option<DateTime<Utc>>,
+    /// What the peer announced when the last connection was set up
+    pub capabilities: Option<Capabilities>,
+}
+
+impl Peer {
+    pub fn new(name: String, addresses: Vec<PeerAddress>, notes: Option<String>) -> Self {
+        Peer {
+            name,
+            addresses,
+            notes,
+            identity: None,
+            last_seen: None,
+            capabilities: None,
+        }
+    }
+
+    pub fn with_identity(self, identity: Option<PublicKey>) -> Self {
+        Peer { identity, ..self }
+    }
+}
+
+/// A peer that is not in the registry is known by its address only
+impl From<PeerAddress> for Peer {
+    fn from(address: PeerAddress) -> Self {
+        Peer::new(address.to_string(), vec![address], None)
+    }
+}
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "Failed to access the peer database: {}", _0)]
+    Database(rusqlite::Error),
+    #[fail(display = "Stored peer is invalid: {}", _0)]
+    InvalidRecord(String),
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Self {
+        Error::Database(e)
+    }
+}
+
+/// The comit nodes this node knows by name
+#[derive(Debug, Default)]
+pub struct PeerRegistry {
+    peers: RwLock<BTreeMap<String, Peer>>,
+    store: Option<SqlitePeerStore>,
+}
+
+impl PeerRegistry {
+    /// A registry that forgets its peers on shutdown
+    pub fn new(peers: Vec<Peer>) -> Self {
+        PeerRegistry {
+            peers: RwLock::new(by_name(peers)),
+            store: None,
+        }
+    }
+
+    /// Loads the peers saved in `store` and writes every change back to it.
+    /// The `configured` peers replace saved ones of the same name.
+    pub fn open(store: SqlitePeerStore, configured: Vec<Peer>) -> Result<Self, Error> {
+        let registry = PeerRegistry {
+            peers: RwLock::new(by_name(store.all()?)),
+            store: Some(store),
+        };
+
+        for peer in configured {
+            let _ = registry.insert(peer)?;
+        }
+
+        Ok(registry)
+    }
+
+    /// All peers, ordered by name
+    pub fn all(&self) -> Vec<Peer> {
+        self.peers.read().unwrap().values().cloned().collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<Peer> {
+        self.peers.read().unwrap().get(name).cloned()
+    }
+
+    /// Adds the peer or replaces the one with the same name, returns whether
+    /// it was added
+    pub fn insert(&self, mut peer: Peer) -> Result<bool, Error> {
+        let mut peers = self.peers.write().unwrap();
+
+        let existing = peers.get(&peer.name);
+        let added = existing.is_none();
+        if let Some(existing) = existing {
+            peer.last_seen = existing.last_seen;
+            peer.capabilities = existing.capabilities.clone();
+        }
+        self.save(&peer)?;
+        let _ = peers.insert(peer.name.clone(), peer);
+
+        Ok(added)
+    }
+
+    pub fn remove(&self, name: &str) -> Result<Option<Peer>, Error> {
+        let mut peers = self.peers.write().unwrap();
+
+        if let Some(ref store) = self.store {
+            store.remove(name)?;
+        }
+
+        Ok(peers.remove(name))
+    }
+
+    /// Records that a connection to the peer succeeded and what it announced
+    pub fn mark_seen(
+        &self,
+        name: &str,
+        at: DateTime<Utc>,
+        capabilities: Capabilities,
+    ) -> Result<(), Error> {
+        let mut peers = self.peers.write().unwrap();
+
+        if let Some(peer) = peers.get_mut(name) {
+            let seen = Peer {
+                last_seen: Some(at),
+                capabilities: Some(capabilities),
+                ..peer.clone()
+            };
+            self.save(&seen)?;
+            *peer = seen;
+        }
+
+        Ok(())
+    }
+
+    pub fn find_by_identity(&self, identity: &PublicKey) -> Option<Peer> {
+        self.peers
+            .read()
+            .unwrap()
+            .values()
+            .find(|peer| peer.identity.as_ref() == Some(identity))
+            .cloned()
+    }
+
+    /// Resolves a peer that is given either by its name or by its address
+    pub fn resolve(&self, peer: &str) -> Option<Peer> {
+        self.get(peer)
+            .or_else(|| peer.parse::<PeerAddress>().ok().map(Peer::from))
+    }
+
+    fn save(&self, peer: &Peer) -> Result<(), Error> {
+        match self.store {
+            Some(ref store) => store.save(peer),
+            None => Ok(()),
+        }
+    }
+}
+
+fn by_name(peers: Vec<Peer>) -> BTreeMap<String, Peer> {
+    peers
+        .into_iter()
+        .map(|peer| (peer.name.clone(), peer))
+        .collect()
+}
+
+/// The node a swap is made with
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Counterparty {
+    pub address: SocketAddr,
+    /// The identity the node proved to own, not known for swaps made before
+    /// connections were authenticated
+    pub identity: Option<PublicKey>,
+}
+
+impl fmt::Display for Counterparty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.identity {
+            Some(identity) => write!(f, "{} ({})", self.address, identity),
+            None => write!(f, "{}", self.address),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use secp256k1_support::KeyPair;
+    use spectral::prelude::*;
+
+    fn bob() -> Peer {
+        Peer::new(
+            String::from("bob"),
+            vec![
+                "bob.example.com:8011".parse().unwrap(),
+                "127.0.0.1:8011".parse().unwrap(),
+            ],
+            Some(String::from("Our market maker")),
+        )
+    }
+
+    #[test]
+    fn peer_addresses_can_be_dns_names_and_ips() {
+        let parsed = |address: &str| {
+            address
+                .parse::<PeerAddress>()
+                .map(|address| address.to_string())
+        };
+
+        assert_that(&parsed("bob.example.com:8011"))
+            .is_ok_containing(String::from("bob.example.com:8011"));
+        assert_that(&parsed("[::1]:8011")).is_ok_containing(String::from("[::1]:8011"));
+        assert_that(&"bob.example.com".parse::<PeerAddress>()).is_err();
+        assert_that(&":8011".parse::<PeerAddress>()).is_err();
+    }
+
+    #[test]
+    fn resolves_names_of_known_peers_and_addresses() {
+        let registry = PeerRegistry::new(vec![bob()]);
+
+        assert_that(&registry.resolve("bob")).contains_value(bob());
+        assert_that(&registry.resolve("192.168.0.2:8011"))
+            .is_some()
+            .map(|peer| &peer.addresses)
+            .is_equal_to(&vec!["192.168.0.2:8011".parse::<PeerAddress>().unwrap()]);
+        assert_that(&registry.resolve("carol")).is_none();
+    }
+
+    #[test]
+    fn replacing_a_peer_keeps_when_it_was_last_seen() {
+        let registry = PeerRegistry::default();
+        let seen = Utc::now();
+
+        assert_that(&registry.insert(bob())).is_ok_containing(true);
+        registry
+            .mark_seen("bob", seen, Capabilities::of_this_node())
+            .unwrap();
+        assert_that(&registry.insert(Peer::new(String::from("bob"), vec![], None)))
+            .is_ok_containing(false);
+
+        assert_that(&registry.get("bob"))
+            .is_some()
+            .map(|peer| &peer.last_seen)
+            .is_equal_to(&Some(seen));
+        assert_that(&registry.get("bob"))
+            .is_some()
+            .map(|peer| &peer.capabilities)
+            .is_equal_to(&Some(Capabilities::of_this_node()));
+    }
+
+    #[test]
+    fn finds_peers_by_the_identity_they_proved() {
+        let identity = |byte| {
+            KeyPair::from_secret_key_slice(&[byte; 32])
+                .unwrap()
+                .public_key()
+        };
+        let registry = PeerRegistry::new(vec![bob().with_identity(Some(identity(1)))]);
+
+        assert_that(&registry.find_by_identity(&identity(1)))
+            .is_some()
+            .map(|peer| &peer.name)
+            .is_equal_to(&String::from("bob"));
+        assert_that(&registry.find_by_identity(&identity(2))).is_none();
+    }
+
+    #[test]
+    fn peers_are_kept_in_the_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("comit_node.sqlite");
+        let open = |configured| {
+            PeerRegistry::open(SqlitePeerStore::open(&path).unwrap(), configured).unwrap()
+        };
+        let carol = Peer::new(
+            String::from("carol"),
+            vec!["carol.example.com:8011".parse().unwrap()],
+            None,
+        );
+
+        let registry = open(vec![bob()]);
+        registry.insert(carol.clone()).unwrap();
+        registry
+            .mark_seen(
+                "bob",
+                Utc.timestamp_millis(1_550_000_000_000),
+                Capabilities::of_this_node(),
+            )
+            .unwrap();
+        let bob_seen = registry.get("bob");
+        drop(registry);
+
+        let registry = open(vec![]);
+        assert_that(&registry.get("bob")).is_equal_to(bob_seen);
+        assert_that(&registry.get("carol")).contains_value(carol);
+
+        registry.remove("carol").unwrap();
+        drop(registry);
+
+        assert_that(&open(vec![]).get("carol")).is_none();
+    }
+}
@@ -0,0 +1,187 @@
+use crate::peers::{Error, Peer};
+use chrono::{TimeZone, Utc};
+use hex::FromHex;
+use rusqlite::{types::ToSql, Connection, Row, NO_PARAMS};
+use secp256k1_support::PublicKey;
+use std::{path::Path, sync::Mutex, time::Duration};
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS peers (
+    name TEXT PRIMARY KEY NOT NULL,
+    addresses TEXT NOT NULL,
+    notes TEXT,
+    identity TEXT,
+    last_seen INTEGER,
+    capabilities TEXT
+)";
+
+/// Writes the peers of the `PeerRegistry` to SQLite, so that peers added
+/// over the HTTP API are still known after a restart
+#[derive(DebugStub)]
+pub struct SqlitePeerStore {
+    #[debug_stub = "Connection"]
+    connection: Mutex<Connection>,
+}
+
+impl SqlitePeerStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::new(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, Error> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    fn new(connection: Connection) -> Result<Self, Error> {
+        // The metadata and state stores share the database file
+        connection.busy_timeout(Duration::from_secs(5))?;
+        connection.execute(CREATE_TABLE, NO_PARAMS)?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    pub fn all(&self) -> Result<Vec<Peer>, Error> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(
+            "SELECT name, addresses, notes, identity, last_seen, capabilities FROM peers",
+        )?;
+        let rows = statement.query_map(NO_PARAMS, |row| peer_from_row(row))?;
+
+        let mut peers = Vec::new();
+        for row in rows {
+            peers.push(row??);
+        }
+
+        Ok(peers)
+    }
+
+    /// Adds the peer or replaces the one with the same name
+    pub fn save(&self, peer: &Peer) -> Result<(), Error> {
+        let invalid_record = |e: serde_json::Error| Error::InvalidRecord(e.to_string());
+        let addresses = serde_json::to_string(&peer.addresses).map_err(invalid_record)?;
+        let capabilities = match peer.capabilities {
+            Some(ref capabilities) => {
+                Some(serde_json::to_string(capabilities).map_err(invalid_record)?)
+            }
+            None => None,
+        };
+        let connection = self.connection.lock().unwrap();
+
+        let _ = connection.execute(
+            "INSERT OR REPLACE INTO peers
+                (name, addresses, notes, identity, last_seen, capabilities)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            &[
+                &peer.name as &dyn ToSql,
+                &addresses,
+                &peer.notes,
+                &peer.identity.map(|identity| identity.to_string()),
+                &peer.last_seen.map(|last_seen| last_seen.timestamp_millis()),
+                &capabilities,
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn remove(&self, name: &str) -> Result<(), Error> {
+        let connection = self.connection.lock().unwrap();
+
+        let _ = connection.execute("DELETE FROM peers WHERE name = ?1", &[&name as &dyn ToSql])?;
+
+        Ok(())
+    }
+}
+
+fn peer_from_row(row: &Row<'_, '_>) -> Result<Peer, Error> {
+    let invalid_record = |e: &dyn ToString| Error::InvalidRecord(e.to_string());
+
+    let addresses =
+        serde_json::from_str(&row.get::<_, String>(1)).map_err(|e| invalid_record(&e))?;
+    let identity = match row.get::<_, Option<String>>(3) {
+        Some(identity) => Some(PublicKey::from_hex(identity).map_err(|e| invalid_record(&e))?),
+        None => None,
+    };
+    let capabilities = match row.get::<_, Option<String>>(5) {
+        Some(capabilities) => {
+            Some(serde_json::from_str(&capabilities).map_err(|e| invalid_record(&e))?)
+        }
+        None => None,
+    };
+
+    Ok(Peer {
+        name: row.get(0),
+        addresses,
+        notes: row.get(2),
+        identity,
+        last_seen: row
+            .get::<_, Option<i64>>(4)
+            .map(|last_seen| Utc.timestamp_millis(last_seen)),
+        capabilities,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bam_api::capabilities::Capabilities;
+    use spectral::prelude::*;
+
+    fn bob() -> Peer {
+        Peer::new(
+            String::from("bob"),
+            vec![
+                "bob.example.com:8011".parse().unwrap(),
+                "127.0.0.1:8011".parse().unwrap(),
+            ],
+            Some(String::from("Our market maker")),
+        )
+        .with_identity(Some(
+            PublicKey::from_hex(
+                "0317b7e1ce1f9f94c32a43739229f88c0b0333296fb46e8f72865849c6ae34b84e",
+            )
+            .unwrap(),
+        ))
+    }
+
+    #[test]
+    fn saved_peers_survive_reopening_the_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("comit_node.sqlite");
+        let peer = Peer {
+            last_seen: Some(Utc.timestamp_millis(1_550_000_000_000)),
+            capabilities: Some(Capabilities::of_this_node()),
+            ..bob()
+        };
+
+        SqlitePeerStore::open(&path).unwrap().save(&peer).unwrap();
+
+        assert_that(&SqlitePeerStore::open(&path).unwrap().all().unwrap()).is_equal_to(vec![peer]);
+    }
+
+    #[test]
+    fn saving_a_peer_replaces_the_one_with_the_same_name() {
+        let store = SqlitePeerStore::open_in_memory().unwrap();
+        let replacement = Peer::new(
+            String::from("bob"),
+            vec!["127.0.0.2:8011".parse().unwrap()],
+            None,
+        );
+
+        store.save(&bob()).unwrap();
+        store.save(&replacement).unwrap();
+
+        assert_that(&store.all().unwrap()).is_equal_to(vec![replacement]);
+    }
+
+    #[test]
+    fn removed_peers_are_gone() {
+        let store = SqlitePeerStore::open_in_memory().unwrap();
+
+        store.save(&bob()).unwrap();
+        store.remove("bob").unwrap();
+
+        assert_that(&store.all().unwrap()).is_empty();
+    }
+}
@@ -0,0 +1,267 @@
+use bigdecimal::BigDecimal;
+use bitcoin_support::BitcoinQuantity;
+use ethereum_support::{Address, Erc20Quantity, EtherQuantity, ToBigDecimal, U256};
+use futures::{future, Future};
+use reqwest::{r#async::Client, Url};
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+#[derive(Fail, Debug, PartialEq, Clone)]
+pub enum Error {
+    #[fail(display = "The request failed to send.")]
+    FailedRequest(String),
+    #[fail(display = "The response was somehow malformed.")]
+    MalformedResponse(String),
+    #[fail(display = "The price feed doesn't know the rate of this pair.")]
+    UnknownPair,
+}
+
+/// An asset as far as its price is concerned, ERC20 tokens are told apart by
+/// their contract
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PricedAsset {
+    Bitcoin,
+    Ether,
+    Erc20 { token_contract: Address },
+}
+
+impl PricedAsset {
+    fn name(self) -> &'static str {
+        match self {
+            PricedAsset::Bitcoin => "Bitcoin",
+            PricedAsset::Ether => "Ether",
+            PricedAsset::Erc20 { .. } => "Erc20",
+        }
+    }
+}
+
+pub trait ToPricedAsset {
+    fn to_priced_asset(&self) -> PricedAsset;
+}
+
+impl ToPricedAsset for BitcoinQuantity {
+    fn to_priced_asset(&self) -> PricedAsset {
+        PricedAsset::Bitcoin
+    }
+}
+
+impl ToPricedAsset for EtherQuantity {
+    fn to_priced_asset(&self) -> PricedAsset {
+        PricedAsset::Ether
+    }
+}
+
+impl ToPricedAsset for Erc20Quantity {
+    fn to_priced_asset(&self) -> PricedAsset {
+        PricedAsset::Erc20 {
+            token_contract: self.token_contract(),
+        }
+    }
+}
+
+/// Tells how much of the `quote` asset one of the `base` asset is worth
+///
+/// Quantities are in bitcoin, ether or, for ERC20 tokens, the smallest unit of
+/// the token, like the quantities of the auto-accept rules.
+pub trait PriceFeed: 'static + Send + Sync + Debug {
+    fn rate(
+        &self,
+        base: PricedAsset,
+        quote: PricedAsset,
+    ) -> Box<dyn Future<Item = f64, Error = Error> + Send>;
+}
+
+/// A price feed that answers `GET <url>?base=Bitcoin&quote=Ether` with
+/// `{"rate": 30.5}`. The contract of an ERC20 token goes into `base_token` or
+/// `quote_token`.
+#[derive(Debug)]
+pub struct HttpPriceFeed {
+    client: Client,
+    url: Url,
+}
+
+impl HttpPriceFeed {
+    pub fn new(url: Url) -> Self {
+        HttpPriceFeed {
+            client: Client::new(),
+            url,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RateResponse {
+    rate: f64,
+}
+
+impl PriceFeed for HttpPriceFeed {
+    fn rate(
+        &self,
+        base: PricedAsset,
+        quote: PricedAsset,
+    ) -> Box<dyn Future<Item = f64, Error = Error> + Send> {
+        let mut url = self.url.clone();
+        {
+            let mut query = url.query_pairs_mut();
+            for (name, asset) in &[("base", base), ("quote", quote)] {
+                query.append_pair(name, asset.name());
+                if let PricedAsset::Erc20 { token_contract } = asset {
+                    query.append_pair(
+                        &format!("{}_token", name),
+                        &format!("0x{:x}", token_contract),
+                    );
+                }
+            }
+        }
+
+        let rate = self
+            .client
+            .get(url.clone())
+            .send()
+            .map_err(move |e| {
+                Error::FailedRequest(format!("Failed to fetch rate {} because {:?}", url, e))
+            })
+            .and_then(|response| {
+                response
+                    .error_for_status()
+                    .map_err(|e| Error::FailedRequest(format!("{:?}", e)))
+            })
+            .and_then(|mut response| {
+                response
+                    .json::<RateResponse>()
+                    .map_err(|e| Error::MalformedResponse(format!("{:?}", e)))
+            })
+            .map(|response| response.rate);
+
+        Box::new(rate)
+    }
+}
+
+/// A price feed with fixed rates
+#[derive(Debug, Default)]
+pub struct StaticPriceFeed {
+    rates: HashMap<(PricedAsset, PricedAsset), f64>,
+}
+
+impl StaticPriceFeed {
+    pub fn with_rate(mut self, base: PricedAsset, quote: PricedAsset, rate: f64) -> Self {
+        self.rates.insert((base, quote), rate);
+        self
+    }
+}
+
+impl PriceFeed for StaticPriceFeed {
+    fn rate(
+        &self,
+        base: PricedAsset,
+        quote: PricedAsset,
+    ) -> Box<dyn Future<Item = f64, Error = Error> + Send> {
+        Box::new(
+            self.rates
+                .get(&(base, quote))
+                .cloned()
+                .map_or_else(|| future::err(Error::UnknownPair), future::ok),
+        )
+    }
+}
+
+/// A price feed and how far from its rates Bob is still willing to trade
+#[derive(Clone, Debug)]
+pub struct RateCheck {
+    pub feed: Arc<dyn PriceFeed>,
+    /// How much more of the beta asset than the market rate Bob gives, e.g.
+    /// `0.01` for 1% or `-0.01` to insist on 1% profit
+    pub spread: f64,
+}
+
+impl RateCheck {
+    /// The highest rate Bob accepts at the given market rate, `None` unless
+    /// the market rate and the spread make it a positive number
+    pub fn max_rate(&self, market_rate: f64) -> Option<BigDecimal> {
+        let max_rate = to_decimal(market_rate)? * to_decimal(1.0 + self.spread)?;
+
+        if max_rate > U256::zero().to_bigdec(0) {
+            Some(max_rate)
+        } else {
+            None
+        }
+    }
+}
+
+/// The number a float is displayed as, `None` for NaN and infinity
+pub fn to_decimal(value: f64) -> Option<BigDecimal> {
+    if value.is_finite() {
+        value.to_string().parse().ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+    use warp::Filter;
+
+    #[test]
+    fn http_price_feed_fetches_rate_of_pair() {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let rates = warp::get2()
+            .and(warp::query::<HashMap<String, String>>())
+            .map(|query: HashMap<String, String>| {
+                let rate = match (
+                    query["base"].as_str(),
+                    query.get("base_token").map(String::as_str),
+                    query["quote"].as_str(),
+                ) {
+                    ("Bitcoin", None, "Ether") => 30.5,
+                    ("Erc20", Some("0xb97048628db6b661d4c2aa833e95dbe1a905b280"), "Bitcoin") => {
+                        0.0001
+                    }
+                    _ => 0.0,
+                };
+                warp::reply::json(&json!({ "rate": rate }))
+            });
+        let (address, server) = warp::serve(rates).bind_ephemeral(([127, 0, 0, 1], 0));
+        runtime.spawn(server);
+
+        let price_feed = HttpPriceFeed::new(format!("http://{}/rate", address).parse().unwrap());
+        let rate = runtime.block_on(price_feed.rate(PricedAsset::Bitcoin, PricedAsset::Ether));
+        let token_rate = runtime.block_on(price_feed.rate(
+            PricedAsset::Erc20 {
+                token_contract: "b97048628db6b661d4c2aa833e95dbe1a905b280".parse().unwrap(),
+            },
+            PricedAsset::Bitcoin,
+        ));
+
+        assert_that(&rate).is_ok_containing(30.5);
+        assert_that(&token_rate).is_ok_containing(0.0001);
+    }
+
+    fn decimal(value: &str) -> BigDecimal {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn max_rate_is_market_rate_plus_spread() {
+        let rate_check = RateCheck {
+            feed: Arc::new(StaticPriceFeed::default()),
+            spread: 0.01,
+        };
+
+        assert_that(&rate_check.max_rate(30.0)).is_equal_to(Some(decimal("30.3")));
+        assert_that(&rate_check.max_rate(0.1)).is_equal_to(Some(decimal("0.101")));
+    }
+
+    #[test]
+    fn there_is_no_max_rate_without_positive_market_rate() {
+        let rate_check = RateCheck {
+            feed: Arc::new(StaticPriceFeed::default()),
+            spread: 0.01,
+        };
+
+        assert_that(&rate_check.max_rate(0.0)).is_none();
+        assert_that(&rate_check.max_rate(-30.0)).is_none();
+        assert_that(&rate_check.max_rate(std::f64::NAN)).is_none();
+        assert_that(&rate_check.max_rate(std::f64::INFINITY)).is_none();
+    }
+}
@@ -1,4 +1,5 @@
 use crypto::{digest::Digest, sha2::Sha256};
+use secp256k1_support::KeyPair;
 use std::fmt;
 
 pub const SEED_LENGTH: usize = 32;
@@ -22,6 +23,12 @@ impl Seed {
         sha.result(&mut result);
         result
     }
+
+    /// The key this node proves its identity to other comit nodes with
+    pub fn node_identity(&self) -> KeyPair {
+        KeyPair::from_secret_key_slice(&self.sha256_with_seed(&[b"NODE_IDENTITY"]))
+            .expect("The probability of this happening is < 1 in 2^120")
+    }
 }
 
 impl From<[u8; 32]> for Seed {
@@ -1,12 +1,19 @@
 mod serde;
 
-use crate::seed::Seed;
+use crate::{
+    peers::PeerAddress,
+    seed::Seed,
+    swap_protocols::metadata_store::{AssetKind, LedgerKind},
+};
 use ::serde::Deserialize;
 use config::{Config, ConfigError, File};
+use ethereum_support::{Address as EthereumAddress, ChainId};
+use secp256k1_support::PublicKey;
 use std::{
+    collections::HashMap,
     ffi::OsStr,
     net::{IpAddr, SocketAddr},
-    path::Path,
+    path::{Path, PathBuf},
     time::Duration,
 };
 
@@ -15,15 +22,43 @@ pub struct ComitNodeSettings {
     pub comit: Comit,
     pub http_api: HttpApi,
     pub ledger_query_service: LedgerQueryService,
+    pub database: Database,
+    pub ethereum: Ethereum,
+    #[serde(default)]
+    pub webhooks: Vec<Webhook>,
+    /// Other comit nodes by name
+    #[serde(default)]
+    pub peers: HashMap<String, PeerSettings>,
+    #[serde(default)]
+    pub auto_accept: AutoAccept,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Comit {
-    #[serde(with = "serde::socket_addr")]
-    pub remote_comit_node_url: SocketAddr,
     #[serde(with = "serde::socket_addr")]
     pub comit_listen: SocketAddr,
     pub secret_seed: Seed,
+    /// How much earlier than the alpha HTLC the beta HTLC has to expire
+    #[serde(with = "serde::duration")]
+    pub lock_duration_margin_secs: Duration,
+    /// How long to wait for the counterparty to respond to a swap request
+    #[serde(with = "serde::duration")]
+    pub response_timeout_secs: Duration,
+    /// Only accept connections from nodes whose identity is configured for
+    /// one of the peers
+    #[serde(default)]
+    pub refuse_unknown_peers: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PeerSettings {
+    /// Host names or IP addresses with a port, dialled in this order
+    pub addresses: Vec<PeerAddress>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// The hex-encoded public key the peer has to prove to own
+    #[serde(default)]
+    pub identity: Option<PublicKey>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -33,6 +68,76 @@ pub struct HttpApi {
     pub logging: bool,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct Database {
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Ethereum {
+    pub chain_id: ChainId,
+}
+
+#[derive(Clone, DebugStub, Deserialize)]
+pub struct Webhook {
+    #[serde(with = "serde::url")]
+    pub url: url::Url,
+    /// The state names (e.g. `BothFunded`), action names (e.g. `redeem`) or
+    /// `Final` to be notified about, every new state if not given
+    #[serde(default)]
+    pub events: Option<Vec<String>>,
+    /// Key of the HMAC-SHA256 signature of each notification
+    #[debug_stub = "Secret"]
+    pub secret: String,
+}
+
+/// Lets Bob respond to swap requests without waiting for a human
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct AutoAccept {
+    /// Used as `beta_ledger_refund_identity` if Ethereum is the beta ledger
+    pub ethereum_refund_identity: Option<EthereumAddress>,
+    /// Used as `alpha_ledger_redeem_identity` if Ethereum is the alpha ledger
+    pub ethereum_redeem_identity: Option<EthereumAddress>,
+    /// Decline the requests that none of the rules accept instead of leaving
+    /// them to be answered through the HTTP API
+    #[serde(default)]
+    pub decline_unmatched: bool,
+    #[serde(default)]
+    pub rules: Vec<AutoAcceptRule>,
+    /// Declines the requests with a worse rate for Bob than the market rate
+    pub price_feed: Option<PriceFeed>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PriceFeed {
+    #[serde(with = "serde::url")]
+    pub url: url::Url,
+    /// How much more of the beta asset than the market rate Bob gives, e.g.
+    /// `0.01` for 1%
+    #[serde(default)]
+    pub spread: f64,
+}
+
+/// A swap request is accepted by the first rule it satisfies
+///
+/// Quantities are given in bitcoin, ether or, for ERC20 tokens, the smallest
+/// unit of the token. The rate is the beta quantity divided by the alpha
+/// quantity in these units.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AutoAcceptRule {
+    pub alpha_ledger: LedgerKind,
+    pub beta_ledger: LedgerKind,
+    pub alpha_asset: AssetKind,
+    pub beta_asset: AssetKind,
+    pub min_alpha_quantity: Option<f64>,
+    pub max_alpha_quantity: Option<f64>,
+    /// The most of the beta asset Bob gives for one of the alpha asset
+    pub max_rate: Option<f64>,
+    pub min_alpha_lock_duration_secs: Option<u64>,
+    /// The lock duration of the beta HTLC Bob offers when accepting
+    pub beta_lock_duration_secs: u64,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct LedgerQueryService {
     #[serde(with = "serde::url")]
@@ -109,4 +214,30 @@ mod tests {
         .is_equal_to(&Duration::from_secs(20));
     }
 
+    #[test]
+    fn can_read_ethereum_chain_id() {
+        let settings = comit_settings();
+
+        assert_that(&settings).is_ok();
+        assert_that(&settings.unwrap().ethereum.chain_id).is_equal_to(&ChainId::regtest());
+    }
+
+    #[test]
+    fn webhooks_default_to_none() {
+        let settings = comit_settings();
+
+        assert_that(&settings).is_ok();
+        assert_that(&settings.unwrap().webhooks).is_empty();
+    }
+
+    #[test]
+    fn auto_accept_defaults_to_no_rules() {
+        let settings = comit_settings();
+
+        assert_that(&settings).is_ok();
+        let auto_accept = settings.unwrap().auto_accept;
+        assert_that(&auto_accept.rules).is_empty();
+        assert_that(&auto_accept.decline_unmatched).is_false();
+    }
+
 }
@@ -1,9 +1,11 @@
 use crate::{
     bam_api::header::{FromBamHeader, ToBamHeader},
     http_api::asset::{FromHttpAsset, ToHttpAsset},
+    swap_protocols::metadata_store::AssetKind,
 };
 use bitcoin_support::BitcoinQuantity;
 use ethereum_support::{Erc20Quantity, EtherQuantity};
+use serde::{de::DeserializeOwned, Serialize};
 use std::{
     fmt::{Debug, Display},
     hash::Hash,
@@ -19,6 +21,9 @@ pub trait Asset:
     + PartialEq
     + Eq
     + Hash
+    + Serialize
+    + DeserializeOwned
+    + Into<AssetKind>
     + FromHttpAsset
     + ToHttpAsset
     + FromBamHeader
@@ -1,10 +1,10 @@
-use crate::swap_protocols::ledger::Ledger;
+use crate::swap_protocols::ledger::{Ledger, Networks};
 use bitcoin_support::{
     Address, BitcoinQuantity, IntoP2wpkhAddress, Network, PubkeyHash, Transaction, TransactionId,
 };
 use secp256k1_support::PublicKey;
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Bitcoin {
     pub network: Network,
 }
@@ -41,4 +41,13 @@ impl Ledger for Bitcoin {
     fn address_for_identity(&self, pubkeyhash: PubkeyHash) -> Address {
         pubkeyhash.into_p2wpkh_address(self.network)
     }
+
+    fn transaction_id(transaction: &Transaction) -> TransactionId {
+        transaction.txid()
+    }
+
+    fn is_on(&self, _networks: &Networks) -> bool {
+        // The Bitcoin network of the node is not configurable yet
+        true
+    }
 }
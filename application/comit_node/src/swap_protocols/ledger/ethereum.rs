@@ -1,9 +1,26 @@
-use crate::swap_protocols::ledger::Ledger;
-use ethereum_support::{Address, EtherQuantity, Transaction, H256};
+use crate::swap_protocols::ledger::{Ledger, Networks};
+use ethereum_support::{Address, ChainId, EtherQuantity, Transaction, H256};
 use secp256k1_support::PublicKey;
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Default)]
-pub struct Ethereum {}
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Ethereum {
+    pub chain_id: ChainId,
+}
+
+impl Ethereum {
+    pub fn new(chain_id: ChainId) -> Self {
+        Ethereum { chain_id }
+    }
+}
+
+// TODO: fix with #376
+impl Default for Ethereum {
+    fn default() -> Self {
+        Ethereum {
+            chain_id: ChainId::regtest(),
+        }
+    }
+}
 
 impl Ledger for Ethereum {
     type Quantity = EtherQuantity;
@@ -16,4 +33,12 @@ impl Ledger for Ethereum {
     fn address_for_identity(&self, address: Address) -> Address {
         address
     }
+
+    fn transaction_id(transaction: &Transaction) -> H256 {
+        transaction.hash
+    }
+
+    fn is_on(&self, networks: &Networks) -> bool {
+        self.chain_id == networks.ethereum_chain_id
+    }
 }
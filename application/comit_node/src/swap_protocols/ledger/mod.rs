@@ -8,9 +8,17 @@ pub use self::{bitcoin::Bitcoin, ethereum::Ethereum};
 use crate::{
     bam_api::header::{FromBamHeader, ToBamHeader},
     http_api::ledger::{FromHttpLedger, ToHttpLedger},
+    swap_protocols::{metadata_store::LedgerKind, rfc003::state_store::Persist},
 };
+use ethereum_support::ChainId;
 use std::hash::Hash;
 
+/// The networks of the ledgers this node is connected to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Networks {
+    pub ethereum_chain_id: ChainId,
+}
+
 pub trait Ledger:
     Clone
     + Debug
@@ -21,6 +29,9 @@ pub trait Ledger:
     + PartialEq
     + Eq
     + Hash
+    + Serialize
+    + DeserializeOwned
+    + Into<LedgerKind>
     + FromHttpLedger
     + ToHttpLedger
     + FromBamHeader
@@ -40,7 +51,8 @@ pub trait Ledger:
         + 'static
         + From<Self::Address>
         + Serialize
-        + DeserializeOwned;
+        + DeserializeOwned
+        + Persist;
     type Transaction: Debug
         + Clone
         + DeserializeOwned
@@ -51,4 +63,9 @@ pub trait Ledger:
         + 'static;
 
     fn address_for_identity(&self, identity: Self::Identity) -> Self::Address;
+
+    fn transaction_id(transaction: &Self::Transaction) -> Self::TxId;
+
+    /// Whether this ledger is on one of the networks the node is connected to
+    fn is_on(&self, networks: &Networks) -> bool;
 }
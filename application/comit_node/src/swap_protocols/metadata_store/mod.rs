@@ -0,0 +1,483 @@
+mod sqlite;
+
+pub use self::sqlite::SqliteMetadataStore;
+
+use crate::{
+    peers::Counterparty,
+    swap_protocols::{
+        ledger::{Bitcoin, Ethereum},
+        SwapId,
+    },
+};
+use bitcoin_support::BitcoinQuantity;
+use chrono::{DateTime, Utc};
+use ethereum_support::{Erc20Quantity, EtherQuantity};
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display},
+    hash::Hash,
+    str::FromStr,
+    sync::Mutex,
+};
+
+#[derive(Clone, Copy, Debug, Display, PartialEq)]
+pub enum RoleKind {
+    Alice,
+    Bob,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Display, PartialEq, Serialize)]
+pub enum LedgerKind {
+    Bitcoin,
+    Ethereum,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Display, PartialEq, Eq, Hash, Serialize)]
+pub enum AssetKind {
+    Bitcoin,
+    Ether,
+    Erc20,
+}
+
+#[derive(Debug, Fail, PartialEq)]
+#[fail(display = "Unknown kind: {}", _0)]
+pub struct UnknownKind(String);
+
+impl FromStr for RoleKind {
+    type Err = UnknownKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Alice" => Ok(RoleKind::Alice),
+            "Bob" => Ok(RoleKind::Bob),
+            _ => Err(UnknownKind(s.to_string())),
+        }
+    }
+}
+
+impl FromStr for LedgerKind {
+    type Err = UnknownKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Bitcoin" => Ok(LedgerKind::Bitcoin),
+            "Ethereum" => Ok(LedgerKind::Ethereum),
+            _ => Err(UnknownKind(s.to_string())),
+        }
+    }
+}
+
+impl FromStr for AssetKind {
+    type Err = UnknownKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Bitcoin" => Ok(AssetKind::Bitcoin),
+            "Ether" => Ok(AssetKind::Ether),
+            "Erc20" => Ok(AssetKind::Erc20),
+            _ => Err(UnknownKind(s.to_string())),
+        }
+    }
+}
+
+impl From<Bitcoin> for LedgerKind {
+    fn from(_: Bitcoin) -> Self {
+        LedgerKind::Bitcoin
+    }
+}
+
+impl From<Ethereum> for LedgerKind {
+    fn from(_: Ethereum) -> Self {
+        LedgerKind::Ethereum
+    }
+}
+
+impl From<BitcoinQuantity> for AssetKind {
+    fn from(_: BitcoinQuantity) -> Self {
+        AssetKind::Bitcoin
+    }
+}
+
+impl From<EtherQuantity> for AssetKind {
+    fn from(_: EtherQuantity) -> Self {
+        AssetKind::Ether
+    }
+}
+
+impl From<Erc20Quantity> for AssetKind {
+    fn from(_: Erc20Quantity) -> Self {
+        AssetKind::Erc20
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Metadata {
+    pub alpha_ledger: LedgerKind,
+    pub beta_ledger: LedgerKind,
+    pub alpha_asset: AssetKind,
+    pub beta_asset: AssetKind,
+    pub role: RoleKind,
+}
+
+impl Metadata {
+    pub fn from_kind_names(
+        alpha_ledger: &str,
+        beta_ledger: &str,
+        alpha_asset: &str,
+        beta_asset: &str,
+        role: &str,
+    ) -> Result<Self, UnknownKind> {
+        Ok(Metadata {
+            alpha_ledger: alpha_ledger.parse()?,
+            beta_ledger: beta_ledger.parse()?,
+            alpha_asset: alpha_asset.parse()?,
+            beta_asset: beta_asset.parse()?,
+            role: role.parse()?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SwapRecord {
+    pub metadata: Metadata,
+    pub created_at: DateTime<Utc>,
+    pub counterparty: Option<Counterparty>,
+    pub state: String,
+}
+
+/// The order in which swaps are listed, by the time they were created
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Order {
+    OldestFirst,
+    NewestFirst,
+}
+
+impl Default for Order {
+    fn default() -> Self {
+        Order::OldestFirst
+    }
+}
+
+/// The position of a swap in a listing, swaps created at the same time are
+/// ordered by their key
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cursor {
+    pub created_at: DateTime<Utc>,
+    pub key: String,
+}
+
+impl Cursor {
+    pub fn of<K: Display>(key: &K, record: &SwapRecord) -> Self {
+        Cursor {
+            created_at: record.created_at,
+            key: key.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Query {
+    pub role: Option<RoleKind>,
+    pub alpha_ledger: Option<LedgerKind>,
+    pub beta_ledger: Option<LedgerKind>,
+    pub alpha_asset: Option<AssetKind>,
+    pub beta_asset: Option<AssetKind>,
+    pub state: Option<String>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub order: Order,
+    /// Only swaps that are listed after this one
+    pub after: Option<Cursor>,
+    /// Only swaps that are listed before this one
+    pub before: Option<Cursor>,
+    /// At most this many swaps: the first ones, or the last ones before
+    /// `before` if only that is given
+    pub limit: Option<usize>,
+}
+
+impl Query {
+    pub fn matches(&self, record: &SwapRecord) -> bool {
+        fn matches<T: PartialEq>(expected: &Option<T>, actual: &T) -> bool {
+            expected
+                .as_ref()
+                .map_or(true, |expected| expected == actual)
+        }
+
+        let metadata = &record.metadata;
+
+        matches(&self.role, &metadata.role)
+            && matches(&self.alpha_ledger, &metadata.alpha_ledger)
+            && matches(&self.beta_ledger, &metadata.beta_ledger)
+            && matches(&self.alpha_asset, &metadata.alpha_asset)
+            && matches(&self.beta_asset, &metadata.beta_asset)
+            && matches(&self.state, &record.state)
+            && self
+                .created_after
+                .map_or(true, |created_after| record.created_at > created_after)
+            && self
+                .created_before
+                .map_or(true, |created_before| record.created_at < created_before)
+    }
+
+    /// Whether only `limit` swaps before `before` are asked for, which
+    /// requires going through the listing backwards
+    pub fn pages_backwards(&self) -> bool {
+        self.limit.is_some() && self.before.is_some() && self.after.is_none()
+    }
+
+    /// Brings `records` into the order of the query and applies its cursors
+    /// and limit
+    pub fn page<K: Display>(&self, records: Vec<(K, SwapRecord)>) -> Vec<(K, SwapRecord)> {
+        let mut records: Vec<_> = records
+            .into_iter()
+            .map(|(key, record)| (Cursor::of(&key, &record), key, record))
+            .collect();
+        records.sort_by(|(left, ..), (right, ..)| {
+            (&left.created_at, &left.key).cmp(&(&right.created_at, &right.key))
+        });
+        if self.order == Order::NewestFirst {
+            records.reverse();
+        }
+
+        let mut records: Vec<_> = records
+            .into_iter()
+            .filter(|(cursor, ..)| {
+                self.after
+                    .as_ref()
+                    .map_or(true, |after| self.is_listed_before(after, cursor))
+                    && self
+                        .before
+                        .as_ref()
+                        .map_or(true, |before| self.is_listed_before(cursor, before))
+            })
+            .map(|(_, key, record)| (key, record))
+            .collect();
+
+        if let Some(limit) = self.limit {
+            if self.pages_backwards() {
+                let surplus = records.len().saturating_sub(limit);
+                let _ = records.drain(..surplus);
+            } else {
+                records.truncate(limit);
+            }
+        }
+
+        records
+    }
+
+    fn is_listed_before(&self, left: &Cursor, right: &Cursor) -> bool {
+        let left = (&left.created_at, &left.key);
+        let right = (&right.created_at, &right.key);
+
+        match self.order {
+            Order::OldestFirst => left < right,
+            Order::NewestFirst => left > right,
+        }
+    }
+}
+
+pub const INITIAL_STATE: &str = "Start";
+
+/// Something that happened to a swap, as recorded in its history
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HistoryEvent {
+    /// The swap moved to `state`. `transaction` is the HTLC location or the
+    /// id of the ledger transaction that caused it, if there was one.
+    Transition {
+        state: String,
+        transaction: Option<serde_json::Value>,
+    },
+    /// The user executed `action` through the HTTP API. Ledger actions are
+    /// executed outside of the node and show up as transitions instead.
+    ActionExecuted { action: String },
+    /// The counter-offer was accepted by requesting the swap `swap_id`
+    CounterOfferAccepted { swap_id: SwapId },
+    /// A chain reorganisation removed `transaction` from the ledger
+    Reverted { transaction: serde_json::Value },
+    /// The reverted `transaction` is part of the ledger again
+    Reconfirmed { transaction: serde_json::Value },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    pub timestamp: DateTime<Utc>,
+    pub event: HistoryEvent,
+}
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "Metadata already exists")]
+    DuplicateKey,
+    #[fail(display = "Metadata does not exist")]
+    NotFound,
+    #[fail(display = "Failed to access the metadata database: {}", _0)]
+    Database(rusqlite::Error),
+    #[fail(display = "Stored metadata is invalid: {}", _0)]
+    InvalidRecord(String),
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Self {
+        Error::Database(e)
+    }
+}
+
+pub trait MetadataStore<K>: Send + Sync + 'static {
+    fn get(&self, key: &K) -> Result<Option<Metadata>, Error>;
+    fn counterparty(&self, key: &K) -> Result<Option<Counterparty>, Error>;
+    fn insert<M: Into<Metadata>>(
+        &self,
+        key: K,
+        metadata: M,
+        counterparty: Counterparty,
+    ) -> Result<(), Error>;
+    fn update_state(&self, key: &K, state: String) -> Result<(), Error>;
+    fn all(&self) -> Result<Vec<(K, Metadata)>, Error>;
+    fn query(&self, query: &Query) -> Result<Vec<(K, SwapRecord)>, Error>;
+    fn append_history(&self, key: &K, event: HistoryEvent) -> Result<(), Error>;
+    /// The history of the swap, oldest entry first
+    fn history(&self, key: &K) -> Result<Vec<HistoryEntry>, Error>;
+    /// Records that the counter-offer of the swap was accepted by requesting
+    /// `swap_id`, unless it was accepted before. Returns the swap that was
+    /// requested first.
+    fn accept_counter_offer(&self, key: &K, swap_id: SwapId) -> Result<SwapId, Error>;
+}
+
+fn accepted_counter_offer(history: &[HistoryEntry]) -> Option<SwapId> {
+    history.iter().find_map(|entry| match entry.event {
+        HistoryEvent::CounterOfferAccepted { swap_id } => Some(swap_id),
+        _ => None,
+    })
+}
+
+#[derive(Debug, Default)]
+pub struct InMemoryMetadataStore<K: Hash + Eq> {
+    records: Mutex<HashMap<K, SwapRecord>>,
+    history: Mutex<HashMap<K, Vec<HistoryEntry>>>,
+}
+
+impl<K: Debug + Display + Hash + Eq + Clone + Send + Sync + 'static> MetadataStore<K>
+    for InMemoryMetadataStore<K>
+{
+    fn get(&self, key: &K) -> Result<Option<Metadata>, Error> {
+        let records = self.records.lock().unwrap();
+        let metadata = records.get(key).map(|record| record.metadata);
+        trace!("Fetched metadata of swap with id {}: {:?}", key, metadata);
+
+        Ok(metadata)
+    }
+
+    fn counterparty(&self, key: &K) -> Result<Option<Counterparty>, Error> {
+        let records = self.records.lock().unwrap();
+
+        Ok(records.get(key).and_then(|record| record.counterparty))
+    }
+
+    fn insert<M: Into<Metadata>>(
+        &self,
+        key: K,
+        value: M,
+        counterparty: Counterparty,
+    ) -> Result<(), Error> {
+        let mut records = self.records.lock().unwrap();
+
+        if records.contains_key(&key) {
+            return Err(Error::DuplicateKey);
+        }
+
+        let _ = records.insert(
+            key,
+            SwapRecord {
+                metadata: value.into(),
+                created_at: Utc::now(),
+                counterparty: Some(counterparty),
+                state: INITIAL_STATE.to_string(),
+            },
+        );
+        Ok(())
+    }
+
+    fn update_state(&self, key: &K, state: String) -> Result<(), Error> {
+        let mut records = self.records.lock().unwrap();
+
+        match records.get_mut(key) {
+            Some(record) => {
+                record.state = state;
+                Ok(())
+            }
+            None => Err(Error::NotFound),
+        }
+    }
+
+    fn all(&self) -> Result<Vec<(K, Metadata)>, Error> {
+        let records = self.records.lock().unwrap();
+
+        Ok(records
+            .iter()
+            .map(|(key, record)| (key.clone(), record.metadata))
+            .collect())
+    }
+
+    fn query(&self, query: &Query) -> Result<Vec<(K, SwapRecord)>, Error> {
+        let records = self.records.lock().unwrap();
+
+        let records: Vec<_> = records
+            .iter()
+            .filter(|(_, record)| query.matches(record))
+            .map(|(key, record)| (key.clone(), record.clone()))
+            .collect();
+
+        Ok(query.page(records))
+    }
+
+    fn append_history(&self, key: &K, event: HistoryEvent) -> Result<(), Error> {
+        let records = self.records.lock().unwrap();
+
+        if !records.contains_key(key) {
+            return Err(Error::NotFound);
+        }
+
+        let mut history = self.history.lock().unwrap();
+        history
+            .entry(key.clone())
+            .or_insert_with(Vec::new)
+            .push(HistoryEntry {
+                timestamp: Utc::now(),
+                event,
+            });
+        Ok(())
+    }
+
+    fn history(&self, key: &K) -> Result<Vec<HistoryEntry>, Error> {
+        let records = self.records.lock().unwrap();
+
+        if !records.contains_key(key) {
+            return Err(Error::NotFound);
+        }
+
+        let history = self.history.lock().unwrap();
+        Ok(history.get(key).cloned().unwrap_or_default())
+    }
+
+    fn accept_counter_offer(&self, key: &K, swap_id: SwapId) -> Result<SwapId, Error> {
+        let records = self.records.lock().unwrap();
+
+        if !records.contains_key(key) {
+            return Err(Error::NotFound);
+        }
+
+        let mut history = self.history.lock().unwrap();
+        let history = history.entry(key.clone()).or_insert_with(Vec::new);
+
+        if let Some(accepted) = accepted_counter_offer(history) {
+            return Ok(accepted);
+        }
+
+        history.push(HistoryEntry {
+            timestamp: Utc::now(),
+            event: HistoryEvent::CounterOfferAccepted { swap_id },
+        });
+        Ok(swap_id)
+    }
+}
@@ -0,0 +1,707 @@
+use crate::{
+    peers::Counterparty,
+    swap_protocols::{
+        metadata_store::{
+            accepted_counter_offer, Cursor, Error, HistoryEntry, HistoryEvent, Metadata,
+            MetadataStore, Order, Query, SwapRecord, INITIAL_STATE,
+        },
+        SwapId,
+    },
+};
+use chrono::{TimeZone, Utc};
+use hex::FromHex;
+use rusqlite::{types::ToSql, Connection, OptionalExtension, Row, TransactionBehavior, NO_PARAMS};
+use secp256k1_support::PublicKey;
+use std::{net::SocketAddr, path::Path, sync::Mutex, time::Duration};
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS swap_metadata (
+    swap_id TEXT PRIMARY KEY NOT NULL,
+    role TEXT NOT NULL,
+    alpha_ledger TEXT NOT NULL,
+    beta_ledger TEXT NOT NULL,
+    alpha_asset TEXT NOT NULL,
+    beta_asset TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    counterparty TEXT,
+    state TEXT NOT NULL,
+    counterparty_identity TEXT
+)";
+
+const ADD_COUNTERPARTY_IDENTITY: &str =
+    "ALTER TABLE swap_metadata ADD COLUMN counterparty_identity TEXT";
+
+const CREATE_INDEX: &str =
+    "CREATE INDEX IF NOT EXISTS swap_metadata_created_at ON swap_metadata (created_at)";
+
+const CREATE_HISTORY_TABLE: &str = "CREATE TABLE IF NOT EXISTS swap_history (
+    swap_id TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    event TEXT NOT NULL
+)";
+
+const CREATE_HISTORY_INDEX: &str =
+    "CREATE INDEX IF NOT EXISTS swap_history_swap_id ON swap_history (swap_id)";
+
+const LATER_CURSOR: &str = "(created_at > ? OR (created_at = ? AND swap_id > ?))";
+const EARLIER_CURSOR: &str = "(created_at < ? OR (created_at = ? AND swap_id < ?))";
+
+const SELECT_RECORD: &str = "SELECT swap_id, alpha_ledger, beta_ledger, alpha_asset, beta_asset, \
+                             role, created_at, counterparty, state, counterparty_identity \
+                             FROM swap_metadata";
+
+#[derive(DebugStub)]
+pub struct SqliteMetadataStore {
+    #[debug_stub = "Connection"]
+    connection: Mutex<Connection>,
+}
+
+impl SqliteMetadataStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::new(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, Error> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    fn new(connection: Connection) -> Result<Self, Error> {
+        // The state store writes to the same database file
+        connection.busy_timeout(Duration::from_secs(5))?;
+        connection.execute(CREATE_TABLE, NO_PARAMS)?;
+        if !has_column(&connection, "swap_metadata", "counterparty_identity")? {
+            connection.execute(ADD_COUNTERPARTY_IDENTITY, NO_PARAMS)?;
+        }
+        connection.execute(CREATE_INDEX, NO_PARAMS)?;
+        connection.execute(CREATE_HISTORY_TABLE, NO_PARAMS)?;
+        connection.execute(CREATE_HISTORY_INDEX, NO_PARAMS)?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn record(&self, key: &SwapId) -> Result<Option<SwapRecord>, Error> {
+        let connection = self.connection.lock().unwrap();
+        let record = connection
+            .query_row(
+                &format!("{} WHERE swap_id = ?1", SELECT_RECORD),
+                &[&key.to_string() as &dyn ToSql],
+                |row| record_from_row(row),
+            )
+            .optional()?
+            .map_or(Ok(None), |record| record.map(Some))?;
+
+        Ok(record.map(|(_, record)| record))
+    }
+}
+
+impl MetadataStore<SwapId> for SqliteMetadataStore {
+    fn get(&self, key: &SwapId) -> Result<Option<Metadata>, Error> {
+        let record = self.record(key)?;
+
+        trace!("Fetched metadata of swap with id {}: {:?}", key, record);
+
+        Ok(record.map(|record| record.metadata))
+    }
+
+    fn counterparty(&self, key: &SwapId) -> Result<Option<Counterparty>, Error> {
+        Ok(self.record(key)?.and_then(|record| record.counterparty))
+    }
+
+    fn insert<M: Into<Metadata>>(
+        &self,
+        key: SwapId,
+        metadata: M,
+        counterparty: Counterparty,
+    ) -> Result<(), Error> {
+        let metadata = metadata.into();
+        let connection = self.connection.lock().unwrap();
+
+        let result = connection.execute(
+            "INSERT INTO swap_metadata
+                (swap_id, role, alpha_ledger, beta_ledger, alpha_asset, beta_asset,
+                 created_at, counterparty, state, counterparty_identity)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            &[
+                &key.to_string() as &dyn ToSql,
+                &metadata.role.to_string(),
+                &metadata.alpha_ledger.to_string(),
+                &metadata.beta_ledger.to_string(),
+                &metadata.alpha_asset.to_string(),
+                &metadata.beta_asset.to_string(),
+                &Utc::now().timestamp_millis(),
+                &counterparty.address.to_string(),
+                &INITIAL_STATE,
+                &counterparty.identity.map(|identity| identity.to_string()),
+            ],
+        );
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(rusqlite::Error::SqliteFailure(ref e, _))
+                if e.code == rusqlite::ErrorCode::ConstraintViolation =>
+            {
+                Err(Error::DuplicateKey)
+            }
+            Err(e) => Err(Error::Database(e)),
+        }
+    }
+
+    fn update_state(&self, key: &SwapId, state: String) -> Result<(), Error> {
+        let connection = self.connection.lock().unwrap();
+
+        let updated_rows = connection.execute(
+            "UPDATE swap_metadata SET state = ?1 WHERE swap_id = ?2",
+            &[&state as &dyn ToSql, &key.to_string()],
+        )?;
+
+        if updated_rows == 0 {
+            return Err(Error::NotFound);
+        }
+
+        Ok(())
+    }
+
+    fn all(&self) -> Result<Vec<(SwapId, Metadata)>, Error> {
+        Ok(self
+            .query(&Query::default())?
+            .into_iter()
+            .map(|(key, record)| (key, record.metadata))
+            .collect())
+    }
+
+    fn query(&self, query: &Query) -> Result<Vec<(SwapId, SwapRecord)>, Error> {
+        let mut conditions: Vec<&str> = Vec::new();
+        let mut params: Vec<Box<dyn ToSql>> = Vec::new();
+
+        let mut push_condition = |condition, param: Box<dyn ToSql>| {
+            conditions.push(condition);
+            params.push(param);
+        };
+
+        if let Some(role) = query.role {
+            push_condition("role = ?", Box::new(role.to_string()));
+        }
+        if let Some(alpha_ledger) = query.alpha_ledger {
+            push_condition("alpha_ledger = ?", Box::new(alpha_ledger.to_string()));
+        }
+        if let Some(beta_ledger) = query.beta_ledger {
+            push_condition("beta_ledger = ?", Box::new(beta_ledger.to_string()));
+        }
+        if let Some(alpha_asset) = query.alpha_asset {
+            push_condition("alpha_asset = ?", Box::new(alpha_asset.to_string()));
+        }
+        if let Some(beta_asset) = query.beta_asset {
+            push_condition("beta_asset = ?", Box::new(beta_asset.to_string()));
+        }
+        if let Some(ref state) = query.state {
+            push_condition("state = ?", Box::new(state.clone()));
+        }
+        if let Some(created_after) = query.created_after {
+            push_condition("created_at > ?", Box::new(created_after.timestamp_millis()));
+        }
+        if let Some(created_before) = query.created_before {
+            push_condition(
+                "created_at < ?",
+                Box::new(created_before.timestamp_millis()),
+            );
+        }
+
+        let (listed_later, listed_earlier) = match query.order {
+            Order::OldestFirst => (LATER_CURSOR, EARLIER_CURSOR),
+            Order::NewestFirst => (EARLIER_CURSOR, LATER_CURSOR),
+        };
+        if let Some(ref after) = query.after {
+            conditions.push(listed_later);
+            push_cursor(&mut params, after);
+        }
+        if let Some(ref before) = query.before {
+            conditions.push(listed_earlier);
+            push_cursor(&mut params, before);
+        }
+
+        let mut sql = String::from(SELECT_RECORD);
+        if !conditions.is_empty() {
+            sql.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+        }
+
+        let ascending = (query.order == Order::OldestFirst) != query.pages_backwards();
+        let direction = if ascending { "ASC" } else { "DESC" };
+        sql.push_str(&format!(" ORDER BY created_at {0}, swap_id {0}", direction));
+
+        if let Some(limit) = query.limit {
+            sql.push_str(" LIMIT ?");
+            // SQLite takes a negative limit as no limit at all
+            params.push(Box::new(limit.min(i64::max_value() as usize) as i64));
+        }
+
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(&sql)?;
+        let rows =
+            statement.query_map(params.iter().map(AsRef::as_ref), |row| record_from_row(row))?;
+
+        let mut records = Vec::new();
+        for row in rows {
+            records.push(row??);
+        }
+
+        if query.pages_backwards() {
+            records.reverse();
+        }
+
+        Ok(records)
+    }
+
+    fn append_history(&self, key: &SwapId, event: HistoryEvent) -> Result<(), Error> {
+        let connection = self.connection.lock().unwrap();
+
+        append_history(&connection, key, &event)
+    }
+
+    fn history(&self, key: &SwapId) -> Result<Vec<HistoryEntry>, Error> {
+        let connection = self.connection.lock().unwrap();
+
+        history(&connection, key)
+    }
+
+    fn accept_counter_offer(&self, key: &SwapId, swap_id: SwapId) -> Result<SwapId, Error> {
+        let mut connection = self.connection.lock().unwrap();
+        // Another node process could accept it between reading and writing
+        // the history otherwise
+        let transaction = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        if let Some(accepted) = accepted_counter_offer(&history(&transaction, key)?) {
+            return Ok(accepted);
+        }
+
+        append_history(
+            &transaction,
+            key,
+            &HistoryEvent::CounterOfferAccepted { swap_id },
+        )?;
+        transaction.commit()?;
+
+        Ok(swap_id)
+    }
+}
+
+fn append_history(
+    connection: &Connection,
+    key: &SwapId,
+    event: &HistoryEvent,
+) -> Result<(), Error> {
+    let event = serde_json::to_string(event).map_err(|e| Error::InvalidRecord(e.to_string()))?;
+
+    let inserted_rows = connection.execute(
+        "INSERT INTO swap_history (swap_id, timestamp, event)
+            SELECT ?1, ?2, ?3
+            WHERE EXISTS (SELECT 1 FROM swap_metadata WHERE swap_id = ?1)",
+        &[
+            &key.to_string() as &dyn ToSql,
+            &Utc::now().timestamp_millis(),
+            &event,
+        ],
+    )?;
+
+    if inserted_rows == 0 {
+        return Err(Error::NotFound);
+    }
+
+    Ok(())
+}
+
+fn history(connection: &Connection, key: &SwapId) -> Result<Vec<HistoryEntry>, Error> {
+    let exists = connection
+        .query_row(
+            "SELECT 1 FROM swap_metadata WHERE swap_id = ?1",
+            &[&key.to_string() as &dyn ToSql],
+            |_| (),
+        )
+        .optional()?
+        .is_some();
+    if !exists {
+        return Err(Error::NotFound);
+    }
+
+    let mut statement = connection
+        .prepare("SELECT timestamp, event FROM swap_history WHERE swap_id = ?1 ORDER BY rowid")?;
+    let rows = statement.query_map(&[&key.to_string() as &dyn ToSql], |row| {
+        let event = row.get::<_, String>(1);
+
+        serde_json::from_str(&event)
+            .map(|event| HistoryEntry {
+                timestamp: Utc.timestamp_millis(row.get(0)),
+                event,
+            })
+            .map_err(|e| Error::InvalidRecord(e.to_string()))
+    })?;
+
+    let mut history = Vec::new();
+    for row in rows {
+        history.push(row??);
+    }
+
+    Ok(history)
+}
+
+/// Databases created by earlier versions lack the columns added since
+fn has_column(connection: &Connection, table: &str, column: &str) -> Result<bool, Error> {
+    let mut statement = connection.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = statement
+        .query_map(NO_PARAMS, |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(columns.iter().any(|name| name == column))
+}
+
+fn push_cursor(params: &mut Vec<Box<dyn ToSql>>, cursor: &Cursor) {
+    params.push(Box::new(cursor.created_at.timestamp_millis()));
+    params.push(Box::new(cursor.created_at.timestamp_millis()));
+    params.push(Box::new(cursor.key.clone()));
+}
+
+fn record_from_row(row: &Row<'_, '_>) -> Result<(SwapId, SwapRecord), Error> {
+    let invalid_record = |e: &dyn ToString| Error::InvalidRecord(e.to_string());
+
+    let swap_id = row
+        .get::<_, String>(0)
+        .parse::<SwapId>()
+        .map_err(|e| Error::InvalidRecord(format!("{:?}", e)))?;
+    let metadata = Metadata::from_kind_names(
+        &row.get::<_, String>(1),
+        &row.get::<_, String>(2),
+        &row.get::<_, String>(3),
+        &row.get::<_, String>(4),
+        &row.get::<_, String>(5),
+    )
+    .map_err(|e| invalid_record(&e))?;
+    let identity = match row.get::<_, Option<String>>(9) {
+        Some(identity) => Some(PublicKey::from_hex(identity).map_err(|e| invalid_record(&e))?),
+        None => None,
+    };
+    let counterparty = match row.get::<_, Option<String>>(7) {
+        Some(address) => Some(Counterparty {
+            address: address
+                .parse::<SocketAddr>()
+                .map_err(|e| invalid_record(&e))?,
+            identity,
+        }),
+        None => None,
+    };
+
+    Ok((
+        swap_id,
+        SwapRecord {
+            metadata,
+            created_at: Utc.timestamp_millis(row.get(6)),
+            counterparty,
+            state: row.get(8),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swap_protocols::metadata_store::{AssetKind, LedgerKind, RoleKind};
+    use spectral::prelude::*;
+
+    fn metadata(role: RoleKind, beta_asset: AssetKind) -> Metadata {
+        Metadata {
+            alpha_ledger: LedgerKind::Bitcoin,
+            beta_ledger: LedgerKind::Ethereum,
+            alpha_asset: AssetKind::Bitcoin,
+            beta_asset,
+            role,
+        }
+    }
+
+    fn counterparty() -> Counterparty {
+        Counterparty {
+            address: "127.0.0.1:8011".parse().unwrap(),
+            identity: Some(
+                PublicKey::from_hex(
+                    "0317b7e1ce1f9f94c32a43739229f88c0b0333296fb46e8f72865849c6ae34b84e",
+                )
+                .unwrap(),
+            ),
+        }
+    }
+
+    #[test]
+    fn insert_and_get_metadata() {
+        let store = SqliteMetadataStore::open_in_memory().unwrap();
+        let id = SwapId::default();
+
+        store
+            .insert(
+                id,
+                metadata(RoleKind::Alice, AssetKind::Ether),
+                counterparty(),
+            )
+            .unwrap();
+
+        assert_that(&store.get(&id).unwrap())
+            .contains_value(metadata(RoleKind::Alice, AssetKind::Ether));
+        assert_that(&store.get(&SwapId::default()).unwrap()).is_none();
+        assert_that(&store.counterparty(&id).unwrap()).contains_value(counterparty());
+    }
+
+    #[test]
+    fn swaps_recorded_before_identities_were_verified_have_none() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute(
+                "CREATE TABLE swap_metadata (
+                    swap_id TEXT PRIMARY KEY NOT NULL,
+                    role TEXT NOT NULL,
+                    alpha_ledger TEXT NOT NULL,
+                    beta_ledger TEXT NOT NULL,
+                    alpha_asset TEXT NOT NULL,
+                    beta_asset TEXT NOT NULL,
+                    created_at INTEGER NOT NULL,
+                    counterparty TEXT,
+                    state TEXT NOT NULL
+                )",
+                NO_PARAMS,
+            )
+            .unwrap();
+        let id = SwapId::default();
+        connection
+            .execute(
+                "INSERT INTO swap_metadata VALUES
+                    (?1, 'Alice', 'Bitcoin', 'Ethereum', 'Bitcoin', 'Ether', 0, '127.0.0.1:8011', 'Start')",
+                &[&id.to_string() as &dyn ToSql],
+            )
+            .unwrap();
+
+        let store = SqliteMetadataStore::new(connection).unwrap();
+
+        assert_that(&store.counterparty(&id).unwrap()).contains_value(Counterparty {
+            identity: None,
+            ..counterparty()
+        });
+    }
+
+    #[test]
+    fn inserting_twice_fails_with_duplicate_key() {
+        let store = SqliteMetadataStore::open_in_memory().unwrap();
+        let id = SwapId::default();
+
+        store
+            .insert(
+                id,
+                metadata(RoleKind::Alice, AssetKind::Ether),
+                counterparty(),
+            )
+            .unwrap();
+        let result = store.insert(
+            id,
+            metadata(RoleKind::Bob, AssetKind::Ether),
+            counterparty(),
+        );
+
+        match result {
+            Err(Error::DuplicateKey) => {}
+            _ => panic!("expected DuplicateKey but got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn query_filters_by_role_asset_and_state() {
+        let store = SqliteMetadataStore::open_in_memory().unwrap();
+        let alice_ether = SwapId::default();
+        let bob_ether = SwapId::default();
+        let bob_erc20 = SwapId::default();
+
+        store
+            .insert(
+                alice_ether,
+                metadata(RoleKind::Alice, AssetKind::Ether),
+                counterparty(),
+            )
+            .unwrap();
+        store
+            .insert(
+                bob_ether,
+                metadata(RoleKind::Bob, AssetKind::Ether),
+                counterparty(),
+            )
+            .unwrap();
+        store
+            .insert(
+                bob_erc20,
+                metadata(RoleKind::Bob, AssetKind::Erc20),
+                counterparty(),
+            )
+            .unwrap();
+        store
+            .update_state(&bob_erc20, String::from("BothFunded"))
+            .unwrap();
+
+        let ids = |query: Query| -> Vec<SwapId> {
+            store
+                .query(&query)
+                .unwrap()
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect()
+        };
+
+        let bobs = ids(Query {
+            role: Some(RoleKind::Bob),
+            ..Query::default()
+        });
+        assert_that(&bobs).has_length(2);
+        assert_that(&bobs).contains(bob_ether);
+        assert_that(&bobs).contains(bob_erc20);
+
+        let ether_swaps = ids(Query {
+            beta_asset: Some(AssetKind::Ether),
+            ..Query::default()
+        });
+        assert_that(&ether_swaps).has_length(2);
+        assert_that(&ether_swaps).contains(alice_ether);
+        assert_that(&ether_swaps).contains(bob_ether);
+
+        assert_that(&ids(Query {
+            state: Some(String::from("BothFunded")),
+            ..Query::default()
+        }))
+        .is_equal_to(vec![bob_erc20]);
+        assert_that(&ids(Query {
+            created_before: Some(Utc.timestamp(0, 0)),
+            ..Query::default()
+        }))
+        .is_empty();
+    }
+
+    #[test]
+    fn query_pages_through_swaps_in_both_directions() {
+        let store = SqliteMetadataStore::open_in_memory().unwrap();
+        for _ in 0..5 {
+            store
+                .insert(
+                    SwapId::default(),
+                    metadata(RoleKind::Alice, AssetKind::Ether),
+                    counterparty(),
+                )
+                .unwrap();
+        }
+        let all: Vec<SwapId> = store
+            .query(&Query {
+                order: Order::NewestFirst,
+                ..Query::default()
+            })
+            .unwrap()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        let cursor = |index: usize| {
+            let (id, record) = store
+                .query(&Query {
+                    order: Order::NewestFirst,
+                    ..Query::default()
+                })
+                .unwrap()
+                .remove(index);
+            Cursor::of(&id, &record)
+        };
+        let ids = |query: Query| -> Vec<SwapId> {
+            store
+                .query(&query)
+                .unwrap()
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect()
+        };
+
+        assert_that(&ids(Query {
+            order: Order::NewestFirst,
+            after: Some(cursor(1)),
+            limit: Some(2),
+            ..Query::default()
+        }))
+        .is_equal_to(all[2..4].to_vec());
+        assert_that(&ids(Query {
+            order: Order::NewestFirst,
+            before: Some(cursor(3)),
+            limit: Some(2),
+            ..Query::default()
+        }))
+        .is_equal_to(all[1..3].to_vec());
+    }
+
+    #[test]
+    fn history_is_returned_in_the_order_it_was_appended() {
+        let store = SqliteMetadataStore::open_in_memory().unwrap();
+        let id = SwapId::default();
+        store
+            .insert(
+                id,
+                metadata(RoleKind::Alice, AssetKind::Ether),
+                counterparty(),
+            )
+            .unwrap();
+
+        let events = vec![
+            HistoryEvent::Transition {
+                state: String::from("AlphaDeployed"),
+                transaction: Some(json!("0x00a329c0648769a73afac7f9381e08fb43dbea72")),
+            },
+            HistoryEvent::ActionExecuted {
+                action: String::from("accept"),
+            },
+        ];
+        for event in events.clone() {
+            store.append_history(&id, event).unwrap();
+        }
+
+        let history: Vec<HistoryEvent> = store
+            .history(&id)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.event)
+            .collect();
+        assert_that(&history).is_equal_to(events);
+    }
+
+    #[test]
+    fn counter_offer_is_only_accepted_by_the_first_swap() {
+        let store = SqliteMetadataStore::open_in_memory().unwrap();
+        let id = SwapId::default();
+        let first_swap = SwapId::default();
+        store
+            .insert(
+                id,
+                metadata(RoleKind::Alice, AssetKind::Ether),
+                counterparty(),
+            )
+            .unwrap();
+
+        let accepted = store.accept_counter_offer(&id, first_swap).unwrap();
+        let accepted_again = store.accept_counter_offer(&id, SwapId::default()).unwrap();
+
+        assert_that(&accepted).is_equal_to(first_swap);
+        assert_that(&accepted_again).is_equal_to(first_swap);
+        assert_that(&store.history(&id).unwrap().len()).is_equal_to(1);
+    }
+
+    #[test]
+    fn history_of_unknown_swap_is_not_found() {
+        let store = SqliteMetadataStore::open_in_memory().unwrap();
+
+        let result = store.append_history(
+            &SwapId::default(),
+            HistoryEvent::ActionExecuted {
+                action: String::from("accept"),
+            },
+        );
+
+        match result {
+            Err(Error::NotFound) => {}
+            _ => panic!("expected NotFound but got {:?}", result),
+        }
+        assert_that(&store.history(&SwapId::default()).is_err()).is_true();
+    }
+}
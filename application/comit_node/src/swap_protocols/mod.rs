@@ -7,12 +7,14 @@ pub mod metadata_store;
 pub use self::{
     ledger::Ledger,
     metadata_store::{
-        AssetKind, InMemoryMetadataStore, LedgerKind, Metadata, MetadataStore, RoleKind,
+        AssetKind, InMemoryMetadataStore, LedgerKind, Metadata, MetadataStore, Query, RoleKind,
+        SqliteMetadataStore, SwapRecord,
     },
 };
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum SwapProtocols {
+    #[serde(rename = "COMIT-RFC-003")]
     Rfc003,
 }
 
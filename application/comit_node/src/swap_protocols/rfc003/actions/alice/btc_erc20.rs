@@ -1,16 +1,20 @@
-use crate::swap_protocols::{
-    ledger::{Bitcoin, Ethereum},
-    rfc003::{
-        actions::{ActionKind, Actions},
-        bitcoin,
-        ethereum::{self, Erc20Htlc},
-        roles::Alice,
-        state_machine::*,
+use crate::{
+    comit_client::SwapReject,
+    swap_protocols::{
+        ledger::{Bitcoin, Ethereum},
+        rfc003::{
+            actions::{alice::AcceptCounterOffer, ActionKind, Actions},
+            bitcoin,
+            ethereum::{self, Erc20Htlc},
+            roles::Alice,
+            state_machine::*,
+        },
     },
 };
 use bitcoin_support::{BitcoinQuantity, OutPoint};
 use bitcoin_witness::PrimedInput;
 use ethereum_support::{Bytes, Erc20Quantity, EtherQuantity};
+use std::time::SystemTime;
 
 impl OngoingSwap<Alice<Bitcoin, Ethereum, BitcoinQuantity, Erc20Quantity>> {
     pub fn fund_action(&self) -> bitcoin::SendToAddress {
@@ -45,12 +49,19 @@ impl OngoingSwap<Alice<Bitcoin, Ethereum, BitcoinQuantity, Erc20Quantity>> {
             data,
             gas_limit,
             value: EtherQuantity::zero(),
+            chain_id: self.beta_ledger.chain_id,
         }
     }
 }
 
-type AliceActionKind =
-    ActionKind<(), (), (), bitcoin::SendToAddress, ethereum::SendTransaction, bitcoin::SpendOutput>;
+type AliceActionKind = ActionKind<
+    AcceptCounterOffer<Bitcoin, Ethereum, BitcoinQuantity, Erc20Quantity>,
+    (),
+    (),
+    bitcoin::SendToAddress,
+    ethereum::SendTransaction,
+    bitcoin::SpendOutput,
+>;
 
 impl Actions for SwapStates<Alice<Bitcoin, Ethereum, BitcoinQuantity, Erc20Quantity>> {
     type ActionKind = AliceActionKind;
@@ -63,31 +74,50 @@ impl Actions for SwapStates<Alice<Bitcoin, Ethereum, BitcoinQuantity, Erc20Quant
                 ref alpha_htlc_location,
                 ref beta_htlc_location,
                 ref swap,
+                alpha_htlc_expired,
+                beta_htlc_expired,
                 ..
-            }) => vec![
-                ActionKind::Redeem(swap.redeem_action(*beta_htlc_location)),
-                ActionKind::Refund(swap.refund_action(*alpha_htlc_location)),
-            ],
+            }) => {
+                let mut actions = vec![];
+                if !beta_htlc_expired {
+                    actions.push(ActionKind::Redeem(swap.redeem_action(*beta_htlc_location)));
+                }
+                if alpha_htlc_expired {
+                    actions.push(ActionKind::Refund(swap.refund_action(*alpha_htlc_location)));
+                }
+                actions
+            }
             SS::AlphaFundedBetaRefunded(AlphaFundedBetaRefunded {
                 ref swap,
                 ref alpha_htlc_location,
+                alpha_htlc_expired: true,
                 ..
             })
             | SS::AlphaFundedBetaRedeemed(AlphaFundedBetaRedeemed {
                 ref swap,
                 ref alpha_htlc_location,
+                alpha_htlc_expired: true,
                 ..
             }) => vec![ActionKind::Refund(swap.refund_action(*alpha_htlc_location))],
             SS::AlphaRefundedBetaFunded(AlphaRefundedBetaFunded {
                 ref beta_htlc_location,
                 ref swap,
+                beta_htlc_expired: false,
                 ..
             })
             | SS::AlphaRedeemedBetaFunded(AlphaRedeemedBetaFunded {
                 ref beta_htlc_location,
                 ref swap,
+                beta_htlc_expired: false,
                 ..
             }) => vec![ActionKind::Redeem(swap.redeem_action(*beta_htlc_location))],
+            SS::Final(Final(SwapOutcome::Rejected {
+                ref start,
+                rejection_type: SwapReject::CounterOffered(ref offer),
+            })) => AcceptCounterOffer::new(start, offer, SystemTime::now())
+                .map(ActionKind::Accept)
+                .into_iter()
+                .collect(),
             _ => vec![],
         }
     }
@@ -1,16 +1,20 @@
-use crate::swap_protocols::{
-    ledger::{Bitcoin, Ethereum},
-    rfc003::{
-        actions::{ActionKind, Actions},
-        bitcoin,
-        ethereum::{self, EtherHtlc},
-        roles::Alice,
-        state_machine::*,
+use crate::{
+    comit_client::SwapReject,
+    swap_protocols::{
+        ledger::{Bitcoin, Ethereum},
+        rfc003::{
+            actions::{alice::AcceptCounterOffer, ActionKind, Actions},
+            bitcoin,
+            ethereum::{self, EtherHtlc},
+            roles::Alice,
+            state_machine::*,
+        },
     },
 };
 use bitcoin_support::{BitcoinQuantity, OutPoint};
 use bitcoin_witness::PrimedInput;
 use ethereum_support::{Bytes, EtherQuantity, U256};
+use std::time::SystemTime;
 
 impl OngoingSwap<Alice<Bitcoin, Ethereum, BitcoinQuantity, EtherQuantity>> {
     pub fn fund_action(&self) -> bitcoin::SendToAddress {
@@ -43,12 +47,19 @@ impl OngoingSwap<Alice<Bitcoin, Ethereum, BitcoinQuantity, EtherQuantity>> {
             data,
             gas_limit,
             value: EtherQuantity::from_wei(U256::zero()),
+            chain_id: self.beta_ledger.chain_id,
         }
     }
 }
 
-type AliceActionKind =
-    ActionKind<(), (), (), bitcoin::SendToAddress, ethereum::SendTransaction, bitcoin::SpendOutput>;
+type AliceActionKind = ActionKind<
+    AcceptCounterOffer<Bitcoin, Ethereum, BitcoinQuantity, EtherQuantity>,
+    (),
+    (),
+    bitcoin::SendToAddress,
+    ethereum::SendTransaction,
+    bitcoin::SpendOutput,
+>;
 
 impl Actions for SwapStates<Alice<Bitcoin, Ethereum, BitcoinQuantity, EtherQuantity>> {
     type ActionKind = AliceActionKind;
@@ -61,32 +72,149 @@ impl Actions for SwapStates<Alice<Bitcoin, Ethereum, BitcoinQuantity, EtherQuant
                 ref alpha_htlc_location,
                 ref beta_htlc_location,
                 ref swap,
+                alpha_htlc_expired,
+                beta_htlc_expired,
                 ..
-            }) => vec![
-                ActionKind::Redeem(swap.redeem_action(*beta_htlc_location)),
-                ActionKind::Refund(swap.refund_action(*alpha_htlc_location)),
-            ],
+            }) => {
+                let mut actions = vec![];
+                if !beta_htlc_expired {
+                    actions.push(ActionKind::Redeem(swap.redeem_action(*beta_htlc_location)));
+                }
+                if alpha_htlc_expired {
+                    actions.push(ActionKind::Refund(swap.refund_action(*alpha_htlc_location)));
+                }
+                actions
+            }
             SS::AlphaFundedBetaRefunded(AlphaFundedBetaRefunded {
                 ref swap,
                 ref alpha_htlc_location,
+                alpha_htlc_expired: true,
                 ..
             })
             | SS::AlphaFundedBetaRedeemed(AlphaFundedBetaRedeemed {
                 ref swap,
                 ref alpha_htlc_location,
+                alpha_htlc_expired: true,
                 ..
             }) => vec![ActionKind::Refund(swap.refund_action(*alpha_htlc_location))],
             SS::AlphaRefundedBetaFunded(AlphaRefundedBetaFunded {
                 ref beta_htlc_location,
                 ref swap,
+                beta_htlc_expired: false,
                 ..
             })
             | SS::AlphaRedeemedBetaFunded(AlphaRedeemedBetaFunded {
                 ref beta_htlc_location,
                 ref swap,
+                beta_htlc_expired: false,
                 ..
             }) => vec![ActionKind::Redeem(swap.redeem_action(*beta_htlc_location))],
+            SS::Final(Final(SwapOutcome::Rejected {
+                ref start,
+                rejection_type: SwapReject::CounterOffered(ref offer),
+            })) => AcceptCounterOffer::new(start, offer, SystemTime::now())
+                .map(ActionKind::Accept)
+                .into_iter()
+                .collect(),
             _ => vec![],
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swap_protocols::rfc003::{
+        bitcoin::{AbsoluteTimelock, Timelock},
+        ethereum::Seconds,
+        roles::test::Alisha,
+        Secret, Timestamp,
+    };
+    use bitcoin_support::{Address, Blocks, Transaction};
+    use bitcoin_witness::SEQUENCE_ALLOW_NTIMELOCK_NO_RBF;
+    use hex::FromHex;
+    use std::str::FromStr;
+
+    fn both_funded_with_expired_alpha_htlc(
+        alpha_ledger_lock_duration: Timelock,
+    ) -> SwapStates<Alisha> {
+        let start = Start {
+            alpha_ledger_refund_identity: secp256k1_support::KeyPair::from_secret_key_slice(
+                &hex::decode("18e14a7b6a307f426a94f8114701e7c8e774e7f9a47e2c2035db29a206321725")
+                    .unwrap(),
+            )
+            .unwrap(),
+            beta_ledger_redeem_identity: "8457037fcd80a8650c4692d7fcfc1d0a96b92867"
+                .parse()
+                .unwrap(),
+            alpha_ledger: Bitcoin::regtest(),
+            beta_ledger: Ethereum::default(),
+            alpha_asset: BitcoinQuantity::from_bitcoin(1.0),
+            beta_asset: EtherQuantity::from_eth(10.0),
+            alpha_ledger_lock_duration,
+            secret: Secret::from(*b"hello world, you are beautiful!!"),
+            role: Alisha::default(),
+        };
+        let response = StateMachineResponse {
+            alpha_ledger_redeem_identity: bitcoin_support::PubkeyHash::from_hex(
+                "875638cac0b0ae9f826575e190f2788918c354c2",
+            )
+            .unwrap(),
+            beta_ledger_refund_identity: "71b9f69dcabb340a3fe229c3f94f1662ad85e5e8"
+                .parse()
+                .unwrap(),
+            beta_ledger_lock_duration: Seconds(43200),
+        };
+
+        SwapStates::from(BothFunded {
+            swap: OngoingSwap::new(start, response),
+            alpha_htlc_location: OutPoint {
+                txid: bitcoin_support::Sha256dHash::from_data(b"funding"),
+                vout: 0,
+            },
+            alpha_htlc_deployed_at: Timestamp(1_550_000_000),
+            beta_htlc_location: "e5d2e6c23e6b8e5bb4e6a5b4b24f5d18ad55ec60".parse().unwrap(),
+            beta_htlc_deployed_at: Timestamp(1_550_000_000),
+            alpha_htlc_expired: true,
+            beta_htlc_expired: true,
+        })
+    }
+
+    fn refund_transaction(state: SwapStates<Alisha>) -> Transaction {
+        let refund = state
+            .actions()
+            .into_iter()
+            .find_map(|action| match action {
+                ActionKind::Refund(refund) => Some(refund),
+                _ => None,
+            })
+            .unwrap();
+
+        refund
+            .spend_to(Address::from_str("bcrt1qcqslz7lfn34dl096t5uwurff9spen5h4v2pmap").unwrap())
+            .sign_with_fee(BitcoinQuantity::from_satoshi(1000))
+    }
+
+    #[test]
+    fn given_relative_timelock_then_refund_waits_for_the_input_sequence() {
+        let transaction = refund_transaction(both_funded_with_expired_alpha_htlc(
+            Timelock::Relative(Blocks::from(144)),
+        ));
+
+        assert_eq!(transaction.input[0].sequence, 144);
+        assert_eq!(transaction.lock_time, 0);
+    }
+
+    #[test]
+    fn given_absolute_timelock_then_refund_waits_for_the_transaction_lock_time() {
+        let transaction = refund_transaction(both_funded_with_expired_alpha_htlc(
+            Timelock::Absolute(AbsoluteTimelock::BlockHeight(600_000)),
+        ));
+
+        assert_eq!(
+            transaction.input[0].sequence,
+            SEQUENCE_ALLOW_NTIMELOCK_NO_RBF
+        );
+        assert_eq!(transaction.lock_time, 600_000);
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{
+    comit_client::SwapReject,
+    swap_protocols::{
+        ledger::Ethereum,
+        rfc003::{
+            actions::{alice::AcceptCounterOffer, ActionKind, Actions},
+            ethereum::{self, Erc20Htlc, Htlc},
+            roles::Alice,
+            state_machine::*,
+        },
+    },
+};
+use ethereum_support::{Bytes, Erc20Quantity, EtherQuantity};
+use std::time::SystemTime;
+
+impl OngoingSwap<Alice<Ethereum, Ethereum, Erc20Quantity, Erc20Quantity>> {
+    pub fn deploy_action(&self) -> ethereum::ContractDeploy {
+        let htlc = Erc20Htlc::from(self.alpha_htlc_params());
+        let data = htlc.compile_to_hex().into();
+        let gas_limit = htlc.deployment_gas_limit();
+
+        ethereum::ContractDeploy {
+            data,
+            value: EtherQuantity::zero(),
+            gas_limit,
+            chain_id: self.alpha_ledger.chain_id,
+        }
+    }
+
+    pub fn fund_action(
+        &self,
+        alpha_htlc_location: ethereum_support::Address,
+    ) -> ethereum::SendTransaction {
+        let htlc = Erc20Htlc::from(self.alpha_htlc_params());
+        let gas_limit = Erc20Htlc::fund_tx_gas_limit();
+
+        ethereum::SendTransaction {
+            to: self.alpha_asset.token_contract(),
+            data: htlc.funding_tx_payload(alpha_htlc_location),
+            gas_limit,
+            value: EtherQuantity::zero(),
+            chain_id: self.alpha_ledger.chain_id,
+        }
+    }
+
+    pub fn refund_action(
+        &self,
+        alpha_htlc_location: ethereum_support::Address,
+    ) -> ethereum::SendTransaction {
+        let data = Bytes::default();
+        let gas_limit = Erc20Htlc::tx_gas_limit();
+
+        ethereum::SendTransaction {
+            to: alpha_htlc_location,
+            data,
+            gas_limit,
+            value: EtherQuantity::zero(),
+            chain_id: self.alpha_ledger.chain_id,
+        }
+    }
+
+    pub fn redeem_action(
+        &self,
+        beta_htlc_location: ethereum_support::Address,
+    ) -> ethereum::SendTransaction {
+        let data = Bytes::from(self.secret.raw_secret().to_vec());
+        let gas_limit = Erc20Htlc::tx_gas_limit();
+
+        ethereum::SendTransaction {
+            to: beta_htlc_location,
+            data,
+            gas_limit,
+            value: EtherQuantity::zero(),
+            chain_id: self.beta_ledger.chain_id,
+        }
+    }
+}
+
+type AliceActionKind = ActionKind<
+    AcceptCounterOffer<Ethereum, Ethereum, Erc20Quantity, Erc20Quantity>,
+    (),
+    ethereum::ContractDeploy,
+    ethereum::SendTransaction,
+    ethereum::SendTransaction,
+    ethereum::SendTransaction,
+>;
+
+impl Actions for SwapStates<Alice<Ethereum, Ethereum, Erc20Quantity, Erc20Quantity>> {
+    type ActionKind = AliceActionKind;
+
+    fn actions(&self) -> Vec<AliceActionKind> {
+        use self::SwapStates as SS;
+        match *self {
+            SS::Accepted(Accepted { ref swap, .. }) => {
+                vec![ActionKind::Deploy(swap.deploy_action())]
+            }
+            SS::AlphaDeployed(AlphaDeployed {
+                ref swap,
+                ref alpha_htlc_location,
+                ..
+            }) => vec![ActionKind::Fund(swap.fund_action(*alpha_htlc_location))],
+            SS::BothFunded(BothFunded {
+                ref alpha_htlc_location,
+                ref beta_htlc_location,
+                ref swap,
+                alpha_htlc_expired,
+                beta_htlc_expired,
+                ..
+            }) => {
+                let mut actions = vec![];
+                if !beta_htlc_expired {
+                    actions.push(ActionKind::Redeem(swap.redeem_action(*beta_htlc_location)));
+                }
+                if alpha_htlc_expired {
+                    actions.push(ActionKind::Refund(swap.refund_action(*alpha_htlc_location)));
+                }
+                actions
+            }
+            SS::AlphaFundedBetaRefunded(AlphaFundedBetaRefunded {
+                ref swap,
+                ref alpha_htlc_location,
+                alpha_htlc_expired: true,
+                ..
+            })
+            | SS::AlphaFundedBetaRedeemed(AlphaFundedBetaRedeemed {
+                ref swap,
+                ref alpha_htlc_location,
+                alpha_htlc_expired: true,
+                ..
+            }) => vec![ActionKind::Refund(swap.refund_action(*alpha_htlc_location))],
+            SS::AlphaRefundedBetaFunded(AlphaRefundedBetaFunded {
+                ref beta_htlc_location,
+                ref swap,
+                beta_htlc_expired: false,
+                ..
+            })
+            | SS::AlphaRedeemedBetaFunded(AlphaRedeemedBetaFunded {
+                ref beta_htlc_location,
+                ref swap,
+                beta_htlc_expired: false,
+                ..
+            }) => vec![ActionKind::Redeem(swap.redeem_action(*beta_htlc_location))],
+            SS::Final(Final(SwapOutcome::Rejected {
+                ref start,
+                rejection_type: SwapReject::CounterOffered(ref offer),
+            })) => AcceptCounterOffer::new(start, offer, SystemTime::now())
+                .map(ActionKind::Accept)
+                .into_iter()
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
@@ -1,2 +1,146 @@
+use crate::{
+    comit_client::CounterOffer,
+    swap_protocols::{
+        asset::Asset,
+        rfc003::{
+            alice::{SwapRequest, SwapRequestIdentities},
+            roles::Alice,
+            state_machine::Start,
+            Ledger,
+        },
+    },
+};
+use std::time::SystemTime;
+
 mod btc_erc20;
 mod btc_eth;
+mod erc20_btc;
+mod erc20_erc20;
+mod erc20_eth;
+mod eth_btc;
+mod eth_erc20;
+
+/// Sends a new swap request on the terms Bob counter-offered
+#[derive(Clone, Debug, PartialEq)]
+pub struct AcceptCounterOffer<AL: Ledger, BL: Ledger, AA, BA> {
+    pub request: SwapRequest<AL, BL, AA, BA>,
+    /// The beta lock Bob offered to respond with
+    pub beta_ledger_lock_duration: Option<BL::LockDuration>,
+}
+
+impl<AL: Ledger, BL: Ledger, AA: Asset, BA: Asset> AcceptCounterOffer<AL, BL, AA, BA> {
+    pub fn new(
+        start: &Start<Alice<AL, BL, AA, BA>>,
+        offer: &CounterOffer,
+        now: SystemTime,
+    ) -> Option<Self> {
+        if offer.is_expired(now) {
+            return None;
+        }
+
+        let terms = offer
+            .terms::<AL, BL, BA>()
+            .map_err(|e| error!("Counter-offer {:?} has invalid terms: {}", offer, e))
+            .ok()?;
+
+        Some(AcceptCounterOffer {
+            request: SwapRequest {
+                alpha_asset: start.alpha_asset.clone(),
+                beta_asset: terms.beta_asset,
+                alpha_ledger: start.alpha_ledger.clone(),
+                beta_ledger: start.beta_ledger.clone(),
+                alpha_ledger_lock_duration: terms.alpha_ledger_lock_duration,
+                identities: SwapRequestIdentities {
+                    alpha_ledger_refund_identity: start.alpha_ledger_refund_identity.clone(),
+                    beta_ledger_redeem_identity: start.beta_ledger_redeem_identity.clone(),
+                },
+            },
+            beta_ledger_lock_duration: terms.beta_ledger_lock_duration,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        comit_client::{rfc003::CounterOfferBody, SwapReject},
+        swap_protocols::{
+            ledger::{Bitcoin, Ethereum},
+            rfc003::{
+                actions::{ActionKind, Actions},
+                bitcoin::Timelock,
+                ethereum::Seconds,
+                roles::test::Alisha,
+                state_machine::{Final, SwapOutcome, SwapStates},
+                Secret,
+            },
+        },
+    };
+    use bitcoin_support::{BitcoinQuantity, Blocks};
+    use ethereum_support::EtherQuantity;
+    use spectral::prelude::*;
+    use std::{str::FromStr, time::Duration};
+
+    fn counter_offered_state(expires_at: u64) -> SwapStates<Alisha> {
+        let start = Start {
+            alpha_ledger_refund_identity: secp256k1_support::KeyPair::from_secret_key_slice(
+                &hex::decode("18e14a7b6a307f426a94f8114701e7c8e774e7f9a47e2c2035db29a206321725")
+                    .unwrap(),
+            )
+            .unwrap(),
+            beta_ledger_redeem_identity: ethereum_support::Address::from_str(
+                "8457037fcd80a8650c4692d7fcfc1d0a96b92867",
+            )
+            .unwrap(),
+            alpha_ledger: Bitcoin::regtest(),
+            beta_ledger: Ethereum::default(),
+            alpha_asset: BitcoinQuantity::from_bitcoin(1.0),
+            beta_asset: EtherQuantity::from_eth(10.0),
+            alpha_ledger_lock_duration: Blocks::from(144).into(),
+            secret: Secret::from(*b"hello world, you are beautiful!!"),
+            role: Alisha::default(),
+        };
+        let offer = CounterOffer::new(CounterOfferBody::<Bitcoin, Ethereum, EtherQuantity> {
+            beta_asset: EtherQuantity::from_eth(9.0),
+            alpha_ledger_lock_duration: Blocks::from(288).into(),
+            beta_ledger_lock_duration: Some(Seconds(86_400)),
+            expires_at,
+        });
+
+        SwapStates::from(Final(SwapOutcome::Rejected {
+            start,
+            rejection_type: SwapReject::CounterOffered(offer),
+        }))
+    }
+
+    #[test]
+    fn counter_offer_can_be_accepted_with_revised_request() {
+        let now = SystemTime::now() + Duration::from_secs(60);
+        let expires_at = now.duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let state = counter_offered_state(expires_at);
+
+        let accept = state
+            .actions()
+            .into_iter()
+            .find_map(|action| match action {
+                ActionKind::Accept(accept) => Some(accept),
+                _ => None,
+            })
+            .unwrap();
+        let request = accept.request;
+
+        assert_that(&accept.beta_ledger_lock_duration).is_equal_to(Some(Seconds(86_400)));
+        assert_that(&request.alpha_asset).is_equal_to(BitcoinQuantity::from_bitcoin(1.0));
+        assert_that(&request.beta_asset).is_equal_to(EtherQuantity::from_eth(9.0));
+        assert_that(&request.alpha_ledger_lock_duration)
+            .is_equal_to(Timelock::from(Blocks::from(288)));
+    }
+
+    #[test]
+    fn expired_counter_offer_cannot_be_accepted() {
+        let state = counter_offered_state(0);
+
+        assert_that(&state.actions()).is_empty();
+    }
+}
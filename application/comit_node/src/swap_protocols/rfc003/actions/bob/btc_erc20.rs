@@ -26,6 +26,7 @@ impl OngoingSwap<Bob<Bitcoin, Ethereum, BitcoinQuantity, Erc20Quantity>> {
             data,
             value: EtherQuantity::zero(),
             gas_limit,
+            chain_id: self.beta_ledger.chain_id,
         }
     }
 
@@ -41,6 +42,7 @@ impl OngoingSwap<Bob<Bitcoin, Ethereum, BitcoinQuantity, Erc20Quantity>> {
             data,
             gas_limit,
             value: EtherQuantity::zero(),
+            chain_id: self.beta_ledger.chain_id,
         }
     }
 
@@ -56,6 +58,7 @@ impl OngoingSwap<Bob<Bitcoin, Ethereum, BitcoinQuantity, Erc20Quantity>> {
             data: htlc.funding_tx_payload(beta_htlc_location),
             gas_limit,
             value: EtherQuantity::zero(),
+            chain_id: self.beta_ledger.chain_id,
         }
     }
 
@@ -90,8 +93,12 @@ impl Actions for SwapStates<Bob<Bitcoin, Ethereum, BitcoinQuantity, Erc20Quantit
     fn actions(&self) -> Vec<BobActionKind> {
         use self::SwapStates as SS;
         match *self {
-            SS::Start(Start { ref role, .. }) => vec![
-                ActionKind::Accept(role.accept_action()),
+            SS::Start(Start {
+                ref role,
+                ref alpha_ledger_lock_duration,
+                ..
+            }) => vec![
+                ActionKind::Accept(role.accept_action(alpha_ledger_lock_duration.clone())),
                 ActionKind::Decline(role.decline_action()),
             ],
             SS::AlphaFunded(AlphaFunded { ref swap, .. }) => {
@@ -105,22 +112,26 @@ impl Actions for SwapStates<Bob<Bitcoin, Ethereum, BitcoinQuantity, Erc20Quantit
             SS::BothFunded(BothFunded {
                 ref beta_htlc_location,
                 ref swap,
+                beta_htlc_expired: true,
                 ..
             })
             | SS::AlphaRedeemedBetaFunded(AlphaRedeemedBetaFunded {
                 ref beta_htlc_location,
                 ref swap,
+                beta_htlc_expired: true,
                 ..
             })
             | SS::AlphaRefundedBetaFunded(AlphaRefundedBetaFunded {
                 ref beta_htlc_location,
                 ref swap,
+                beta_htlc_expired: true,
                 ..
             }) => vec![ActionKind::Refund(swap.refund_action(*beta_htlc_location))],
             SS::AlphaFundedBetaRedeemed(AlphaFundedBetaRedeemed {
                 ref swap,
                 ref alpha_htlc_location,
                 ref beta_redeemed_tx,
+                alpha_htlc_expired: false,
                 ..
             }) => vec![ActionKind::Redeem(
                 swap.redeem_action(*alpha_htlc_location, beta_redeemed_tx.secret),
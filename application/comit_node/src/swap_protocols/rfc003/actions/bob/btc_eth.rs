@@ -26,6 +26,7 @@ impl OngoingSwap<Bob<Bitcoin, Ethereum, BitcoinQuantity, EtherQuantity>> {
             data,
             value: self.beta_asset,
             gas_limit,
+            chain_id: self.beta_ledger.chain_id,
         }
     }
 
@@ -41,6 +42,7 @@ impl OngoingSwap<Bob<Bitcoin, Ethereum, BitcoinQuantity, EtherQuantity>> {
             data,
             gas_limit,
             value: EtherQuantity::zero(),
+            chain_id: self.alpha_ledger.chain_id,
         }
     }
 
@@ -76,8 +78,12 @@ impl Actions for SwapStates<Bob<Bitcoin, Ethereum, BitcoinQuantity, EtherQuantit
     fn actions(&self) -> Vec<BobActionKind> {
         use self::SwapStates as SS;
         match *self {
-            SS::Start(Start { ref role, .. }) => vec![
-                ActionKind::Accept(role.accept_action()),
+            SS::Start(Start {
+                ref role,
+                ref alpha_ledger_lock_duration,
+                ..
+            }) => vec![
+                ActionKind::Accept(role.accept_action(alpha_ledger_lock_duration.clone())),
                 ActionKind::Decline(role.decline_action()),
             ],
             SS::AlphaFunded(AlphaFunded { ref swap, .. }) => {
@@ -86,23 +92,27 @@ impl Actions for SwapStates<Bob<Bitcoin, Ethereum, BitcoinQuantity, EtherQuantit
             SS::BothFunded(BothFunded {
                 ref beta_htlc_location,
                 ref swap,
+                beta_htlc_expired: true,
                 ..
             }) => vec![ActionKind::Refund(swap.refund_action(*beta_htlc_location))],
             SS::AlphaFundedBetaRefunded { .. } => vec![],
             SS::AlphaRedeemedBetaFunded(AlphaRedeemedBetaFunded {
                 ref beta_htlc_location,
                 ref swap,
+                beta_htlc_expired: true,
                 ..
             }) => vec![ActionKind::Refund(swap.refund_action(*beta_htlc_location))],
             SS::AlphaRefundedBetaFunded(AlphaRefundedBetaFunded {
                 ref beta_htlc_location,
                 ref swap,
+                beta_htlc_expired: true,
                 ..
             }) => vec![ActionKind::Refund(swap.refund_action(*beta_htlc_location))],
             SS::AlphaFundedBetaRedeemed(AlphaFundedBetaRedeemed {
                 ref swap,
                 ref alpha_htlc_location,
                 ref beta_redeemed_tx,
+                alpha_htlc_expired: false,
                 ..
             }) => vec![ActionKind::Redeem(
                 swap.redeem_action(*alpha_htlc_location, beta_redeemed_tx.secret),
@@ -116,13 +126,18 @@ impl Actions for SwapStates<Bob<Bitcoin, Ethereum, BitcoinQuantity, EtherQuantit
 mod tests {
 
     use super::*;
-    use crate::swap_protocols::rfc003::{roles::test::Bobisha, Secret};
+    use crate::swap_protocols::rfc003::{
+        actions::bob::AcceptError,
+        bitcoin::{AbsoluteTimelock, Timelock},
+        ethereum::Seconds,
+        roles::test::Bobisha,
+        Secret, UnsafeLockDurations,
+    };
     use hex::FromHex;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-    #[test]
-    fn given_state_instance_when_calling_actions_should_not_need_to_specify_type_arguments() {
-        let (bobisha, _) = Bobisha::create();
-        let swap_state = SwapStates::from(Start::<Bobisha> {
+    fn start(bobisha: Bobisha, alpha_ledger_lock_duration: Timelock) -> Start<Bobisha> {
+        Start {
             alpha_ledger_refund_identity: bitcoin_support::PubkeyHash::from_hex(
                 "875638cac0b0ae9f826575e190f2788918c354c2",
             )
@@ -134,10 +149,48 @@ mod tests {
             beta_ledger: Ethereum::default(),
             alpha_asset: BitcoinQuantity::from_bitcoin(1.0),
             beta_asset: EtherQuantity::from_eth(10.0),
-            alpha_ledger_lock_duration: bitcoin_support::Blocks::from(144),
+            alpha_ledger_lock_duration,
             secret: Secret::from(*b"hello world, you are beautiful!!").hash(),
             role: bobisha,
-        });
+        }
+    }
+
+    fn response(
+        beta_ledger_lock_duration: u32,
+    ) -> StateMachineResponse<secp256k1_support::KeyPair, ethereum_support::Address, Seconds> {
+        StateMachineResponse {
+            alpha_ledger_redeem_identity: secp256k1_support::KeyPair::from_secret_key_slice(
+                &hex::decode("18e14a7b6a307f426a94f8114701e7c8e774e7f9a47e2c2035db29a206321725")
+                    .unwrap(),
+            )
+            .unwrap(),
+            beta_ledger_refund_identity: "71b9f69dcabb340a3fe229c3f94f1662ad85e5e8"
+                .parse()
+                .unwrap(),
+            beta_ledger_lock_duration: Seconds(beta_ledger_lock_duration.into()),
+        }
+    }
+
+    fn accept_action(swap_state: SwapStates<Bobisha>) -> Accept<Bitcoin, Ethereum> {
+        swap_state
+            .actions()
+            .into_iter()
+            .find_map(|action| match action {
+                ActionKind::Accept(accept) => Some(accept),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    fn six_hours() -> Duration {
+        Duration::from_secs(6 * 60 * 60)
+    }
+
+    #[test]
+    fn given_state_instance_when_calling_actions_should_not_need_to_specify_type_arguments() {
+        let (bobisha, _) = Bobisha::create(None);
+        let swap_state =
+            SwapStates::from(start(bobisha, bitcoin_support::Blocks::from(144).into()));
 
         let actions = swap_state.actions();
 
@@ -150,4 +203,69 @@ mod tests {
             .is_some());
     }
 
+    #[test]
+    fn given_relative_alpha_timelock_then_beta_lock_must_leave_margin() {
+        let (bobisha, _response) = Bobisha::create(None);
+        let accept = accept_action(SwapStates::from(start(
+            bobisha,
+            Timelock::Relative(bitcoin_support::Blocks::from(144)),
+        )));
+
+        match accept.accept(response(86400), six_hours()) {
+            Err(AcceptError::UnsafeLockDurations(_)) => {}
+            other => panic!("expected unsafe lock durations but got {:?}", other),
+        }
+        assert_eq!(accept.accept(response(43200), six_hours()), Ok(()));
+    }
+
+    #[test]
+    fn given_absolute_alpha_timestamp_then_beta_lock_must_leave_margin() {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as u32;
+        let (bobisha, _response) = Bobisha::create(None);
+        let accept = accept_action(SwapStates::from(start(
+            bobisha,
+            Timelock::Absolute(AbsoluteTimelock::Timestamp(now + 86400)),
+        )));
+
+        match accept.accept(response(86400), six_hours()) {
+            Err(AcceptError::UnsafeLockDurations(_)) => {}
+            other => panic!("expected unsafe lock durations but got {:?}", other),
+        }
+        assert_eq!(accept.accept(response(43200), six_hours()), Ok(()));
+    }
+
+    #[test]
+    fn accepting_after_the_swap_stopped_waiting_for_the_response_fails() {
+        let (bobisha, response_future) = Bobisha::create(None);
+        let accept = accept_action(SwapStates::from(start(
+            bobisha,
+            Timelock::Relative(bitcoin_support::Blocks::from(144)),
+        )));
+        // The swap drops the response future once the request timed out
+        drop(response_future);
+
+        assert_eq!(
+            accept.accept(response(43200), six_hours()),
+            Err(AcceptError::NoLongerAwaited)
+        );
+    }
+
+    #[test]
+    fn given_absolute_alpha_block_height_before_latest_block_is_known_then_accept_fails() {
+        let (bobisha, _response) = Bobisha::create(None);
+        let accept = accept_action(SwapStates::from(start(
+            bobisha,
+            Timelock::Absolute(AbsoluteTimelock::BlockHeight(600_000)),
+        )));
+
+        assert_eq!(
+            accept.accept(response(43200), six_hours()),
+            Err(AcceptError::UnsafeLockDurations(
+                UnsafeLockDurations::Unknown
+            ))
+        );
+    }
 }
@@ -0,0 +1,141 @@
+use crate::swap_protocols::{
+    ledger::Ethereum,
+    rfc003::{
+        actions::{
+            bob::{Accept, Decline},
+            ActionKind, Actions,
+        },
+        ethereum::{self, Erc20Htlc, Htlc},
+        roles::Bob,
+        secret::Secret,
+        state_machine::*,
+    },
+};
+use ethereum_support::{Bytes, Erc20Quantity, EtherQuantity};
+
+impl OngoingSwap<Bob<Ethereum, Ethereum, Erc20Quantity, Erc20Quantity>> {
+    pub fn deploy_action(&self) -> ethereum::ContractDeploy {
+        let htlc = Erc20Htlc::from(self.beta_htlc_params());
+        let data = htlc.compile_to_hex().into();
+        let gas_limit = htlc.deployment_gas_limit();
+
+        ethereum::ContractDeploy {
+            data,
+            value: EtherQuantity::zero(),
+            gas_limit,
+            chain_id: self.beta_ledger.chain_id,
+        }
+    }
+
+    pub fn fund_action(
+        &self,
+        beta_htlc_location: ethereum_support::Address,
+    ) -> ethereum::SendTransaction {
+        let htlc = Erc20Htlc::from(self.beta_htlc_params());
+        let gas_limit = Erc20Htlc::fund_tx_gas_limit();
+
+        ethereum::SendTransaction {
+            to: self.beta_asset.token_contract(),
+            data: htlc.funding_tx_payload(beta_htlc_location),
+            gas_limit,
+            value: EtherQuantity::zero(),
+            chain_id: self.beta_ledger.chain_id,
+        }
+    }
+
+    pub fn refund_action(
+        &self,
+        beta_htlc_location: ethereum_support::Address,
+    ) -> ethereum::SendTransaction {
+        let data = Bytes::default();
+        let gas_limit = Erc20Htlc::tx_gas_limit();
+
+        ethereum::SendTransaction {
+            to: beta_htlc_location,
+            data,
+            gas_limit,
+            value: EtherQuantity::zero(),
+            chain_id: self.beta_ledger.chain_id,
+        }
+    }
+
+    pub fn redeem_action(
+        &self,
+        alpha_htlc_location: ethereum_support::Address,
+        secret: Secret,
+    ) -> ethereum::SendTransaction {
+        let data = Bytes::from(secret.raw_secret().to_vec());
+        let gas_limit = Erc20Htlc::tx_gas_limit();
+
+        ethereum::SendTransaction {
+            to: alpha_htlc_location,
+            data,
+            gas_limit,
+            value: EtherQuantity::zero(),
+            chain_id: self.alpha_ledger.chain_id,
+        }
+    }
+}
+
+type BobActionKind = ActionKind<
+    Accept<Ethereum, Ethereum>,
+    Decline<Ethereum, Ethereum>,
+    ethereum::ContractDeploy,
+    ethereum::SendTransaction,
+    ethereum::SendTransaction,
+    ethereum::SendTransaction,
+>;
+
+impl Actions for SwapStates<Bob<Ethereum, Ethereum, Erc20Quantity, Erc20Quantity>> {
+    type ActionKind = BobActionKind;
+
+    fn actions(&self) -> Vec<BobActionKind> {
+        use self::SwapStates as SS;
+        match *self {
+            SS::Start(Start {
+                ref role,
+                ref alpha_ledger_lock_duration,
+                ..
+            }) => vec![
+                ActionKind::Accept(role.accept_action(alpha_ledger_lock_duration.clone())),
+                ActionKind::Decline(role.decline_action()),
+            ],
+            SS::AlphaFunded(AlphaFunded { ref swap, .. }) => {
+                vec![ActionKind::Deploy(swap.deploy_action())]
+            }
+            SS::AlphaFundedBetaDeployed(AlphaFundedBetaDeployed {
+                ref swap,
+                ref beta_htlc_location,
+                ..
+            }) => vec![ActionKind::Fund(swap.fund_action(*beta_htlc_location))],
+            SS::BothFunded(BothFunded {
+                ref beta_htlc_location,
+                ref swap,
+                beta_htlc_expired: true,
+                ..
+            })
+            | SS::AlphaRedeemedBetaFunded(AlphaRedeemedBetaFunded {
+                ref beta_htlc_location,
+                ref swap,
+                beta_htlc_expired: true,
+                ..
+            })
+            | SS::AlphaRefundedBetaFunded(AlphaRefundedBetaFunded {
+                ref beta_htlc_location,
+                ref swap,
+                beta_htlc_expired: true,
+                ..
+            }) => vec![ActionKind::Refund(swap.refund_action(*beta_htlc_location))],
+            SS::AlphaFundedBetaRedeemed(AlphaFundedBetaRedeemed {
+                ref swap,
+                ref alpha_htlc_location,
+                ref beta_redeemed_tx,
+                alpha_htlc_expired: false,
+                ..
+            }) => vec![ActionKind::Redeem(
+                swap.redeem_action(*alpha_htlc_location, beta_redeemed_tx.secret),
+            )],
+            _ => vec![],
+        }
+    }
+}
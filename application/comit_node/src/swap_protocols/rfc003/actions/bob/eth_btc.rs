@@ -0,0 +1,283 @@
+use crate::swap_protocols::{
+    ledger::{Bitcoin, Ethereum},
+    rfc003::{
+        actions::{
+            bob::{Accept, Decline},
+            ActionKind, Actions,
+        },
+        bitcoin,
+        ethereum::{self, EtherHtlc},
+        roles::Bob,
+        secret::Secret,
+        state_machine::*,
+    },
+};
+use bitcoin_support::{BitcoinQuantity, OutPoint};
+use bitcoin_witness::PrimedInput;
+use ethereum_support::{Bytes, EtherQuantity};
+
+impl OngoingSwap<Bob<Ethereum, Bitcoin, EtherQuantity, BitcoinQuantity>> {
+    pub fn fund_action(&self) -> bitcoin::SendToAddress {
+        bitcoin::SendToAddress {
+            address: self.beta_htlc_params().compute_address(),
+            value: self.beta_asset,
+        }
+    }
+
+    pub fn refund_action(&self, beta_htlc_location: OutPoint) -> bitcoin::SpendOutput {
+        bitcoin::SpendOutput {
+            output: PrimedInput::new(
+                beta_htlc_location,
+                self.beta_asset,
+                bitcoin::Htlc::from(self.beta_htlc_params())
+                    .unlock_after_timeout(self.beta_ledger_refund_identity),
+            ),
+        }
+    }
+
+    pub fn redeem_action(
+        &self,
+        alpha_htlc_location: ethereum_support::Address,
+        secret: Secret,
+    ) -> ethereum::SendTransaction {
+        let data = Bytes::from(secret.raw_secret().to_vec());
+        let gas_limit = EtherHtlc::tx_gas_limit();
+
+        ethereum::SendTransaction {
+            to: alpha_htlc_location,
+            data,
+            gas_limit,
+            value: EtherQuantity::zero(),
+            chain_id: self.alpha_ledger.chain_id,
+        }
+    }
+}
+
+type BobActionKind = ActionKind<
+    Accept<Ethereum, Bitcoin>,
+    Decline<Ethereum, Bitcoin>,
+    (),
+    bitcoin::SendToAddress,
+    ethereum::SendTransaction,
+    bitcoin::SpendOutput,
+>;
+
+impl Actions for SwapStates<Bob<Ethereum, Bitcoin, EtherQuantity, BitcoinQuantity>> {
+    type ActionKind = BobActionKind;
+
+    fn actions(&self) -> Vec<BobActionKind> {
+        use self::SwapStates as SS;
+        match *self {
+            SS::Start(Start {
+                ref role,
+                ref alpha_ledger_lock_duration,
+                ..
+            }) => vec![
+                ActionKind::Accept(role.accept_action(alpha_ledger_lock_duration.clone())),
+                ActionKind::Decline(role.decline_action()),
+            ],
+            SS::AlphaFunded(AlphaFunded { ref swap, .. }) => {
+                vec![ActionKind::Fund(swap.fund_action())]
+            }
+            SS::BothFunded(BothFunded {
+                ref beta_htlc_location,
+                ref swap,
+                beta_htlc_expired: true,
+                ..
+            })
+            | SS::AlphaRedeemedBetaFunded(AlphaRedeemedBetaFunded {
+                ref beta_htlc_location,
+                ref swap,
+                beta_htlc_expired: true,
+                ..
+            })
+            | SS::AlphaRefundedBetaFunded(AlphaRefundedBetaFunded {
+                ref beta_htlc_location,
+                ref swap,
+                beta_htlc_expired: true,
+                ..
+            }) => vec![ActionKind::Refund(swap.refund_action(*beta_htlc_location))],
+            SS::AlphaFundedBetaRedeemed(AlphaFundedBetaRedeemed {
+                ref swap,
+                ref alpha_htlc_location,
+                ref beta_redeemed_tx,
+                alpha_htlc_expired: false,
+                ..
+            }) => vec![ActionKind::Redeem(
+                swap.redeem_action(*alpha_htlc_location, beta_redeemed_tx.secret),
+            )],
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::swap_protocols::rfc003::{
+        actions::bob::AcceptError, ethereum::Seconds, Secret, Timestamp,
+    };
+    use hex::FromHex;
+    use std::time::Duration;
+
+    #[test]
+    fn given_start_state_bob_can_accept_or_decline() {
+        let (bob, _) = Bob::<Ethereum, Bitcoin, EtherQuantity, BitcoinQuantity>::create(None);
+        let swap_state = SwapStates::from(Start {
+            alpha_ledger_refund_identity: "8457037fcd80a8650c4692d7fcfc1d0a96b92867"
+                .parse()
+                .unwrap(),
+            beta_ledger_redeem_identity: bitcoin_support::PubkeyHash::from_hex(
+                "875638cac0b0ae9f826575e190f2788918c354c2",
+            )
+            .unwrap(),
+            alpha_ledger: Ethereum::default(),
+            beta_ledger: Bitcoin::default(),
+            alpha_asset: EtherQuantity::from_eth(10.0),
+            beta_asset: BitcoinQuantity::from_bitcoin(1.0),
+            alpha_ledger_lock_duration: Seconds(86400),
+            secret: Secret::from(*b"hello world, you are beautiful!!").hash(),
+            role: bob,
+        });
+
+        let actions: Vec<String> = swap_state.actions().iter().map(ActionKind::name).collect();
+
+        assert_eq!(
+            actions,
+            vec![String::from("accept"), String::from("decline")]
+        );
+    }
+
+    #[test]
+    fn given_both_funded_bob_can_only_refund_after_beta_htlc_expired() {
+        let (bob, _) = Bob::<Ethereum, Bitcoin, EtherQuantity, BitcoinQuantity>::create(None);
+        let start = Start {
+            alpha_ledger_refund_identity: "8457037fcd80a8650c4692d7fcfc1d0a96b92867"
+                .parse()
+                .unwrap(),
+            beta_ledger_redeem_identity: bitcoin_support::PubkeyHash::from_hex(
+                "875638cac0b0ae9f826575e190f2788918c354c2",
+            )
+            .unwrap(),
+            alpha_ledger: Ethereum::default(),
+            beta_ledger: Bitcoin::default(),
+            alpha_asset: EtherQuantity::from_eth(10.0),
+            beta_asset: BitcoinQuantity::from_bitcoin(1.0),
+            alpha_ledger_lock_duration: Seconds(86400),
+            secret: Secret::from(*b"hello world, you are beautiful!!").hash(),
+            role: bob,
+        };
+        let response = StateMachineResponse {
+            alpha_ledger_redeem_identity: "71b9f69dcabb340a3fe229c3f94f1662ad85e5e8"
+                .parse()
+                .unwrap(),
+            beta_ledger_refund_identity: secp256k1_support::KeyPair::from_secret_key_slice(
+                &hex::decode("18e14a7b6a307f426a94f8114701e7c8e774e7f9a47e2c2035db29a206321725")
+                    .unwrap(),
+            )
+            .unwrap(),
+            beta_ledger_lock_duration: bitcoin_support::Blocks::from(144).into(),
+        };
+        let both_funded = |beta_htlc_expired| {
+            SwapStates::from(BothFunded {
+                swap: OngoingSwap::new(start.clone(), response.clone()),
+                alpha_htlc_location: "8457037fcd80a8650c4692d7fcfc1d0a96b92867".parse().unwrap(),
+                alpha_htlc_deployed_at: Timestamp(1_550_000_000),
+                beta_htlc_location: OutPoint {
+                    txid: bitcoin_support::Sha256dHash::from_data(b"funding"),
+                    vout: 0,
+                },
+                beta_htlc_deployed_at: Timestamp(1_550_000_000),
+                alpha_htlc_expired: false,
+                beta_htlc_expired,
+            })
+        };
+
+        let actions: Vec<String> = both_funded(false)
+            .actions()
+            .iter()
+            .map(ActionKind::name)
+            .collect();
+        assert!(actions.is_empty());
+
+        let actions: Vec<String> = both_funded(true)
+            .actions()
+            .iter()
+            .map(ActionKind::name)
+            .collect();
+        assert_eq!(actions, vec![String::from("refund")]);
+    }
+
+    #[test]
+    fn given_beta_lock_too_close_to_alpha_lock_then_accept_fails() {
+        let (bob, _response) =
+            Bob::<Ethereum, Bitcoin, EtherQuantity, BitcoinQuantity>::create(None);
+        let swap_state = SwapStates::from(Start {
+            alpha_ledger_refund_identity: "8457037fcd80a8650c4692d7fcfc1d0a96b92867"
+                .parse()
+                .unwrap(),
+            beta_ledger_redeem_identity: bitcoin_support::PubkeyHash::from_hex(
+                "875638cac0b0ae9f826575e190f2788918c354c2",
+            )
+            .unwrap(),
+            alpha_ledger: Ethereum::default(),
+            beta_ledger: Bitcoin::default(),
+            alpha_asset: EtherQuantity::from_eth(10.0),
+            beta_asset: BitcoinQuantity::from_bitcoin(1.0),
+            alpha_ledger_lock_duration: Seconds(86400),
+            secret: Secret::from(*b"hello world, you are beautiful!!").hash(),
+            role: bob,
+        });
+        let accept = swap_state
+            .actions()
+            .into_iter()
+            .find_map(|action| match action {
+                ActionKind::Accept(accept) => Some(accept),
+                _ => None,
+            })
+            .unwrap();
+        let response = |beta_ledger_lock_duration: u32| StateMachineResponse {
+            alpha_ledger_redeem_identity: "71b9f69dcabb340a3fe229c3f94f1662ad85e5e8"
+                .parse()
+                .unwrap(),
+            beta_ledger_refund_identity: secp256k1_support::KeyPair::from_secret_key_slice(
+                &hex::decode("18e14a7b6a307f426a94f8114701e7c8e774e7f9a47e2c2035db29a206321725")
+                    .unwrap(),
+            )
+            .unwrap(),
+            beta_ledger_lock_duration: bitcoin_support::Blocks::from(beta_ledger_lock_duration)
+                .into(),
+        };
+        let six_hours = Duration::from_secs(6 * 60 * 60);
+
+        match accept.accept(response(144), six_hours) {
+            Err(AcceptError::UnsafeLockDurations(_)) => {}
+            other => panic!("expected unsafe lock durations but got {:?}", other),
+        }
+        assert_eq!(accept.accept(response(72), six_hours), Ok(()));
+    }
+
+    #[test]
+    fn given_request_expired_then_accept_fails() {
+        let (bob, _response) =
+            Bob::<Ethereum, Bitcoin, EtherQuantity, BitcoinQuantity>::create(Some(1));
+        let accept = bob.accept_action(Seconds(86400));
+        let response = StateMachineResponse {
+            alpha_ledger_redeem_identity: "71b9f69dcabb340a3fe229c3f94f1662ad85e5e8"
+                .parse()
+                .unwrap(),
+            beta_ledger_refund_identity: secp256k1_support::KeyPair::from_secret_key_slice(
+                &hex::decode("18e14a7b6a307f426a94f8114701e7c8e774e7f9a47e2c2035db29a206321725")
+                    .unwrap(),
+            )
+            .unwrap(),
+            beta_ledger_lock_duration: bitcoin_support::Blocks::from(72).into(),
+        };
+
+        assert_eq!(
+            accept.accept(response, Duration::from_secs(6 * 60 * 60)),
+            Err(AcceptError::RequestExpired)
+        );
+    }
+}
@@ -1,41 +1,88 @@
 mod btc_erc20;
 mod btc_eth;
+mod erc20_btc;
+mod erc20_erc20;
+mod erc20_eth;
+mod eth_btc;
+mod eth_erc20;
 use crate::{
-    comit_client::{SwapDeclineReason, SwapReject},
-    swap_protocols::rfc003::{state_machine::StateMachineResponse, Ledger},
+    comit_client::{self, CounterOffer, DeclineDetails, SwapDeclineReason, SwapReject},
+    swap_protocols::rfc003::{
+        state_machine::StateMachineResponse, validate_lock_durations, Ledger, UnsafeLockDurations,
+    },
 };
 use futures::sync::oneshot;
-use std::sync::{Arc, Mutex};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
 
 #[allow(type_alias_bounds)]
 type Response<AL: Ledger, BL: Ledger> =
     Result<StateMachineResponse<AL::HtlcIdentity, BL::HtlcIdentity, BL::LockDuration>, SwapReject>;
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum AcceptError {
+    AlreadyResponded,
+    RequestExpired,
+    /// The swap stopped waiting for the response, e.g. because it timed out
+    NoLongerAwaited,
+    UnsafeLockDurations(UnsafeLockDurations),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeclineError {
+    AlreadyResponded,
+    /// The swap stopped waiting for the response, e.g. because it timed out
+    NoLongerAwaited,
+}
+
 #[derive(Debug, Clone)]
 pub struct Accept<AL: Ledger, BL: Ledger> {
     #[allow(clippy::type_complexity)]
     sender: Arc<Mutex<Option<oneshot::Sender<Response<AL, BL>>>>>,
+    alpha_ledger_lock_duration: AL::LockDuration,
+    expires_at: Option<u64>,
 }
 
 impl<AL: Ledger, BL: Ledger> Accept<AL, BL> {
     #[allow(clippy::type_complexity)]
-    pub fn new(sender: Arc<Mutex<Option<oneshot::Sender<Response<AL, BL>>>>>) -> Self {
-        Self { sender }
+    pub fn new(
+        sender: Arc<Mutex<Option<oneshot::Sender<Response<AL, BL>>>>>,
+        alpha_ledger_lock_duration: AL::LockDuration,
+        expires_at: Option<u64>,
+    ) -> Self {
+        Self {
+            sender,
+            alpha_ledger_lock_duration,
+            expires_at,
+        }
     }
     pub fn accept(
         &self,
         response: StateMachineResponse<AL::HtlcIdentity, BL::HtlcIdentity, BL::LockDuration>,
-    ) -> Result<(), ()> {
+        lock_duration_margin: Duration,
+    ) -> Result<(), AcceptError> {
+        if self.expires_at.map_or(false, |expires_at| {
+            comit_client::has_expired(expires_at, SystemTime::now())
+        }) {
+            return Err(AcceptError::RequestExpired);
+        }
+
+        validate_lock_durations(
+            &self.alpha_ledger_lock_duration,
+            &response.beta_ledger_lock_duration,
+            lock_duration_margin,
+        )
+        .map_err(AcceptError::UnsafeLockDurations)?;
+
         let mut sender = self.sender.lock().unwrap();
 
         match sender.take() {
-            Some(sender) => {
-                sender
-                    .send(Ok(response))
-                    .expect("Action shouldn't outlive BobToAlice");
-                Ok(())
-            }
-            None => Err(()),
+            Some(sender) => sender
+                .send(Ok(response))
+                .map_err(|_| AcceptError::NoLongerAwaited),
+            None => Err(AcceptError::AlreadyResponded),
         }
     }
 }
@@ -51,16 +98,25 @@ impl<AL: Ledger, BL: Ledger> Decline<AL, BL> {
     pub fn new(sender: Arc<Mutex<Option<oneshot::Sender<Response<AL, BL>>>>>) -> Self {
         Self { sender }
     }
-    pub fn decline(&self, reason: Option<SwapDeclineReason>) -> Result<(), ()> {
+    pub fn decline(
+        &self,
+        reason: Option<SwapDeclineReason>,
+        details: Option<DeclineDetails>,
+    ) -> Result<(), DeclineError> {
+        self.reject(SwapReject::Declined { reason, details })
+    }
+
+    pub fn counter_offer(&self, offer: CounterOffer) -> Result<(), DeclineError> {
+        self.reject(SwapReject::CounterOffered(offer))
+    }
+
+    fn reject(&self, rejection: SwapReject) -> Result<(), DeclineError> {
         let mut sender = self.sender.lock().unwrap();
         match sender.take() {
-            Some(sender) => {
-                sender
-                    .send(Err(SwapReject::Declined { reason }))
-                    .expect("Action shouldn't outlive BobToAlice");
-                Ok(())
-            }
-            None => Err(()),
+            Some(sender) => sender
+                .send(Err(rejection))
+                .map_err(|_| DeclineError::NoLongerAwaited),
+            None => Err(DeclineError::AlreadyResponded),
         }
     }
 }
@@ -1,6 +1,7 @@
 use crate::{
     comit_client,
     ledger_query_service::{DefaultLedgerQueryServiceApiClient, FirstMatch, QueryIdCache},
+    peers::Peer,
     seed::Seed,
     swap_protocols::{
         asset::Asset,
@@ -9,17 +10,15 @@ use crate::{
             alice::SwapRequestKind,
             events::{AliceToBob, CommunicationEvents, LedgerEvents, LqsEvents, LqsEventsForErc20},
             roles::Alice,
-            secret_source::SecretSource,
             state_machine::{Context, Start, Swap, SwapStates},
             state_store::StateStore,
-            Ledger, Secret,
+            Ledger, UpdateMetadataState,
         },
         SwapId,
     },
 };
 use futures::{stream::Stream, sync::mpsc::UnboundedReceiver, Future};
-use rand::thread_rng;
-use std::{marker::PhantomData, net::SocketAddr, sync::Arc, time::Duration};
+use std::{marker::PhantomData, sync::Arc, time::Duration};
 
 #[derive(Debug)]
 pub struct SwapRequestHandler<
@@ -29,17 +28,18 @@ pub struct SwapRequestHandler<
     StateStore,
 > {
     // new dependencies
-    pub receiver: UnboundedReceiver<(SwapId, SwapRequestKind)>,
+    pub receiver: UnboundedReceiver<(SwapId, Peer, SwapRequestKind)>,
     pub metadata_store: Arc<MetadataStore>,
     pub seed: Seed,
     pub state_store: Arc<StateStore>,
     pub lqs_api_client: Arc<DefaultLedgerQueryServiceApiClient>,
     // legacy code dependencies
     pub client_factory: Arc<F>,
-    pub comit_node_addr: SocketAddr,
     pub phantom_data: PhantomData<C>,
     pub bitcoin_poll_interval: Duration,
     pub ethereum_poll_interval: Duration,
+    pub lock_duration_margin: Duration,
+    pub response_timeout: Duration,
 }
 
 impl<
@@ -60,131 +60,308 @@ impl<
         let state_store = Arc::clone(&self.state_store);
         let lqs_api_client = Arc::clone(&self.lqs_api_client);
         let client_factory = Arc::clone(&self.client_factory);
-        let comit_node_addr = self.comit_node_addr;
+        let lock_duration_margin = self.lock_duration_margin;
+        let response_timeout = self.response_timeout;
 
         receiver
-            .for_each(move |(id, requests)| {
-                match requests {
-                    SwapRequestKind::BitcoinEthereumBitcoinQuantityEtherQuantity(request) => {
-                        if let Err(e) = metadata_store.insert(id, request.clone()) {
-                            error!("Failed to store metadata for swap {} because {:?}", id, e);
-                            // Return Ok to keep the loop running
+            .for_each(move |(id, peer, requests)| {
+                let metadata_store = Arc::clone(&metadata_store);
+                let state_store = Arc::clone(&state_store);
+                let lqs_api_client = Arc::clone(&lqs_api_client);
+                let peer_name = peer.name.clone();
+
+                // Connecting must not hold up the requests of other swaps
+                let swap = client_factory.client_for(&peer).then(move |result| {
+                    let (counterparty, comit_client) = match result {
+                        Ok(client) => client,
+                        Err(e) => {
+                            debug!("Couldn't get client for {}: {:?}", peer_name, e);
                             return Ok(());
                         }
+                    };
 
-                        let secret = seed.new_secret(id);
-
-                        let start_state = Start {
-                            alpha_ledger_refund_identity: request
-                                .identities
-                                .alpha_ledger_refund_identity,
-                            beta_ledger_redeem_identity: request
-                                .identities
-                                .beta_ledger_redeem_identity,
-                            alpha_ledger: request.alpha_ledger,
-                            beta_ledger: request.beta_ledger,
-                            alpha_asset: request.alpha_asset,
-                            beta_asset: request.beta_asset,
-                            alpha_ledger_lock_duration: request.alpha_ledger_lock_duration,
-                            secret,
-                            role: Alice::default(),
-                        };
-
-                        let comit_client = match client_factory.client_for(comit_node_addr) {
-                            Ok(client) => client,
-                            Err(e) => {
-                                debug!("Couldn't get client for {}: {:?}", comit_node_addr, e);
+                    match requests {
+                        SwapRequestKind::BitcoinEthereumBitcoinQuantityEtherQuantity(request) => {
+                            if let Err(e) = metadata_store.insert(id, request.clone(), counterparty)
+                            {
+                                error!("Failed to store metadata for swap {} because {:?}", id, e);
                                 return Ok(());
                             }
-                        };
-
-                        spawn_state_machine(
-                            id,
-                            start_state,
-                            state_store.as_ref(),
-                            Box::new(LqsEvents::new(
-                                QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
-                                FirstMatch::new(Arc::clone(&lqs_api_client), bitcoin_poll_interval),
-                            )),
-                            Box::new(LqsEvents::new(
-                                QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
-                                FirstMatch::new(
-                                    Arc::clone(&lqs_api_client),
-                                    ethereum_poll_interval,
-                                ),
-                            )),
-                            Box::new(AliceToBob::new(Arc::clone(&comit_client))),
-                        );
-                        Ok(())
-                    }
-                    SwapRequestKind::BitcoinEthereumBitcoinQuantityErc20Quantity(request) => {
-                        if let Err(e) = metadata_store.insert(id, request.clone()) {
-                            error!("Failed to store metadata for swap {} because {:?}", id, e);
-                            // Return Ok to keep the loop running
-                            return Ok(());
+
+                            let start_state = request.into_start_state(id, &seed);
+
+                            spawn_state_machine(
+                                id,
+                                start_state,
+                                state_store.as_ref(),
+                                Arc::clone(&metadata_store),
+                                Box::new(LqsEvents::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        bitcoin_poll_interval,
+                                    ),
+                                )),
+                                Box::new(LqsEvents::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        ethereum_poll_interval,
+                                    ),
+                                )),
+                                Box::new(AliceToBob::new(
+                                    Arc::clone(&comit_client),
+                                    lock_duration_margin,
+                                    response_timeout,
+                                )),
+                            );
+                            Ok(())
                         }
+                        SwapRequestKind::BitcoinEthereumBitcoinQuantityErc20Quantity(request) => {
+                            if let Err(e) = metadata_store.insert(id, request.clone(), counterparty)
+                            {
+                                error!("Failed to store metadata for swap {} because {:?}", id, e);
+                                return Ok(());
+                            }
 
-                        let secret = Secret::generate(&mut thread_rng());
-
-                        let start_state = Start {
-                            alpha_ledger_refund_identity: request
-                                .identities
-                                .alpha_ledger_refund_identity,
-                            beta_ledger_redeem_identity: request
-                                .identities
-                                .beta_ledger_redeem_identity,
-                            alpha_ledger: request.alpha_ledger,
-                            beta_ledger: request.beta_ledger,
-                            alpha_asset: request.alpha_asset,
-                            beta_asset: request.beta_asset,
-                            alpha_ledger_lock_duration: request.alpha_ledger_lock_duration,
-                            secret,
-                            role: Alice::default(),
-                        };
-
-                        let comit_client = match client_factory.client_for(comit_node_addr) {
-                            Ok(client) => client,
-                            Err(e) => {
-                                debug!("Couldn't get client for {}: {:?}", comit_node_addr, e);
+                            let start_state = request.into_start_state(id, &seed);
+
+                            spawn_state_machine(
+                                id,
+                                start_state,
+                                state_store.as_ref(),
+                                Arc::clone(&metadata_store),
+                                Box::new(LqsEvents::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        bitcoin_poll_interval,
+                                    ),
+                                )),
+                                Box::new(LqsEventsForErc20::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        ethereum_poll_interval,
+                                    ),
+                                )),
+                                Box::new(AliceToBob::new(
+                                    Arc::clone(&comit_client),
+                                    lock_duration_margin,
+                                    response_timeout,
+                                )),
+                            );
+                            Ok(())
+                        }
+                        SwapRequestKind::EthereumBitcoinEtherQuantityBitcoinQuantity(request) => {
+                            if let Err(e) = metadata_store.insert(id, request.clone(), counterparty)
+                            {
+                                error!("Failed to store metadata for swap {} because {:?}", id, e);
                                 return Ok(());
                             }
-                        };
-
-                        spawn_state_machine(
-                            id,
-                            start_state,
-                            state_store.as_ref(),
-                            Box::new(LqsEvents::new(
-                                QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
-                                FirstMatch::new(Arc::clone(&lqs_api_client), bitcoin_poll_interval),
-                            )),
-                            Box::new(LqsEventsForErc20::new(
-                                QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
-                                FirstMatch::new(
-                                    Arc::clone(&lqs_api_client),
-                                    ethereum_poll_interval,
-                                ),
-                            )),
-                            Box::new(AliceToBob::new(Arc::clone(&comit_client))),
-                        );
-                        Ok(())
+
+                            let start_state = request.into_start_state(id, &seed);
+
+                            spawn_state_machine(
+                                id,
+                                start_state,
+                                state_store.as_ref(),
+                                Arc::clone(&metadata_store),
+                                Box::new(LqsEvents::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        ethereum_poll_interval,
+                                    ),
+                                )),
+                                Box::new(LqsEvents::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        bitcoin_poll_interval,
+                                    ),
+                                )),
+                                Box::new(AliceToBob::new(
+                                    Arc::clone(&comit_client),
+                                    lock_duration_margin,
+                                    response_timeout,
+                                )),
+                            );
+                            Ok(())
+                        }
+                        SwapRequestKind::EthereumBitcoinErc20QuantityBitcoinQuantity(request) => {
+                            if let Err(e) = metadata_store.insert(id, request.clone(), counterparty)
+                            {
+                                error!("Failed to store metadata for swap {} because {:?}", id, e);
+                                return Ok(());
+                            }
+
+                            let start_state = request.into_start_state(id, &seed);
+
+                            spawn_state_machine(
+                                id,
+                                start_state,
+                                state_store.as_ref(),
+                                Arc::clone(&metadata_store),
+                                Box::new(LqsEventsForErc20::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        ethereum_poll_interval,
+                                    ),
+                                )),
+                                Box::new(LqsEvents::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        bitcoin_poll_interval,
+                                    ),
+                                )),
+                                Box::new(AliceToBob::new(
+                                    Arc::clone(&comit_client),
+                                    lock_duration_margin,
+                                    response_timeout,
+                                )),
+                            );
+                            Ok(())
+                        }
+                        SwapRequestKind::EthereumEthereumEtherQuantityErc20Quantity(request) => {
+                            if let Err(e) = metadata_store.insert(id, request.clone(), counterparty)
+                            {
+                                error!("Failed to store metadata for swap {} because {:?}", id, e);
+                                return Ok(());
+                            }
+
+                            let start_state = request.into_start_state(id, &seed);
+
+                            spawn_state_machine(
+                                id,
+                                start_state,
+                                state_store.as_ref(),
+                                Arc::clone(&metadata_store),
+                                Box::new(LqsEvents::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        ethereum_poll_interval,
+                                    ),
+                                )),
+                                Box::new(LqsEventsForErc20::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        ethereum_poll_interval,
+                                    ),
+                                )),
+                                Box::new(AliceToBob::new(
+                                    Arc::clone(&comit_client),
+                                    lock_duration_margin,
+                                    response_timeout,
+                                )),
+                            );
+                            Ok(())
+                        }
+                        SwapRequestKind::EthereumEthereumErc20QuantityEtherQuantity(request) => {
+                            if let Err(e) = metadata_store.insert(id, request.clone(), counterparty)
+                            {
+                                error!("Failed to store metadata for swap {} because {:?}", id, e);
+                                return Ok(());
+                            }
+
+                            let start_state = request.into_start_state(id, &seed);
+
+                            spawn_state_machine(
+                                id,
+                                start_state,
+                                state_store.as_ref(),
+                                Arc::clone(&metadata_store),
+                                Box::new(LqsEventsForErc20::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        ethereum_poll_interval,
+                                    ),
+                                )),
+                                Box::new(LqsEvents::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        ethereum_poll_interval,
+                                    ),
+                                )),
+                                Box::new(AliceToBob::new(
+                                    Arc::clone(&comit_client),
+                                    lock_duration_margin,
+                                    response_timeout,
+                                )),
+                            );
+                            Ok(())
+                        }
+                        SwapRequestKind::EthereumEthereumErc20QuantityErc20Quantity(request) => {
+                            if let Err(e) = metadata_store.insert(id, request.clone(), counterparty)
+                            {
+                                error!("Failed to store metadata for swap {} because {:?}", id, e);
+                                return Ok(());
+                            }
+
+                            let start_state = request.into_start_state(id, &seed);
+
+                            spawn_state_machine(
+                                id,
+                                start_state,
+                                state_store.as_ref(),
+                                Arc::clone(&metadata_store),
+                                Box::new(LqsEventsForErc20::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        ethereum_poll_interval,
+                                    ),
+                                )),
+                                Box::new(LqsEventsForErc20::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        ethereum_poll_interval,
+                                    ),
+                                )),
+                                Box::new(AliceToBob::new(
+                                    Arc::clone(&comit_client),
+                                    lock_duration_margin,
+                                    response_timeout,
+                                )),
+                            );
+                            Ok(())
+                        }
                     }
-                }
+                });
+                tokio::spawn(swap);
+
+                Ok(())
             })
             .map_err(|_| ())
     }
 }
 
-fn spawn_state_machine<AL: Ledger, BL: Ledger, AA: Asset, BA: Asset, S: StateStore<SwapId>>(
+fn spawn_state_machine<
+    AL: Ledger,
+    BL: Ledger,
+    AA: Asset,
+    BA: Asset,
+    S: StateStore<SwapId>,
+    M: MetadataStore<SwapId>,
+>(
     id: SwapId,
     start_state: Start<Alice<AL, BL, AA, BA>>,
     state_store: &S,
+    metadata_store: Arc<M>,
     alpha_ledger_events: Box<dyn LedgerEvents<AL, AA>>,
     beta_ledger_events: Box<dyn LedgerEvents<BL, BA>>,
     communication_events: Box<dyn CommunicationEvents<Alice<AL, BL, AA, BA>>>,
 ) {
     let state = SwapStates::Start(start_state);
     let state_repo = state_store.insert(id, state.clone()).expect("");
+    let state_repo = Arc::new(UpdateMetadataState::new(id, metadata_store, state_repo));
 
     let context = Context {
         alpha_ledger_events,
@@ -1,7 +1,9 @@
 use crate::swap_protocols::{
+    asset::Asset,
     ledger::{Bitcoin, Ethereum},
-    metadata_store::{AssetKind, LedgerKind, Metadata, RoleKind},
-    rfc003::Ledger,
+    metadata_store::{Metadata, RoleKind},
+    rfc003::{roles::Alice, state_machine::Start, Ledger, SecretSource, SwapIdentity},
+    SwapId,
 };
 use bitcoin_support::BitcoinQuantity;
 use ethereum_support::{Erc20Quantity, EtherQuantity};
@@ -22,25 +24,51 @@ pub struct SwapRequestIdentities<AL: Ledger, BL: Ledger> {
     pub beta_ledger_redeem_identity: BL::HtlcIdentity,
 }
 
-impl From<SwapRequest<Bitcoin, Ethereum, BitcoinQuantity, EtherQuantity>> for Metadata {
-    fn from(_: SwapRequest<Bitcoin, Ethereum, BitcoinQuantity, EtherQuantity>) -> Self {
-        Self {
-            alpha_ledger: LedgerKind::Bitcoin,
-            beta_ledger: LedgerKind::Ethereum,
-            alpha_asset: AssetKind::Bitcoin,
-            beta_asset: AssetKind::Ether,
-            role: RoleKind::Alice,
+impl<AL: Ledger, BL: Ledger, AA: Asset, BA: Asset> SwapRequest<AL, BL, AA, BA> {
+    /// The same request for the swap `id`
+    pub fn for_swap(self, id: SwapId, secret_source: &dyn SecretSource) -> Self {
+        let identities = SwapRequestIdentities {
+            alpha_ledger_refund_identity: self
+                .identities
+                .alpha_ledger_refund_identity
+                .refund_identity_for(secret_source, id),
+            beta_ledger_redeem_identity: self
+                .identities
+                .beta_ledger_redeem_identity
+                .redeem_identity_for(secret_source, id),
+        };
+
+        SwapRequest { identities, ..self }
+    }
+
+    /// The secret is derived from `secret_source`, otherwise the swap can't
+    /// be restored after a restart
+    pub fn into_start_state(
+        self,
+        id: SwapId,
+        secret_source: &dyn SecretSource,
+    ) -> Start<Alice<AL, BL, AA, BA>> {
+        Start {
+            alpha_ledger_refund_identity: self.identities.alpha_ledger_refund_identity,
+            beta_ledger_redeem_identity: self.identities.beta_ledger_redeem_identity,
+            alpha_ledger: self.alpha_ledger,
+            beta_ledger: self.beta_ledger,
+            alpha_asset: self.alpha_asset,
+            beta_asset: self.beta_asset,
+            alpha_ledger_lock_duration: self.alpha_ledger_lock_duration,
+            secret: secret_source.new_secret(id),
+            role: Alice::default(),
         }
     }
 }
 
-impl From<SwapRequest<Bitcoin, Ethereum, BitcoinQuantity, Erc20Quantity>> for Metadata {
-    fn from(_: SwapRequest<Bitcoin, Ethereum, BitcoinQuantity, Erc20Quantity>) -> Self {
+impl<AL: Ledger, BL: Ledger, AA: Asset, BA: Asset> From<SwapRequest<AL, BL, AA, BA>> for Metadata {
+    fn from(request: SwapRequest<AL, BL, AA, BA>) -> Self {
         Self {
-            alpha_ledger: LedgerKind::Bitcoin,
-            beta_ledger: LedgerKind::Ethereum,
-            alpha_asset: AssetKind::Bitcoin,
-            beta_asset: AssetKind::Erc20,
+            alpha_ledger: request.alpha_ledger.into(),
+            beta_ledger: request.beta_ledger.into(),
+            alpha_asset: request.alpha_asset.into(),
+            beta_asset: request.beta_asset.into(),
             role: RoleKind::Alice,
         }
     }
@@ -54,4 +82,83 @@ pub enum SwapRequestKind {
     BitcoinEthereumBitcoinQuantityErc20Quantity(
         SwapRequest<Bitcoin, Ethereum, BitcoinQuantity, Erc20Quantity>,
     ),
+    EthereumBitcoinEtherQuantityBitcoinQuantity(
+        SwapRequest<Ethereum, Bitcoin, EtherQuantity, BitcoinQuantity>,
+    ),
+    EthereumBitcoinErc20QuantityBitcoinQuantity(
+        SwapRequest<Ethereum, Bitcoin, Erc20Quantity, BitcoinQuantity>,
+    ),
+    EthereumEthereumEtherQuantityErc20Quantity(
+        SwapRequest<Ethereum, Ethereum, EtherQuantity, Erc20Quantity>,
+    ),
+    EthereumEthereumErc20QuantityEtherQuantity(
+        SwapRequest<Ethereum, Ethereum, Erc20Quantity, EtherQuantity>,
+    ),
+    EthereumEthereumErc20QuantityErc20Quantity(
+        SwapRequest<Ethereum, Ethereum, Erc20Quantity, Erc20Quantity>,
+    ),
 }
+
+macro_rules! impl_from_swap_request {
+    ($variant:ident, $alpha_ledger:ty, $beta_ledger:ty, $alpha_asset:ty, $beta_asset:ty) => {
+        impl From<SwapRequest<$alpha_ledger, $beta_ledger, $alpha_asset, $beta_asset>>
+            for SwapRequestKind
+        {
+            fn from(
+                request: SwapRequest<$alpha_ledger, $beta_ledger, $alpha_asset, $beta_asset>,
+            ) -> Self {
+                SwapRequestKind::$variant(request)
+            }
+        }
+    };
+}
+
+impl_from_swap_request!(
+    BitcoinEthereumBitcoinQuantityEtherQuantity,
+    Bitcoin,
+    Ethereum,
+    BitcoinQuantity,
+    EtherQuantity
+);
+impl_from_swap_request!(
+    BitcoinEthereumBitcoinQuantityErc20Quantity,
+    Bitcoin,
+    Ethereum,
+    BitcoinQuantity,
+    Erc20Quantity
+);
+impl_from_swap_request!(
+    EthereumBitcoinEtherQuantityBitcoinQuantity,
+    Ethereum,
+    Bitcoin,
+    EtherQuantity,
+    BitcoinQuantity
+);
+impl_from_swap_request!(
+    EthereumBitcoinErc20QuantityBitcoinQuantity,
+    Ethereum,
+    Bitcoin,
+    Erc20Quantity,
+    BitcoinQuantity
+);
+impl_from_swap_request!(
+    EthereumEthereumEtherQuantityErc20Quantity,
+    Ethereum,
+    Ethereum,
+    EtherQuantity,
+    Erc20Quantity
+);
+impl_from_swap_request!(
+    EthereumEthereumErc20QuantityEtherQuantity,
+    Ethereum,
+    Ethereum,
+    Erc20Quantity,
+    EtherQuantity
+);
+impl_from_swap_request!(
+    EthereumEthereumErc20QuantityErc20Quantity,
+    Ethereum,
+    Ethereum,
+    Erc20Quantity,
+    Erc20Quantity
+);
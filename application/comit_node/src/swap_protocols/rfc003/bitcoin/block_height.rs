@@ -0,0 +1,48 @@
+use crate::{ledger_query_service::FetchLatestBlockHeight, swap_protocols::ledger::Bitcoin};
+use futures::{Future, Stream};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::timer::Interval;
+
+// 0 stands for "not known yet", the genesis block is never the latest one
+static LATEST_BLOCK_HEIGHT: AtomicUsize = AtomicUsize::new(0);
+
+/// The height of the latest Bitcoin block or `None` until
+/// `track_latest_block_height` learned about one
+pub fn latest_block_height() -> Option<u32> {
+    match LATEST_BLOCK_HEIGHT.load(Ordering::SeqCst) {
+        0 => None,
+        height => Some(height as u32),
+    }
+}
+
+pub fn track_latest_block_height<C: FetchLatestBlockHeight<Bitcoin>>(
+    client: Arc<C>,
+    poll_interval: Duration,
+) -> impl Future<Item = (), Error = ()> + Send {
+    Interval::new(Instant::now(), poll_interval)
+        .map_err(|e| {
+            error!(
+                "Interval for fetching the latest Bitcoin block failed: {:?}",
+                e
+            )
+        })
+        .for_each(move |_| {
+            client.fetch_latest_block_height().then(|result| {
+                match result {
+                    Ok(Some(height)) => {
+                        LATEST_BLOCK_HEIGHT.store(height as usize, Ordering::SeqCst);
+                    }
+                    Ok(None) => {}
+                    Err(e) => warn!("Could not fetch latest Bitcoin block height: {:?}", e),
+                }
+
+                Ok(())
+            })
+        })
+}
@@ -1,6 +1,6 @@
-use crate::swap_protocols::rfc003::{Secret, SecretHash};
+use crate::swap_protocols::rfc003::{bitcoin::Timelock, Secret, SecretHash};
 use bitcoin_support::{
-    opcodes::All::{OP_NOP3 as OP_CHECKSEQUENCEVERIFY, *},
+    opcodes::All::{OP_NOP2 as OP_CHECKLOCKTIMEVERIFY, OP_NOP3 as OP_CHECKSEQUENCEVERIFY, *},
     script::Builder,
     Address, Network, PubkeyHash, Script,
 };
@@ -25,7 +25,7 @@ pub struct Htlc {
     recipient_redeem_pubkey_hash: PubkeyHash,
     sender_refund_pubkey_hash: PubkeyHash,
     secret_hash: SecretHash,
-    relative_timelock: u32,
+    timelock: Timelock,
     script: Script,
 }
 
@@ -37,8 +37,7 @@ impl Htlc {
         recipient_redeem_pubkey_hash: RecipientRedeemPubkeyHash,
         sender_refund_pubkey_hash: SenderRefundPubkeyHash,
         secret_hash: SecretHash,
-        // TODO: use bitcoin_support::Blocks type
-        relative_timelock: u32,
+        timelock: Timelock,
     ) -> Htlc {
         let recipient_redeem_pubkey_hash = recipient_redeem_pubkey_hash.into();
         let sender_refund_pubkey_hash = sender_refund_pubkey_hash.into();
@@ -46,14 +45,14 @@ impl Htlc {
             &recipient_redeem_pubkey_hash,
             &sender_refund_pubkey_hash,
             secret_hash.raw(),
-            relative_timelock,
+            timelock,
         );
 
         Htlc {
             recipient_redeem_pubkey_hash,
             sender_refund_pubkey_hash,
             secret_hash,
-            relative_timelock,
+            timelock,
             script,
         }
     }
@@ -106,11 +105,17 @@ impl Htlc {
             ],
             sequence: SEQUENCE_ALLOW_NTIMELOCK_NO_RBF,
             prev_script: self.script.clone(),
+            locktime: 0,
         }
     }
 
     pub fn unlock_after_timeout(&self, keypair: KeyPair) -> UnlockParameters {
         let public_key = keypair.public_key();
+        let (sequence, locktime) = match self.timelock {
+            Timelock::Relative(blocks) => (blocks.into(), 0),
+            Timelock::Absolute(absolute) => (SEQUENCE_ALLOW_NTIMELOCK_NO_RBF, absolute.locktime()),
+        };
+
         UnlockParameters {
             witness: vec![
                 Witness::Signature(keypair),
@@ -118,8 +123,9 @@ impl Htlc {
                 Witness::Bool(false),
                 Witness::PrevScript,
             ],
-            sequence: self.relative_timelock,
+            sequence,
             prev_script: self.script.clone(),
+            locktime,
         }
     }
 }
@@ -128,8 +134,13 @@ fn create_htlc(
     recipient_pubkey_hash: &PubkeyHash,
     sender_pubkey_hash: &PubkeyHash,
     secret_hash: &[u8],
-    redeem_block_height: u32,
+    timelock: Timelock,
 ) -> Script {
+    let (timelock, timelock_opcode) = match timelock {
+        Timelock::Relative(blocks) => (u32::from(blocks), OP_CHECKSEQUENCEVERIFY),
+        Timelock::Absolute(absolute) => (absolute.locktime(), OP_CHECKLOCKTIMEVERIFY),
+    };
+
     let script = Builder::new()
         .push_opcode(OP_IF)
         .push_opcode(OP_SIZE)
@@ -142,8 +153,8 @@ fn create_htlc(
         .push_opcode(OP_HASH160)
         .push_slice(recipient_pubkey_hash.as_ref())
         .push_opcode(OP_ELSE)
-        .push_int(i64::from(redeem_block_height))
-        .push_opcode(OP_CHECKSEQUENCEVERIFY)
+        .push_int(i64::from(timelock))
+        .push_opcode(timelock_opcode)
         .push_opcode(OP_DROP)
         .push_opcode(OP_DUP)
         .push_opcode(OP_HASH160)
@@ -159,6 +170,8 @@ fn create_htlc(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::swap_protocols::rfc003::bitcoin::AbsoluteTimelock;
+    use bitcoin_support::Blocks;
     use std::str::FromStr;
 
     // Secret: 12345678901234567890123456789012
@@ -194,7 +207,7 @@ mod tests {
             recipient_pubkey_hash,
             sender_pubkey_hash,
             SecretHash::from_str(secret_hash).unwrap(),
-            900,
+            Blocks::new(900).into(),
         );
 
         assert_eq!(
@@ -222,7 +235,7 @@ mod tests {
             recipient_pubkey_hash,
             sender_pubkey_hash,
             SecretHash::from_str(secret_hash).unwrap(),
-            900,
+            Blocks::new(900).into(),
         );
 
         let address = htlc.compute_address(Network::Regtest);
@@ -237,4 +250,33 @@ mod tests {
         // -> witness_program returned = sha256 of htlc script
         // Hence I guess it's correct!
     }
+
+    #[test]
+    fn given_an_absolute_timelock_return_cltv_htlc_redeem_script() {
+        let recipient_pubkey_hash: Vec<u8> =
+            hex::decode("c021f17be99c6adfbcba5d38ee0d292c0399d2f5").unwrap();
+        let sender_pubkey_hash: Vec<u8> =
+            hex::decode("1925a274ac004373bb5429553bdb55c40e57b124").unwrap();
+
+        let recipient_pubkey_hash = PubkeyHash::from(&recipient_pubkey_hash[..]);
+        let sender_pubkey_hash = PubkeyHash::from(&sender_pubkey_hash[..]);
+
+        let secret_hash = "51a488e06e9c69c555b8ad5e2c4629bb3135b96accd1f23451af75e06d3aee9c";
+
+        let htlc = Htlc::new(
+            recipient_pubkey_hash,
+            sender_pubkey_hash,
+            SecretHash::from_str(secret_hash).unwrap(),
+            AbsoluteTimelock::BlockHeight(900).into(),
+        );
+
+        // Same as the relative HTLC, except for OP_CHECKLOCKTIMEVERIFY (b1)
+        assert_eq!(
+            htlc.script.into_bytes(),
+            hex::decode(
+                "6382012088a82051a488e06e9c69c555b8ad5e2c4629bb3135b96accd1f23451af75e06d3aee9c8876a914c021f17be99c6adfbcba5d38ee0d292c0399d2f567028403b17576a9141925a274ac004373bb5429553bdb55c40e57b1246888ac"
+            )
+            .unwrap()
+        );
+    }
 }
@@ -2,23 +2,27 @@ use crate::swap_protocols::{
     ledger::Bitcoin,
     rfc003::{state_machine::HtlcParams, Ledger},
 };
-use bitcoin_support::{Address, BitcoinQuantity, Blocks, OutPoint};
+use bitcoin_support::{Address, BitcoinQuantity, OutPoint};
 use secp256k1_support::KeyPair;
 
 mod actions;
+mod block_height;
 mod extract_secret;
 mod htlc;
 mod queries;
+mod timelock;
 mod validation;
 
 pub use self::{
     actions::*,
+    block_height::{latest_block_height, track_latest_block_height},
     htlc::{Htlc, UnlockingError},
     queries::*,
+    timelock::*,
 };
 
 impl Ledger for Bitcoin {
-    type LockDuration = Blocks;
+    type LockDuration = Timelock;
     type HtlcLocation = OutPoint;
     type HtlcIdentity = KeyPair;
 }
@@ -29,7 +33,7 @@ impl From<HtlcParams<Bitcoin, BitcoinQuantity>> for Htlc {
             htlc_params.redeem_identity,
             htlc_params.refund_identity,
             htlc_params.secret_hash,
-            htlc_params.lock_duration.into(),
+            htlc_params.lock_duration,
         )
     }
 }
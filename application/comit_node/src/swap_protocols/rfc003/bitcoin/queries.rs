@@ -3,8 +3,12 @@ use crate::{
     swap_protocols::{
         ledger::Bitcoin,
         rfc003::{
-            events::{NewHtlcFundedQuery, NewHtlcRedeemedQuery, NewHtlcRefundedQuery},
+            bitcoin::{AbsoluteTimelock, Htlc, Timelock},
+            events::{
+                NewHtlcExpiredQuery, NewHtlcFundedQuery, NewHtlcRedeemedQuery, NewHtlcRefundedQuery,
+            },
             state_machine::HtlcParams,
+            Timestamp,
         },
     },
 };
@@ -22,13 +26,17 @@ impl NewHtlcFundedQuery<Bitcoin, BitcoinQuantity> for BitcoinQuery {
 
 impl NewHtlcRefundedQuery<Bitcoin, BitcoinQuantity> for BitcoinQuery {
     fn new_htlc_refunded_query(
-        _htlc_params: &HtlcParams<Bitcoin, BitcoinQuantity>,
+        htlc_params: &HtlcParams<Bitcoin, BitcoinQuantity>,
         htlc_location: &OutPoint,
     ) -> Self {
+        let htlc = Htlc::from(htlc_params.clone());
+
+        // The refund path is taken with an empty (false) witness item, the
+        // htlc script tells the relative and absolute timelock variants apart
         BitcoinQuery::Transaction {
             to_address: None,
             from_outpoint: Some(*htlc_location),
-            unlock_script: Some(vec![vec![0u8]]),
+            unlock_script: Some(vec![vec![], htlc.script().clone().into_bytes()]),
         }
     }
 }
@@ -45,3 +53,42 @@ impl NewHtlcRedeemedQuery<Bitcoin, BitcoinQuantity> for BitcoinQuery {
         }
     }
 }
+
+impl NewHtlcExpiredQuery<Bitcoin, BitcoinQuantity> for BitcoinQuery {
+    fn new_htlc_expired_query(
+        htlc_params: &HtlcParams<Bitcoin, BitcoinQuantity>,
+        htlc_deployed_at: Timestamp,
+    ) -> (Self, u32) {
+        match htlc_params.lock_duration {
+            // Every block mined after the HTLC was funded counts towards the
+            // relative timelock. Blocks that were already reported before a
+            // restart are not reported again, which can only make the HTLC
+            // expire later.
+            Timelock::Relative(blocks) => (
+                BitcoinQuery::Block {
+                    min_height: None,
+                    min_timestamp_secs: Some(htlc_deployed_at.0),
+                },
+                blocks.into(),
+            ),
+            // A transaction with this locktime can go into the block after
+            Timelock::Absolute(AbsoluteTimelock::BlockHeight(height)) => (
+                BitcoinQuery::Block {
+                    min_height: Some(height),
+                    min_timestamp_secs: None,
+                },
+                1,
+            ),
+            // Timestamp locktimes are checked against the median time of the
+            // last 11 blocks (BIP113), which has to be past the locktime.
+            // That is the case once 6 blocks were mined after it.
+            Timelock::Absolute(AbsoluteTimelock::Timestamp(timestamp)) => (
+                BitcoinQuery::Block {
+                    min_height: None,
+                    min_timestamp_secs: Some(timestamp + 1),
+                },
+                6,
+            ),
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use crate::swap_protocols::rfc003::{bitcoin::latest_block_height, EstimateDuration};
+use bitcoin_support::Blocks;
+use serde::{de, Deserialize, Deserializer};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Values of nLockTime below this are block heights, above are timestamps
+pub const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+
+/// When the refund path of a Bitcoin HTLC opens
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Timelock {
+    /// Blocks after the HTLC has been funded (`OP_CHECKSEQUENCEVERIFY`)
+    Relative(Blocks),
+    /// Fixed point in time (`OP_CHECKLOCKTIMEVERIFY`)
+    Absolute(AbsoluteTimelock),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AbsoluteTimelock {
+    BlockHeight(u32),
+    Timestamp(u32),
+}
+
+impl AbsoluteTimelock {
+    pub fn locktime(self) -> u32 {
+        match self {
+            AbsoluteTimelock::BlockHeight(height) => height,
+            AbsoluteTimelock::Timestamp(timestamp) => timestamp,
+        }
+    }
+
+    /// Whether the Bitcoin consensus rules interpret the locktime as the same
+    /// kind of lock
+    pub fn is_valid(self) -> bool {
+        match self {
+            AbsoluteTimelock::BlockHeight(height) => height < LOCKTIME_THRESHOLD,
+            AbsoluteTimelock::Timestamp(timestamp) => timestamp >= LOCKTIME_THRESHOLD,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for AbsoluteTimelock {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "snake_case")]
+        enum Unchecked {
+            BlockHeight(u32),
+            Timestamp(u32),
+        }
+
+        let timelock = match Unchecked::deserialize(deserializer)? {
+            Unchecked::BlockHeight(height) => AbsoluteTimelock::BlockHeight(height),
+            Unchecked::Timestamp(timestamp) => AbsoluteTimelock::Timestamp(timestamp),
+        };
+
+        if !timelock.is_valid() {
+            return Err(de::Error::custom(format!(
+                "{:?} would not be interpreted as such by Bitcoin",
+                timelock
+            )));
+        }
+
+        Ok(timelock)
+    }
+}
+
+impl From<Blocks> for Timelock {
+    fn from(blocks: Blocks) -> Self {
+        Timelock::Relative(blocks)
+    }
+}
+
+impl From<AbsoluteTimelock> for Timelock {
+    fn from(absolute: AbsoluteTimelock) -> Self {
+        Timelock::Absolute(absolute)
+    }
+}
+
+impl Timelock {
+    fn estimate_duration_at(&self, latest_block_height: Option<u32>) -> Option<Duration> {
+        match self {
+            Timelock::Relative(blocks) => blocks.estimate_duration(),
+            Timelock::Absolute(AbsoluteTimelock::BlockHeight(block_height)) => {
+                let remaining_blocks = block_height.saturating_sub(latest_block_height?);
+
+                Blocks::new(remaining_blocks).estimate_duration()
+            }
+            Timelock::Absolute(AbsoluteTimelock::Timestamp(timestamp)) => {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+                let timestamp = Duration::from_secs(u64::from(*timestamp));
+
+                Some(
+                    timestamp
+                        .checked_sub(now)
+                        .unwrap_or_else(|| Duration::from_secs(0)),
+                )
+            }
+        }
+    }
+}
+
+impl EstimateDuration for Timelock {
+    fn estimate_duration(&self) -> Option<Duration> {
+        self.estimate_duration_at(latest_block_height())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_timelock_deserializes_from_plain_number() {
+        let timelock: Timelock = serde_json::from_str("144").unwrap();
+
+        assert_eq!(timelock, Timelock::Relative(Blocks::new(144)));
+    }
+
+    #[test]
+    fn absolute_timelock_deserializes_from_tagged_object() {
+        let block_height: Timelock = serde_json::from_str(r#"{"block_height":600000}"#).unwrap();
+        let timestamp: Timelock = serde_json::from_str(r#"{"timestamp":1546300800}"#).unwrap();
+
+        assert_eq!(
+            block_height,
+            Timelock::Absolute(AbsoluteTimelock::BlockHeight(600_000))
+        );
+        assert_eq!(
+            timestamp,
+            Timelock::Absolute(AbsoluteTimelock::Timestamp(1_546_300_800))
+        );
+    }
+
+    #[test]
+    fn block_height_above_threshold_is_rejected() {
+        let block_height = serde_json::from_str::<Timelock>(r#"{"block_height":500000000}"#);
+        let timestamp = serde_json::from_str::<Timelock>(r#"{"timestamp":600000}"#);
+
+        assert!(block_height.is_err());
+        assert!(timestamp.is_err());
+    }
+
+    #[test]
+    fn given_latest_block_height_then_absolute_block_height_is_estimated_in_blocks() {
+        let timelock = Timelock::Absolute(AbsoluteTimelock::BlockHeight(600_144));
+
+        assert_eq!(
+            timelock.estimate_duration_at(Some(600_000)),
+            Some(Duration::from(Blocks::new(144)))
+        );
+    }
+
+    #[test]
+    fn given_absolute_block_height_in_the_past_then_it_expires_now() {
+        let timelock = Timelock::Absolute(AbsoluteTimelock::BlockHeight(600_000));
+
+        assert_eq!(
+            timelock.estimate_duration_at(Some(600_010)),
+            Some(Duration::from_secs(0))
+        );
+    }
+
+    #[test]
+    fn given_unknown_latest_block_height_then_absolute_block_height_cannot_be_estimated() {
+        let timelock = Timelock::Absolute(AbsoluteTimelock::BlockHeight(600_000));
+
+        assert_eq!(timelock.estimate_duration_at(None), None);
+    }
+}
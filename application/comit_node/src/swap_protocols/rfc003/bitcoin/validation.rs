@@ -48,7 +48,7 @@ mod tests {
                 "d38e554430c4035f2877a579a07a99886153f072",
             )
             .unwrap(),
-            lock_duration: Blocks::from(144),
+            lock_duration: Blocks::from(144).into(),
             secret_hash: Secret::from(*b"hello world, you are beautiful!!").into(),
         }
     }
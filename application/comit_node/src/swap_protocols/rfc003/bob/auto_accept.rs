@@ -0,0 +1,693 @@
+use crate::{
+    comit_client::{Decimal, DeclineDetails, SwapDeclineReason},
+    price_feed::{to_decimal, RateCheck, ToPricedAsset},
+    seed::Seed,
+    settings::{AutoAccept, AutoAcceptRule},
+    swap_protocols::{
+        asset::Asset,
+        metadata_store::Metadata,
+        rfc003::{
+            bitcoin::Timelock, bob::SwapRequest, ethereum::Seconds, roles::Bob,
+            state_machine::StateMachineResponse, EstimateDuration, Ledger, SecretSource,
+        },
+        SwapId,
+    },
+};
+use bigdecimal::BigDecimal;
+use bitcoin_support::{BitcoinQuantity, Blocks};
+use ethereum_support::{Address, Erc20Quantity, EtherQuantity, ToBigDecimal, U256};
+use futures::{
+    future::{self, Either},
+    Future,
+};
+use secp256k1_support::KeyPair;
+use std::{sync::Arc, time::Duration};
+
+/// The quantity of an asset in the units the auto-accept rules are given in,
+/// exactly as many of them as the smallest unit of the asset adds up to
+pub trait RuleQuantity {
+    fn rule_quantity(&self) -> BigDecimal;
+}
+
+impl RuleQuantity for BitcoinQuantity {
+    fn rule_quantity(&self) -> BigDecimal {
+        U256::from(self.satoshi()).to_bigdec(8)
+    }
+}
+
+impl RuleQuantity for EtherQuantity {
+    fn rule_quantity(&self) -> BigDecimal {
+        self.wei().to_bigdec(18)
+    }
+}
+
+impl RuleQuantity for Erc20Quantity {
+    fn rule_quantity(&self) -> BigDecimal {
+        self.quantity().to_bigdec(0)
+    }
+}
+
+/// The response Bob sends when a rule accepts a request, `None` if the
+/// identities it needs are not configured
+pub trait AutoAcceptResponse: Sized {
+    fn auto_accept_response(
+        auto_accept: &AutoAccept,
+        id: SwapId,
+        secret_source: &dyn SecretSource,
+        beta_ledger_lock_duration: Duration,
+    ) -> Option<Self>;
+}
+
+impl AutoAcceptResponse for StateMachineResponse<KeyPair, Address, Seconds> {
+    fn auto_accept_response(
+        auto_accept: &AutoAccept,
+        id: SwapId,
+        secret_source: &dyn SecretSource,
+        beta_ledger_lock_duration: Duration,
+    ) -> Option<Self> {
+        Some(StateMachineResponse {
+            alpha_ledger_redeem_identity: secret_source.new_secp256k1_redeem(id),
+            beta_ledger_refund_identity: auto_accept.ethereum_refund_identity?,
+            beta_ledger_lock_duration: Seconds::from(beta_ledger_lock_duration),
+        })
+    }
+}
+
+impl AutoAcceptResponse for StateMachineResponse<Address, KeyPair, Timelock> {
+    fn auto_accept_response(
+        auto_accept: &AutoAccept,
+        id: SwapId,
+        secret_source: &dyn SecretSource,
+        beta_ledger_lock_duration: Duration,
+    ) -> Option<Self> {
+        Some(StateMachineResponse {
+            alpha_ledger_redeem_identity: auto_accept.ethereum_redeem_identity?,
+            beta_ledger_refund_identity: secret_source.new_secp256k1_refund(id),
+            beta_ledger_lock_duration: Timelock::from(Blocks::from(beta_ledger_lock_duration)),
+        })
+    }
+}
+
+impl AutoAcceptResponse for StateMachineResponse<Address, Address, Seconds> {
+    fn auto_accept_response(
+        auto_accept: &AutoAccept,
+        _id: SwapId,
+        _secret_source: &dyn SecretSource,
+        beta_ledger_lock_duration: Duration,
+    ) -> Option<Self> {
+        Some(StateMachineResponse {
+            alpha_ledger_redeem_identity: auto_accept.ethereum_redeem_identity?,
+            beta_ledger_refund_identity: auto_accept.ethereum_refund_identity?,
+            beta_ledger_lock_duration: Seconds::from(beta_ledger_lock_duration),
+        })
+    }
+}
+
+/// What the rules look at in a swap request
+#[derive(Clone, Debug, PartialEq)]
+pub struct RequestTerms {
+    pub metadata: Metadata,
+    pub alpha_quantity: BigDecimal,
+    pub beta_quantity: BigDecimal,
+    pub alpha_lock_duration: Option<Duration>,
+}
+
+impl RequestTerms {
+    pub fn of<AL: Ledger, BL: Ledger, AA: Asset + RuleQuantity, BA: Asset + RuleQuantity>(
+        request: &SwapRequest<AL, BL, AA, BA>,
+    ) -> Self {
+        RequestTerms {
+            metadata: Metadata::from(request.clone()),
+            alpha_quantity: request.alpha_asset.rule_quantity(),
+            beta_quantity: request.beta_asset.rule_quantity(),
+            alpha_lock_duration: request.alpha_ledger_lock_duration.estimate_duration(),
+        }
+    }
+
+    /// Requests for nothing of either asset are never accepted
+    fn has_quantities(&self) -> bool {
+        let zero = U256::zero().to_bigdec(0);
+
+        self.alpha_quantity > zero && self.beta_quantity > zero
+    }
+
+    /// Limits that aren't numbers are never met
+    fn has_alpha_quantity_at_least(&self, min: f64) -> bool {
+        to_decimal(min).map_or(false, |min| self.alpha_quantity >= min)
+    }
+
+    fn has_alpha_quantity_at_most(&self, max: f64) -> bool {
+        to_decimal(max).map_or(false, |max| self.alpha_quantity <= max)
+    }
+
+    fn has_rate_at_most(&self, max_rate: f64) -> bool {
+        to_decimal(max_rate).map_or(false, |max_rate| self.has_exact_rate_at_most(max_rate))
+    }
+
+    /// Whether Bob gives at most `max_rate` of the beta asset per alpha asset
+    fn has_exact_rate_at_most(&self, max_rate: BigDecimal) -> bool {
+        self.beta_quantity <= self.alpha_quantity.clone() * max_rate
+    }
+}
+
+fn is_for_pair(rule: &AutoAcceptRule, metadata: &Metadata) -> bool {
+    rule.alpha_ledger == metadata.alpha_ledger
+        && rule.beta_ledger == metadata.beta_ledger
+        && rule.alpha_asset == metadata.alpha_asset
+        && rule.beta_asset == metadata.beta_asset
+}
+
+pub fn accepts(rule: &AutoAcceptRule, terms: &RequestTerms) -> bool {
+    is_for_pair(rule, &terms.metadata)
+        && terms.has_quantities()
+        && rule
+            .min_alpha_quantity
+            .map_or(true, |min| terms.has_alpha_quantity_at_least(min))
+        && rule
+            .max_alpha_quantity
+            .map_or(true, |max| terms.has_alpha_quantity_at_most(max))
+        && rule
+            .max_rate
+            .map_or(true, |max_rate| terms.has_rate_at_most(max_rate))
+        && rule.min_alpha_lock_duration_secs.map_or(true, |min| {
+            terms
+                .alpha_lock_duration
+                .map_or(false, |duration| duration >= Duration::from_secs(min))
+        })
+}
+
+/// Why the request is declined, going by the first rule for its ledgers and
+/// assets
+pub fn decline_reason(
+    rules: &[AutoAcceptRule],
+    terms: &RequestTerms,
+) -> (SwapDeclineReason, Option<DeclineDetails>) {
+    let rule = match rules.iter().find(|rule| is_for_pair(rule, &terms.metadata)) {
+        Some(rule) => rule,
+        None => return (SwapDeclineReason::UnsupportedPair, None),
+    };
+    let quantity_limits = DeclineDetails {
+        min_alpha_quantity: limit_decimal(rule.min_alpha_quantity),
+        max_alpha_quantity: limit_decimal(rule.max_alpha_quantity),
+        ..DeclineDetails::default()
+    };
+
+    if !terms.has_quantities()
+        || rule
+            .min_alpha_quantity
+            .map_or(false, |min| !terms.has_alpha_quantity_at_least(min))
+    {
+        (SwapDeclineReason::AmountTooLow, Some(quantity_limits))
+    } else if rule
+        .max_alpha_quantity
+        .map_or(false, |max| !terms.has_alpha_quantity_at_most(max))
+    {
+        (SwapDeclineReason::AmountTooHigh, Some(quantity_limits))
+    } else if rule
+        .max_rate
+        .map_or(false, |max_rate| !terms.has_rate_at_most(max_rate))
+    {
+        (
+            SwapDeclineReason::BadRate,
+            Some(DeclineDetails {
+                max_rate: limit_decimal(rule.max_rate),
+                ..DeclineDetails::default()
+            }),
+        )
+    } else {
+        (
+            SwapDeclineReason::LockDurationTooShort,
+            Some(DeclineDetails {
+                min_alpha_lock_duration_secs: rule.min_alpha_lock_duration_secs,
+                ..DeclineDetails::default()
+            }),
+        )
+    }
+}
+
+fn limit_decimal(limit: Option<f64>) -> Option<Decimal> {
+    limit
+        .filter(|limit| limit.is_finite() && *limit >= 0.0)
+        .map(Decimal::from)
+}
+
+/// Accepts or declines the request if the policy has an answer for it,
+/// otherwise it is left to be answered through the HTTP API
+pub fn auto_respond<AL: Ledger, BL: Ledger, AA: Asset + RuleQuantity, BA: Asset + RuleQuantity>(
+    id: SwapId,
+    bob: &Bob<AL, BL, AA, BA>,
+    request: &SwapRequest<AL, BL, AA, BA>,
+    auto_accept: &AutoAccept,
+    secret_source: &dyn SecretSource,
+    lock_duration_margin: Duration,
+) where
+    StateMachineResponse<AL::HtlcIdentity, BL::HtlcIdentity, BL::LockDuration>: AutoAcceptResponse,
+{
+    let terms = RequestTerms::of(request);
+
+    match auto_accept.rules.iter().find(|rule| accepts(rule, &terms)) {
+        Some(rule) => {
+            let response = match StateMachineResponse::auto_accept_response(
+                auto_accept,
+                id,
+                secret_source,
+                Duration::from_secs(rule.beta_lock_duration_secs),
+            ) {
+                Some(response) => response,
+                None => {
+                    warn!(
+                        "Cannot accept swap {} automatically without default identities",
+                        id
+                    );
+                    return;
+                }
+            };
+
+            match bob
+                .accept_action(request.alpha_ledger_lock_duration.clone())
+                .accept(response, lock_duration_margin)
+            {
+                Ok(()) => info!("Accepted swap {} automatically", id),
+                Err(e) => warn!("Failed to accept swap {} automatically: {:?}", id, e),
+            }
+        }
+        None if auto_accept.decline_unmatched => {
+            let (reason, details) = decline_reason(&auto_accept.rules, &terms);
+            info!(
+                "Declining swap {} because no rule accepts it: {:?}",
+                id, reason
+            );
+            if let Err(e) = bob.decline_action().decline(Some(reason), details) {
+                warn!("Failed to decline swap {} automatically: {:?}", id, e);
+            }
+        }
+        None => {}
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum RateVerdict {
+    Acceptable,
+    TooHigh { max_rate: BigDecimal },
+    Unknown,
+}
+
+/// Declines the request with `BadRate` if the rate check says so and
+/// otherwise leaves it to `auto_respond`. If the market rate cannot be
+/// fetched, the request is left to be answered through the HTTP API.
+#[allow(clippy::too_many_arguments)]
+pub fn check_rate_and_auto_respond<
+    AL: Ledger,
+    BL: Ledger,
+    AA: Asset + RuleQuantity + ToPricedAsset,
+    BA: Asset + RuleQuantity + ToPricedAsset,
+>(
+    id: SwapId,
+    bob: Bob<AL, BL, AA, BA>,
+    request: SwapRequest<AL, BL, AA, BA>,
+    auto_accept: Arc<AutoAccept>,
+    rate_check: Option<RateCheck>,
+    seed: Seed,
+    lock_duration_margin: Duration,
+) -> impl Future<Item = (), Error = ()>
+where
+    StateMachineResponse<AL::HtlcIdentity, BL::HtlcIdentity, BL::LockDuration>: AutoAcceptResponse,
+{
+    let terms = RequestTerms::of(&request);
+
+    let verdict = match rate_check {
+        None => Either::A(future::ok(RateVerdict::Acceptable)),
+        Some(rate_check) => Either::B(
+            rate_check
+                .feed
+                .rate(
+                    request.alpha_asset.to_priced_asset(),
+                    request.beta_asset.to_priced_asset(),
+                )
+                .then(move |market_rate| match market_rate.map(|rate| rate_check.max_rate(rate)) {
+                    Ok(Some(max_rate)) => {
+                        if terms.has_exact_rate_at_most(max_rate.clone()) {
+                            Ok(RateVerdict::Acceptable)
+                        } else {
+                            Ok(RateVerdict::TooHigh { max_rate })
+                        }
+                    }
+                    Ok(None) => {
+                        warn!(
+                            "Cannot check the rate of swap {} against a market rate that isn't positive",
+                            id
+                        );
+                        Ok(RateVerdict::Unknown)
+                    }
+                    Err(e) => {
+                        warn!("Cannot check the rate of swap {}: {:?}", id, e);
+                        Ok(RateVerdict::Unknown)
+                    }
+                }),
+        ),
+    };
+
+    verdict.map(move |verdict| match verdict {
+        RateVerdict::TooHigh { max_rate } => {
+            info!("Declining swap {} because of its rate", id);
+            let details = DeclineDetails {
+                max_rate: Some(Decimal::from(&max_rate)),
+                ..DeclineDetails::default()
+            };
+            if let Err(e) = bob
+                .decline_action()
+                .decline(Some(SwapDeclineReason::BadRate), Some(details))
+            {
+                warn!("Failed to decline swap {} automatically: {:?}", id, e);
+            }
+        }
+        RateVerdict::Unknown => info!("Leaving swap {} to be answered manually", id),
+        RateVerdict::Acceptable => {
+            auto_respond(
+                id,
+                &bob,
+                &request,
+                &auto_accept,
+                &seed,
+                lock_duration_margin,
+            );
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        comit_client::SwapReject,
+        price_feed::{PricedAsset, StaticPriceFeed},
+        swap_protocols::{
+            ledger::{Bitcoin, Ethereum},
+            metadata_store::{AssetKind, LedgerKind},
+            rfc003::Secret,
+        },
+    };
+    use futures::Future;
+    use hex::FromHex;
+    use spectral::prelude::*;
+
+    fn rule() -> AutoAcceptRule {
+        AutoAcceptRule {
+            alpha_ledger: LedgerKind::Bitcoin,
+            beta_ledger: LedgerKind::Ethereum,
+            alpha_asset: AssetKind::Bitcoin,
+            beta_asset: AssetKind::Ether,
+            min_alpha_quantity: Some(0.1),
+            max_alpha_quantity: Some(2.0),
+            max_rate: Some(11.0),
+            min_alpha_lock_duration_secs: Some(86400),
+            beta_lock_duration_secs: 43200,
+        }
+    }
+
+    fn request(
+        alpha_bitcoin: f64,
+        beta_ether: f64,
+    ) -> SwapRequest<Bitcoin, Ethereum, BitcoinQuantity, EtherQuantity> {
+        SwapRequest {
+            alpha_asset: BitcoinQuantity::from_bitcoin(alpha_bitcoin),
+            beta_asset: EtherQuantity::from_eth(beta_ether),
+            alpha_ledger: Bitcoin::default(),
+            beta_ledger: Ethereum::default(),
+            alpha_ledger_refund_identity: bitcoin_support::PubkeyHash::from_hex(
+                "875638cac0b0ae9f826575e190f2788918c354c2",
+            )
+            .unwrap(),
+            beta_ledger_redeem_identity: "8457037fcd80a8650c4692d7fcfc1d0a96b92867"
+                .parse()
+                .unwrap(),
+            alpha_ledger_lock_duration: Blocks::from(144).into(),
+            secret_hash: Secret::from(*b"hello world, you are beautiful!!").hash(),
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn rule_checks_amounts_and_rate() {
+        let rule = rule();
+
+        assert_that(&accepts(&rule, &RequestTerms::of(&request(1.0, 10.0)))).is_true();
+        assert_that(&accepts(&rule, &RequestTerms::of(&request(1.0, 12.0)))).is_false();
+        assert_that(&accepts(&rule, &RequestTerms::of(&request(3.0, 30.0)))).is_false();
+        assert_that(&accepts(&rule, &RequestTerms::of(&request(0.01, 0.1)))).is_false();
+    }
+
+    #[test]
+    fn matching_request_is_accepted_with_default_identities() {
+        let refund_identity: Address = "0x00a329c0648769a73afac7f9381e08fb43dbea72"
+            .parse()
+            .unwrap();
+        let auto_accept = AutoAccept {
+            ethereum_refund_identity: Some(refund_identity),
+            ethereum_redeem_identity: None,
+            rules: vec![rule()],
+            ..AutoAccept::default()
+        };
+        let seed = Seed::from(*b"hello world, you are beautiful!!");
+        let (bob, response_future) = Bob::create(None);
+
+        auto_respond(
+            SwapId::default(),
+            &bob,
+            &request(1.0, 10.0),
+            &auto_accept,
+            &seed,
+            Duration::from_secs(6 * 60 * 60),
+        );
+
+        let response = response_future.wait().unwrap().unwrap();
+        assert_that(&response.beta_ledger_refund_identity).is_equal_to(refund_identity);
+        assert_that(&response.beta_ledger_lock_duration).is_equal_to(Seconds(43200));
+    }
+
+    #[test]
+    fn unmatched_request_is_declined_if_configured() {
+        let auto_accept = AutoAccept {
+            decline_unmatched: true,
+            ..AutoAccept::default()
+        };
+        let seed = Seed::from(*b"hello world, you are beautiful!!");
+        let (bob, response_future) = Bob::create(None);
+
+        auto_respond(
+            SwapId::default(),
+            &bob,
+            &request(1.0, 10.0),
+            &auto_accept,
+            &seed,
+            Duration::from_secs(6 * 60 * 60),
+        );
+
+        assert_that(&response_future.wait().unwrap()).is_err_containing(SwapReject::Declined {
+            reason: Some(SwapDeclineReason::UnsupportedPair),
+            details: None,
+        });
+    }
+
+    #[test]
+    fn decline_reason_names_the_limit_the_request_is_outside_of() {
+        let rules = vec![rule()];
+        let quantity_limits = DeclineDetails {
+            min_alpha_quantity: Some(Decimal::from(0.1)),
+            max_alpha_quantity: Some(Decimal::from(2.0)),
+            ..DeclineDetails::default()
+        };
+
+        let decline_reason =
+            |alpha, beta| decline_reason(&rules, &RequestTerms::of(&request(alpha, beta)));
+
+        assert_that(&decline_reason(3.0, 30.0)).is_equal_to((
+            SwapDeclineReason::AmountTooHigh,
+            Some(quantity_limits.clone()),
+        ));
+        assert_that(&decline_reason(0.01, 0.1))
+            .is_equal_to((SwapDeclineReason::AmountTooLow, Some(quantity_limits)));
+        assert_that(&decline_reason(1.0, 12.0)).is_equal_to((
+            SwapDeclineReason::BadRate,
+            Some(DeclineDetails {
+                max_rate: Some(Decimal::from(11.0)),
+                ..DeclineDetails::default()
+            }),
+        ));
+    }
+
+    #[test]
+    fn request_for_nothing_is_never_accepted() {
+        let rule = AutoAcceptRule {
+            min_alpha_quantity: None,
+            max_alpha_quantity: None,
+            max_rate: None,
+            ..rule()
+        };
+
+        for (alpha, beta) in &[(0.0, 10.0), (1.0, 0.0), (0.0, 0.0)] {
+            let terms = RequestTerms::of(&request(*alpha, *beta));
+
+            assert_that(&accepts(&rule, &terms)).is_false();
+            assert_that(&decline_reason(&[rule.clone()], &terms).0)
+                .is_equal_to(SwapDeclineReason::AmountTooLow);
+        }
+    }
+
+    #[test]
+    fn limits_that_are_not_numbers_accept_nothing() {
+        let terms = RequestTerms::of(&request(1.0, 10.0));
+
+        for limit in &[std::f64::NAN, std::f64::INFINITY, std::f64::NEG_INFINITY] {
+            assert_that(&accepts(
+                &AutoAcceptRule {
+                    max_rate: Some(*limit),
+                    ..rule()
+                },
+                &terms,
+            ))
+            .is_false();
+            assert_that(&accepts(
+                &AutoAcceptRule {
+                    min_alpha_quantity: Some(*limit),
+                    ..rule()
+                },
+                &terms,
+            ))
+            .is_false();
+            assert_that(&accepts(
+                &AutoAcceptRule {
+                    max_alpha_quantity: Some(*limit),
+                    ..rule()
+                },
+                &terms,
+            ))
+            .is_false();
+        }
+
+        assert_that(&decline_reason(
+            &[AutoAcceptRule {
+                max_rate: Some(std::f64::NAN),
+                ..rule()
+            }],
+            &terms,
+        ))
+        .is_equal_to((SwapDeclineReason::BadRate, Some(DeclineDetails::default())));
+    }
+
+    #[test]
+    fn quantities_are_compared_exactly() {
+        let request = SwapRequest {
+            beta_asset: EtherQuantity::from_wei(U256::from(1_100_000_000_000_000_000u64)),
+            ..request(0.1, 1.0)
+        };
+        let terms = RequestTerms::of(&request);
+
+        assert_that(&accepts(
+            &AutoAcceptRule {
+                min_alpha_quantity: Some(0.1),
+                max_alpha_quantity: Some(0.1),
+                max_rate: Some(11.0),
+                ..rule()
+            },
+            &terms,
+        ))
+        .is_true();
+    }
+
+    #[test]
+    fn request_above_market_rate_is_declined_with_bad_rate() {
+        let rate_check = RateCheck {
+            feed: Arc::new(StaticPriceFeed::default().with_rate(
+                PricedAsset::Bitcoin,
+                PricedAsset::Ether,
+                10.0,
+            )),
+            spread: 0.01,
+        };
+        let seed = Seed::from(*b"hello world, you are beautiful!!");
+        let (bob, response_future) = Bob::create(None);
+
+        check_rate_and_auto_respond(
+            SwapId::default(),
+            bob,
+            request(1.0, 12.0),
+            Arc::new(AutoAccept::default()),
+            Some(rate_check),
+            seed,
+            Duration::from_secs(6 * 60 * 60),
+        )
+        .wait()
+        .unwrap();
+
+        assert_that(&response_future.wait().unwrap()).is_err_containing(SwapReject::Declined {
+            reason: Some(SwapDeclineReason::BadRate),
+            details: Some(DeclineDetails {
+                max_rate: Some(Decimal::from(10.0 * (1.0 + 0.01))),
+                ..DeclineDetails::default()
+            }),
+        });
+    }
+
+    #[test]
+    fn request_is_left_unanswered_if_the_market_rate_is_unknown() {
+        let rate_check = RateCheck {
+            feed: Arc::new(StaticPriceFeed::default()),
+            spread: 0.01,
+        };
+        let auto_accept = AutoAccept {
+            decline_unmatched: true,
+            ..AutoAccept::default()
+        };
+        let seed = Seed::from(*b"hello world, you are beautiful!!");
+        let (bob, _response_future) = Bob::create(None);
+
+        check_rate_and_auto_respond(
+            SwapId::default(),
+            bob.clone(),
+            request(1.0, 10.0),
+            Arc::new(auto_accept),
+            Some(rate_check),
+            seed,
+            Duration::from_secs(6 * 60 * 60),
+        )
+        .wait()
+        .unwrap();
+
+        assert_that(&bob.decline_action().decline(None, None).is_ok()).is_true();
+    }
+
+    #[test]
+    fn request_is_left_unanswered_if_the_market_rate_is_not_positive() {
+        for market_rate in &[0.0, std::f64::NAN, std::f64::INFINITY] {
+            let rate_check = RateCheck {
+                feed: Arc::new(StaticPriceFeed::default().with_rate(
+                    PricedAsset::Bitcoin,
+                    PricedAsset::Ether,
+                    *market_rate,
+                )),
+                spread: 0.01,
+            };
+            let auto_accept = AutoAccept {
+                decline_unmatched: true,
+                ..AutoAccept::default()
+            };
+            let seed = Seed::from(*b"hello world, you are beautiful!!");
+            let (bob, _response_future) = Bob::create(None);
+
+            check_rate_and_auto_respond(
+                SwapId::default(),
+                bob.clone(),
+                request(1.0, 10.0),
+                Arc::new(auto_accept),
+                Some(rate_check),
+                seed,
+                Duration::from_secs(6 * 60 * 60),
+            )
+            .wait()
+            .unwrap();
+
+            assert_that(&bob.decline_action().decline(None, None).is_ok()).is_true();
+        }
+    }
+}
@@ -1,17 +1,21 @@
 use crate::{
+    comit_client::SwapDeclineReason,
     ledger_query_service::{DefaultLedgerQueryServiceApiClient, FirstMatch, QueryIdCache},
+    peers::Counterparty,
+    price_feed::RateCheck,
     seed::Seed,
+    settings::AutoAccept,
     swap_protocols::{
         asset::Asset,
         metadata_store::MetadataStore,
         rfc003::{
             self,
-            bob::SwapRequestKind,
+            bob::{auto_accept::check_rate_and_auto_respond, SwapRequestKind},
             events::{BobToAlice, CommunicationEvents, LedgerEvents, LqsEvents, LqsEventsForErc20},
             roles::Bob,
             state_machine::*,
             state_store::StateStore,
-            Ledger,
+            Ledger, UpdateMetadataState,
         },
         SwapId,
     },
@@ -28,6 +32,7 @@ pub struct SwapRequestHandler<MetadataStore, StateStore> {
     // new dependencies
     pub receiver: UnboundedReceiver<(
         SwapId,
+        Counterparty,
         SwapRequestKind,
         oneshot::Sender<rfc003::bob::SwapResponseKind>,
     )>,
@@ -36,7 +41,10 @@ pub struct SwapRequestHandler<MetadataStore, StateStore> {
     pub lqs_api_client: Arc<DefaultLedgerQueryServiceApiClient>,
     pub bitcoin_poll_interval: Duration,
     pub ethereum_poll_interval: Duration,
+    pub lock_duration_margin: Duration,
     pub seed: Seed,
+    pub auto_accept: Arc<AutoAccept>,
+    pub rate_check: Option<RateCheck>,
 }
 
 impl<M: MetadataStore<SwapId>, S: StateStore<SwapId>> SwapRequestHandler<M, S> {
@@ -49,15 +57,17 @@ impl<M: MetadataStore<SwapId>, S: StateStore<SwapId>> SwapRequestHandler<M, S> {
         );
         let state_store = Arc::clone(&self.state_store);
         let lqs_api_client = Arc::clone(&self.lqs_api_client);
+        let lock_duration_margin = self.lock_duration_margin;
+        let (seed, auto_accept, rate_check) = (self.seed, self.auto_accept, self.rate_check);
 
         receiver
-            .for_each(move |(id, requests, response_sender)| {
-                info!("Received swap {:?} on channel", id);
+            .for_each(move |(id, counterparty, requests, response_sender)| {
+                info!("Received swap {:?} from {} on channel", id, counterparty);
                 match requests {
                     rfc003::bob::SwapRequestKind::BitcoinEthereumBitcoinQuantityEtherQuantity(
                         request,
                     ) => {
-                        if let Err(e) = metadata_store.insert(id, request.clone()) {
+                        if let Err(e) = metadata_store.insert(id, request.clone(), counterparty) {
                             error!("Failed to store metadata for swap {} because {:?}", id, e);
 
                             // Return Ok to keep the loop running
@@ -66,7 +76,23 @@ impl<M: MetadataStore<SwapId>, S: StateStore<SwapId>> SwapRequestHandler<M, S> {
 
                         {
                             let request = request.clone();
-                            let (bob, response_future) = Bob::create();
+                            let (bob, response_future) = Bob::create(request.expires_at);
+                            if !decline_if_unsafe(
+                                id,
+                                &bob,
+                                &request.alpha_ledger_lock_duration,
+                                lock_duration_margin,
+                            ) {
+                                tokio::spawn(check_rate_and_auto_respond(
+                                    id,
+                                    bob.clone(),
+                                    request.clone(),
+                                    Arc::clone(&auto_accept),
+                                    rate_check.clone(),
+                                    seed,
+                                    lock_duration_margin,
+                                ));
+                            }
 
                             let response_future = response_future.inspect(|response| {
                                 response_sender
@@ -90,6 +116,7 @@ impl<M: MetadataStore<SwapId>, S: StateStore<SwapId>> SwapRequestHandler<M, S> {
                                 id,
                                 start_state,
                                 state_store.as_ref(),
+                                Arc::clone(&metadata_store),
                                 Box::new(LqsEvents::new(
                                     QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
                                     FirstMatch::new(
@@ -113,7 +140,153 @@ impl<M: MetadataStore<SwapId>, S: StateStore<SwapId>> SwapRequestHandler<M, S> {
                     rfc003::bob::SwapRequestKind::BitcoinEthereumBitcoinQuantityErc20Quantity(
                         request,
                     ) => {
-                        if let Err(e) = metadata_store.insert(id, request.clone()) {
+                        if let Err(e) = metadata_store.insert(id, request.clone(), counterparty) {
+                            error!("Failed to store metadata for swap {} because {:?}", id, e);
+
+                            // Return Ok to keep the loop running
+                            return Ok(());
+                        }
+
+                        {
+                            let request = request.clone();
+                            let (bob, response_future) = Bob::create(request.expires_at);
+                            if !decline_if_unsafe(
+                                id,
+                                &bob,
+                                &request.alpha_ledger_lock_duration,
+                                lock_duration_margin,
+                            ) {
+                                tokio::spawn(check_rate_and_auto_respond(
+                                    id,
+                                    bob.clone(),
+                                    request.clone(),
+                                    Arc::clone(&auto_accept),
+                                    rate_check.clone(),
+                                    seed,
+                                    lock_duration_margin,
+                                ));
+                            }
+
+                            let response_future = response_future.inspect(|response| {
+                                response_sender
+                                    .send(response.clone().into())
+                                    .expect("receiver should never go out of scope");
+                            });
+
+                            let start_state = Start {
+                                alpha_ledger_refund_identity: request.alpha_ledger_refund_identity,
+                                beta_ledger_redeem_identity: request.beta_ledger_redeem_identity,
+                                alpha_ledger: request.alpha_ledger,
+                                beta_ledger: request.beta_ledger,
+                                alpha_asset: request.alpha_asset,
+                                beta_asset: request.beta_asset,
+                                alpha_ledger_lock_duration: request.alpha_ledger_lock_duration,
+                                secret: request.secret_hash,
+                                role: bob,
+                            };
+
+                            spawn_state_machine(
+                                id,
+                                start_state,
+                                state_store.as_ref(),
+                                Arc::clone(&metadata_store),
+                                Box::new(LqsEvents::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        bitcoin_poll_interval,
+                                    ),
+                                )),
+                                Box::new(LqsEventsForErc20::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        ethereum_poll_interval,
+                                    ),
+                                )),
+                                Box::new(BobToAlice::new(Box::new(response_future))),
+                            );
+                        }
+
+                        Ok(())
+                    }
+                    rfc003::bob::SwapRequestKind::EthereumBitcoinEtherQuantityBitcoinQuantity(
+                        request,
+                    ) => {
+                        if let Err(e) = metadata_store.insert(id, request.clone(), counterparty) {
+                            error!("Failed to store metadata for swap {} because {:?}", id, e);
+
+                            // Return Ok to keep the loop running
+                            return Ok(());
+                        }
+
+                        {
+                            let request = request.clone();
+                            let (bob, response_future) = Bob::create(request.expires_at);
+                            if !decline_if_unsafe(
+                                id,
+                                &bob,
+                                &request.alpha_ledger_lock_duration,
+                                lock_duration_margin,
+                            ) {
+                                tokio::spawn(check_rate_and_auto_respond(
+                                    id,
+                                    bob.clone(),
+                                    request.clone(),
+                                    Arc::clone(&auto_accept),
+                                    rate_check.clone(),
+                                    seed,
+                                    lock_duration_margin,
+                                ));
+                            }
+
+                            let response_future = response_future.inspect(|response| {
+                                response_sender
+                                    .send(response.clone().into())
+                                    .expect("receiver should never go out of scope");
+                            });
+
+                            let start_state = Start {
+                                alpha_ledger_refund_identity: request.alpha_ledger_refund_identity,
+                                beta_ledger_redeem_identity: request.beta_ledger_redeem_identity,
+                                alpha_ledger: request.alpha_ledger,
+                                beta_ledger: request.beta_ledger,
+                                alpha_asset: request.alpha_asset,
+                                beta_asset: request.beta_asset,
+                                alpha_ledger_lock_duration: request.alpha_ledger_lock_duration,
+                                secret: request.secret_hash,
+                                role: bob,
+                            };
+
+                            spawn_state_machine(
+                                id,
+                                start_state,
+                                state_store.as_ref(),
+                                Arc::clone(&metadata_store),
+                                Box::new(LqsEvents::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        ethereum_poll_interval,
+                                    ),
+                                )),
+                                Box::new(LqsEvents::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        bitcoin_poll_interval,
+                                    ),
+                                )),
+                                Box::new(BobToAlice::new(Box::new(response_future))),
+                            );
+                        }
+
+                        Ok(())
+                    }
+                    rfc003::bob::SwapRequestKind::EthereumBitcoinErc20QuantityBitcoinQuantity(
+                        request,
+                    ) => {
+                        if let Err(e) = metadata_store.insert(id, request.clone(), counterparty) {
                             error!("Failed to store metadata for swap {} because {:?}", id, e);
 
                             // Return Ok to keep the loop running
@@ -122,7 +295,23 @@ impl<M: MetadataStore<SwapId>, S: StateStore<SwapId>> SwapRequestHandler<M, S> {
 
                         {
                             let request = request.clone();
-                            let (bob, response_future) = Bob::create();
+                            let (bob, response_future) = Bob::create(request.expires_at);
+                            if !decline_if_unsafe(
+                                id,
+                                &bob,
+                                &request.alpha_ledger_lock_duration,
+                                lock_duration_margin,
+                            ) {
+                                tokio::spawn(check_rate_and_auto_respond(
+                                    id,
+                                    bob.clone(),
+                                    request.clone(),
+                                    Arc::clone(&auto_accept),
+                                    rate_check.clone(),
+                                    seed,
+                                    lock_duration_margin,
+                                ));
+                            }
 
                             let response_future = response_future.inspect(|response| {
                                 response_sender
@@ -146,6 +335,14 @@ impl<M: MetadataStore<SwapId>, S: StateStore<SwapId>> SwapRequestHandler<M, S> {
                                 id,
                                 start_state,
                                 state_store.as_ref(),
+                                Arc::clone(&metadata_store),
+                                Box::new(LqsEventsForErc20::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        ethereum_poll_interval,
+                                    ),
+                                )),
                                 Box::new(LqsEvents::new(
                                     QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
                                     FirstMatch::new(
@@ -153,6 +350,218 @@ impl<M: MetadataStore<SwapId>, S: StateStore<SwapId>> SwapRequestHandler<M, S> {
                                         bitcoin_poll_interval,
                                     ),
                                 )),
+                                Box::new(BobToAlice::new(Box::new(response_future))),
+                            );
+                        }
+
+                        Ok(())
+                    }
+                    rfc003::bob::SwapRequestKind::EthereumEthereumEtherQuantityErc20Quantity(
+                        request,
+                    ) => {
+                        if let Err(e) = metadata_store.insert(id, request.clone(), counterparty) {
+                            error!("Failed to store metadata for swap {} because {:?}", id, e);
+
+                            // Return Ok to keep the loop running
+                            return Ok(());
+                        }
+
+                        {
+                            let request = request.clone();
+                            let (bob, response_future) = Bob::create(request.expires_at);
+                            if !decline_if_unsafe(
+                                id,
+                                &bob,
+                                &request.alpha_ledger_lock_duration,
+                                lock_duration_margin,
+                            ) {
+                                tokio::spawn(check_rate_and_auto_respond(
+                                    id,
+                                    bob.clone(),
+                                    request.clone(),
+                                    Arc::clone(&auto_accept),
+                                    rate_check.clone(),
+                                    seed,
+                                    lock_duration_margin,
+                                ));
+                            }
+
+                            let response_future = response_future.inspect(|response| {
+                                response_sender
+                                    .send(response.clone().into())
+                                    .expect("receiver should never go out of scope");
+                            });
+
+                            let start_state = Start {
+                                alpha_ledger_refund_identity: request.alpha_ledger_refund_identity,
+                                beta_ledger_redeem_identity: request.beta_ledger_redeem_identity,
+                                alpha_ledger: request.alpha_ledger,
+                                beta_ledger: request.beta_ledger,
+                                alpha_asset: request.alpha_asset,
+                                beta_asset: request.beta_asset,
+                                alpha_ledger_lock_duration: request.alpha_ledger_lock_duration,
+                                secret: request.secret_hash,
+                                role: bob,
+                            };
+
+                            spawn_state_machine(
+                                id,
+                                start_state,
+                                state_store.as_ref(),
+                                Arc::clone(&metadata_store),
+                                Box::new(LqsEvents::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        ethereum_poll_interval,
+                                    ),
+                                )),
+                                Box::new(LqsEventsForErc20::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        ethereum_poll_interval,
+                                    ),
+                                )),
+                                Box::new(BobToAlice::new(Box::new(response_future))),
+                            );
+                        }
+
+                        Ok(())
+                    }
+                    rfc003::bob::SwapRequestKind::EthereumEthereumErc20QuantityEtherQuantity(
+                        request,
+                    ) => {
+                        if let Err(e) = metadata_store.insert(id, request.clone(), counterparty) {
+                            error!("Failed to store metadata for swap {} because {:?}", id, e);
+
+                            // Return Ok to keep the loop running
+                            return Ok(());
+                        }
+
+                        {
+                            let request = request.clone();
+                            let (bob, response_future) = Bob::create(request.expires_at);
+                            if !decline_if_unsafe(
+                                id,
+                                &bob,
+                                &request.alpha_ledger_lock_duration,
+                                lock_duration_margin,
+                            ) {
+                                tokio::spawn(check_rate_and_auto_respond(
+                                    id,
+                                    bob.clone(),
+                                    request.clone(),
+                                    Arc::clone(&auto_accept),
+                                    rate_check.clone(),
+                                    seed,
+                                    lock_duration_margin,
+                                ));
+                            }
+
+                            let response_future = response_future.inspect(|response| {
+                                response_sender
+                                    .send(response.clone().into())
+                                    .expect("receiver should never go out of scope");
+                            });
+
+                            let start_state = Start {
+                                alpha_ledger_refund_identity: request.alpha_ledger_refund_identity,
+                                beta_ledger_redeem_identity: request.beta_ledger_redeem_identity,
+                                alpha_ledger: request.alpha_ledger,
+                                beta_ledger: request.beta_ledger,
+                                alpha_asset: request.alpha_asset,
+                                beta_asset: request.beta_asset,
+                                alpha_ledger_lock_duration: request.alpha_ledger_lock_duration,
+                                secret: request.secret_hash,
+                                role: bob,
+                            };
+
+                            spawn_state_machine(
+                                id,
+                                start_state,
+                                state_store.as_ref(),
+                                Arc::clone(&metadata_store),
+                                Box::new(LqsEventsForErc20::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        ethereum_poll_interval,
+                                    ),
+                                )),
+                                Box::new(LqsEvents::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        ethereum_poll_interval,
+                                    ),
+                                )),
+                                Box::new(BobToAlice::new(Box::new(response_future))),
+                            );
+                        }
+
+                        Ok(())
+                    }
+                    rfc003::bob::SwapRequestKind::EthereumEthereumErc20QuantityErc20Quantity(
+                        request,
+                    ) => {
+                        if let Err(e) = metadata_store.insert(id, request.clone(), counterparty) {
+                            error!("Failed to store metadata for swap {} because {:?}", id, e);
+
+                            // Return Ok to keep the loop running
+                            return Ok(());
+                        }
+
+                        {
+                            let request = request.clone();
+                            let (bob, response_future) = Bob::create(request.expires_at);
+                            if !decline_if_unsafe(
+                                id,
+                                &bob,
+                                &request.alpha_ledger_lock_duration,
+                                lock_duration_margin,
+                            ) {
+                                tokio::spawn(check_rate_and_auto_respond(
+                                    id,
+                                    bob.clone(),
+                                    request.clone(),
+                                    Arc::clone(&auto_accept),
+                                    rate_check.clone(),
+                                    seed,
+                                    lock_duration_margin,
+                                ));
+                            }
+
+                            let response_future = response_future.inspect(|response| {
+                                response_sender
+                                    .send(response.clone().into())
+                                    .expect("receiver should never go out of scope");
+                            });
+
+                            let start_state = Start {
+                                alpha_ledger_refund_identity: request.alpha_ledger_refund_identity,
+                                beta_ledger_redeem_identity: request.beta_ledger_redeem_identity,
+                                alpha_ledger: request.alpha_ledger,
+                                beta_ledger: request.beta_ledger,
+                                alpha_asset: request.alpha_asset,
+                                beta_asset: request.beta_asset,
+                                alpha_ledger_lock_duration: request.alpha_ledger_lock_duration,
+                                secret: request.secret_hash,
+                                role: bob,
+                            };
+
+                            spawn_state_machine(
+                                id,
+                                start_state,
+                                state_store.as_ref(),
+                                Arc::clone(&metadata_store),
+                                Box::new(LqsEventsForErc20::new(
+                                    QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
+                                    FirstMatch::new(
+                                        Arc::clone(&lqs_api_client),
+                                        ethereum_poll_interval,
+                                    ),
+                                )),
                                 Box::new(LqsEventsForErc20::new(
                                     QueryIdCache::wrap(Arc::clone(&lqs_api_client)),
                                     FirstMatch::new(
@@ -172,10 +581,39 @@ impl<M: MetadataStore<SwapId>, S: StateStore<SwapId>> SwapRequestHandler<M, S> {
     }
 }
 
-fn spawn_state_machine<AL: Ledger, BL: Ledger, AA: Asset, BA: Asset, S: StateStore<SwapId>>(
+fn decline_if_unsafe<AL: Ledger, BL: Ledger, AA: Asset, BA: Asset>(
+    id: SwapId,
+    bob: &Bob<AL, BL, AA, BA>,
+    alpha_ledger_lock_duration: &AL::LockDuration,
+    lock_duration_margin: Duration,
+) -> bool {
+    match rfc003::validate_alpha_lock_duration(alpha_ledger_lock_duration, lock_duration_margin) {
+        Ok(()) => false,
+        Err(e) => {
+            warn!(
+                "Declining swap {} because of unsafe lock durations: {}",
+                id, e
+            );
+            bob.decline_action()
+                .decline(Some(SwapDeclineReason::UnsafeLockDurations), None)
+                .expect("nobody can have responded to a swap that was just received");
+            true
+        }
+    }
+}
+
+fn spawn_state_machine<
+    AL: Ledger,
+    BL: Ledger,
+    AA: Asset,
+    BA: Asset,
+    S: StateStore<SwapId>,
+    M: MetadataStore<SwapId>,
+>(
     id: SwapId,
     start_state: Start<Bob<AL, BL, AA, BA>>,
     state_store: &S,
+    metadata_store: Arc<M>,
     alpha_ledger_events: Box<dyn LedgerEvents<AL, AA>>,
     beta_ledger_events: Box<dyn LedgerEvents<BL, BA>>,
     communication_events: Box<dyn CommunicationEvents<Bob<AL, BL, AA, BA>>>,
@@ -185,6 +623,7 @@ fn spawn_state_machine<AL: Ledger, BL: Ledger, AA: Asset, BA: Asset, S: StateSto
     let save_state = state_store
         .insert(id, state.clone())
         .expect("handle errors :)"); // TODO: handle errors
+    let save_state = Arc::new(UpdateMetadataState::new(id, metadata_store, save_state));
 
     let context = Context {
         alpha_ledger_events,
@@ -1,3 +1,4 @@
+pub mod auto_accept;
 mod handler;
 mod swap_request;
 mod swap_response;
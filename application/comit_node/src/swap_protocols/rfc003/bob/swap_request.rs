@@ -1,10 +1,15 @@
-use crate::swap_protocols::{
-    ledger::{Bitcoin, Ethereum},
-    metadata_store::{AssetKind, LedgerKind, Metadata, RoleKind},
-    rfc003::{Ledger, SecretHash},
+use crate::{
+    comit_client,
+    swap_protocols::{
+        asset::Asset,
+        ledger::{Bitcoin, Ethereum, Networks},
+        metadata_store::{Metadata, RoleKind},
+        rfc003::{Ledger, SecretHash},
+    },
 };
 use bitcoin_support::BitcoinQuantity;
 use ethereum_support::{Erc20Quantity, EtherQuantity};
+use std::time::SystemTime;
 
 #[derive(Clone, Debug, PartialEq, LabelledGeneric)]
 pub struct SwapRequest<AL: Ledger, BL: Ledger, AA, BA> {
@@ -16,27 +21,28 @@ pub struct SwapRequest<AL: Ledger, BL: Ledger, AA, BA> {
     pub beta_ledger_redeem_identity: BL::Identity,
     pub alpha_ledger_lock_duration: AL::LockDuration,
     pub secret_hash: SecretHash,
+    pub expires_at: Option<u64>,
 }
 
-impl From<SwapRequest<Bitcoin, Ethereum, BitcoinQuantity, EtherQuantity>> for Metadata {
-    fn from(_: SwapRequest<Bitcoin, Ethereum, BitcoinQuantity, EtherQuantity>) -> Self {
-        Self {
-            alpha_ledger: LedgerKind::Bitcoin,
-            beta_ledger: LedgerKind::Ethereum,
-            alpha_asset: AssetKind::Bitcoin,
-            beta_asset: AssetKind::Ether,
-            role: RoleKind::Bob,
-        }
+impl<AL: Ledger, BL: Ledger, AA, BA> SwapRequest<AL, BL, AA, BA> {
+    pub fn is_on(&self, networks: &Networks) -> bool {
+        self.alpha_ledger.is_on(networks) && self.beta_ledger.is_on(networks)
+    }
+
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires_at.map_or(false, |expires_at| {
+            comit_client::has_expired(expires_at, now)
+        })
     }
 }
 
-impl From<SwapRequest<Bitcoin, Ethereum, BitcoinQuantity, Erc20Quantity>> for Metadata {
-    fn from(_: SwapRequest<Bitcoin, Ethereum, BitcoinQuantity, Erc20Quantity>) -> Self {
+impl<AL: Ledger, BL: Ledger, AA: Asset, BA: Asset> From<SwapRequest<AL, BL, AA, BA>> for Metadata {
+    fn from(request: SwapRequest<AL, BL, AA, BA>) -> Self {
         Self {
-            alpha_ledger: LedgerKind::Bitcoin,
-            beta_ledger: LedgerKind::Ethereum,
-            alpha_asset: AssetKind::Bitcoin,
-            beta_asset: AssetKind::Erc20,
+            alpha_ledger: request.alpha_ledger.into(),
+            beta_ledger: request.beta_ledger.into(),
+            alpha_asset: request.alpha_asset.into(),
+            beta_asset: request.beta_asset.into(),
             role: RoleKind::Bob,
         }
     }
@@ -50,4 +56,73 @@ pub enum SwapRequestKind {
     BitcoinEthereumBitcoinQuantityErc20Quantity(
         SwapRequest<Bitcoin, Ethereum, BitcoinQuantity, Erc20Quantity>,
     ),
+    EthereumBitcoinEtherQuantityBitcoinQuantity(
+        SwapRequest<Ethereum, Bitcoin, EtherQuantity, BitcoinQuantity>,
+    ),
+    EthereumBitcoinErc20QuantityBitcoinQuantity(
+        SwapRequest<Ethereum, Bitcoin, Erc20Quantity, BitcoinQuantity>,
+    ),
+    EthereumEthereumEtherQuantityErc20Quantity(
+        SwapRequest<Ethereum, Ethereum, EtherQuantity, Erc20Quantity>,
+    ),
+    EthereumEthereumErc20QuantityEtherQuantity(
+        SwapRequest<Ethereum, Ethereum, Erc20Quantity, EtherQuantity>,
+    ),
+    EthereumEthereumErc20QuantityErc20Quantity(
+        SwapRequest<Ethereum, Ethereum, Erc20Quantity, Erc20Quantity>,
+    ),
+}
+
+impl SwapRequestKind {
+    pub fn is_on(&self, networks: &Networks) -> bool {
+        match *self {
+            SwapRequestKind::BitcoinEthereumBitcoinQuantityEtherQuantity(ref request) => {
+                request.is_on(networks)
+            }
+            SwapRequestKind::BitcoinEthereumBitcoinQuantityErc20Quantity(ref request) => {
+                request.is_on(networks)
+            }
+            SwapRequestKind::EthereumBitcoinEtherQuantityBitcoinQuantity(ref request) => {
+                request.is_on(networks)
+            }
+            SwapRequestKind::EthereumBitcoinErc20QuantityBitcoinQuantity(ref request) => {
+                request.is_on(networks)
+            }
+            SwapRequestKind::EthereumEthereumEtherQuantityErc20Quantity(ref request) => {
+                request.is_on(networks)
+            }
+            SwapRequestKind::EthereumEthereumErc20QuantityEtherQuantity(ref request) => {
+                request.is_on(networks)
+            }
+            SwapRequestKind::EthereumEthereumErc20QuantityErc20Quantity(ref request) => {
+                request.is_on(networks)
+            }
+        }
+    }
+
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        match *self {
+            SwapRequestKind::BitcoinEthereumBitcoinQuantityEtherQuantity(ref request) => {
+                request.is_expired(now)
+            }
+            SwapRequestKind::BitcoinEthereumBitcoinQuantityErc20Quantity(ref request) => {
+                request.is_expired(now)
+            }
+            SwapRequestKind::EthereumBitcoinEtherQuantityBitcoinQuantity(ref request) => {
+                request.is_expired(now)
+            }
+            SwapRequestKind::EthereumBitcoinErc20QuantityBitcoinQuantity(ref request) => {
+                request.is_expired(now)
+            }
+            SwapRequestKind::EthereumEthereumEtherQuantityErc20Quantity(ref request) => {
+                request.is_expired(now)
+            }
+            SwapRequestKind::EthereumEthereumErc20QuantityEtherQuantity(ref request) => {
+                request.is_expired(now)
+            }
+            SwapRequestKind::EthereumEthereumErc20QuantityErc20Quantity(ref request) => {
+                request.is_expired(now)
+            }
+        }
+    }
 }
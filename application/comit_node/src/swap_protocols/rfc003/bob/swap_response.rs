@@ -1,6 +1,8 @@
 use crate::{
     comit_client::SwapReject,
-    swap_protocols::rfc003::{ethereum::Seconds, state_machine::StateMachineResponse},
+    swap_protocols::rfc003::{
+        bitcoin::Timelock, ethereum::Seconds, state_machine::StateMachineResponse,
+    },
 };
 
 #[derive(Clone, Debug, PartialEq)]
@@ -11,6 +13,18 @@ pub enum SwapResponseKind {
             SwapReject,
         >,
     ),
+    EthereumBitcoin(
+        Result<
+            StateMachineResponse<ethereum_support::Address, secp256k1_support::KeyPair, Timelock>,
+            SwapReject,
+        >,
+    ),
+    EthereumEthereum(
+        Result<
+            StateMachineResponse<ethereum_support::Address, ethereum_support::Address, Seconds>,
+            SwapReject,
+        >,
+    ),
 }
 
 impl
@@ -30,3 +44,39 @@ impl
         SwapResponseKind::BitcoinEthereum(result)
     }
 }
+
+impl
+    From<
+        Result<
+            StateMachineResponse<ethereum_support::Address, secp256k1_support::KeyPair, Timelock>,
+            SwapReject,
+        >,
+    > for SwapResponseKind
+{
+    fn from(
+        result: Result<
+            StateMachineResponse<ethereum_support::Address, secp256k1_support::KeyPair, Timelock>,
+            SwapReject,
+        >,
+    ) -> Self {
+        SwapResponseKind::EthereumBitcoin(result)
+    }
+}
+
+impl
+    From<
+        Result<
+            StateMachineResponse<ethereum_support::Address, ethereum_support::Address, Seconds>,
+            SwapReject,
+        >,
+    > for SwapResponseKind
+{
+    fn from(
+        result: Result<
+            StateMachineResponse<ethereum_support::Address, ethereum_support::Address, Seconds>,
+            SwapReject,
+        >,
+    ) -> Self {
+        SwapResponseKind::EthereumEthereum(result)
+    }
+}
@@ -1,10 +1,11 @@
-use ethereum_support::{web3::types::U256, Address, Bytes, EtherQuantity};
+use ethereum_support::{web3::types::U256, Address, Bytes, ChainId, EtherQuantity};
 
 #[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct ContractDeploy {
     pub data: Bytes,
     pub value: EtherQuantity,
     pub gas_limit: U256,
+    pub chain_id: ChainId,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize)]
@@ -13,4 +14,5 @@ pub struct SendTransaction {
     pub data: Bytes,
     pub gas_limit: U256,
     pub value: EtherQuantity,
+    pub chain_id: ChainId,
 }
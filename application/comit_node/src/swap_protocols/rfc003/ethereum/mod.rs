@@ -1,6 +1,6 @@
 use crate::swap_protocols::{
     ledger::Ethereum,
-    rfc003::{state_machine::HtlcParams, Ledger},
+    rfc003::{state_machine::HtlcParams, EstimateDuration, Ledger},
 };
 use ethereum_support::{web3::types::Address, Bytes, Erc20Quantity, EtherQuantity};
 use std::time::Duration;
@@ -42,6 +42,12 @@ impl From<Seconds> for Duration {
     }
 }
 
+impl EstimateDuration for Seconds {
+    fn estimate_duration(&self) -> Option<Duration> {
+        Some(Duration::from(*self))
+    }
+}
+
 impl Ledger for Ethereum {
     type LockDuration = Seconds;
     type HtlcLocation = Address;
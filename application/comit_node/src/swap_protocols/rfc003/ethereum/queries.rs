@@ -1,11 +1,14 @@
 use crate::{
     ledger_query_service::EthereumQuery,
     swap_protocols::{
+        asset::Asset,
         ledger::Ethereum,
         rfc003::{
-            events::{NewHtlcFundedQuery, NewHtlcRedeemedQuery, NewHtlcRefundedQuery},
+            events::{
+                NewHtlcExpiredQuery, NewHtlcFundedQuery, NewHtlcRedeemedQuery, NewHtlcRefundedQuery,
+            },
             state_machine::HtlcParams,
-            Secret,
+            Secret, Timestamp,
         },
     },
 };
@@ -53,6 +56,25 @@ impl NewHtlcRedeemedQuery<Ethereum, EtherQuantity> for EthereumQuery {
     }
 }
 
+impl<A: Asset> NewHtlcExpiredQuery<Ethereum, A> for EthereumQuery {
+    fn new_htlc_expired_query(
+        htlc_params: &HtlcParams<Ethereum, A>,
+        htlc_deployed_at: Timestamp,
+    ) -> (Self, u32) {
+        // The contracts count the lock duration from the timestamp of the
+        // block they were deployed in, which is before we saw the deployment.
+        // Counting from then errs on the side of expiring late.
+        let expiry = htlc_deployed_at.plus(htlc_params.lock_duration.0 as u32 + 1);
+
+        (
+            EthereumQuery::Block {
+                min_timestamp_secs: Some(expiry.0),
+            },
+            1,
+        )
+    }
+}
+
 pub mod erc20 {
     use super::*;
     use ethereum_support::Erc20Quantity;
@@ -1,5 +1,5 @@
 use crate::{
-    comit_client,
+    comit_client::{self, SwapDeclineReason, SwapReject, SwapResponseError},
     swap_protocols::{
         asset::Asset,
         rfc003::{
@@ -7,11 +7,17 @@ use crate::{
             events::{CommunicationEvents, ResponseFuture, StateMachineResponseFuture},
             ledger::Ledger,
             roles::Alice,
+            validate_lock_durations,
         },
     },
 };
 use futures::Future;
-use std::sync::Arc;
+use std::{
+    cmp,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::timer::Timeout;
 
 #[allow(missing_debug_implementations)]
 pub struct AliceToBob<C, AL: Ledger, BL: Ledger> {
@@ -19,17 +25,38 @@ pub struct AliceToBob<C, AL: Ledger, BL: Ledger> {
     response_future:
         Option<Box<StateMachineResponseFuture<AL::Identity, BL::Identity, BL::LockDuration>>>,
     client: Arc<C>,
+    lock_duration_margin: Duration,
+    response_timeout: Duration,
 }
 
 impl<C, AL: Ledger, BL: Ledger> AliceToBob<C, AL, BL> {
-    pub fn new(client: Arc<C>) -> Self {
+    pub fn new(client: Arc<C>, lock_duration_margin: Duration, response_timeout: Duration) -> Self {
         AliceToBob {
             client,
+            lock_duration_margin,
+            response_timeout,
             response_future: None,
         }
     }
 }
 
+// How long a response may take from Bob's node to Alice's
+const RESPONSE_TRANSIT_SECS: u64 = 30;
+
+/// The deadline for Bob, early enough that the response arrives before Alice
+/// stops waiting for it
+fn expires_at(now: SystemTime, response_timeout: Duration) -> u64 {
+    let transit_time = cmp::min(
+        Duration::from_secs(RESPONSE_TRANSIT_SECS),
+        response_timeout / 2,
+    );
+
+    (now + response_timeout - transit_time)
+        .duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_secs())
+        .unwrap_or(0)
+}
+
 impl<C: comit_client::Client, AL: Ledger, BL: Ledger, AA: Asset, BA: Asset>
     CommunicationEvents<Alice<AL, BL, AA, BA>> for AliceToBob<C, AL, BL>
 {
@@ -38,13 +65,67 @@ impl<C: comit_client::Client, AL: Ledger, BL: Ledger, AA: Asset, BA: Asset>
         request: &comit_client::rfc003::Request<AL, BL, AA, BA>,
     ) -> &mut ResponseFuture<Alice<AL, BL, AA, BA>> {
         let client = Arc::clone(&self.client);
+        let lock_duration_margin = self.lock_duration_margin;
+        let response_timeout = self.response_timeout;
         self.response_future.get_or_insert_with(|| {
+            let alpha_ledger_lock_duration = request.alpha_ledger_lock_duration.clone();
+            let request = comit_client::rfc003::Request {
+                expires_at: Some(expires_at(SystemTime::now(), response_timeout)),
+                ..request.clone()
+            };
+
             Box::new(
-                client
-                    .send_swap_request(request.clone())
-                    .map_err(rfc003::Error::SwapResponse)
-                    .map(|result| result.map(Into::into)),
+                Timeout::new(client.send_swap_request(request), response_timeout)
+                    .map_err(|e| {
+                        if e.is_elapsed() {
+                            warn!("Bob did not respond to the swap request in time");
+                            return rfc003::Error::SwapResponse(SwapResponseError::TimedOut);
+                        }
+                        e.into_inner()
+                            .map(rfc003::Error::SwapResponse)
+                            .unwrap_or(rfc003::Error::TimerError)
+                    })
+                    .map(move |result| {
+                        result
+                            .and_then(|accept| {
+                                validate_lock_durations(
+                                    &alpha_ledger_lock_duration,
+                                    &accept.beta_ledger_lock_duration,
+                                    lock_duration_margin,
+                                )
+                                .map_err(|e| {
+                                    warn!("Bob accepted with unsafe lock durations: {}", e);
+                                    SwapReject::Declined {
+                                        reason: Some(SwapDeclineReason::UnsafeLockDurations),
+                                        details: None,
+                                    }
+                                })?;
+
+                                Ok(accept)
+                            })
+                            .map_err(|reject| {
+                                if let SwapReject::CounterOffered(ref offer) = reject {
+                                    info!("Bob counter-offered: {:?}", offer);
+                                }
+                                reject
+                            })
+                            .map(Into::into)
+                    }),
             )
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn bob_has_to_respond_before_alice_stops_waiting() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_550_000_000);
+
+        assert_that(&expires_at(now, Duration::from_secs(3600))).is_equal_to(1_550_003_570);
+        assert_that(&expires_at(now, Duration::from_secs(20))).is_equal_to(1_550_000_010);
+    }
+}
@@ -0,0 +1,45 @@
+use crate::{
+    comit_client,
+    swap_protocols::rfc003::{
+        events::{CommunicationEvents, ResponseFuture},
+        ledger::Ledger,
+        roles::Role,
+        state_machine::StateMachineResponse,
+    },
+};
+use futures::future;
+
+/// Communication events of a swap whose request has already been answered,
+/// e.g. a swap that is resumed after a restart.
+#[allow(missing_debug_implementations)]
+pub struct AlreadyResponded<R: Role> {
+    response_future: Box<ResponseFuture<R>>,
+}
+
+impl<R: Role> AlreadyResponded<R> {
+    pub fn new(
+        response: StateMachineResponse<
+            R::AlphaRedeemHtlcIdentity,
+            R::BetaRefundHtlcIdentity,
+            <R::BetaLedger as Ledger>::LockDuration,
+        >,
+    ) -> Self {
+        Self {
+            response_future: Box::new(future::ok(Ok(response))),
+        }
+    }
+}
+
+impl<R: Role> CommunicationEvents<R> for AlreadyResponded<R> {
+    fn request_responded(
+        &mut self,
+        _request: &comit_client::rfc003::Request<
+            R::AlphaLedger,
+            R::BetaLedger,
+            R::AlphaAsset,
+            R::BetaAsset,
+        >,
+    ) -> &mut ResponseFuture<R> {
+        &mut self.response_future
+    }
+}
@@ -8,20 +8,22 @@ use crate::{
             self,
             ethereum::erc20,
             events::{
-                Deployed, Funded, LedgerEvents, NewHtlcFundedQuery, NewHtlcRedeemedQuery,
-                NewHtlcRefundedQuery, RedeemedOrRefunded,
+                Deployed, Expired, Funded, FundingReverts, LedgerEvents, NewHtlcExpiredQuery,
+                NewHtlcFundedQuery, NewHtlcRedeemedQuery, NewHtlcRefundedQuery, RedeemedOrRefunded,
+                Reverted,
             },
             find_htlc_location::FindHtlcLocation,
             secret::SecretHash,
             state_machine::HtlcParams,
             ExtractSecret, FundTransaction, Ledger, RedeemTransaction, RefundTransaction,
+            Timestamp,
         },
     },
 };
 use ethereum_support::Erc20Quantity;
 use futures::{
     future::{self, Either},
-    Future,
+    stream, Future, Stream,
 };
 
 #[allow(missing_debug_implementations)]
@@ -32,6 +34,9 @@ pub struct LqsEvents<L: Ledger, Q: Query> {
     htlc_deployed_and_funded: Option<Box<Deployed<L>>>,
     htlc_funded: Option<Box<Funded<L>>>,
     htlc_redeemed_or_refunded: Option<Box<RedeemedOrRefunded<L>>>,
+    htlc_expired: Option<Box<Expired>>,
+    htlc_funding_reverts: Option<Box<FundingReverts<L>>>,
+    htlc_redeemed_or_refunded_reverted: Option<Box<Reverted<L>>>,
 }
 
 impl<L: Ledger, Q: Query> LqsEvents<L, Q>
@@ -45,6 +50,9 @@ where
             htlc_deployed_and_funded: None,
             htlc_funded: None,
             htlc_redeemed_or_refunded: None,
+            htlc_expired: None,
+            htlc_funding_reverts: None,
+            htlc_redeemed_or_refunded_reverted: None,
         }
     }
 
@@ -64,6 +72,7 @@ where
                         .first_match_of(query_id)
                         .and_then(move |tx| {
                             tx.find_htlc_location(&htlc_params)
+                                .map(|htlc_location| (htlc_location, Timestamp::now()))
                                 .map_err(|_| rfc003::Error::InsufficientFunding)
                         })
                 });
@@ -122,13 +131,114 @@ where
             )
         })
     }
+
+    fn htlc_expired<F>(&mut self, new_expired_query: F) -> &mut Expired
+    where
+        F: FnOnce() -> (Q, u32),
+    {
+        let create_ledger_query = &self.create_ledger_query;
+        let ledger_first_match = &self.ledger_first_match;
+
+        self.htlc_expired.get_or_insert_with(move || {
+            let (expired_query, matches_needed) = new_expired_query();
+            let ledger_first_match = ledger_first_match.clone();
+
+            let expired_future = create_ledger_query
+                .create_query(expired_query)
+                .map_err(rfc003::Error::LedgerQueryService)
+                .and_then(move |query_id| {
+                    ledger_first_match.nth_match_id_of(query_id, matches_needed)
+                })
+                .map(|_block_id| ());
+
+            Box::new(expired_future)
+        })
+    }
+
+    fn htlc_funding_reverts(&mut self, funded_queries: Vec<Q>) -> &mut FundingReverts<L> {
+        let create_ledger_query = &self.create_ledger_query;
+        let ledger_first_match = &self.ledger_first_match;
+
+        self.htlc_funding_reverts.get_or_insert_with(move || {
+            let no_reverts: Box<FundingReverts<L>> = Box::new(stream::empty());
+
+            funded_queries
+                .into_iter()
+                .fold(no_reverts, |reverts, query| {
+                    let ledger_first_match = ledger_first_match.clone();
+                    let query_reverts = create_ledger_query
+                        .create_query(query)
+                        .map_err(rfc003::Error::LedgerQueryService)
+                        .map(move |query_id| ledger_first_match.reverts_of(query_id))
+                        .flatten_stream();
+
+                    Box::new(reverts.select(query_reverts))
+                })
+        })
+    }
+
+    fn htlc_redeemed_or_refunded_reverted(
+        &mut self,
+        redeemed_query: Q,
+        refunded_query: Q,
+    ) -> &mut Reverted<L> {
+        let create_ledger_query = &self.create_ledger_query;
+        let ledger_first_match = &self.ledger_first_match;
+
+        self.htlc_redeemed_or_refunded_reverted
+            .get_or_insert_with(move || {
+                any_query_invalidated(
+                    create_ledger_query,
+                    ledger_first_match,
+                    vec![redeemed_query, refunded_query],
+                )
+            })
+    }
+
+    fn forget_htlc(&mut self) {
+        self.htlc_deployed_and_funded = None;
+        self.htlc_funded = None;
+        self.htlc_expired = None;
+        self.htlc_funding_reverts = None;
+        self.forget_htlc_redeemed_or_refunded();
+    }
+
+    fn forget_htlc_redeemed_or_refunded(&mut self) {
+        self.htlc_redeemed_or_refunded = None;
+        self.htlc_redeemed_or_refunded_reverted = None;
+    }
+}
+
+fn any_query_invalidated<L: Ledger, Q: Query>(
+    create_ledger_query: &QueryIdCache<L, Q>,
+    ledger_first_match: &FirstMatch<L>,
+    queries: Vec<Q>,
+) -> Box<Reverted<L>> {
+    let reverted_futures = queries.into_iter().map(|query| {
+        let ledger_first_match = ledger_first_match.clone();
+
+        create_ledger_query
+            .create_query(query)
+            .map_err(rfc003::Error::LedgerQueryService)
+            .and_then(move |query_id| ledger_first_match.invalidated_match_id_of(query_id))
+    });
+
+    Box::new(
+        future::select_all(reverted_futures)
+            .map(|(reverted, ..)| reverted)
+            .map_err(|(error, ..)| error),
+    )
 }
 impl<L, A, Q> LedgerEvents<L, A> for LqsEvents<L, Q>
 where
     L: Ledger,
     L::Transaction: ExtractSecret,
     A: Asset,
-    Q: Query + NewHtlcRefundedQuery<L, A> + NewHtlcFundedQuery<L, A> + NewHtlcRedeemedQuery<L, A>,
+    Q: Query
+        + NewHtlcRefundedQuery<L, A>
+        + NewHtlcFundedQuery<L, A>
+        + NewHtlcRedeemedQuery<L, A>
+        + NewHtlcExpiredQuery<L, A>,
     <L as swap_protocols::Ledger>::Transaction: FindHtlcLocation<L, A>,
 {
     fn htlc_deployed(&mut self, htlc_params: HtlcParams<L, A>) -> &mut Deployed<L> {
@@ -154,6 +264,42 @@ where
 
         self.htlc_redeemed_or_refunded(redeemed_query, refunded_query, htlc_params.secret_hash)
     }
+
+    fn htlc_expired(
+        &mut self,
+        htlc_params: HtlcParams<L, A>,
+        htlc_deployed_at: Timestamp,
+    ) -> &mut Expired {
+        self.htlc_expired(move || Q::new_htlc_expired_query(&htlc_params, htlc_deployed_at))
+    }
+
+    fn htlc_funding_reverts(
+        &mut self,
+        htlc_params: HtlcParams<L, A>,
+        _htlc_location: &L::HtlcLocation,
+    ) -> &mut FundingReverts<L> {
+        let funded_query = Q::new_htlc_funded_query(&htlc_params);
+        self.htlc_funding_reverts(vec![funded_query])
+    }
+
+    fn htlc_redeemed_or_refunded_reverted(
+        &mut self,
+        htlc_params: HtlcParams<L, A>,
+        htlc_location: &L::HtlcLocation,
+    ) -> &mut Reverted<L> {
+        let refunded_query = Q::new_htlc_refunded_query(&htlc_params, htlc_location);
+        let redeemed_query = Q::new_htlc_redeemed_query(&htlc_params, htlc_location);
+
+        self.htlc_redeemed_or_refunded_reverted(redeemed_query, refunded_query)
+    }
+
+    fn forget_htlc(&mut self) {
+        self.forget_htlc()
+    }
+
+    fn forget_htlc_redeemed_or_refunded(&mut self) {
+        self.forget_htlc_redeemed_or_refunded()
+    }
 }
 
 #[allow(missing_debug_implementations)]
@@ -167,13 +313,7 @@ impl LqsEventsForErc20 {
         ledger_first_match: FirstMatch<Ethereum>,
     ) -> Self {
         Self {
-            lqs_events: LqsEvents {
-                create_ledger_query,
-                ledger_first_match,
-                htlc_deployed_and_funded: None,
-                htlc_funded: None,
-                htlc_redeemed_or_refunded: None,
-            },
+            lqs_events: LqsEvents::new(create_ledger_query, ledger_first_match),
         }
     }
 }
@@ -224,4 +364,46 @@ impl LedgerEvents<Ethereum, Erc20Quantity> for LqsEventsForErc20 {
             htlc_params.secret_hash,
         )
     }
+
+    fn htlc_expired(
+        &mut self,
+        htlc_params: HtlcParams<Ethereum, Erc20Quantity>,
+        htlc_deployed_at: Timestamp,
+    ) -> &mut Expired {
+        self.lqs_events.htlc_expired(move || {
+            EthereumQuery::new_htlc_expired_query(&htlc_params, htlc_deployed_at)
+        })
+    }
+
+    fn htlc_funding_reverts(
+        &mut self,
+        htlc_params: HtlcParams<Ethereum, Erc20Quantity>,
+        htlc_location: &<Ethereum as Ledger>::HtlcLocation,
+    ) -> &mut FundingReverts<Ethereum> {
+        let deployed_query = erc20::new_htlc_deployed_query(&htlc_params);
+        let funded_query = erc20::new_htlc_funded_query(&htlc_params, htlc_location);
+
+        self.lqs_events
+            .htlc_funding_reverts(vec![deployed_query, funded_query])
+    }
+
+    fn htlc_redeemed_or_refunded_reverted(
+        &mut self,
+        _htlc_params: HtlcParams<Ethereum, Erc20Quantity>,
+        htlc_location: &<Ethereum as Ledger>::HtlcLocation,
+    ) -> &mut Reverted<Ethereum> {
+        let refunded_query = erc20::new_htlc_refunded_query(htlc_location);
+        let redeemed_query = erc20::new_htlc_redeemed_query(htlc_location);
+
+        self.lqs_events
+            .htlc_redeemed_or_refunded_reverted(redeemed_query, refunded_query)
+    }
+
+    fn forget_htlc(&mut self) {
+        self.lqs_events.forget_htlc()
+    }
+
+    fn forget_htlc_redeemed_or_refunded(&mut self) {
+        self.lqs_events.forget_htlc_redeemed_or_refunded()
+    }
 }
@@ -4,7 +4,7 @@
 
 use crate::{
     comit_client::{self, SwapReject},
-    ledger_query_service::Query,
+    ledger_query_service::{Query, Revert},
     swap_protocols::{
         asset::Asset,
         rfc003::{
@@ -12,18 +12,23 @@ use crate::{
             ledger::Ledger,
             roles::Role,
             state_machine::{HtlcParams, StateMachineResponse},
-            FundTransaction, RedeemTransaction, RefundTransaction,
+            FundTransaction, RedeemTransaction, RefundTransaction, Timestamp,
         },
     },
 };
-use tokio::{self, prelude::future::Either};
+use tokio::{
+    self,
+    prelude::{future::Either, Stream},
+};
 
 mod alice;
+mod already_responded;
 mod bob;
 mod lqs;
 
 pub use self::{
     alice::AliceToBob,
+    already_responded::AlreadyResponded,
     bob::BobToAlice,
     lqs::{LqsEvents, LqsEventsForErc20},
 };
@@ -40,13 +45,19 @@ pub type ResponseFuture<R: Role> = StateMachineResponseFuture<
     <R::BetaLedger as Ledger>::LockDuration,
 >;
 
-pub type Deployed<L: Ledger> = Future<L::HtlcLocation>;
+/// The location of the HTLC and when its deployment was first seen, which is
+/// what the timelock of the HTLC is counted from
+pub type Deployed<L: Ledger> = Future<(L::HtlcLocation, Timestamp)>;
 pub type Funded<L: Ledger> = Future<Option<FundTransaction<L>>>;
 pub type Refunded<L: Ledger> = Future<L::TxId>;
 pub type Redeemed<L: Ledger> = Future<L::TxId>;
 pub type AlphaRefundedOrBetaFunded<AL: Ledger, BL: Ledger> =
     Future<Either<AL::Transaction, BL::HtlcLocation>>;
 pub type RedeemedOrRefunded<L: Ledger> = Future<Either<RedeemTransaction<L>, RefundTransaction<L>>>;
+pub type Expired = Future<()>;
+/// The id of the transaction that is no longer part of the ledger
+pub type Reverted<L: Ledger> = Future<L::TxId>;
+pub type FundingReverts<L: Ledger> = dyn Stream<Item = Revert<L>, Error = rfc003::Error> + Send;
 
 pub trait LedgerEvents<L: Ledger, A: Asset>: Send {
     fn htlc_deployed(&mut self, htlc_params: HtlcParams<L, A>) -> &mut Deployed<L>;
@@ -62,6 +73,38 @@ pub trait LedgerEvents<L: Ledger, A: Asset>: Send {
         htlc_params: HtlcParams<L, A>,
         htlc_location: &L::HtlcLocation,
     ) -> &mut RedeemedOrRefunded<L>;
+
+    /// Resolves once the timelock of the HTLC ran out, i.e. once it can be
+    /// refunded and must no longer be redeemed
+    fn htlc_expired(
+        &mut self,
+        htlc_params: HtlcParams<L, A>,
+        htlc_deployed_at: Timestamp,
+    ) -> &mut Expired;
+
+    /// Reports every time the funding of the HTLC is no longer part of the
+    /// ledger because of a chain reorganisation and whether it got confirmed
+    /// again afterwards or replaced by a different transaction
+    fn htlc_funding_reverts(
+        &mut self,
+        htlc_params: HtlcParams<L, A>,
+        htlc_location: &L::HtlcLocation,
+    ) -> &mut FundingReverts<L>;
+
+    /// Resolves once the redeem or refund of the HTLC is no longer part of the
+    /// ledger because of a chain reorganisation
+    fn htlc_redeemed_or_refunded_reverted(
+        &mut self,
+        htlc_params: HtlcParams<L, A>,
+        htlc_location: &L::HtlcLocation,
+    ) -> &mut Reverted<L>;
+
+    /// Drops everything that was observed about the HTLC, so that it is
+    /// watched from scratch after its funding got replaced
+    fn forget_htlc(&mut self);
+
+    /// Drops the observed redeem or refund of the HTLC after it got reverted
+    fn forget_htlc_redeemed_or_refunded(&mut self);
 }
 
 pub trait CommunicationEvents<R: Role>: Send {
@@ -109,3 +152,18 @@ where
         htlc_location: &L::HtlcLocation,
     ) -> Self;
 }
+
+pub trait NewHtlcExpiredQuery<L: Ledger, A: Asset>: Send + Sync
+where
+    Self: Query,
+{
+    /// Returns the query together with the number of blocks that have to
+    /// match it before the HTLC is expired
+    ///
+    /// The query must only depend on the given values, so that it is the
+    /// same after a restart.
+    fn new_htlc_expired_query(
+        htlc_params: &HtlcParams<L, A>,
+        htlc_deployed_at: Timestamp,
+    ) -> (Self, u32);
+}
@@ -1,6 +1,11 @@
 use crate::swap_protocols::{
     self,
-    rfc003::secret::{Secret, SecretHash},
+    rfc003::{
+        lock_duration::EstimateDuration,
+        secret::{Secret, SecretHash},
+        secret_source::SwapIdentity,
+        state_store::Persist,
+    },
 };
 use serde::{de::DeserializeOwned, Serialize};
 use std::{fmt::Debug, hash::Hash};
@@ -15,6 +20,7 @@ pub trait Ledger: swap_protocols::Ledger {
         + Sync
         + Serialize
         + DeserializeOwned
+        + EstimateDuration
         + 'static;
     type HtlcLocation: PartialEq + Debug + Clone + DeserializeOwned + Serialize + Send + Sync;
     type HtlcIdentity: Clone
@@ -22,6 +28,8 @@ pub trait Ledger: swap_protocols::Ledger {
         + Sync
         + PartialEq
         + Debug
+        + Persist
+        + SwapIdentity
         + Into<<Self as swap_protocols::ledger::Ledger>::Identity>;
 }
 
@@ -41,6 +49,24 @@ pub struct RedeemTransaction<L: Ledger> {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RefundTransaction<L: Ledger>(pub L::Transaction);
 
+impl<L: Ledger> FundTransaction<L> {
+    pub fn transaction_id(&self) -> L::TxId {
+        L::transaction_id(&self.0)
+    }
+}
+
+impl<L: Ledger> RedeemTransaction<L> {
+    pub fn transaction_id(&self) -> L::TxId {
+        L::transaction_id(&self.transaction)
+    }
+}
+
+impl<L: Ledger> RefundTransaction<L> {
+    pub fn transaction_id(&self) -> L::TxId {
+        L::transaction_id(&self.0)
+    }
+}
+
 impl<L: Ledger> AsRef<L::Transaction> for FundTransaction<L> {
     fn as_ref(&self) -> &L::Transaction {
         &self.0
@@ -0,0 +1,122 @@
+use bitcoin_support::Blocks;
+use std::time::Duration;
+
+/// A lock duration that can be put on a common time scale with the lock
+/// durations of other ledgers
+pub trait EstimateDuration {
+    /// How long it takes, from now, until the lock expires or `None` if that
+    /// cannot be told without looking at the ledger
+    fn estimate_duration(&self) -> Option<Duration>;
+}
+
+impl EstimateDuration for Blocks {
+    fn estimate_duration(&self) -> Option<Duration> {
+        Some(Duration::from(*self))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Fail)]
+pub enum UnsafeLockDurations {
+    #[fail(display = "The lock duration cannot be estimated.")]
+    Unknown,
+    #[fail(display = "The alpha lock doesn't leave the minimum margin for the beta lock.")]
+    InsufficientMargin {
+        alpha: Duration,
+        beta: Duration,
+        minimum_margin: Duration,
+    },
+}
+
+/// Checks that there is room for a beta lock that expires at least
+/// `minimum_margin` before the alpha lock
+pub fn validate_alpha_lock_duration<A: EstimateDuration>(
+    alpha_lock_duration: &A,
+    minimum_margin: Duration,
+) -> Result<(), UnsafeLockDurations> {
+    validate(
+        alpha_lock_duration,
+        Some(Duration::from_secs(0)),
+        minimum_margin,
+    )
+}
+
+/// Checks that the beta lock expires at least `minimum_margin` before the
+/// alpha lock, which is what gives Bob the time to redeem the alpha HTLC once
+/// Alice has revealed the secret
+pub fn validate_lock_durations<A: EstimateDuration, B: EstimateDuration>(
+    alpha_lock_duration: &A,
+    beta_lock_duration: &B,
+    minimum_margin: Duration,
+) -> Result<(), UnsafeLockDurations> {
+    validate(
+        alpha_lock_duration,
+        beta_lock_duration.estimate_duration(),
+        minimum_margin,
+    )
+}
+
+fn validate<A: EstimateDuration>(
+    alpha_lock_duration: &A,
+    beta: Option<Duration>,
+    minimum_margin: Duration,
+) -> Result<(), UnsafeLockDurations> {
+    let (alpha, beta) = match (alpha_lock_duration.estimate_duration(), beta) {
+        (Some(alpha), Some(beta)) => (alpha, beta),
+        _ => return Err(UnsafeLockDurations::Unknown),
+    };
+
+    match beta.checked_add(minimum_margin) {
+        Some(required) if alpha >= required => Ok(()),
+        _ => Err(UnsafeLockDurations::InsufficientMargin {
+            alpha,
+            beta,
+            minimum_margin,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swap_protocols::rfc003::{
+        bitcoin::{AbsoluteTimelock, Timelock},
+        ethereum::Seconds,
+    };
+    use spectral::prelude::*;
+
+    fn six_hours() -> Duration {
+        Duration::from_secs(6 * 60 * 60)
+    }
+
+    #[test]
+    fn given_24h_of_blocks_and_12h_of_seconds_then_margin_of_6h_is_kept() {
+        let result = validate_lock_durations(
+            &Timelock::Relative(Blocks::new(144)),
+            &Seconds(43200),
+            six_hours(),
+        );
+
+        assert_that(&result).is_ok();
+    }
+
+    #[test]
+    fn given_beta_lock_too_close_to_alpha_lock_then_unsafe() {
+        let result = validate_lock_durations(&Seconds(43200), &Blocks::new(48), six_hours());
+
+        assert_that(&result).is_err_containing(&UnsafeLockDurations::InsufficientMargin {
+            alpha: Duration::from_secs(43200),
+            beta: Duration::from_secs(28800),
+            minimum_margin: six_hours(),
+        });
+    }
+
+    #[test]
+    fn given_absolute_block_height_before_latest_block_is_known_then_lock_duration_is_unknown() {
+        let result = validate_alpha_lock_duration(
+            &Timelock::Absolute(AbsoluteTimelock::BlockHeight(600_000)),
+            six_hours(),
+        );
+
+        assert_that(&result).is_err_containing(&UnsafeLockDurations::Unknown);
+    }
+}
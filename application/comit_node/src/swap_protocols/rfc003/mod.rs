@@ -8,6 +8,7 @@ pub mod bob;
 pub mod ethereum;
 pub mod events;
 pub mod find_htlc_location;
+pub mod recovery;
 pub mod roles;
 
 pub mod state_machine;
@@ -16,9 +17,12 @@ pub mod state_store;
 mod error;
 
 mod ledger;
+mod lock_duration;
 mod save_state;
 mod secret;
 mod secret_source;
+mod timestamp;
+mod with_swap_types;
 
 #[cfg(test)]
 mod state_machine_test;
@@ -26,7 +30,12 @@ mod state_machine_test;
 pub use self::{
     error::Error,
     ledger::{ExtractSecret, FundTransaction, Ledger, RedeemTransaction, RefundTransaction},
-    save_state::SaveState,
+    lock_duration::{
+        validate_alpha_lock_duration, validate_lock_durations, EstimateDuration,
+        UnsafeLockDurations,
+    },
+    save_state::{SaveState, UpdateMetadataState},
     secret::{FromErr, RandomnessSource, Secret, SecretHash},
     secret_source::*,
+    timestamp::Timestamp,
 };
@@ -0,0 +1,222 @@
+use crate::{
+    ledger_query_service::{DefaultLedgerQueryServiceApiClient, FirstMatch, QueryIdCache},
+    swap_protocols::{
+        asset::Asset,
+        ledger::{Bitcoin, Ethereum},
+        metadata_store::{AssetKind, LedgerKind, Metadata, MetadataStore, RoleKind},
+        rfc003::{
+            events::{AlreadyResponded, LedgerEvents, LqsEvents, LqsEventsForErc20},
+            roles::Role,
+            state_machine::{Context, Final, Swap, SwapOutcome, SwapStates},
+            state_store::{self, SqliteStateStore, StateStore},
+            Ledger, SaveState, UpdateMetadataState,
+        },
+        SwapId,
+    },
+    with_swap_types,
+};
+use bitcoin_support::BitcoinQuantity;
+use ethereum_support::{Erc20Quantity, EtherQuantity};
+use futures::Future;
+use std::{sync::Arc, time::Duration};
+
+/// Loads all swaps from the `SqliteStateStore` and re-spawns the state machine
+/// of every swap that isn't finished. Swaps that were still waiting for a
+/// response can no longer get one and time out. Must be called from within a
+/// tokio runtime.
+pub fn resume_swaps<M: MetadataStore<SwapId>>(
+    state_store: &SqliteStateStore,
+    metadata_store: &Arc<M>,
+    lqs_api_client: &Arc<DefaultLedgerQueryServiceApiClient>,
+    bitcoin_poll_interval: Duration,
+    ethereum_poll_interval: Duration,
+) -> Result<(), state_store::Error> {
+    let ledger_events = LqsLedgerEvents {
+        lqs_api_client: Arc::clone(lqs_api_client),
+        bitcoin_poll_interval,
+        ethereum_poll_interval,
+    };
+
+    for (id, metadata) in state_store.swaps()? {
+        let result = with_swap_types!(
+            &metadata,
+            (|| resume_swap::<Role, M>(
+                id,
+                state_store,
+                Arc::clone(metadata_store),
+                CreateLedgerEvents::<AL, AA>::create(&ledger_events),
+                CreateLedgerEvents::<BL, BA>::create(&ledger_events),
+            )),
+            {
+                warn!("Cannot resume swap {} with unsupported {:?}", id, metadata);
+                Ok(())
+            }
+        );
+
+        if let Err(e) = result {
+            error!("Failed to resume swap {} because {:?}", id, e);
+        }
+    }
+
+    Ok(())
+}
+
+trait CreateLedgerEvents<L: Ledger, A: Asset> {
+    fn create(&self) -> Box<dyn LedgerEvents<L, A>>;
+}
+
+struct LqsLedgerEvents {
+    lqs_api_client: Arc<DefaultLedgerQueryServiceApiClient>,
+    bitcoin_poll_interval: Duration,
+    ethereum_poll_interval: Duration,
+}
+
+impl CreateLedgerEvents<Bitcoin, BitcoinQuantity> for LqsLedgerEvents {
+    fn create(&self) -> Box<dyn LedgerEvents<Bitcoin, BitcoinQuantity>> {
+        Box::new(LqsEvents::new(
+            QueryIdCache::wrap(Arc::clone(&self.lqs_api_client)),
+            FirstMatch::new(Arc::clone(&self.lqs_api_client), self.bitcoin_poll_interval),
+        ))
+    }
+}
+
+impl CreateLedgerEvents<Ethereum, EtherQuantity> for LqsLedgerEvents {
+    fn create(&self) -> Box<dyn LedgerEvents<Ethereum, EtherQuantity>> {
+        Box::new(LqsEvents::new(
+            QueryIdCache::wrap(Arc::clone(&self.lqs_api_client)),
+            FirstMatch::new(
+                Arc::clone(&self.lqs_api_client),
+                self.ethereum_poll_interval,
+            ),
+        ))
+    }
+}
+
+impl CreateLedgerEvents<Ethereum, Erc20Quantity> for LqsLedgerEvents {
+    fn create(&self) -> Box<dyn LedgerEvents<Ethereum, Erc20Quantity>> {
+        Box::new(LqsEventsForErc20::new(
+            QueryIdCache::wrap(Arc::clone(&self.lqs_api_client)),
+            FirstMatch::new(
+                Arc::clone(&self.lqs_api_client),
+                self.ethereum_poll_interval,
+            ),
+        ))
+    }
+}
+
+fn resume_swap<R: Role, M: MetadataStore<SwapId>>(
+    id: SwapId,
+    state_store: &SqliteStateStore,
+    metadata_store: Arc<M>,
+    alpha_ledger_events: Box<dyn LedgerEvents<R::AlphaLedger, R::AlphaAsset>>,
+    beta_ledger_events: Box<dyn LedgerEvents<R::BetaLedger, R::BetaAsset>>,
+) -> Result<(), state_store::Error> {
+    let state = match state_store.get::<R>(&id)? {
+        Some(state) => state,
+        None => return Ok(()),
+    };
+
+    let response = match (&state, state.ongoing_swap()) {
+        (_, Some(swap)) => swap.state_machine_response(),
+        (SwapStates::Start(start), None) => {
+            // The connection the response would have been sent over is gone
+            warn!("Swap {} was still waiting for a response and timed out", id);
+            let state_repo = state_store.insert(id, state.clone())?;
+            return UpdateMetadataState::new(id, metadata_store, state_repo).save(
+                SwapStates::Final(Final(SwapOutcome::TimedOut {
+                    start: start.clone(),
+                })),
+            );
+        }
+        (_, None) => {
+            debug!("Swap {} is already finished: {}", id, state.name());
+            return Ok(());
+        }
+    };
+
+    let state_repo = state_store.insert(id, state.clone())?;
+    let state_repo = Arc::new(UpdateMetadataState::new(id, metadata_store, state_repo));
+
+    let context = Context {
+        alpha_ledger_events,
+        beta_ledger_events,
+        communication_events: Box::new(AlreadyResponded::<R>::new(response)),
+        state_repo,
+    };
+
+    info!("Resuming swap {} in state {}", id, state.name());
+
+    tokio::spawn(
+        Swap::start_in(state, context)
+            .map(move |outcome| {
+                info!("Swap {} finished with {:?}", id, outcome);
+            })
+            .map_err(move |e| {
+                error!("Swap {} failed with {:?}", id, e);
+            }),
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        seed::Seed,
+        swap_protocols::{
+            rfc003::{roles::test::Bobisha, state_machine::Start, Secret},
+            InMemoryMetadataStore,
+        },
+    };
+    use hex::FromHex;
+    use spectral::prelude::*;
+
+    #[test]
+    fn swap_waiting_for_a_response_times_out_when_resumed() {
+        let state_store = SqliteStateStore::open_in_memory(Arc::new(Seed::from(
+            *b"hello world, you are beautiful!!",
+        )))
+        .unwrap();
+        let ledger_events = LqsLedgerEvents {
+            lqs_api_client: Arc::new(DefaultLedgerQueryServiceApiClient::new(
+                &"http://localhost:8080".parse().unwrap(),
+            )),
+            bitcoin_poll_interval: Duration::from_secs(1),
+            ethereum_poll_interval: Duration::from_secs(1),
+        };
+        let id = SwapId::default();
+        let (bobisha, _response) = Bobisha::create(None);
+        let start = Start {
+            alpha_ledger_refund_identity: bitcoin_support::PubkeyHash::from_hex(
+                "875638cac0b0ae9f826575e190f2788918c354c2",
+            )
+            .unwrap(),
+            beta_ledger_redeem_identity: "8457037fcd80a8650c4692d7fcfc1d0a96b92867"
+                .parse()
+                .unwrap(),
+            alpha_ledger: Bitcoin::default(),
+            beta_ledger: Ethereum::default(),
+            alpha_asset: BitcoinQuantity::from_bitcoin(1.0),
+            beta_asset: EtherQuantity::from_eth(10.0),
+            alpha_ledger_lock_duration: bitcoin_support::Blocks::from(144).into(),
+            secret: Secret::from(*b"hello world, you are beautiful!!").hash(),
+            role: bobisha,
+        };
+        state_store
+            .insert(id, SwapStates::from(start.clone()))
+            .unwrap();
+
+        resume_swap::<Bobisha, _>(
+            id,
+            &state_store,
+            Arc::new(InMemoryMetadataStore::default()),
+            ledger_events.create(),
+            ledger_events.create(),
+        )
+        .unwrap();
+
+        assert_that(&state_store.get::<Bobisha>(&id).unwrap())
+            .contains_value(SwapStates::Final(Final(SwapOutcome::TimedOut { start })));
+    }
+}
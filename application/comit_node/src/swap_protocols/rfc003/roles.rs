@@ -3,23 +3,28 @@ use crate::{
     swap_protocols::{
         self,
         asset::Asset,
+        metadata_store::RoleKind,
         rfc003::{
             actions::bob::{Accept, Decline},
             events::ResponseFuture,
             ledger::Ledger,
             state_machine::StateMachineResponse,
+            state_store::Persist,
             Secret, SecretHash,
         },
     },
 };
 use futures::{sync::oneshot, Future};
+use serde::{de::DeserializeOwned, Serialize};
 use std::{
     fmt::Debug,
     marker::PhantomData,
     sync::{Arc, Mutex},
 };
 
-pub trait Role: Send + Sync + Debug + Clone + 'static {
+pub trait Role:
+    Send + Sync + Debug + Clone + Serialize + DeserializeOwned + Into<RoleKind> + 'static
+{
     type AlphaLedger: Ledger;
     type BetaLedger: Ledger;
     type AlphaAsset: Asset;
@@ -29,6 +34,7 @@ pub trait Role: Send + Sync + Debug + Clone + 'static {
         + Clone
         + Debug
         + PartialEq
+        + Persist
         + Into<<Self::AlphaLedger as swap_protocols::Ledger>::Identity>;
 
     type AlphaRefundHtlcIdentity: Send
@@ -36,6 +42,7 @@ pub trait Role: Send + Sync + Debug + Clone + 'static {
         + Clone
         + Debug
         + PartialEq
+        + Persist
         + Into<<Self::AlphaLedger as swap_protocols::Ledger>::Identity>;
 
     type BetaRedeemHtlcIdentity: Send
@@ -43,6 +50,7 @@ pub trait Role: Send + Sync + Debug + Clone + 'static {
         + Clone
         + Debug
         + PartialEq
+        + Persist
         + Into<<Self::BetaLedger as swap_protocols::Ledger>::Identity>;
 
     type BetaRefundHtlcIdentity: Send
@@ -50,12 +58,14 @@ pub trait Role: Send + Sync + Debug + Clone + 'static {
         + Clone
         + Debug
         + PartialEq
+        + Persist
         + Into<<Self::BetaLedger as swap_protocols::Ledger>::Identity>;
 
-    type Secret: Send + Sync + Clone + Into<SecretHash> + Debug + PartialEq;
+    type Secret: Send + Sync + Clone + Into<SecretHash> + Debug + PartialEq + Persist;
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct Alice<AL, BL, AA, BA> {
     phantom_data: PhantomData<(AL, BL, AA, BA)>,
 }
@@ -68,6 +78,12 @@ impl<AL, BL, AA, BA> Default for Alice<AL, BL, AA, BA> {
     }
 }
 
+impl<AL, BL, AA, BA> From<Alice<AL, BL, AA, BA>> for RoleKind {
+    fn from(_: Alice<AL, BL, AA, BA>) -> Self {
+        RoleKind::Alice
+    }
+}
+
 impl<AL: Ledger, BL: Ledger, AA: Asset, BA: Asset> Role for Alice<AL, BL, AA, BA> {
     type AlphaLedger = AL;
     type BetaLedger = BL;
@@ -80,9 +96,13 @@ impl<AL: Ledger, BL: Ledger, AA: Asset, BA: Asset> Role for Alice<AL, BL, AA, BA
     type Secret = Secret;
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct Bob<AL: Ledger, BL: Ledger, AA, BA> {
     phantom_data: PhantomData<(AL, BL, AA, BA)>,
+    // The response channel only lives as long as the process that received the
+    // request, a restored Bob can no longer accept or decline.
+    #[serde(skip)]
     #[allow(clippy::type_complexity)]
     response_sender: Arc<
         Mutex<
@@ -96,15 +116,20 @@ pub struct Bob<AL: Ledger, BL: Ledger, AA, BA> {
             >,
         >,
     >,
+    /// Seconds since the unix epoch after which Alice no longer waits for a
+    /// response
+    #[serde(default)]
+    expires_at: Option<u64>,
 }
 
 impl<AL: Ledger, BL: Ledger, AA: Asset, BA: Asset> Bob<AL, BL, AA, BA> {
-    pub fn create() -> (Self, Box<ResponseFuture<Self>>) {
+    pub fn create(expires_at: Option<u64>) -> (Self, Box<ResponseFuture<Self>>) {
         let (sender, receiver) = oneshot::channel();
         (
             Bob {
                 phantom_data: PhantomData,
                 response_sender: Arc::new(Mutex::new(Some(sender))),
+                expires_at,
             },
             Box::new(
                 receiver
@@ -113,8 +138,12 @@ impl<AL: Ledger, BL: Ledger, AA: Asset, BA: Asset> Bob<AL, BL, AA, BA> {
         )
     }
 
-    pub fn accept_action(&self) -> Accept<AL, BL> {
-        Accept::new(self.response_sender.clone())
+    pub fn accept_action(&self, alpha_ledger_lock_duration: AL::LockDuration) -> Accept<AL, BL> {
+        Accept::new(
+            self.response_sender.clone(),
+            alpha_ledger_lock_duration,
+            self.expires_at,
+        )
     }
 
     pub fn decline_action(&self) -> Decline<AL, BL> {
@@ -122,6 +151,12 @@ impl<AL: Ledger, BL: Ledger, AA: Asset, BA: Asset> Bob<AL, BL, AA, BA> {
     }
 }
 
+impl<AL: Ledger, BL: Ledger, AA, BA> From<Bob<AL, BL, AA, BA>> for RoleKind {
+    fn from(_: Bob<AL, BL, AA, BA>) -> Self {
+        RoleKind::Bob
+    }
+}
+
 impl<AL: Ledger, BL: Ledger, AA: Asset, BA: Asset> Role for Bob<AL, BL, AA, BA> {
     type AlphaLedger = AL;
     type BetaLedger = BL;
@@ -150,14 +185,14 @@ pub mod test {
     pub type Alisha = Alice<Bitcoin, Ethereum, BitcoinQuantity, EtherQuantity>;
     pub type Bobisha = Bob<Bitcoin, Ethereum, BitcoinQuantity, EtherQuantity>;
 
-    impl PartialEq<Alisha> for Alisha {
-        fn eq(&self, _: &Alisha) -> bool {
+    impl<AL, BL, AA, BA> PartialEq for Alice<AL, BL, AA, BA> {
+        fn eq(&self, _: &Self) -> bool {
             true
         }
     }
 
-    impl PartialEq<Bobisha> for Bobisha {
-        fn eq(&self, _: &Bobisha) -> bool {
+    impl<AL: Ledger, BL: Ledger, AA, BA> PartialEq for Bob<AL, BL, AA, BA> {
+        fn eq(&self, _: &Self) -> bool {
             true
         }
     }
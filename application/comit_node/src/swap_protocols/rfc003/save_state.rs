@@ -1,21 +1,109 @@
-use crate::swap_protocols::rfc003::{roles::Role, state_machine::SwapStates};
+use crate::swap_protocols::{
+    metadata_store::{HistoryEvent, MetadataStore},
+    rfc003::{roles::Role, state_machine::SwapStates, state_store::Error},
+    SwapId,
+};
 use futures::sync::mpsc;
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
 
 pub trait SaveState<R: Role>: Send + Sync {
-    fn save(&self, state: SwapStates<R>);
+    fn save(&self, state: SwapStates<R>) -> Result<(), Error>;
+
+    /// Saves a state that was reached because of something that happened on
+    /// a ledger. `transaction` is the HTLC location or the id of the ledger
+    /// transaction in question.
+    fn save_caused_by(
+        &self,
+        state: SwapStates<R>,
+        _transaction: serde_json::Value,
+    ) -> Result<(), Error> {
+        self.save(state)
+    }
+
+    /// Records something that happened to the swap without changing its
+    /// state
+    fn record(&self, _event: HistoryEvent) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 impl<R: Role + Sync> SaveState<R> for RwLock<SwapStates<R>> {
-    fn save(&self, state: SwapStates<R>) {
+    fn save(&self, state: SwapStates<R>) -> Result<(), Error> {
         let _self = &mut *self.write().unwrap();
         *_self = state;
+        Ok(())
     }
 }
 
 impl<R: Role> SaveState<R> for mpsc::UnboundedSender<SwapStates<R>> {
-    fn save(&self, state: SwapStates<R>) {
+    fn save(&self, state: SwapStates<R>) -> Result<(), Error> {
         // ignore error the subscriber is no longer interested in state updates
         let _ = self.unbounded_send(state);
+        Ok(())
+    }
+}
+
+/// Keeps the state name recorded in the `MetadataStore` in sync with the
+/// states saved through `inner` and appends every transition to the history
+/// of the swap.
+#[allow(missing_debug_implementations)]
+pub struct UpdateMetadataState<M, R: Role> {
+    id: SwapId,
+    metadata_store: Arc<M>,
+    inner: Arc<dyn SaveState<R>>,
+}
+
+impl<M: MetadataStore<SwapId>, R: Role> UpdateMetadataState<M, R> {
+    pub fn new(id: SwapId, metadata_store: Arc<M>, inner: Arc<dyn SaveState<R>>) -> Self {
+        Self {
+            id,
+            metadata_store,
+            inner,
+        }
+    }
+
+    fn update(
+        &self,
+        state: SwapStates<R>,
+        transaction: Option<serde_json::Value>,
+    ) -> Result<(), Error> {
+        let name = state.name();
+        self.inner.save(state)?;
+
+        if let Err(e) = self.metadata_store.update_state(&self.id, name.clone()) {
+            error!("Failed to update state name of swap {}: {:?}", self.id, e);
+        }
+
+        let event = HistoryEvent::Transition {
+            state: name,
+            transaction,
+        };
+        if let Err(e) = self.metadata_store.append_history(&self.id, event) {
+            error!("Failed to record transition of swap {}: {:?}", self.id, e);
+        }
+
+        Ok(())
+    }
+}
+
+impl<M: MetadataStore<SwapId>, R: Role> SaveState<R> for UpdateMetadataState<M, R> {
+    fn save(&self, state: SwapStates<R>) -> Result<(), Error> {
+        self.update(state, None)
+    }
+
+    fn save_caused_by(
+        &self,
+        state: SwapStates<R>,
+        transaction: serde_json::Value,
+    ) -> Result<(), Error> {
+        self.update(state, Some(transaction))
+    }
+
+    fn record(&self, event: HistoryEvent) -> Result<(), Error> {
+        if let Err(e) = self.metadata_store.append_history(&self.id, event) {
+            error!("Failed to record event of swap {}: {:?}", self.id, e);
+        }
+
+        Ok(())
     }
 }
@@ -2,6 +2,7 @@ use crate::{
     seed::Seed,
     swap_protocols::{rfc003::Secret, SwapId},
 };
+use ethereum_support::Address;
 use secp256k1_support::KeyPair;
 
 pub trait SecretSource: Send + Sync {
@@ -31,3 +32,32 @@ impl SecretSource for Seed {
         .expect("The probability of this happening is < 1 in 2^120")
     }
 }
+
+/// An identity of this node's HTLCs, as it is used for another swap
+///
+/// Keys are derived from the seed for every swap, otherwise the other swap
+/// can't be restored after a restart.
+pub trait SwapIdentity {
+    fn refund_identity_for(&self, secret_source: &dyn SecretSource, id: SwapId) -> Self;
+    fn redeem_identity_for(&self, secret_source: &dyn SecretSource, id: SwapId) -> Self;
+}
+
+impl SwapIdentity for KeyPair {
+    fn refund_identity_for(&self, secret_source: &dyn SecretSource, id: SwapId) -> Self {
+        secret_source.new_secp256k1_refund(id)
+    }
+
+    fn redeem_identity_for(&self, secret_source: &dyn SecretSource, id: SwapId) -> Self {
+        secret_source.new_secp256k1_redeem(id)
+    }
+}
+
+impl SwapIdentity for Address {
+    fn refund_identity_for(&self, _: &dyn SecretSource, _: SwapId) -> Self {
+        *self
+    }
+
+    fn redeem_identity_for(&self, _: &dyn SecretSource, _: SwapId) -> Self {
+        *self
+    }
+}
@@ -2,14 +2,18 @@
 
 use crate::{
     comit_client::{self, SwapReject},
+    ledger_query_service::Revert,
     swap_protocols::{
         asset::Asset,
+        metadata_store::{HistoryEvent, Metadata},
         rfc003::{
-            self, events, ledger::Ledger, roles::Role, RedeemTransaction, SaveState, SecretHash,
+            self, events, ledger::Ledger, roles::Role, state_store::persisted, RedeemTransaction,
+            RefundTransaction, SaveState, SecretHash, Timestamp,
         },
     },
 };
-use futures::{future::Either, Async};
+use futures::{future::Either, Async, Stream};
+use serde::Serialize;
 use state_machine_future::{RentToOwn, StateMachineFuture};
 use std::{fmt, sync::Arc};
 
@@ -42,18 +46,24 @@ pub struct HtlcParams<L: Ledger, A: Asset> {
     pub secret_hash: SecretHash,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct OngoingSwap<R: Role> {
     pub alpha_ledger: R::AlphaLedger,
     pub beta_ledger: R::BetaLedger,
     pub alpha_asset: R::AlphaAsset,
     pub beta_asset: R::BetaAsset,
+    #[serde(with = "persisted")]
     pub alpha_ledger_redeem_identity: R::AlphaRedeemHtlcIdentity,
+    #[serde(with = "persisted")]
     pub alpha_ledger_refund_identity: R::AlphaRefundHtlcIdentity,
+    #[serde(with = "persisted")]
     pub beta_ledger_redeem_identity: R::BetaRedeemHtlcIdentity,
+    #[serde(with = "persisted")]
     pub beta_ledger_refund_identity: R::BetaRefundHtlcIdentity,
     pub alpha_ledger_lock_duration: <R::AlphaLedger as Ledger>::LockDuration,
     pub beta_ledger_lock_duration: <R::BetaLedger as Ledger>::LockDuration,
+    #[serde(with = "persisted")]
     pub secret: R::Secret,
     pub role: R,
 }
@@ -83,6 +93,20 @@ impl<R: Role> OngoingSwap<R> {
         }
     }
 
+    pub fn state_machine_response(
+        &self,
+    ) -> StateMachineResponse<
+        R::AlphaRedeemHtlcIdentity,
+        R::BetaRefundHtlcIdentity,
+        <R::BetaLedger as Ledger>::LockDuration,
+    > {
+        StateMachineResponse {
+            alpha_ledger_redeem_identity: self.alpha_ledger_redeem_identity.clone(),
+            beta_ledger_refund_identity: self.beta_ledger_refund_identity.clone(),
+            beta_ledger_lock_duration: self.beta_ledger_lock_duration.clone(),
+        }
+    }
+
     pub fn alpha_htlc_params(&self) -> HtlcParams<R::AlphaLedger, R::AlphaAsset> {
         HtlcParams {
             asset: self.alpha_asset.clone(),
@@ -106,12 +130,16 @@ impl<R: Role> OngoingSwap<R> {
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub enum SwapOutcome<R: Role> {
     Rejected {
         start: Start<R>,
         rejection_type: SwapReject,
     },
+    TimedOut {
+        start: Start<R>,
+    },
     AlphaRefunded {
         swap: OngoingSwap<R>,
     },
@@ -129,6 +157,18 @@ pub enum SwapOutcome<R: Role> {
     },
 }
 
+impl<R: Role> From<Start<R>> for Metadata {
+    fn from(start: Start<R>) -> Self {
+        Self {
+            alpha_ledger: start.alpha_ledger.into(),
+            beta_ledger: start.beta_ledger.into(),
+            alpha_asset: start.alpha_asset.into(),
+            beta_asset: start.beta_asset.into(),
+            role: start.role.into(),
+        }
+    }
+}
+
 #[allow(missing_debug_implementations)]
 pub struct Context<R: Role> {
     pub alpha_ledger_events: Box<dyn events::LedgerEvents<R::AlphaLedger, R::AlphaAsset>>,
@@ -138,18 +178,24 @@ pub struct Context<R: Role> {
 }
 
 #[derive(StateMachineFuture)]
-#[state_machine_future(context = "Context", derive(Clone, Debug, PartialEq))]
+#[state_machine_future(
+    context = "Context",
+    derive(Clone, Debug, PartialEq, Serialize, Deserialize)
+)]
 #[allow(missing_debug_implementations)]
 pub enum Swap<R: Role> {
     #[state_machine_future(start, transitions(Accepted, Final))]
     Start {
+        #[serde(with = "persisted")]
         alpha_ledger_refund_identity: R::AlphaRefundHtlcIdentity,
+        #[serde(with = "persisted")]
         beta_ledger_redeem_identity: R::BetaRedeemHtlcIdentity,
         alpha_ledger: R::AlphaLedger,
         beta_ledger: R::BetaLedger,
         alpha_asset: R::AlphaAsset,
         beta_asset: R::BetaAsset,
         alpha_ledger_lock_duration: <R::AlphaLedger as Ledger>::LockDuration,
+        #[serde(with = "persisted")]
         secret: R::Secret,
         role: R,
     },
@@ -161,22 +207,31 @@ pub enum Swap<R: Role> {
     AlphaDeployed {
         swap: OngoingSwap<R>,
         alpha_htlc_location: <R::AlphaLedger as Ledger>::HtlcLocation,
+        alpha_htlc_deployed_at: Timestamp,
     },
 
-    #[state_machine_future(transitions(AlphaFundedBetaDeployed, Final))]
+    #[state_machine_future(transitions(Accepted, AlphaFunded, AlphaFundedBetaDeployed, Final))]
     AlphaFunded {
         swap: OngoingSwap<R>,
         alpha_htlc_location: <R::AlphaLedger as Ledger>::HtlcLocation,
+        alpha_htlc_deployed_at: Timestamp,
+        alpha_htlc_expired: bool,
     },
 
-    #[state_machine_future(transitions(BothFunded, Final))]
+    #[state_machine_future(transitions(Accepted, AlphaFundedBetaDeployed, BothFunded, Final))]
     AlphaFundedBetaDeployed {
         swap: OngoingSwap<R>,
         alpha_htlc_location: <R::AlphaLedger as Ledger>::HtlcLocation,
+        alpha_htlc_deployed_at: Timestamp,
         beta_htlc_location: <R::BetaLedger as Ledger>::HtlcLocation,
+        beta_htlc_deployed_at: Timestamp,
+        alpha_htlc_expired: bool,
     },
 
     #[state_machine_future(transitions(
+        Accepted,
+        AlphaFunded,
+        BothFunded,
         AlphaFundedBetaRedeemed,
         AlphaFundedBetaRefunded,
         AlphaRefundedBetaFunded,
@@ -185,32 +240,59 @@ pub enum Swap<R: Role> {
     BothFunded {
         swap: OngoingSwap<R>,
         alpha_htlc_location: <R::AlphaLedger as Ledger>::HtlcLocation,
+        alpha_htlc_deployed_at: Timestamp,
         beta_htlc_location: <R::BetaLedger as Ledger>::HtlcLocation,
+        beta_htlc_deployed_at: Timestamp,
+        alpha_htlc_expired: bool,
+        beta_htlc_expired: bool,
     },
 
-    #[state_machine_future(transitions(Final))]
+    // The states after one of the HTLCs got redeemed or refunded keep what
+    // is needed to go back to `BothFunded` if that gets reverted. The expiry
+    // of the closed HTLC is the last one seen in `BothFunded`.
+    #[state_machine_future(transitions(BothFunded, AlphaFundedBetaRefunded, Final))]
     AlphaFundedBetaRefunded {
         swap: OngoingSwap<R>,
         alpha_htlc_location: <R::AlphaLedger as Ledger>::HtlcLocation,
+        alpha_htlc_deployed_at: Timestamp,
+        beta_htlc_location: <R::BetaLedger as Ledger>::HtlcLocation,
+        beta_htlc_deployed_at: Timestamp,
+        alpha_htlc_expired: bool,
+        beta_htlc_expired: bool,
     },
 
-    #[state_machine_future(transitions(Final))]
+    #[state_machine_future(transitions(BothFunded, AlphaRefundedBetaFunded, Final))]
     AlphaRefundedBetaFunded {
         swap: OngoingSwap<R>,
+        alpha_htlc_location: <R::AlphaLedger as Ledger>::HtlcLocation,
+        alpha_htlc_deployed_at: Timestamp,
         beta_htlc_location: <R::BetaLedger as Ledger>::HtlcLocation,
+        beta_htlc_deployed_at: Timestamp,
+        alpha_htlc_expired: bool,
+        beta_htlc_expired: bool,
     },
 
-    #[state_machine_future(transitions(Final))]
+    #[state_machine_future(transitions(BothFunded, AlphaRedeemedBetaFunded, Final))]
     AlphaRedeemedBetaFunded {
         swap: OngoingSwap<R>,
+        alpha_htlc_location: <R::AlphaLedger as Ledger>::HtlcLocation,
+        alpha_htlc_deployed_at: Timestamp,
         beta_htlc_location: <R::BetaLedger as Ledger>::HtlcLocation,
+        beta_htlc_deployed_at: Timestamp,
+        alpha_htlc_expired: bool,
+        beta_htlc_expired: bool,
     },
 
-    #[state_machine_future(transitions(Final))]
+    #[state_machine_future(transitions(BothFunded, AlphaFundedBetaRedeemed, Final))]
     AlphaFundedBetaRedeemed {
         swap: OngoingSwap<R>,
         beta_redeemed_tx: RedeemTransaction<R::BetaLedger>,
         alpha_htlc_location: <R::AlphaLedger as Ledger>::HtlcLocation,
+        alpha_htlc_deployed_at: Timestamp,
+        beta_htlc_location: <R::BetaLedger as Ledger>::HtlcLocation,
+        beta_htlc_deployed_at: Timestamp,
+        alpha_htlc_expired: bool,
+        beta_htlc_expired: bool,
     },
 
     #[state_machine_future(ready)]
@@ -234,12 +316,24 @@ impl<R: Role> PollSwap<R> for Swap<R> {
             beta_ledger_redeem_identity: state.beta_ledger_redeem_identity.clone().into(),
             alpha_ledger_lock_duration: state.alpha_ledger_lock_duration.clone(),
             secret_hash: state.secret.clone().into(),
+            expires_at: None,
         };
 
-        let response = try_ready!(context
+        let response = match context
             .communication_events
             .request_responded(&request)
-            .poll());
+            .poll()
+        {
+            Err(rfc003::Error::SwapResponse(comit_client::SwapResponseError::TimedOut)) => {
+                transition_save!(
+                    context.state_repo,
+                    Final(SwapOutcome::TimedOut {
+                        start: state.take()
+                    })
+                )
+            }
+            response => try_ready!(response),
+        };
 
         let state = state.take();
 
@@ -264,7 +358,7 @@ impl<R: Role> PollSwap<R> for Swap<R> {
         state: &'s mut RentToOwn<'s, Accepted<R>>,
         context: &'c mut RentToOwn<'c, Context<R>>,
     ) -> Result<Async<AfterAccepted<R>>, rfc003::Error> {
-        let alpha_htlc_location = try_ready!(context
+        let (alpha_htlc_location, alpha_htlc_deployed_at) = try_ready!(context
             .alpha_ledger_events
             .htlc_deployed(state.swap.alpha_htlc_params())
             .poll());
@@ -274,7 +368,9 @@ impl<R: Role> PollSwap<R> for Swap<R> {
             AlphaDeployed {
                 swap: state.swap,
                 alpha_htlc_location,
-            }
+                alpha_htlc_deployed_at,
+            },
+            alpha_htlc_location
         )
     }
 
@@ -282,7 +378,7 @@ impl<R: Role> PollSwap<R> for Swap<R> {
         state: &'s mut RentToOwn<'s, AlphaDeployed<R>>,
         context: &'c mut RentToOwn<'c, Context<R>>,
     ) -> Result<Async<AfterAlphaDeployed<R>>, rfc003::Error> {
-        let _alpha_funding_transaction = try_ready!(context
+        let alpha_funding_transaction = try_ready!(context
             .alpha_ledger_events
             .htlc_funded(state.swap.alpha_htlc_params(), &state.alpha_htlc_location)
             .poll());
@@ -292,7 +388,10 @@ impl<R: Role> PollSwap<R> for Swap<R> {
             AlphaFunded {
                 swap: state.swap,
                 alpha_htlc_location: state.alpha_htlc_location,
-            }
+                alpha_htlc_deployed_at: state.alpha_htlc_deployed_at,
+                alpha_htlc_expired: false,
+            },
+            alpha_funding_transaction.map(|transaction| transaction.transaction_id())
         )
     }
 
@@ -300,7 +399,25 @@ impl<R: Role> PollSwap<R> for Swap<R> {
         state: &'s mut RentToOwn<'s, AlphaFunded<R>>,
         context: &'c mut RentToOwn<'c, Context<R>>,
     ) -> Result<Async<AfterAlphaFunded<R>>, rfc003::Error> {
-        if let Async::Ready(_alpha_redeemed_or_refunded) = context
+        let state_repo = Arc::clone(&context.state_repo);
+        if let Some(replaced_funding) = poll_funding_reverts(
+            context
+                .alpha_ledger_events
+                .htlc_funding_reverts(state.swap.alpha_htlc_params(), &state.alpha_htlc_location),
+            state_repo.as_ref(),
+        )? {
+            context.alpha_ledger_events.forget_htlc();
+            context.beta_ledger_events.forget_htlc();
+            transition_save!(
+                context.state_repo,
+                Accepted {
+                    swap: state.take().swap
+                },
+                replaced_funding
+            )
+        }
+
+        if let Async::Ready(alpha_redeemed_or_refunded) = context
             .alpha_ledger_events
             .htlc_redeemed_or_refunded(state.swap.alpha_htlc_params(), &state.alpha_htlc_location)
             .poll()?
@@ -309,11 +426,28 @@ impl<R: Role> PollSwap<R> for Swap<R> {
                 context.state_repo,
                 Final(SwapOutcome::AlphaRefunded {
                     swap: state.take().swap
-                })
+                }),
+                redeemed_or_refunded_transaction_id(&alpha_redeemed_or_refunded)
             )
         }
 
-        let beta_htlc_location = try_ready!(context
+        if !state.alpha_htlc_expired {
+            if let Async::Ready(()) = context
+                .alpha_ledger_events
+                .htlc_expired(state.swap.alpha_htlc_params(), state.alpha_htlc_deployed_at)
+                .poll()?
+            {
+                transition_save!(
+                    context.state_repo,
+                    AlphaFunded {
+                        alpha_htlc_expired: true,
+                        ..state.take()
+                    }
+                )
+            }
+        }
+
+        let (beta_htlc_location, beta_htlc_deployed_at) = try_ready!(context
             .beta_ledger_events
             .htlc_deployed(state.swap.beta_htlc_params())
             .poll());
@@ -323,8 +457,12 @@ impl<R: Role> PollSwap<R> for Swap<R> {
             AlphaFundedBetaDeployed {
                 swap: state.swap,
                 alpha_htlc_location: state.alpha_htlc_location,
+                alpha_htlc_deployed_at: state.alpha_htlc_deployed_at,
                 beta_htlc_location,
-            }
+                beta_htlc_deployed_at,
+                alpha_htlc_expired: state.alpha_htlc_expired,
+            },
+            beta_htlc_location
         )
     }
 
@@ -332,7 +470,25 @@ impl<R: Role> PollSwap<R> for Swap<R> {
         state: &'s mut RentToOwn<'s, AlphaFundedBetaDeployed<R>>,
         context: &'c mut RentToOwn<'c, Context<R>>,
     ) -> Result<Async<AfterAlphaFundedBetaDeployed<R>>, rfc003::Error> {
-        if let Async::Ready(_alpha_redeemed_or_refunded) = context
+        let state_repo = Arc::clone(&context.state_repo);
+        if let Some(replaced_funding) = poll_funding_reverts(
+            context
+                .alpha_ledger_events
+                .htlc_funding_reverts(state.swap.alpha_htlc_params(), &state.alpha_htlc_location),
+            state_repo.as_ref(),
+        )? {
+            context.alpha_ledger_events.forget_htlc();
+            context.beta_ledger_events.forget_htlc();
+            transition_save!(
+                context.state_repo,
+                Accepted {
+                    swap: state.take().swap
+                },
+                replaced_funding
+            )
+        }
+
+        if let Async::Ready(alpha_redeemed_or_refunded) = context
             .alpha_ledger_events
             .htlc_redeemed_or_refunded(state.swap.alpha_htlc_params(), &state.alpha_htlc_location)
             .poll()?
@@ -341,11 +497,28 @@ impl<R: Role> PollSwap<R> for Swap<R> {
                 context.state_repo,
                 Final(SwapOutcome::AlphaRefunded {
                     swap: state.take().swap
-                })
+                }),
+                redeemed_or_refunded_transaction_id(&alpha_redeemed_or_refunded)
             )
         }
 
-        let _ = try_ready!(context
+        if !state.alpha_htlc_expired {
+            if let Async::Ready(()) = context
+                .alpha_ledger_events
+                .htlc_expired(state.swap.alpha_htlc_params(), state.alpha_htlc_deployed_at)
+                .poll()?
+            {
+                transition_save!(
+                    context.state_repo,
+                    AlphaFundedBetaDeployed {
+                        alpha_htlc_expired: true,
+                        ..state.take()
+                    }
+                )
+            }
+        }
+
+        let beta_funding_transaction = try_ready!(context
             .beta_ledger_events
             .htlc_funded(state.swap.beta_htlc_params(), &state.beta_htlc_location)
             .poll());
@@ -355,8 +528,13 @@ impl<R: Role> PollSwap<R> for Swap<R> {
             BothFunded {
                 swap: state.swap,
                 alpha_htlc_location: state.alpha_htlc_location,
+                alpha_htlc_deployed_at: state.alpha_htlc_deployed_at,
                 beta_htlc_location: state.beta_htlc_location,
-            }
+                beta_htlc_deployed_at: state.beta_htlc_deployed_at,
+                alpha_htlc_expired: state.alpha_htlc_expired,
+                beta_htlc_expired: false,
+            },
+            beta_funding_transaction.map(|transaction| transaction.transaction_id())
         )
     }
 
@@ -364,6 +542,44 @@ impl<R: Role> PollSwap<R> for Swap<R> {
         state: &'s mut RentToOwn<'s, BothFunded<R>>,
         context: &'c mut RentToOwn<'c, Context<R>>,
     ) -> Result<Async<AfterBothFunded<R>>, rfc003::Error> {
+        let state_repo = Arc::clone(&context.state_repo);
+        if let Some(replaced_funding) = poll_funding_reverts(
+            context
+                .alpha_ledger_events
+                .htlc_funding_reverts(state.swap.alpha_htlc_params(), &state.alpha_htlc_location),
+            state_repo.as_ref(),
+        )? {
+            context.alpha_ledger_events.forget_htlc();
+            context.beta_ledger_events.forget_htlc();
+            transition_save!(
+                context.state_repo,
+                Accepted {
+                    swap: state.take().swap
+                },
+                replaced_funding
+            )
+        }
+
+        if let Some(replaced_funding) = poll_funding_reverts(
+            context
+                .beta_ledger_events
+                .htlc_funding_reverts(state.swap.beta_htlc_params(), &state.beta_htlc_location),
+            state_repo.as_ref(),
+        )? {
+            context.beta_ledger_events.forget_htlc();
+            let state = state.take();
+            transition_save!(
+                context.state_repo,
+                AlphaFunded {
+                    swap: state.swap,
+                    alpha_htlc_location: state.alpha_htlc_location,
+                    alpha_htlc_deployed_at: state.alpha_htlc_deployed_at,
+                    alpha_htlc_expired: state.alpha_htlc_expired,
+                },
+                replaced_funding
+            )
+        }
+
         if let Async::Ready(redeemed_or_refunded) = context
             .beta_ledger_events
             .htlc_redeemed_or_refunded(state.swap.beta_htlc_params(), &state.beta_htlc_location)
@@ -377,66 +593,166 @@ impl<R: Role> PollSwap<R> for Swap<R> {
                         swap: state.swap,
                         beta_redeemed_tx,
                         alpha_htlc_location: state.alpha_htlc_location,
-                    }
+                        alpha_htlc_deployed_at: state.alpha_htlc_deployed_at,
+                        beta_htlc_location: state.beta_htlc_location,
+                        beta_htlc_deployed_at: state.beta_htlc_deployed_at,
+                        alpha_htlc_expired: state.alpha_htlc_expired,
+                        beta_htlc_expired: state.beta_htlc_expired,
+                    },
+                    beta_redeemed_tx.transaction_id()
                 ),
-                Either::B(_beta_refunded_txid) => transition_save!(
+                Either::B(beta_refunded_tx) => transition_save!(
                     context.state_repo,
                     AlphaFundedBetaRefunded {
                         swap: state.swap,
                         alpha_htlc_location: state.alpha_htlc_location,
-                    }
+                        alpha_htlc_deployed_at: state.alpha_htlc_deployed_at,
+                        beta_htlc_location: state.beta_htlc_location,
+                        beta_htlc_deployed_at: state.beta_htlc_deployed_at,
+                        alpha_htlc_expired: state.alpha_htlc_expired,
+                        beta_htlc_expired: state.beta_htlc_expired,
+                    },
+                    beta_refunded_tx.transaction_id()
                 ),
             }
         }
 
-        match try_ready!(context
+        if let Async::Ready(redeemed_or_refunded) = context
             .alpha_ledger_events
             .htlc_redeemed_or_refunded(state.swap.alpha_htlc_params(), &state.alpha_htlc_location)
-            .poll())
+            .poll()?
         {
-            Either::A(_alpha_redeemed_tx) => {
-                let state = state.take();
-                transition_save!(
+            let state = state.take();
+            match redeemed_or_refunded {
+                Either::A(alpha_redeemed_tx) => transition_save!(
                     context.state_repo,
                     AlphaRedeemedBetaFunded {
                         swap: state.swap,
+                        alpha_htlc_location: state.alpha_htlc_location,
+                        alpha_htlc_deployed_at: state.alpha_htlc_deployed_at,
                         beta_htlc_location: state.beta_htlc_location,
+                        beta_htlc_deployed_at: state.beta_htlc_deployed_at,
+                        alpha_htlc_expired: state.alpha_htlc_expired,
+                        beta_htlc_expired: state.beta_htlc_expired,
+                    },
+                    alpha_redeemed_tx.transaction_id()
+                ),
+                Either::B(alpha_refunded_tx) => transition_save!(
+                    context.state_repo,
+                    AlphaRefundedBetaFunded {
+                        swap: state.swap,
+                        alpha_htlc_location: state.alpha_htlc_location,
+                        alpha_htlc_deployed_at: state.alpha_htlc_deployed_at,
+                        beta_htlc_location: state.beta_htlc_location,
+                        beta_htlc_deployed_at: state.beta_htlc_deployed_at,
+                        alpha_htlc_expired: state.alpha_htlc_expired,
+                        beta_htlc_expired: state.beta_htlc_expired,
+                    },
+                    alpha_refunded_tx.transaction_id()
+                ),
+            }
+        }
+
+        if !state.alpha_htlc_expired {
+            if let Async::Ready(()) = context
+                .alpha_ledger_events
+                .htlc_expired(state.swap.alpha_htlc_params(), state.alpha_htlc_deployed_at)
+                .poll()?
+            {
+                transition_save!(
+                    context.state_repo,
+                    BothFunded {
+                        alpha_htlc_expired: true,
+                        ..state.take()
                     }
                 )
             }
-            Either::B(_alpha_refunded_txid) => {
-                let state = state.take();
+        }
+
+        if !state.beta_htlc_expired {
+            if let Async::Ready(()) = context
+                .beta_ledger_events
+                .htlc_expired(state.swap.beta_htlc_params(), state.beta_htlc_deployed_at)
+                .poll()?
+            {
                 transition_save!(
                     context.state_repo,
-                    AlphaRefundedBetaFunded {
-                        swap: state.swap,
-                        beta_htlc_location: state.beta_htlc_location,
+                    BothFunded {
+                        beta_htlc_expired: true,
+                        ..state.take()
                     }
                 )
             }
         }
+
+        Ok(Async::NotReady)
     }
 
     fn poll_alpha_funded_beta_refunded<'s, 'c>(
         state: &'s mut RentToOwn<'s, AlphaFundedBetaRefunded<R>>,
         context: &'c mut RentToOwn<'c, Context<R>>,
     ) -> Result<Async<AfterAlphaFundedBetaRefunded<R>>, rfc003::Error> {
+        if let Async::Ready(reverted_transaction) = context
+            .beta_ledger_events
+            .htlc_redeemed_or_refunded_reverted(
+                state.swap.beta_htlc_params(),
+                &state.beta_htlc_location,
+            )
+            .poll()?
+        {
+            context
+                .beta_ledger_events
+                .forget_htlc_redeemed_or_refunded();
+            record_reverted(context.state_repo.as_ref(), &reverted_transaction)?;
+            let state = state.take();
+            transition_save!(
+                context.state_repo,
+                BothFunded {
+                    swap: state.swap,
+                    alpha_htlc_location: state.alpha_htlc_location,
+                    alpha_htlc_deployed_at: state.alpha_htlc_deployed_at,
+                    beta_htlc_location: state.beta_htlc_location,
+                    beta_htlc_deployed_at: state.beta_htlc_deployed_at,
+                    alpha_htlc_expired: state.alpha_htlc_expired,
+                    beta_htlc_expired: state.beta_htlc_expired,
+                }
+            )
+        }
+
+        if !state.alpha_htlc_expired {
+            if let Async::Ready(()) = context
+                .alpha_ledger_events
+                .htlc_expired(state.swap.alpha_htlc_params(), state.alpha_htlc_deployed_at)
+                .poll()?
+            {
+                transition_save!(
+                    context.state_repo,
+                    AlphaFundedBetaRefunded {
+                        alpha_htlc_expired: true,
+                        ..state.take()
+                    }
+                )
+            }
+        }
+
         match try_ready!(context
             .alpha_ledger_events
             .htlc_redeemed_or_refunded(state.swap.alpha_htlc_params(), &state.alpha_htlc_location)
             .poll())
         {
-            Either::A(_alpha_redeemed_txid) => transition_save!(
+            Either::A(alpha_redeemed_tx) => transition_save!(
                 context.state_repo,
                 Final(SwapOutcome::AlphaRedeemedBetaRefunded {
                     swap: state.take().swap
-                })
+                }),
+                alpha_redeemed_tx.transaction_id()
             ),
-            Either::B(_alpha_refunded_txid) => transition_save!(
+            Either::B(alpha_refunded_tx) => transition_save!(
                 context.state_repo,
                 Final(SwapOutcome::BothRefunded {
                     swap: state.take().swap
-                })
+                }),
+                alpha_refunded_tx.transaction_id()
             ),
         }
     }
@@ -445,22 +761,67 @@ impl<R: Role> PollSwap<R> for Swap<R> {
         state: &'s mut RentToOwn<'s, AlphaRefundedBetaFunded<R>>,
         context: &'c mut RentToOwn<'c, Context<R>>,
     ) -> Result<Async<AfterAlphaRefundedBetaFunded<R>>, rfc003::Error> {
+        if let Async::Ready(reverted_transaction) = context
+            .alpha_ledger_events
+            .htlc_redeemed_or_refunded_reverted(
+                state.swap.alpha_htlc_params(),
+                &state.alpha_htlc_location,
+            )
+            .poll()?
+        {
+            context
+                .alpha_ledger_events
+                .forget_htlc_redeemed_or_refunded();
+            record_reverted(context.state_repo.as_ref(), &reverted_transaction)?;
+            let state = state.take();
+            transition_save!(
+                context.state_repo,
+                BothFunded {
+                    swap: state.swap,
+                    alpha_htlc_location: state.alpha_htlc_location,
+                    alpha_htlc_deployed_at: state.alpha_htlc_deployed_at,
+                    beta_htlc_location: state.beta_htlc_location,
+                    beta_htlc_deployed_at: state.beta_htlc_deployed_at,
+                    alpha_htlc_expired: state.alpha_htlc_expired,
+                    beta_htlc_expired: state.beta_htlc_expired,
+                }
+            )
+        }
+
+        if !state.beta_htlc_expired {
+            if let Async::Ready(()) = context
+                .beta_ledger_events
+                .htlc_expired(state.swap.beta_htlc_params(), state.beta_htlc_deployed_at)
+                .poll()?
+            {
+                transition_save!(
+                    context.state_repo,
+                    AlphaRefundedBetaFunded {
+                        beta_htlc_expired: true,
+                        ..state.take()
+                    }
+                )
+            }
+        }
+
         match try_ready!(context
             .beta_ledger_events
             .htlc_redeemed_or_refunded(state.swap.beta_htlc_params(), &state.beta_htlc_location)
             .poll())
         {
-            Either::A(_beta_redeemed_txid) => transition_save!(
+            Either::A(beta_redeemed_tx) => transition_save!(
                 context.state_repo,
                 Final(SwapOutcome::AlphaRefundedBetaRedeemed {
                     swap: state.take().swap
-                })
+                }),
+                beta_redeemed_tx.transaction_id()
             ),
-            Either::B(_beta_refunded_txid) => transition_save!(
+            Either::B(beta_refunded_tx) => transition_save!(
                 context.state_repo,
                 Final(SwapOutcome::BothRefunded {
                     swap: state.take().swap
-                })
+                }),
+                beta_refunded_tx.transaction_id()
             ),
         }
     }
@@ -469,22 +830,67 @@ impl<R: Role> PollSwap<R> for Swap<R> {
         state: &'s mut RentToOwn<'s, AlphaRedeemedBetaFunded<R>>,
         context: &'c mut RentToOwn<'c, Context<R>>,
     ) -> Result<Async<AfterAlphaRedeemedBetaFunded<R>>, rfc003::Error> {
+        if let Async::Ready(reverted_transaction) = context
+            .alpha_ledger_events
+            .htlc_redeemed_or_refunded_reverted(
+                state.swap.alpha_htlc_params(),
+                &state.alpha_htlc_location,
+            )
+            .poll()?
+        {
+            context
+                .alpha_ledger_events
+                .forget_htlc_redeemed_or_refunded();
+            record_reverted(context.state_repo.as_ref(), &reverted_transaction)?;
+            let state = state.take();
+            transition_save!(
+                context.state_repo,
+                BothFunded {
+                    swap: state.swap,
+                    alpha_htlc_location: state.alpha_htlc_location,
+                    alpha_htlc_deployed_at: state.alpha_htlc_deployed_at,
+                    beta_htlc_location: state.beta_htlc_location,
+                    beta_htlc_deployed_at: state.beta_htlc_deployed_at,
+                    alpha_htlc_expired: state.alpha_htlc_expired,
+                    beta_htlc_expired: state.beta_htlc_expired,
+                }
+            )
+        }
+
+        if !state.beta_htlc_expired {
+            if let Async::Ready(()) = context
+                .beta_ledger_events
+                .htlc_expired(state.swap.beta_htlc_params(), state.beta_htlc_deployed_at)
+                .poll()?
+            {
+                transition_save!(
+                    context.state_repo,
+                    AlphaRedeemedBetaFunded {
+                        beta_htlc_expired: true,
+                        ..state.take()
+                    }
+                )
+            }
+        }
+
         match try_ready!(context
             .beta_ledger_events
             .htlc_redeemed_or_refunded(state.swap.beta_htlc_params(), &state.beta_htlc_location)
             .poll())
         {
-            Either::A(_beta_redeemed_txid) => transition_save!(
+            Either::A(beta_redeemed_tx) => transition_save!(
                 context.state_repo,
                 Final(SwapOutcome::BothRedeemed {
                     swap: state.take().swap
-                })
+                }),
+                beta_redeemed_tx.transaction_id()
             ),
-            Either::B(_beta_refunded_txid) => transition_save!(
+            Either::B(beta_refunded_tx) => transition_save!(
                 context.state_repo,
                 Final(SwapOutcome::AlphaRedeemedBetaRefunded {
                     swap: state.take().swap
-                })
+                }),
+                beta_refunded_tx.transaction_id()
             ),
         }
     }
@@ -493,22 +899,67 @@ impl<R: Role> PollSwap<R> for Swap<R> {
         state: &'s mut RentToOwn<'s, AlphaFundedBetaRedeemed<R>>,
         context: &'c mut RentToOwn<'c, Context<R>>,
     ) -> Result<Async<AfterAlphaFundedBetaRedeemed<R>>, rfc003::Error> {
+        if let Async::Ready(reverted_transaction) = context
+            .beta_ledger_events
+            .htlc_redeemed_or_refunded_reverted(
+                state.swap.beta_htlc_params(),
+                &state.beta_htlc_location,
+            )
+            .poll()?
+        {
+            context
+                .beta_ledger_events
+                .forget_htlc_redeemed_or_refunded();
+            record_reverted(context.state_repo.as_ref(), &reverted_transaction)?;
+            let state = state.take();
+            transition_save!(
+                context.state_repo,
+                BothFunded {
+                    swap: state.swap,
+                    alpha_htlc_location: state.alpha_htlc_location,
+                    alpha_htlc_deployed_at: state.alpha_htlc_deployed_at,
+                    beta_htlc_location: state.beta_htlc_location,
+                    beta_htlc_deployed_at: state.beta_htlc_deployed_at,
+                    alpha_htlc_expired: state.alpha_htlc_expired,
+                    beta_htlc_expired: state.beta_htlc_expired,
+                }
+            )
+        }
+
+        if !state.alpha_htlc_expired {
+            if let Async::Ready(()) = context
+                .alpha_ledger_events
+                .htlc_expired(state.swap.alpha_htlc_params(), state.alpha_htlc_deployed_at)
+                .poll()?
+            {
+                transition_save!(
+                    context.state_repo,
+                    AlphaFundedBetaRedeemed {
+                        alpha_htlc_expired: true,
+                        ..state.take()
+                    }
+                )
+            }
+        }
+
         match try_ready!(context
             .alpha_ledger_events
             .htlc_redeemed_or_refunded(state.swap.alpha_htlc_params(), &state.alpha_htlc_location)
             .poll())
         {
-            Either::A(_beta_redeemed_txid) => transition_save!(
+            Either::A(alpha_redeemed_tx) => transition_save!(
                 context.state_repo,
                 Final(SwapOutcome::BothRedeemed {
                     swap: state.take().swap
-                })
+                }),
+                alpha_redeemed_tx.transaction_id()
             ),
-            Either::B(_beta_refunded_txid) => transition_save!(
+            Either::B(alpha_refunded_tx) => transition_save!(
                 context.state_repo,
                 Final(SwapOutcome::AlphaRefundedBetaRedeemed {
                     swap: state.take().swap
-                })
+                }),
+                alpha_refunded_tx.transaction_id()
             ),
         }
     }
@@ -536,7 +987,72 @@ impl_display!(AlphaFundedBetaRedeemed);
 impl_display!(AlphaRedeemedBetaFunded);
 impl_display!(Final);
 
+/// Records in the history of the swap when the funding of an HTLC got
+/// reverted and when it got confirmed again. The state of the swap is kept
+/// meanwhile, only once a different transaction replaced the funding is the
+/// id of the reverted one returned.
+fn poll_funding_reverts<L: Ledger, R: Role>(
+    funding_reverts: &mut events::FundingReverts<L>,
+    state_repo: &dyn SaveState<R>,
+) -> Result<Option<L::TxId>, rfc003::Error> {
+    while let Async::Ready(Some(revert)) = funding_reverts.poll()? {
+        match revert {
+            Revert::Invalidated(transaction) => record_reverted(state_repo, &transaction)?,
+            Revert::Reconfirmed(transaction) => record(
+                state_repo,
+                HistoryEvent::Reconfirmed {
+                    transaction: history_transaction(&transaction),
+                },
+            )?,
+            Revert::Replaced(transaction) => return Ok(Some(transaction)),
+        }
+    }
+
+    Ok(None)
+}
+
+fn record_reverted<R: Role, T: Serialize>(
+    state_repo: &dyn SaveState<R>,
+    transaction: &T,
+) -> Result<(), rfc003::Error> {
+    record(
+        state_repo,
+        HistoryEvent::Reverted {
+            transaction: history_transaction(transaction),
+        },
+    )
+}
+
+fn record<R: Role>(
+    state_repo: &dyn SaveState<R>,
+    event: HistoryEvent,
+) -> Result<(), rfc003::Error> {
+    state_repo
+        .record(event)
+        .map_err(|e| rfc003::Error::Internal(e.to_string()))
+}
+
+fn history_transaction<T: Serialize>(transaction: &T) -> serde_json::Value {
+    serde_json::to_value(transaction).unwrap_or(serde_json::Value::Null)
+}
+
+fn redeemed_or_refunded_transaction_id<L: Ledger>(
+    redeemed_or_refunded: &Either<RedeemTransaction<L>, RefundTransaction<L>>,
+) -> L::TxId {
+    match redeemed_or_refunded {
+        Either::A(redeemed) => redeemed.transaction_id(),
+        Either::B(refunded) => refunded.transaction_id(),
+    }
+}
+
 impl<R: Role> SwapStates<R> {
+    pub fn is_final(&self) -> bool {
+        match *self {
+            SwapStates::Final(_) => true,
+            _ => false,
+        }
+    }
+
     pub fn name(&self) -> String {
         use self::SwapStates as SS;
         match *self {
@@ -551,6 +1067,7 @@ impl<R: Role> SwapStates<R> {
             SS::AlphaFundedBetaRedeemed { .. } => String::from("AlphaFundedBetaRedeemed"),
             SS::AlphaRedeemedBetaFunded { .. } => String::from("AlphaRedeemedBetaFunded"),
             SS::Final(Final(SwapOutcome::Rejected { .. })) => String::from("Rejected"),
+            SS::Final(Final(SwapOutcome::TimedOut { .. })) => String::from("TimedOut"),
             SS::Final(Final(SwapOutcome::AlphaRefunded { .. })) => String::from("AlphaRefunded"),
             SS::Final(Final(SwapOutcome::BothRefunded { .. })) => String::from("BothRefunded"),
             SS::Final(Final(SwapOutcome::BothRedeemed { .. })) => String::from("BothRedeemed"),
@@ -567,9 +1084,9 @@ impl<R: Role> SwapStates<R> {
     pub fn start_state(&self) -> Result<Start<R>, Error> {
         use self::SwapStates as SS;
         match *self {
-            SS::Start(ref start) | SS::Final(Final(SwapOutcome::Rejected { ref start, .. })) => {
-                Ok(start.clone())
-            }
+            SS::Start(ref start)
+            | SS::Final(Final(SwapOutcome::Rejected { ref start, .. }))
+            | SS::Final(Final(SwapOutcome::TimedOut { ref start })) => Ok(start.clone()),
             SS::Accepted(Accepted { ref swap, .. })
             | SS::AlphaDeployed(AlphaDeployed { ref swap, .. })
             | SS::AlphaFunded(AlphaFunded { ref swap, .. })
@@ -598,6 +1115,31 @@ impl<R: Role> SwapStates<R> {
         }
     }
 
+    pub fn ongoing_swap(&self) -> Option<&OngoingSwap<R>> {
+        use self::SwapStates as SS;
+        match *self {
+            SS::Accepted(Accepted { ref swap, .. })
+            | SS::AlphaDeployed(AlphaDeployed { ref swap, .. })
+            | SS::AlphaFunded(AlphaFunded { ref swap, .. })
+            | SS::AlphaFundedBetaDeployed(AlphaFundedBetaDeployed { ref swap, .. })
+            | SS::BothFunded(BothFunded { ref swap, .. })
+            | SS::AlphaFundedBetaRefunded(AlphaFundedBetaRefunded { ref swap, .. })
+            | SS::AlphaRefundedBetaFunded(AlphaRefundedBetaFunded { ref swap, .. })
+            | SS::AlphaFundedBetaRedeemed(AlphaFundedBetaRedeemed { ref swap, .. })
+            | SS::AlphaRedeemedBetaFunded(AlphaRedeemedBetaFunded { ref swap, .. }) => Some(swap),
+            SS::Start(_) | SS::Final(_) | SS::Error(_) => None,
+        }
+    }
+
+    pub fn rejection(&self) -> Option<&SwapReject> {
+        match *self {
+            SwapStates::Final(Final(SwapOutcome::Rejected {
+                ref rejection_type, ..
+            })) => Some(rejection_type),
+            _ => None,
+        }
+    }
+
     pub fn beta_ledger_lock_duration(&self) -> Option<<R::BetaLedger as Ledger>::LockDuration> {
         use self::SwapStates as SS;
         match *self {
@@ -1,5 +1,6 @@
 use crate::{
     comit_client::SwapReject,
+    ledger_query_service::Revert,
     swap_protocols::{
         ledger::{Bitcoin, Ethereum},
         rfc003::{
@@ -7,7 +8,7 @@ use crate::{
             events::{self, LedgerEvents},
             roles::test::{Alisha, Bobisha, FakeCommunicationEvents},
             state_machine::*,
-            Ledger, RedeemTransaction, Secret,
+            Ledger, RedeemTransaction, Secret, Timestamp,
         },
     },
 };
@@ -15,8 +16,9 @@ use bitcoin_support::{BitcoinQuantity, Blocks, OutPoint, Sha256dHash};
 use ethereum_support::EtherQuantity;
 use futures::{
     future::{self, Either},
+    stream,
     sync::mpsc,
-    Stream,
+    Async, Future, Stream,
 };
 use hex::FromHex;
 use std::{str::FromStr, sync::Arc};
@@ -26,6 +28,25 @@ struct FakeLedgerEvents<L: Ledger> {
     pub htlc_deployed: Option<Box<events::Deployed<L>>>,
     pub htlc_funded: Option<Box<events::Funded<L>>>,
     pub htlc_redeemed_or_refunded: Option<Box<events::RedeemedOrRefunded<L>>>,
+    pub htlc_expired: Option<Box<events::Expired>>,
+    pub htlc_funding_reverts: Option<Box<events::FundingReverts<L>>>,
+    pub htlc_redeemed_or_refunded_reverted: Option<Box<events::Reverted<L>>>,
+}
+
+impl<L: Ledger> FakeLedgerEvents<L> {
+    // Nothing happens on the ledger anymore after a fake reorganisation
+    fn forget_htlc(&mut self) {
+        self.htlc_deployed = Some(Box::new(future::empty()));
+        self.htlc_funded = Some(Box::new(future::empty()));
+        self.htlc_expired = None;
+        self.htlc_funding_reverts = None;
+        self.forget_htlc_redeemed_or_refunded();
+    }
+
+    fn forget_htlc_redeemed_or_refunded(&mut self) {
+        self.htlc_redeemed_or_refunded = Some(Box::new(future::empty()));
+        self.htlc_redeemed_or_refunded_reverted = None;
+    }
 }
 
 impl LedgerEvents<Bitcoin, BitcoinQuantity> for FakeLedgerEvents<Bitcoin> {
@@ -51,6 +72,41 @@ impl LedgerEvents<Bitcoin, BitcoinQuantity> for FakeLedgerEvents<Bitcoin> {
     ) -> &mut events::RedeemedOrRefunded<Bitcoin> {
         self.htlc_redeemed_or_refunded.as_mut().unwrap()
     }
+
+    fn htlc_expired(
+        &mut self,
+        _htlc_params: HtlcParams<Bitcoin, BitcoinQuantity>,
+        _htlc_deployed_at: Timestamp,
+    ) -> &mut events::Expired {
+        self.htlc_expired
+            .get_or_insert_with(|| Box::new(future::empty()))
+    }
+
+    fn htlc_funding_reverts(
+        &mut self,
+        _htlc_params: HtlcParams<Bitcoin, BitcoinQuantity>,
+        _htlc_location: &bitcoin_support::OutPoint,
+    ) -> &mut events::FundingReverts<Bitcoin> {
+        self.htlc_funding_reverts
+            .get_or_insert_with(|| Box::new(stream::empty()))
+    }
+
+    fn htlc_redeemed_or_refunded_reverted(
+        &mut self,
+        _htlc_params: HtlcParams<Bitcoin, BitcoinQuantity>,
+        _htlc_location: &bitcoin_support::OutPoint,
+    ) -> &mut events::Reverted<Bitcoin> {
+        self.htlc_redeemed_or_refunded_reverted
+            .get_or_insert_with(|| Box::new(future::empty()))
+    }
+
+    fn forget_htlc(&mut self) {
+        self.forget_htlc()
+    }
+
+    fn forget_htlc_redeemed_or_refunded(&mut self) {
+        self.forget_htlc_redeemed_or_refunded()
+    }
 }
 
 impl LedgerEvents<Ethereum, EtherQuantity> for FakeLedgerEvents<Ethereum> {
@@ -76,8 +132,42 @@ impl LedgerEvents<Ethereum, EtherQuantity> for FakeLedgerEvents<Ethereum> {
     ) -> &mut events::RedeemedOrRefunded<Ethereum> {
         unimplemented!()
     }
+
+    fn htlc_expired(
+        &mut self,
+        _htlc_params: HtlcParams<Ethereum, EtherQuantity>,
+        _htlc_deployed_at: Timestamp,
+    ) -> &mut events::Expired {
+        unimplemented!()
+    }
+
+    fn htlc_funding_reverts(
+        &mut self,
+        _htlc_params: HtlcParams<Ethereum, EtherQuantity>,
+        _htlc_location: &ethereum_support::Address,
+    ) -> &mut events::FundingReverts<Ethereum> {
+        unimplemented!()
+    }
+
+    fn htlc_redeemed_or_refunded_reverted(
+        &mut self,
+        _htlc_params: HtlcParams<Ethereum, EtherQuantity>,
+        _htlc_location: &ethereum_support::Address,
+    ) -> &mut events::Reverted<Ethereum> {
+        unimplemented!()
+    }
+
+    fn forget_htlc(&mut self) {
+        self.forget_htlc()
+    }
+
+    fn forget_htlc_redeemed_or_refunded(&mut self) {
+        self.forget_htlc_redeemed_or_refunded()
+    }
 }
 
+const DEPLOYED_AT: Timestamp = Timestamp(1_550_000_000);
+
 fn gen_start_state() -> Start<Alisha> {
     Start {
         alpha_ledger_refund_identity: secp256k1_support::KeyPair::from_secret_key_slice(
@@ -93,7 +183,7 @@ fn gen_start_state() -> Start<Alisha> {
         beta_ledger: Ethereum::default(),
         alpha_asset: BitcoinQuantity::from_bitcoin(1.0),
         beta_asset: EtherQuantity::from_eth(10.0),
-        alpha_ledger_lock_duration: Blocks::from(144),
+        alpha_ledger_lock_duration: Blocks::from(144).into(),
         secret: Secret::from(*b"hello world, you are beautiful!!"),
         role: Alisha::default(),
     }
@@ -150,7 +240,9 @@ fn when_swap_is_rejected_go_to_final_reject() {
     let (state_machine, states) = init!(
         Alisha,
         FakeCommunicationEvents::<Alisha> {
-            response: Some(Box::new(future::ok(Err(SwapReject::Rejected)))),
+            response: Some(Box::new(future::ok(Err(SwapReject::Rejected {
+                reason: None
+            })))),
         },
         start.clone().into(),
         FakeLedgerEvents {
@@ -187,10 +279,13 @@ fn alpha_refunded() {
         },
         start.clone().into(),
         FakeLedgerEvents::<Bitcoin> {
-            htlc_deployed: Some(Box::new(future::ok(OutPoint {
-                txid: Sha256dHash::from_data(b"funding"),
-                vout: 0,
-            }))),
+            htlc_deployed: Some(Box::new(future::ok((
+                OutPoint {
+                    txid: Sha256dHash::from_data(b"funding"),
+                    vout: 0,
+                },
+                DEPLOYED_AT,
+            )))),
             htlc_funded: Some(Box::new(future::ok(None))),
             htlc_redeemed_or_refunded: Some(Box::new(future::ok(Either::A(RedeemTransaction {
                 transaction: bitcoin_support::Transaction {
@@ -219,21 +314,24 @@ fn alpha_refunded() {
             alpha_htlc_location: OutPoint {
                 txid: Sha256dHash::from_data(b"funding"),
                 vout: 0
-            }
+            },
+            alpha_htlc_deployed_at: DEPLOYED_AT,
         },
         AlphaFunded {
             swap: OngoingSwap::new(start.clone(), bob_response.clone().into()),
             alpha_htlc_location: OutPoint {
                 txid: Sha256dHash::from_data(b"funding"),
                 vout: 0
-            }
+            },
+            alpha_htlc_deployed_at: DEPLOYED_AT,
+            alpha_htlc_expired: false,
         }
     );
 }
 
 #[test]
 fn bob_transition_alpha_refunded() {
-    let (bobisha, _) = Bobisha::create();
+    let (bobisha, _) = Bobisha::create(None);
     let start = Start {
         alpha_ledger_refund_identity: bitcoin_support::PubkeyHash::from_hex(
             "d38e554430c4035f2877a579a07a99886153f071",
@@ -247,7 +345,7 @@ fn bob_transition_alpha_refunded() {
         beta_ledger: Ethereum::default(),
         alpha_asset: BitcoinQuantity::from_bitcoin(1.0),
         beta_asset: EtherQuantity::from_eth(10.0),
-        alpha_ledger_lock_duration: Blocks::from(144),
+        alpha_ledger_lock_duration: Blocks::from(144).into(),
         secret: Secret::from(*b"hello world, you are beautiful!!").hash(),
         role: bobisha,
     };
@@ -272,10 +370,13 @@ fn bob_transition_alpha_refunded() {
         },
         start.clone().into(),
         FakeLedgerEvents::<Bitcoin> {
-            htlc_deployed: Some(Box::new(future::ok(OutPoint {
-                txid: Sha256dHash::from_data(b"funding"),
-                vout: 0,
-            }))),
+            htlc_deployed: Some(Box::new(future::ok((
+                OutPoint {
+                    txid: Sha256dHash::from_data(b"funding"),
+                    vout: 0,
+                },
+                DEPLOYED_AT,
+            )))),
             htlc_funded: Some(Box::new(future::ok(None))),
             htlc_redeemed_or_refunded: Some(Box::new(future::ok(Either::A(RedeemTransaction {
                 transaction: bitcoin_support::Transaction {
@@ -304,14 +405,238 @@ fn bob_transition_alpha_refunded() {
             alpha_htlc_location: OutPoint {
                 txid: Sha256dHash::from_data(b"funding"),
                 vout: 0
-            }
+            },
+            alpha_htlc_deployed_at: DEPLOYED_AT,
         },
         AlphaFunded {
             swap: OngoingSwap::new(start.clone(), response.clone().into()),
             alpha_htlc_location: OutPoint {
                 txid: Sha256dHash::from_data(b"funding"),
                 vout: 0
-            }
+            },
+            alpha_htlc_deployed_at: DEPLOYED_AT,
+            alpha_htlc_expired: false,
         }
     );
 }
+
+#[test]
+fn alpha_htlc_expiry_is_recorded_in_state() {
+    let bob_response = StateMachineResponse {
+        beta_ledger_refund_identity: ethereum_support::Address::from_str(
+            "71b9f69dcabb340a3fe229c3f94f1662ad85e5e8",
+        )
+        .unwrap(),
+        alpha_ledger_redeem_identity: bitcoin_support::PubkeyHash::from_hex(
+            "d38e554430c4035f2877a579a07a99886153f071",
+        )
+        .unwrap(),
+        beta_ledger_lock_duration: Seconds(42),
+    };
+    let alpha_htlc_location = OutPoint {
+        txid: Sha256dHash::from_data(b"funding"),
+        vout: 0,
+    };
+
+    let start = gen_start_state();
+
+    let (state_machine, states) = init!(
+        Alisha,
+        FakeCommunicationEvents::<Alisha> {
+            response: Some(Box::new(future::ok(Ok(bob_response.clone())))),
+        },
+        start.clone().into(),
+        FakeLedgerEvents::<Bitcoin> {
+            htlc_deployed: Some(Box::new(future::ok((alpha_htlc_location, DEPLOYED_AT)))),
+            htlc_funded: Some(Box::new(future::ok(None))),
+            htlc_redeemed_or_refunded: Some(Box::new(future::empty())),
+            htlc_expired: Some(Box::new(future::ok(()))),
+            ..Default::default()
+        },
+        FakeLedgerEvents::<Ethereum> {
+            htlc_deployed: Some(Box::new(future::empty())),
+            ..Default::default()
+        }
+    );
+
+    // Bob never deploys, so the swap doesn't finish
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(state_machine.map(|_| ()).map_err(|_| ()));
+    let actual_states: Vec<SwapStates<Alisha>> =
+        runtime.block_on(states.take(4).collect()).unwrap();
+
+    let swap = OngoingSwap::new(start.clone(), bob_response.clone().into());
+    assert_eq!(
+        actual_states,
+        vec![
+            SwapStates::from(Accepted { swap: swap.clone() }),
+            SwapStates::from(AlphaDeployed {
+                swap: swap.clone(),
+                alpha_htlc_location,
+                alpha_htlc_deployed_at: DEPLOYED_AT,
+            }),
+            SwapStates::from(AlphaFunded {
+                swap: swap.clone(),
+                alpha_htlc_location,
+                alpha_htlc_deployed_at: DEPLOYED_AT,
+                alpha_htlc_expired: false,
+            }),
+            SwapStates::from(AlphaFunded {
+                swap,
+                alpha_htlc_location,
+                alpha_htlc_deployed_at: DEPLOYED_AT,
+                alpha_htlc_expired: true,
+            }),
+        ]
+    );
+}
+
+#[test]
+fn reverted_alpha_funding_keeps_the_state_until_it_is_replaced() {
+    let bob_response = StateMachineResponse {
+        beta_ledger_refund_identity: ethereum_support::Address::from_str(
+            "71b9f69dcabb340a3fe229c3f94f1662ad85e5e8",
+        )
+        .unwrap(),
+        alpha_ledger_redeem_identity: bitcoin_support::PubkeyHash::from_hex(
+            "d38e554430c4035f2877a579a07a99886153f071",
+        )
+        .unwrap(),
+        beta_ledger_lock_duration: Seconds(42),
+    };
+    let alpha_htlc_location = OutPoint {
+        txid: Sha256dHash::from_data(b"funding"),
+        vout: 0,
+    };
+
+    let start = gen_start_state();
+
+    let (state_machine, states) = init!(
+        Alisha,
+        FakeCommunicationEvents::<Alisha> {
+            response: Some(Box::new(future::ok(Ok(bob_response.clone())))),
+        },
+        start.clone().into(),
+        FakeLedgerEvents::<Bitcoin> {
+            htlc_deployed: Some(Box::new(future::ok((alpha_htlc_location, DEPLOYED_AT)))),
+            htlc_funded: Some(Box::new(future::ok(None))),
+            htlc_redeemed_or_refunded: Some(Box::new(future::empty())),
+            htlc_expired: Some(Box::new(future::ok(()))),
+            htlc_funding_reverts: Some(Box::new(
+                stream::iter_ok(vec![
+                    Revert::Invalidated(alpha_htlc_location.txid),
+                    Revert::Reconfirmed(alpha_htlc_location.txid),
+                    Revert::Invalidated(alpha_htlc_location.txid),
+                ])
+                .chain(stream::poll_fn(|| Ok(Async::NotReady))),
+            )),
+            ..Default::default()
+        },
+        FakeLedgerEvents::<Ethereum> {
+            htlc_deployed: Some(Box::new(future::empty())),
+            ..Default::default()
+        }
+    );
+
+    // The funding transaction is neither mined again nor replaced, so the swap
+    // doesn't finish
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(state_machine.map(|_| ()).map_err(|_| ()));
+    let actual_states: Vec<SwapStates<Alisha>> =
+        runtime.block_on(states.take(4).collect()).unwrap();
+
+    let swap = OngoingSwap::new(start.clone(), bob_response.clone().into());
+    assert_eq!(
+        actual_states,
+        vec![
+            SwapStates::from(Accepted { swap: swap.clone() }),
+            SwapStates::from(AlphaDeployed {
+                swap: swap.clone(),
+                alpha_htlc_location,
+                alpha_htlc_deployed_at: DEPLOYED_AT,
+            }),
+            SwapStates::from(AlphaFunded {
+                swap: swap.clone(),
+                alpha_htlc_location,
+                alpha_htlc_deployed_at: DEPLOYED_AT,
+                alpha_htlc_expired: false,
+            }),
+            SwapStates::from(AlphaFunded {
+                swap,
+                alpha_htlc_location,
+                alpha_htlc_deployed_at: DEPLOYED_AT,
+                alpha_htlc_expired: true,
+            }),
+        ]
+    );
+}
+
+#[test]
+fn replaced_alpha_funding_goes_back_to_accepted() {
+    let bob_response = StateMachineResponse {
+        beta_ledger_refund_identity: ethereum_support::Address::from_str(
+            "71b9f69dcabb340a3fe229c3f94f1662ad85e5e8",
+        )
+        .unwrap(),
+        alpha_ledger_redeem_identity: bitcoin_support::PubkeyHash::from_hex(
+            "d38e554430c4035f2877a579a07a99886153f071",
+        )
+        .unwrap(),
+        beta_ledger_lock_duration: Seconds(42),
+    };
+    let alpha_htlc_location = OutPoint {
+        txid: Sha256dHash::from_data(b"funding"),
+        vout: 0,
+    };
+
+    let start = gen_start_state();
+
+    let (state_machine, states) = init!(
+        Alisha,
+        FakeCommunicationEvents::<Alisha> {
+            response: Some(Box::new(future::ok(Ok(bob_response.clone())))),
+        },
+        start.clone().into(),
+        FakeLedgerEvents::<Bitcoin> {
+            htlc_deployed: Some(Box::new(future::ok((alpha_htlc_location, DEPLOYED_AT)))),
+            htlc_funded: Some(Box::new(future::ok(None))),
+            htlc_redeemed_or_refunded: Some(Box::new(future::empty())),
+            htlc_funding_reverts: Some(Box::new(stream::iter_ok(vec![
+                Revert::Invalidated(alpha_htlc_location.txid),
+                Revert::Replaced(alpha_htlc_location.txid),
+            ]))),
+            ..Default::default()
+        },
+        FakeLedgerEvents::<Ethereum> {
+            htlc_deployed: Some(Box::new(future::empty())),
+            ..Default::default()
+        }
+    );
+
+    // The replacing funding transaction is not seen yet, so the swap doesn't
+    // finish
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(state_machine.map(|_| ()).map_err(|_| ()));
+    let actual_states: Vec<SwapStates<Alisha>> =
+        runtime.block_on(states.take(4).collect()).unwrap();
+
+    let swap = OngoingSwap::new(start.clone(), bob_response.clone().into());
+    assert_eq!(
+        actual_states,
+        vec![
+            SwapStates::from(Accepted { swap: swap.clone() }),
+            SwapStates::from(AlphaDeployed {
+                swap: swap.clone(),
+                alpha_htlc_location,
+                alpha_htlc_deployed_at: DEPLOYED_AT,
+            }),
+            SwapStates::from(AlphaFunded {
+                swap: swap.clone(),
+                alpha_htlc_location,
+                alpha_htlc_deployed_at: DEPLOYED_AT,
+                alpha_htlc_expired: false,
+            }),
+            SwapStates::from(Accepted { swap }),
+        ]
+    );
+}
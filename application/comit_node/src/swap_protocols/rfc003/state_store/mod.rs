@@ -0,0 +1,269 @@
+mod persist;
+mod sqlite;
+
+pub use self::{
+    persist::{persisted, restore_with, Persist, RestoreError},
+    sqlite::SqliteStateStore,
+};
+
+use crate::swap_protocols::rfc003::{roles::Role, state_machine::SwapStates, SaveState};
+use futures::sync::mpsc;
+use std::{
+    any::Any,
+    collections::HashMap,
+    fmt::{self, Debug},
+    hash::Hash,
+    sync::{Arc, Mutex, RwLock},
+};
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "State already exists for given key")]
+    DuplicateKey,
+    #[fail(display = "Failed to access the state database: {}", _0)]
+    Database(rusqlite::Error),
+    #[fail(display = "Failed to (de)serialize a swap state: {}", _0)]
+    Serialization(serde_json::Error),
+    #[fail(display = "Swap states without a start state cannot be persisted")]
+    NotPersistable,
+    #[fail(display = "Stored swap record is invalid: {}", _0)]
+    InvalidRecord(String),
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Self {
+        Error::Database(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Serialization(e)
+    }
+}
+
+pub trait StateStore<K>: Send + Sync + 'static {
+    fn insert<R: Role>(&self, key: K, state: SwapStates<R>)
+        -> Result<Arc<dyn SaveState<R>>, Error>;
+
+    fn get<R: Role>(&self, key: &K) -> Result<Option<SwapStates<R>>, Error>;
+
+    #[allow(clippy::type_complexity)]
+    fn save_state_for_key<R: Role>(&self, key: &K) -> Result<Option<Arc<dyn SaveState<R>>>, Error>;
+
+    /// Yields every state that is inserted or saved, in the order it
+    /// happened
+    fn subscribe(&self) -> mpsc::UnboundedReceiver<StateUpdate<K>>;
+}
+
+/// A state as it was inserted into or saved to a state store
+#[derive(Clone)]
+pub struct StateUpdate<K> {
+    pub key: K,
+    state: Arc<dyn Any + Send + Sync>,
+}
+
+impl<K> StateUpdate<K> {
+    pub fn new<R: Role>(key: K, state: SwapStates<R>) -> Self {
+        StateUpdate {
+            key,
+            state: Arc::new(state),
+        }
+    }
+
+    /// The state or `None` if the swap doesn't have the role `R`
+    pub fn state<R: Role>(&self) -> Option<&SwapStates<R>> {
+        self.state.downcast_ref()
+    }
+}
+
+// The state contains the secret, which has no business in the logs
+impl<K: Debug> Debug for StateUpdate<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StateUpdate")
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+type Subscribers<K> = Arc<Mutex<Vec<mpsc::UnboundedSender<StateUpdate<K>>>>>;
+
+fn notify<K: Clone>(subscribers: &Subscribers<K>, update: StateUpdate<K>) {
+    let mut subscribers = subscribers.lock().unwrap();
+    subscribers.retain(|subscriber| subscriber.unbounded_send(update.clone()).is_ok());
+}
+
+#[derive(Default, Debug)]
+pub struct InMemoryStateStore<K: Hash + Eq> {
+    states: Mutex<HashMap<K, Box<dyn Any + Send + Sync>>>,
+    subscribers: Subscribers<K>,
+}
+
+/// Tells the subscribers of the store about every state saved through
+/// `inner`
+#[allow(missing_debug_implementations)]
+struct NotifySubscribers<K, R: Role> {
+    key: K,
+    inner: Arc<RwLock<SwapStates<R>>>,
+    subscribers: Subscribers<K>,
+}
+
+impl<K: Clone + Send + Sync, R: Role> SaveState<R> for NotifySubscribers<K, R> {
+    fn save(&self, state: SwapStates<R>) -> Result<(), Error> {
+        let update = StateUpdate::new(self.key.clone(), state.clone());
+        self.inner.save(state)?;
+        notify(&self.subscribers, update);
+
+        Ok(())
+    }
+}
+
+impl<K: Hash + Eq + Clone + Send + Sync + 'static> StateStore<K> for InMemoryStateStore<K> {
+    fn insert<R: Role>(
+        &self,
+        key: K,
+        state: SwapStates<R>,
+    ) -> Result<Arc<dyn SaveState<R>>, Error> {
+        let mut states = self.states.lock().unwrap();
+
+        if states.contains_key(&key) {
+            return Err(Error::DuplicateKey);
+        }
+
+        let update = StateUpdate::new(key.clone(), state.clone());
+        let state = Arc::new(RwLock::new(state));
+
+        let value: Box<dyn Any + Send + Sync> = Box::new(state.clone());
+        let _old = states.insert(key.clone(), value);
+        notify(&self.subscribers, update);
+
+        Ok(Arc::new(NotifySubscribers {
+            key,
+            inner: state,
+            subscribers: Arc::clone(&self.subscribers),
+        }))
+    }
+
+    fn get<R: Role>(&self, key: &K) -> Result<Option<SwapStates<R>>, Error> {
+        let states = self.states.lock().unwrap();
+        Ok(states.get(key).map(|state| {
+            let state = state.downcast_ref::<Arc<RwLock<SwapStates<R>>>>().unwrap();
+            let state = state.read().unwrap();
+            state.clone()
+        }))
+    }
+
+    fn save_state_for_key<R: Role>(&self, key: &K) -> Result<Option<Arc<dyn SaveState<R>>>, Error> {
+        let states = self.states.lock().unwrap();
+        Ok(states.get(key).map(|state| -> Arc<dyn SaveState<R>> {
+            let state = state.downcast_ref::<Arc<RwLock<SwapStates<R>>>>().unwrap();
+            Arc::new(NotifySubscribers {
+                key: key.clone(),
+                inner: state.clone(),
+                subscribers: Arc::clone(&self.subscribers),
+            })
+        }))
+    }
+
+    fn subscribe(&self) -> mpsc::UnboundedReceiver<StateUpdate<K>> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swap_protocols::{
+        ledger::{Bitcoin, Ethereum},
+        rfc003::{roles::test::Alisha, state_machine::Start, Secret},
+    };
+    use bitcoin_support::{BitcoinQuantity, Blocks};
+    use ethereum_support::EtherQuantity;
+    use futures::Stream;
+    use spectral::prelude::*;
+
+    #[test]
+    fn store_get_and_save_state() {
+        let state_store = InMemoryStateStore::default();
+        let start_state = Start::<Alisha> {
+            alpha_ledger_refund_identity: secp256k1_support::KeyPair::from_secret_key_slice(
+                &hex::decode("18e14a7b6a307f426a94f8114701e7c8e774e7f9a47e2c2035db29a206321725")
+                    .unwrap(),
+            )
+            .unwrap(),
+            beta_ledger_redeem_identity: "8457037fcd80a8650c4692d7fcfc1d0a96b92867"
+                .parse()
+                .unwrap(),
+            alpha_ledger: Bitcoin::default(),
+            beta_ledger: Ethereum::default(),
+            alpha_asset: BitcoinQuantity::from_bitcoin(1.0),
+            beta_asset: EtherQuantity::from_eth(10.0),
+            alpha_ledger_lock_duration: Blocks::from(144).into(),
+            secret: Secret::from(*b"hello world, you are beautiful!!"),
+            role: Alisha::default(),
+        };
+
+        let state = SwapStates::from(start_state.clone());
+        let id = 1;
+
+        let res = state_store.insert(id, state.clone());
+        assert!(res.is_ok());
+
+        let res = state_store.get(&id).unwrap();
+        assert_that(&res).contains_value(state);
+
+        let save_state = state_store.save_state_for_key(&id).unwrap().unwrap();
+
+        let second_state = SwapStates::from(Start {
+            secret: Secret::from(*b"!!lufituaeb era uoy ,dlrow olleh"),
+            ..start_state
+        });
+
+        save_state.save(second_state.clone()).unwrap();
+
+        let res = state_store.get(&id).unwrap();
+        assert_that(&res).contains_value(second_state)
+    }
+
+    #[test]
+    fn subscribers_are_told_about_inserted_and_saved_states() {
+        let state_store = InMemoryStateStore::default();
+        let start_state = Start::<Alisha> {
+            alpha_ledger_refund_identity: secp256k1_support::KeyPair::from_secret_key_slice(
+                &hex::decode("18e14a7b6a307f426a94f8114701e7c8e774e7f9a47e2c2035db29a206321725")
+                    .unwrap(),
+            )
+            .unwrap(),
+            beta_ledger_redeem_identity: "8457037fcd80a8650c4692d7fcfc1d0a96b92867"
+                .parse()
+                .unwrap(),
+            alpha_ledger: Bitcoin::default(),
+            beta_ledger: Ethereum::default(),
+            alpha_asset: BitcoinQuantity::from_bitcoin(1.0),
+            beta_asset: EtherQuantity::from_eth(10.0),
+            alpha_ledger_lock_duration: Blocks::from(144).into(),
+            secret: Secret::from(*b"hello world, you are beautiful!!"),
+            role: Alisha::default(),
+        };
+        let first_state = SwapStates::from(start_state.clone());
+        let second_state = SwapStates::from(Start {
+            secret: Secret::from(*b"!!lufituaeb era uoy ,dlrow olleh"),
+            ..start_state
+        });
+
+        let updates = state_store.subscribe();
+        let save_state = state_store.insert(1, first_state.clone()).unwrap();
+        save_state.save(second_state.clone()).unwrap();
+        drop(state_store);
+        drop(save_state);
+
+        let updates: Vec<(u32, Option<SwapStates<Alisha>>)> = updates
+            .wait()
+            .map(Result::unwrap)
+            .map(|update| (update.key, update.state::<Alisha>().cloned()))
+            .collect();
+        assert_that(&updates).is_equal_to(vec![(1, Some(first_state)), (1, Some(second_state))]);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::swap_protocols::{
+    rfc003::{Secret, SecretHash, SecretSource},
+    SwapId,
+};
+use bitcoin_support::PubkeyHash;
+use ethereum_support::Address;
+use secp256k1_support::{KeyPair, PublicKey};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{cell::RefCell, sync::Arc};
+
+/// How a value of a swap state is written to the state store
+///
+/// Secret keys and the secret are never written, only their public
+/// counterparts. They are derived again from the seed when the state is
+/// loaded, see `restore_with`.
+pub trait Persist: Sized {
+    type Persisted: Serialize + DeserializeOwned;
+
+    fn to_persisted(&self) -> Self::Persisted;
+    fn from_persisted(persisted: Self::Persisted) -> Result<Self, RestoreError>;
+}
+
+#[derive(Debug, Fail, PartialEq)]
+pub enum RestoreError {
+    #[fail(display = "Secrets can only be restored while loading a swap")]
+    NoSecretSource,
+    #[fail(display = "The stored public data was not derived from this node's seed")]
+    NotDerivedFromSeed,
+}
+
+thread_local! {
+    static SWAP_SECRETS: RefCell<Option<(Arc<dyn SecretSource>, SwapId)>> = RefCell::new(None);
+}
+
+/// Runs `f` with the secrets of swap `id` available to
+/// `Persist::from_persisted`
+pub fn restore_with<T, F: FnOnce() -> T>(
+    secret_source: &Arc<dyn SecretSource>,
+    id: SwapId,
+    f: F,
+) -> T {
+    SWAP_SECRETS.with(|secrets| {
+        let previous = secrets.replace(Some((Arc::clone(secret_source), id)));
+        let result = f();
+        secrets.replace(previous);
+        result
+    })
+}
+
+fn with_swap_secrets<T, F: FnOnce(&dyn SecretSource, SwapId) -> Option<T>>(
+    f: F,
+) -> Result<T, RestoreError> {
+    SWAP_SECRETS.with(|secrets| match *secrets.borrow() {
+        Some((ref secret_source, id)) => {
+            f(secret_source.as_ref(), id).ok_or(RestoreError::NotDerivedFromSeed)
+        }
+        None => Err(RestoreError::NoSecretSource),
+    })
+}
+
+impl Persist for KeyPair {
+    type Persisted = PublicKey;
+
+    fn to_persisted(&self) -> PublicKey {
+        self.public_key()
+    }
+
+    fn from_persisted(public_key: PublicKey) -> Result<Self, RestoreError> {
+        with_swap_secrets(|secret_source, id| {
+            vec![
+                secret_source.new_secp256k1_refund(id),
+                secret_source.new_secp256k1_redeem(id),
+            ]
+            .into_iter()
+            .find(|key_pair| key_pair.public_key() == public_key)
+        })
+    }
+}
+
+impl Persist for Secret {
+    type Persisted = SecretHash;
+
+    fn to_persisted(&self) -> SecretHash {
+        self.hash()
+    }
+
+    fn from_persisted(secret_hash: SecretHash) -> Result<Self, RestoreError> {
+        with_swap_secrets(|secret_source, id| {
+            Some(secret_source.new_secret(id)).filter(|secret| secret.hash() == secret_hash)
+        })
+    }
+}
+
+macro_rules! persist_public {
+    ($($type:ty),*) => {
+        $(
+            impl Persist for $type {
+                type Persisted = Self;
+
+                fn to_persisted(&self) -> Self {
+                    self.clone()
+                }
+
+                fn from_persisted(persisted: Self) -> Result<Self, RestoreError> {
+                    Ok(persisted)
+                }
+            }
+        )*
+    };
+}
+
+persist_public!(Address, PubkeyHash, SecretHash);
+
+/// For `#[serde(with = "persisted")]` on fields that hold a `Persist` value
+pub mod persisted {
+    use super::Persist;
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<T: Persist, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value.to_persisted().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T: Persist, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        T::from_persisted(T::Persisted::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seed::Seed;
+    use spectral::prelude::*;
+
+    fn secret_source() -> Arc<dyn SecretSource> {
+        Arc::new(Seed::from(*b"hello world, you are beautiful!!"))
+    }
+
+    #[test]
+    fn key_pairs_are_restored_from_their_public_key() {
+        let secret_source = secret_source();
+        let id = SwapId::default();
+        let refund = secret_source.new_secp256k1_refund(id);
+
+        let restored = restore_with(&secret_source, id, || {
+            KeyPair::from_persisted(refund.to_persisted())
+        });
+
+        assert_that(&restored).is_ok().is_equal_to(refund);
+    }
+
+    #[test]
+    fn secrets_of_other_swaps_are_not_restored() {
+        let secret_source = secret_source();
+        let secret = secret_source.new_secret(SwapId::default());
+
+        let restored = restore_with(&secret_source, SwapId::default(), || {
+            Secret::from_persisted(secret.to_persisted())
+        });
+
+        assert_that(&restored).is_err_containing(RestoreError::NotDerivedFromSeed);
+    }
+
+    #[test]
+    fn secrets_are_not_restored_without_a_secret_source() {
+        let secret = secret_source().new_secret(SwapId::default());
+
+        assert_that(&Secret::from_persisted(secret.to_persisted()))
+            .is_err_containing(RestoreError::NoSecretSource);
+    }
+}
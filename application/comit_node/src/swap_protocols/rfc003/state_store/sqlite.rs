@@ -0,0 +1,516 @@
+use crate::swap_protocols::{
+    metadata_store::Metadata,
+    rfc003::{
+        roles::Role,
+        state_machine::SwapStates,
+        state_store::{restore_with, Error, InMemoryStateStore, StateStore, StateUpdate},
+        SaveState, SecretSource,
+    },
+    SwapId,
+};
+use futures::sync::mpsc;
+use rusqlite::{types::ToSql, Connection, OptionalExtension, NO_PARAMS};
+use std::{
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS rfc003_swap_states (
+    swap_id TEXT PRIMARY KEY NOT NULL,
+    role TEXT NOT NULL,
+    alpha_ledger TEXT NOT NULL,
+    beta_ledger TEXT NOT NULL,
+    alpha_asset TEXT NOT NULL,
+    beta_asset TEXT NOT NULL,
+    state_name TEXT NOT NULL,
+    state TEXT NOT NULL
+)";
+
+/// Keeps the live states in memory and writes every state to SQLite, so
+/// that swaps can be loaded again after a restart.
+///
+/// Secret keys and secrets are not written, they are derived again from
+/// `secret_source` when a state is loaded.
+#[derive(DebugStub)]
+pub struct SqliteStateStore {
+    #[debug_stub = "Connection"]
+    connection: Arc<Mutex<Connection>>,
+    #[debug_stub = "SecretSource"]
+    secret_source: Arc<dyn SecretSource>,
+    states: InMemoryStateStore<SwapId>,
+}
+
+impl SqliteStateStore {
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        secret_source: Arc<dyn SecretSource>,
+    ) -> Result<Self, Error> {
+        Self::new(Connection::open(path)?, secret_source)
+    }
+
+    pub fn open_in_memory(secret_source: Arc<dyn SecretSource>) -> Result<Self, Error> {
+        Self::new(Connection::open_in_memory()?, secret_source)
+    }
+
+    fn new(connection: Connection, secret_source: Arc<dyn SecretSource>) -> Result<Self, Error> {
+        // The metadata store shares the database file
+        connection.busy_timeout(Duration::from_secs(5))?;
+        connection.execute(CREATE_TABLE, NO_PARAMS)?;
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+            secret_source,
+            states: InMemoryStateStore::default(),
+        })
+    }
+
+    pub fn swaps(&self) -> Result<Vec<(SwapId, Metadata)>, Error> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(
+            "SELECT swap_id, role, alpha_ledger, beta_ledger, alpha_asset, beta_asset
+                FROM rfc003_swap_states",
+        )?;
+
+        let rows = statement.query_map(NO_PARAMS, |row| {
+            (
+                row.get::<_, String>(0),
+                row.get::<_, String>(1),
+                row.get::<_, String>(2),
+                row.get::<_, String>(3),
+                row.get::<_, String>(4),
+                row.get::<_, String>(5),
+            )
+        })?;
+
+        let mut swaps = Vec::new();
+        for row in rows {
+            let (swap_id, role, alpha_ledger, beta_ledger, alpha_asset, beta_asset) = row?;
+
+            let swap_id =
+                SwapId::from_str(&swap_id).map_err(|e| Error::InvalidRecord(format!("{:?}", e)))?;
+            let metadata = Metadata::from_kind_names(
+                &alpha_ledger,
+                &beta_ledger,
+                &alpha_asset,
+                &beta_asset,
+                &role,
+            )
+            .map_err(|e| Error::InvalidRecord(e.to_string()))?;
+
+            swaps.push((swap_id, metadata));
+        }
+
+        Ok(swaps)
+    }
+}
+
+impl StateStore<SwapId> for SqliteStateStore {
+    fn insert<R: Role>(
+        &self,
+        key: SwapId,
+        state: SwapStates<R>,
+    ) -> Result<Arc<dyn SaveState<R>>, Error> {
+        persist(&self.connection, &key, &state)?;
+        let in_memory = self.states.insert(key, state)?;
+
+        Ok(Arc::new(PersistentSaveState {
+            key,
+            connection: Arc::clone(&self.connection),
+            in_memory,
+        }))
+    }
+
+    fn get<R: Role>(&self, key: &SwapId) -> Result<Option<SwapStates<R>>, Error> {
+        if let Some(state) = self.states.get(key)? {
+            return Ok(Some(state));
+        }
+
+        let connection = self.connection.lock().unwrap();
+        let state = connection
+            .query_row(
+                "SELECT state FROM rfc003_swap_states WHERE swap_id = ?1",
+                &[&key.to_string() as &dyn ToSql],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+
+        match state {
+            Some(state) => Ok(Some(restore_with(&self.secret_source, *key, || {
+                serde_json::from_str(&state)
+            })?)),
+            None => Ok(None),
+        }
+    }
+
+    fn save_state_for_key<R: Role>(
+        &self,
+        key: &SwapId,
+    ) -> Result<Option<Arc<dyn SaveState<R>>>, Error> {
+        Ok(self
+            .states
+            .save_state_for_key(key)?
+            .map(|in_memory| -> Arc<dyn SaveState<R>> {
+                Arc::new(PersistentSaveState {
+                    key: *key,
+                    connection: Arc::clone(&self.connection),
+                    in_memory,
+                })
+            }))
+    }
+
+    fn subscribe(&self) -> mpsc::UnboundedReceiver<StateUpdate<SwapId>> {
+        self.states.subscribe()
+    }
+}
+
+#[allow(missing_debug_implementations)]
+struct PersistentSaveState<R: Role> {
+    key: SwapId,
+    connection: Arc<Mutex<Connection>>,
+    in_memory: Arc<dyn SaveState<R>>,
+}
+
+impl<R: Role> SaveState<R> for PersistentSaveState<R> {
+    fn save(&self, state: SwapStates<R>) -> Result<(), Error> {
+        persist(&self.connection, &self.key, &state)?;
+        self.in_memory.save(state)
+    }
+}
+
+fn persist<R: Role>(
+    connection: &Mutex<Connection>,
+    key: &SwapId,
+    state: &SwapStates<R>,
+) -> Result<(), Error> {
+    let metadata = Metadata::from(state.start_state().map_err(|_| Error::NotPersistable)?);
+    let serialized_state = serde_json::to_string(state)?;
+
+    let connection = connection.lock().unwrap();
+    connection.execute(
+        "INSERT OR REPLACE INTO rfc003_swap_states
+            (swap_id, role, alpha_ledger, beta_ledger, alpha_asset, beta_asset, state_name, state)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        &[
+            &key.to_string() as &dyn ToSql,
+            &metadata.role.to_string(),
+            &metadata.alpha_ledger.to_string(),
+            &metadata.beta_ledger.to_string(),
+            &metadata.alpha_asset.to_string(),
+            &metadata.beta_asset.to_string(),
+            &state.name(),
+            &serialized_state,
+        ],
+    )?;
+    trace!("Persisted state {} of swap {}", state.name(), key);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        seed::Seed,
+        swap_protocols::{
+            asset::Asset,
+            ledger::{Bitcoin, Ethereum},
+            metadata_store::{AssetKind, LedgerKind, RoleKind},
+            rfc003::{
+                alice::{SwapRequest, SwapRequestIdentities},
+                ethereum::Seconds,
+                roles::{test::Alisha, Bob},
+                state_machine::{Accepted, OngoingSwap, Start, StateMachineResponse},
+                Ledger,
+            },
+        },
+    };
+    use bitcoin_support::{BitcoinQuantity, Blocks};
+    use ethereum_support::{Erc20Quantity, EtherQuantity, U256};
+    use spectral::prelude::*;
+
+    fn secret_source() -> Arc<dyn SecretSource> {
+        Arc::new(Seed::from(*b"hello world, you are beautiful!!"))
+    }
+
+    fn start_state(secret_source: &dyn SecretSource, id: SwapId) -> Start<Alisha> {
+        Start {
+            alpha_ledger_refund_identity: secret_source.new_secp256k1_refund(id),
+            beta_ledger_redeem_identity: "8457037fcd80a8650c4692d7fcfc1d0a96b92867"
+                .parse()
+                .unwrap(),
+            alpha_ledger: Bitcoin::default(),
+            beta_ledger: Ethereum::default(),
+            alpha_asset: BitcoinQuantity::from_bitcoin(1.0),
+            beta_asset: EtherQuantity::from_eth(10.0),
+            alpha_ledger_lock_duration: Blocks::from(144).into(),
+            secret: secret_source.new_secret(id),
+            role: Alisha::default(),
+        }
+    }
+
+    #[test]
+    fn saved_state_survives_reopening_the_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("comit_node.sqlite");
+        let id = SwapId::default();
+        let start_state = start_state(secret_source().as_ref(), id);
+
+        {
+            let state_store = SqliteStateStore::open(&path, secret_source()).unwrap();
+            let save_state = state_store
+                .insert(id, SwapStates::from(start_state.clone()))
+                .unwrap();
+
+            save_state
+                .save(SwapStates::from(Start {
+                    alpha_asset: BitcoinQuantity::from_bitcoin(2.0),
+                    ..start_state.clone()
+                }))
+                .unwrap();
+        }
+
+        let state_store = SqliteStateStore::open(&path, secret_source()).unwrap();
+        let res = state_store.get::<Alisha>(&id).unwrap();
+
+        assert_that(&res).contains_value(SwapStates::from(Start {
+            alpha_asset: BitcoinQuantity::from_bitcoin(2.0),
+            ..start_state
+        }));
+    }
+
+    #[test]
+    fn secret_keys_and_secrets_are_not_written_to_the_database() {
+        let state_store = SqliteStateStore::open_in_memory(secret_source()).unwrap();
+        let id = SwapId::default();
+        let start_state = start_state(secret_source().as_ref(), id);
+
+        state_store
+            .insert(id, SwapStates::from(start_state.clone()))
+            .unwrap();
+
+        let connection = state_store.connection.lock().unwrap();
+        let stored = connection
+            .query_row(
+                "SELECT state FROM rfc003_swap_states WHERE swap_id = ?1",
+                &[&id.to_string() as &dyn ToSql],
+                |row| row.get::<_, String>(0),
+            )
+            .unwrap();
+
+        let secret_key = hex::encode(&start_state.alpha_ledger_refund_identity.secret_key()[..]);
+        let secret = hex::encode(start_state.secret.raw_secret());
+        assert_that(&stored.contains(&secret_key)).is_false();
+        assert_that(&stored.contains(&secret)).is_false();
+    }
+
+    #[test]
+    fn states_of_another_seed_cannot_be_loaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("comit_node.sqlite");
+        let id = SwapId::default();
+
+        SqliteStateStore::open(&path, secret_source())
+            .unwrap()
+            .insert(
+                id,
+                SwapStates::from(start_state(secret_source().as_ref(), id)),
+            )
+            .unwrap();
+
+        let other_seed = Arc::new(Seed::from(*b"bye world, you are beautiful!!!!"));
+        let state_store = SqliteStateStore::open(&path, other_seed).unwrap();
+
+        assert_that(&state_store.get::<Alisha>(&id)).is_err();
+    }
+
+    #[test]
+    fn lists_metadata_of_stored_swaps() {
+        let state_store = SqliteStateStore::open_in_memory(secret_source()).unwrap();
+        let id = SwapId::default();
+
+        state_store
+            .insert(
+                id,
+                SwapStates::from(start_state(secret_source().as_ref(), id)),
+            )
+            .unwrap();
+
+        let swaps = state_store.swaps().unwrap();
+
+        assert_that(&swaps).is_equal_to(vec![(
+            id,
+            Metadata {
+                alpha_ledger: LedgerKind::Bitcoin,
+                beta_ledger: LedgerKind::Ethereum,
+                alpha_asset: AssetKind::Bitcoin,
+                beta_asset: AssetKind::Ether,
+                role: RoleKind::Alice,
+            },
+        )]);
+    }
+
+    #[test]
+    fn unknown_swap_returns_none() {
+        let state_store = SqliteStateStore::open_in_memory(secret_source()).unwrap();
+
+        let res = state_store.get::<Alisha>(&SwapId::default()).unwrap();
+
+        assert_that(&res).is_none();
+    }
+
+    /// The identities the node picks for its own HTLCs, the keys of Bitcoin
+    /// HTLCs come from the seed
+    trait HtlcIdentities: Ledger {
+        fn refund_identity(secret_source: &dyn SecretSource, id: SwapId) -> Self::HtlcIdentity;
+        fn redeem_identity(secret_source: &dyn SecretSource, id: SwapId) -> Self::HtlcIdentity;
+        fn lock_duration() -> Self::LockDuration;
+    }
+
+    impl HtlcIdentities for Bitcoin {
+        fn refund_identity(secret_source: &dyn SecretSource, id: SwapId) -> Self::HtlcIdentity {
+            secret_source.new_secp256k1_refund(id)
+        }
+
+        fn redeem_identity(secret_source: &dyn SecretSource, id: SwapId) -> Self::HtlcIdentity {
+            secret_source.new_secp256k1_redeem(id)
+        }
+
+        fn lock_duration() -> Self::LockDuration {
+            Blocks::from(144).into()
+        }
+    }
+
+    impl HtlcIdentities for Ethereum {
+        fn refund_identity(_: &dyn SecretSource, _: SwapId) -> Self::HtlcIdentity {
+            "8457037fcd80a8650c4692d7fcfc1d0a96b92867".parse().unwrap()
+        }
+
+        fn redeem_identity(_: &dyn SecretSource, _: SwapId) -> Self::HtlcIdentity {
+            "c5549e335b2786520f4c5d706c76c9ee69d0a028".parse().unwrap()
+        }
+
+        fn lock_duration() -> Self::LockDuration {
+            Seconds(60 * 60 * 24)
+        }
+    }
+
+    fn bitcoin() -> BitcoinQuantity {
+        BitcoinQuantity::from_bitcoin(1.0)
+    }
+
+    fn ether() -> EtherQuantity {
+        EtherQuantity::from_eth(10.0)
+    }
+
+    fn erc20() -> Erc20Quantity {
+        Erc20Quantity::new(
+            "b97048628db6b661d4c2aa833e95dbe1a905b280".parse().unwrap(),
+            U256::from(1_000_000_000u64),
+        )
+    }
+
+    fn reopened<R: Role>(id: SwapId, state: SwapStates<R>) -> Option<SwapStates<R>> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("comit_node.sqlite");
+
+        SqliteStateStore::open(&path, secret_source())
+            .unwrap()
+            .insert(id, state)
+            .unwrap();
+
+        SqliteStateStore::open(&path, secret_source())
+            .unwrap()
+            .get(&id)
+            .unwrap()
+    }
+
+    fn alice_swap_survives_reopening<
+        AL: HtlcIdentities,
+        BL: HtlcIdentities,
+        AA: Asset,
+        BA: Asset,
+    >(
+        alpha_asset: AA,
+        beta_asset: BA,
+    ) {
+        let secret_source = secret_source();
+        let id = SwapId::default();
+        let request = SwapRequest {
+            alpha_asset,
+            beta_asset,
+            alpha_ledger: AL::default(),
+            beta_ledger: BL::default(),
+            alpha_ledger_lock_duration: AL::lock_duration(),
+            identities: SwapRequestIdentities {
+                alpha_ledger_refund_identity: AL::refund_identity(secret_source.as_ref(), id),
+                beta_ledger_redeem_identity: BL::redeem_identity(secret_source.as_ref(), id),
+            },
+        };
+        let state = SwapStates::from(request.into_start_state(id, secret_source.as_ref()));
+
+        assert_that(&reopened(id, state.clone())).contains_value(state);
+    }
+
+    fn bob_swap_survives_reopening<AL: HtlcIdentities, BL: HtlcIdentities, AA: Asset, BA: Asset>(
+        alpha_asset: AA,
+        beta_asset: BA,
+    ) {
+        let secret_source = secret_source();
+        let alice: Arc<dyn SecretSource> =
+            Arc::new(Seed::from(*b"bye world, you are beautiful!!!!"));
+        let id = SwapId::default();
+        let start_state = Start::<Bob<AL, BL, AA, BA>> {
+            alpha_ledger_refund_identity: AL::refund_identity(alice.as_ref(), id).into(),
+            beta_ledger_redeem_identity: BL::redeem_identity(alice.as_ref(), id).into(),
+            alpha_ledger: AL::default(),
+            beta_ledger: BL::default(),
+            alpha_asset,
+            beta_asset,
+            alpha_ledger_lock_duration: AL::lock_duration(),
+            secret: alice.new_secret(id).hash(),
+            role: Bob::create(None).0,
+        };
+        let state = SwapStates::from(Accepted {
+            swap: OngoingSwap::new(
+                start_state,
+                StateMachineResponse {
+                    alpha_ledger_redeem_identity: AL::redeem_identity(secret_source.as_ref(), id),
+                    beta_ledger_refund_identity: BL::refund_identity(secret_source.as_ref(), id),
+                    beta_ledger_lock_duration: BL::lock_duration(),
+                },
+            ),
+        });
+
+        assert_that(&reopened(id, state.clone())).contains_value(state);
+    }
+
+    macro_rules! swaps_survive_reopening {
+        ($($test:ident: $alpha_ledger:ty, $beta_ledger:ty, $alpha_asset:ident, $beta_asset:ident;)*) => {
+            $(
+                #[test]
+                fn $test() {
+                    alice_swap_survives_reopening::<$alpha_ledger, $beta_ledger, _, _>(
+                        $alpha_asset(),
+                        $beta_asset(),
+                    );
+                    bob_swap_survives_reopening::<$alpha_ledger, $beta_ledger, _, _>(
+                        $alpha_asset(),
+                        $beta_asset(),
+                    );
+                }
+            )*
+        };
+    }
+
+    swaps_survive_reopening! {
+        bitcoin_ether_swaps_survive_reopening: Bitcoin, Ethereum, bitcoin, ether;
+        bitcoin_erc20_swaps_survive_reopening: Bitcoin, Ethereum, bitcoin, erc20;
+        ether_bitcoin_swaps_survive_reopening: Ethereum, Bitcoin, ether, bitcoin;
+        erc20_bitcoin_swaps_survive_reopening: Ethereum, Bitcoin, erc20, bitcoin;
+        ether_erc20_swaps_survive_reopening: Ethereum, Ethereum, ether, erc20;
+        erc20_ether_swaps_survive_reopening: Ethereum, Ethereum, erc20, ether;
+        erc20_erc20_swaps_survive_reopening: Ethereum, Ethereum, erc20, erc20;
+    }
+}
@@ -0,0 +1,20 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds since the unix epoch, like the timestamps in block headers
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Timestamp(pub u32);
+
+impl Timestamp {
+    pub fn now() -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time should be after the unix epoch")
+            .as_secs();
+
+        Timestamp(now as u32)
+    }
+
+    pub fn plus(self, seconds: u32) -> Self {
+        Timestamp(self.0.saturating_add(seconds))
+    }
+}
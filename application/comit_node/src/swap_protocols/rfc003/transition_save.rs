@@ -2,7 +2,21 @@
 macro_rules! transition_save {
     ($repo:expr, $new_state:expr) => {{
         let save_state = $new_state;
-        $repo.save(save_state.clone().into());
+        $repo
+            .save(save_state.clone().into())
+            .map_err(|e| $crate::swap_protocols::rfc003::Error::Internal(e.to_string()))?;
+
+        debug!("Transitioning to {}", save_state);
+
+        return Ok(::futures::Async::Ready(save_state.into()));
+    }};
+    ($repo:expr, $new_state:expr, $transaction:expr) => {{
+        let transaction =
+            ::serde_json::to_value(&$transaction).unwrap_or(::serde_json::Value::Null);
+        let save_state = $new_state;
+        $repo
+            .save_caused_by(save_state.clone().into(), transaction)
+            .map_err(|e| $crate::swap_protocols::rfc003::Error::Internal(e.to_string()))?;
 
         debug!("Transitioning to {}", save_state);
 
@@ -0,0 +1,271 @@
+use crate::{
+    http_api::rfc003::stream::{saved_swap_state_updates, SwapStateUpdate},
+    settings::Webhook,
+    swap_protocols::{
+        rfc003::{state_store::StateStore, Timestamp},
+        MetadataStore, SwapId,
+    },
+};
+use crypto::{hmac::Hmac, mac::Mac, sha2::Sha256};
+use futures::{
+    future::{self, Either, Loop},
+    Future, Stream,
+};
+use reqwest::{header::CONTENT_TYPE, r#async::Client};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::timer::Delay;
+
+pub const SIGNATURE_HEADER: &str = "X-Comit-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Comit-Timestamp";
+const MAX_ATTEMPTS: u32 = 6;
+const INITIAL_BACKOFF_SECS: u64 = 1;
+const KEPT_DELIVERIES: usize = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Delivery {
+    pub id: u64,
+    pub url: String,
+    pub swap_id: SwapId,
+    pub state: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct DeliveryLog {
+    next_id: u64,
+    deliveries: VecDeque<Delivery>,
+}
+
+/// The most recent webhook notifications and how their delivery went
+#[derive(Debug, Default)]
+pub struct Deliveries {
+    log: Mutex<DeliveryLog>,
+}
+
+impl Deliveries {
+    pub fn all(&self) -> Vec<Delivery> {
+        let log = self.log.lock().unwrap();
+        log.deliveries.iter().cloned().collect()
+    }
+
+    fn start(&self, webhook: &Webhook, update: &SwapStateUpdate) -> u64 {
+        let mut log = self.log.lock().unwrap();
+
+        let id = log.next_id;
+        log.next_id += 1;
+
+        if log.deliveries.len() == KEPT_DELIVERIES {
+            let _ = log.deliveries.pop_front();
+        }
+        log.deliveries.push_back(Delivery {
+            id,
+            url: webhook.url.to_string(),
+            swap_id: update.id,
+            state: update.state.clone(),
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            last_error: None,
+        });
+
+        id
+    }
+
+    fn record_attempt(&self, id: u64, status: DeliveryStatus, error: Option<String>) {
+        let mut log = self.log.lock().unwrap();
+
+        if let Some(delivery) = log.deliveries.iter_mut().find(|delivery| delivery.id == id) {
+            delivery.attempts += 1;
+            delivery.status = status;
+            delivery.last_error = error;
+        }
+    }
+}
+
+/// Whether `update` is one of the events the webhook asked for
+pub fn is_wanted(webhook: &Webhook, update: &SwapStateUpdate) -> bool {
+    match webhook.events {
+        None => true,
+        Some(ref events) => events.iter().any(|event| {
+            *event == update.state
+                || (event == "Final" && update.is_final)
+                || update.actions.contains(event)
+        }),
+    }
+}
+
+/// The value of the `X-Comit-Signature` header: the hex encoded
+/// HMAC-SHA256 of the `X-Comit-Timestamp` header, a `.` and the body, keyed
+/// with the secret of the webhook. Receivers should reject old timestamps so
+/// that a recorded notification cannot be replayed.
+pub fn sign(secret: &str, timestamp: Timestamp, body: &str) -> String {
+    hmac_sha256(secret, &format!("{}.{}", timestamp.0, body))
+}
+
+fn hmac_sha256(secret: &str, data: &str) -> String {
+    let mut hmac = Hmac::new(Sha256::new(), secret.as_bytes());
+    hmac.input(data.as_bytes());
+
+    format!("sha256={}", hex::encode(hmac.result().code()))
+}
+
+/// POSTs every new state of a swap to the webhooks that want it
+pub fn notify<T: MetadataStore<SwapId>, S: StateStore<SwapId>>(
+    webhooks: Vec<Webhook>,
+    metadata_store: Arc<T>,
+    state_store: Arc<S>,
+    deliveries: Arc<Deliveries>,
+) -> impl Future<Item = (), Error = ()> {
+    let client = Client::new();
+    let webhooks: Vec<Arc<Webhook>> = webhooks.into_iter().map(Arc::new).collect();
+
+    saved_swap_state_updates(metadata_store, state_store.subscribe()).for_each(move |update| {
+        let body = serde_json::to_string(&update).expect("update should serialize");
+
+        for webhook in webhooks
+            .iter()
+            .filter(|webhook| is_wanted(webhook, &update))
+        {
+            let delivery_id = deliveries.start(webhook, &update);
+
+            tokio::spawn(deliver(
+                client.clone(),
+                Arc::clone(webhook),
+                body.clone(),
+                Arc::clone(&deliveries),
+                delivery_id,
+            ));
+        }
+
+        Ok(())
+    })
+}
+
+fn deliver(
+    client: Client,
+    webhook: Arc<Webhook>,
+    body: String,
+    deliveries: Arc<Deliveries>,
+    delivery_id: u64,
+) -> impl Future<Item = (), Error = ()> {
+    future::loop_fn(0, move |attempt| {
+        let deliveries = Arc::clone(&deliveries);
+        let url = webhook.url.clone();
+        // Retries are signed again so that they don't look like replays
+        let timestamp = Timestamp::now();
+        let signature = sign(&webhook.secret, timestamp, &body);
+
+        client
+            .post(url.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .header(TIMESTAMP_HEADER, timestamp.0.to_string().as_str())
+            .header(SIGNATURE_HEADER, signature.as_str())
+            .body(body.clone())
+            .send()
+            .then(move |response| {
+                let error = match response {
+                    Ok(ref response) if response.status().is_success() => None,
+                    Ok(response) => Some(format!("Unexpected status {}", response.status())),
+                    Err(e) => Some(format!("{:?}", e)),
+                };
+                let attempts = attempt + 1;
+
+                let error = match error {
+                    None => {
+                        deliveries.record_attempt(delivery_id, DeliveryStatus::Delivered, None);
+                        return Either::A(future::ok(Loop::Break(())));
+                    }
+                    Some(error) => error,
+                };
+
+                if attempts == MAX_ATTEMPTS {
+                    error!("Giving up on notifying {}: {}", url, error);
+                    deliveries.record_attempt(delivery_id, DeliveryStatus::Failed, Some(error));
+                    return Either::A(future::ok(Loop::Break(())));
+                }
+
+                warn!("Failed to notify {}, retrying: {}", url, error);
+                deliveries.record_attempt(delivery_id, DeliveryStatus::Pending, Some(error));
+
+                let backoff = Duration::from_secs(INITIAL_BACKOFF_SECS << attempt);
+                Either::B(
+                    Delay::new(Instant::now() + backoff)
+                        .then(move |_| Ok(Loop::Continue(attempts))),
+                )
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    fn webhook(events: Option<Vec<&str>>) -> Webhook {
+        Webhook {
+            url: "http://localhost:8080/comit".parse().unwrap(),
+            events: events.map(|events| events.into_iter().map(String::from).collect()),
+            secret: String::from("Jefe"),
+        }
+    }
+
+    fn update(state: &str, is_final: bool, actions: Vec<&str>) -> SwapStateUpdate {
+        SwapStateUpdate {
+            id: SwapId::default(),
+            state: String::from(state),
+            is_final,
+            actions: actions.into_iter().map(String::from).collect(),
+        }
+    }
+
+    #[test]
+    fn hmac_is_hmac_sha256() {
+        // Test case 2 of RFC 4231
+        let signature = hmac_sha256("Jefe", "what do ya want for nothing?");
+
+        assert_that(&signature).is_equal_to(String::from(
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+        ));
+    }
+
+    #[test]
+    fn signature_covers_the_timestamp_and_the_body() {
+        let signature = sign("Jefe", Timestamp(1_550_000_000), "{}");
+
+        assert_that(&signature).is_equal_to(hmac_sha256("Jefe", "1550000000.{}"));
+        assert_that(&signature).is_not_equal_to(sign("Jefe", Timestamp(1_550_000_001), "{}"));
+    }
+
+    #[test]
+    fn events_filter_matches_states_actions_and_final() {
+        let webhook = webhook(Some(vec!["BothFunded", "redeem", "Final"]));
+        let wants = |state: &str, is_final: bool, actions: Vec<&str>| {
+            is_wanted(&webhook, &update(state, is_final, actions))
+        };
+
+        assert_that(&wants("BothFunded", false, vec![])).is_true();
+        assert_that(&wants("AlphaFunded", false, vec!["redeem"])).is_true();
+        assert_that(&wants("BothRedeemed", true, vec![])).is_true();
+        assert_that(&wants("AlphaFunded", false, vec!["fund"])).is_false();
+    }
+
+    #[test]
+    fn webhook_without_events_wants_every_state() {
+        let webhook = webhook(None);
+
+        assert_that(&is_wanted(&webhook, &update("Accepted", false, vec![]))).is_true();
+    }
+}
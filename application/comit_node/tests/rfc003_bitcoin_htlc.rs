@@ -1,12 +1,16 @@
 use bitcoin_rpc_client::*;
 use bitcoin_rpc_test_helpers::RegtestHelperClient;
 use bitcoin_support::{
-    serialize::serialize_hex, Address, BitcoinQuantity, Network, OutPoint, PrivateKey, PubkeyHash,
+    serialize::serialize_hex, Address, BitcoinQuantity, Blocks, Network, OutPoint, PrivateKey,
+    PubkeyHash,
 };
 use bitcoin_witness::{
     PrimedInput, PrimedTransaction, UnlockParameters, Witness, SEQUENCE_ALLOW_NTIMELOCK_NO_RBF,
 };
-use comit_node::swap_protocols::rfc003::{bitcoin::Htlc, Secret, SecretHash};
+use comit_node::swap_protocols::rfc003::{
+    bitcoin::{AbsoluteTimelock, Htlc, Timelock},
+    Secret, SecretHash,
+};
 use crypto::{digest::Digest, sha2::Sha256};
 use hex::FromHexError;
 use secp256k1_support::KeyPair;
@@ -29,6 +33,7 @@ impl CustomSizeSecret {
             ],
             sequence: SEQUENCE_ALLOW_NTIMELOCK_NO_RBF,
             prev_script: htlc.script().clone(),
+            locktime: 0,
         }
     }
 
@@ -54,12 +59,12 @@ impl FromStr for CustomSizeSecret {
 fn fund_htlc(
     client: &BitcoinCoreClient,
     secret_hash: SecretHash,
+    timelock: Timelock,
 ) -> (
     TransactionId,
     rpc::TransactionOutput,
     BitcoinQuantity,
     Htlc,
-    u32,
     KeyPair,
     KeyPair,
 ) {
@@ -71,14 +76,13 @@ fn fund_htlc(
         PrivateKey::from_str("cNZUJxVXghSri4dUaNW8ES3KiFyDoWVffLYDz7KMcHmKhLdFyZPx").unwrap();
     let refund_keypair: KeyPair = refund_privkey.secret_key().clone().into();
     let refund_pubkey_hash: PubkeyHash = refund_keypair.public_key().clone().into();
-    let sequence_lock = 10;
     let amount = BitcoinQuantity::from_satoshi(100_000_001);
 
     let htlc = Htlc::new(
         redeem_pubkey_hash,
         refund_pubkey_hash,
         secret_hash,
-        sequence_lock,
+        timelock,
     );
 
     let htlc_address = htlc.compute_address(Network::Regtest);
@@ -97,7 +101,6 @@ fn fund_htlc(
         vout.clone(),
         amount,
         htlc,
-        sequence_lock,
         redeem_keypair,
         refund_keypair,
     )
@@ -113,7 +116,8 @@ fn redeem_htlc_with_secret() {
     client.generate(432).unwrap().unwrap();
 
     let secret = Secret::from(*b"hello world, you are beautiful!!");
-    let (txid, vout, input_amount, htlc, _, keypair, _) = fund_htlc(&client, secret.hash());
+    let (txid, vout, input_amount, htlc, keypair, _) =
+        fund_htlc(&client, secret.hash(), Blocks::new(10).into());
 
     assert!(
         htlc.can_be_unlocked_with(secret, keypair).is_ok(),
@@ -161,7 +165,9 @@ fn redeem_refund_htlc() {
     client.generate(432).unwrap().unwrap();
 
     let secret = Secret::from(*b"hello world, you are beautiful!!");
-    let (txid, vout, input_amount, htlc, nsequence, _, keypair) = fund_htlc(&client, secret.hash());
+    let nsequence = 10;
+    let (txid, vout, input_amount, htlc, _, keypair) =
+        fund_htlc(&client, secret.hash(), Blocks::new(nsequence).into());
 
     let alice_addr: Address = client.get_new_address().unwrap().unwrap().into();
     let fee = BitcoinQuantity::from_satoshi(1000);
@@ -208,6 +214,64 @@ fn redeem_refund_htlc() {
     );
 }
 
+#[test]
+fn refund_htlc_with_absolute_timelock() {
+    let _ = pretty_env_logger::try_init();
+    let docker = Cli::default();
+
+    let container = docker.run(BitcoinCore::default());
+    let client = tc_bitcoincore_client::new(&container);
+    client.generate(432).unwrap().unwrap();
+
+    // Funding the HTLC mines block 433
+    let expiry_height = 450;
+    let secret = Secret::from(*b"hello world, you are beautiful!!");
+    let (txid, vout, input_amount, htlc, _, keypair) = fund_htlc(
+        &client,
+        secret.hash(),
+        AbsoluteTimelock::BlockHeight(expiry_height).into(),
+    );
+
+    let alice_addr: Address = client.get_new_address().unwrap().unwrap().into();
+    let fee = BitcoinQuantity::from_satoshi(1000);
+
+    let refund_tx = PrimedTransaction {
+        inputs: vec![PrimedInput::new(
+            OutPoint { txid, vout: vout.n },
+            input_amount,
+            htlc.unlock_after_timeout(keypair),
+        )],
+        output_address: alice_addr.clone(),
+        locktime: 0,
+    }
+    .sign_with_fee(fee);
+
+    assert_eq!(refund_tx.lock_time, expiry_height);
+
+    let raw_refund_tx = rpc::SerializedRawTransaction(serialize_hex(&refund_tx).unwrap());
+
+    let rpc_refund_txid_error = client.send_raw_transaction(raw_refund_tx.clone()).unwrap();
+
+    // It should fail because the expiry height has not been reached yet
+    assert!(rpc_refund_txid_error.is_err());
+    let error = rpc_refund_txid_error.unwrap_err();
+    assert_eq!(error.code, -26);
+    assert!(error.message.contains("non-final"));
+
+    client.generate(expiry_height - 433).unwrap().unwrap();
+
+    let rpc_refund_txid = client.send_raw_transaction(raw_refund_tx).unwrap().unwrap();
+
+    client.generate(1).unwrap().unwrap();
+
+    assert!(
+        client
+            .find_utxo_at_tx_for_address(&rpc_refund_txid, &alice_addr)
+            .is_some(),
+        "utxo should exist after refunding htlc"
+    );
+}
+
 #[test]
 fn redeem_htlc_with_long_secret() -> Result<(), failure::Error> {
     let _ = pretty_env_logger::try_init();
@@ -220,7 +284,8 @@ fn redeem_htlc_with_long_secret() -> Result<(), failure::Error> {
     let secret = CustomSizeSecret::from_str("Grandmother, what big secret you have!")?;
     assert_eq!(secret.0.len(), 38);
 
-    let (txid, vout, input_amount, htlc, _, keypair, _) = fund_htlc(&client, secret.hash());
+    let (txid, vout, input_amount, htlc, keypair, _) =
+        fund_htlc(&client, secret.hash(), Blocks::new(10).into());
 
     let alice_addr: Address = client.get_new_address().unwrap().unwrap().into();
 
@@ -265,7 +330,8 @@ fn redeem_htlc_with_short_secret() -> Result<(), failure::Error> {
     let secret = CustomSizeSecret::from_str("teeny-weeny-bunny")?;
     assert_eq!(secret.0.len(), 17);
 
-    let (txid, vout, input_amount, htlc, _, keypair, _) = fund_htlc(&client, secret.hash());
+    let (txid, vout, input_amount, htlc, keypair, _) =
+        fund_htlc(&client, secret.hash(), Blocks::new(10).into());
 
     let alice_addr: Address = client.get_new_address().unwrap().unwrap().into();
 
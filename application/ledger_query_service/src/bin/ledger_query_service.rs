@@ -15,7 +15,7 @@ use ledger_query_service::{
     ethereum::{EthereumBlockQuery, EthereumTransactionQuery},
     settings::{self, Settings},
     BlockProcessor, DefaultBlockProcessor, InMemoryQueryRepository, InMemoryQueryResultRepository,
-    QueryResultRepository, RouteFactory,
+    LatestBlock, LatestBlockRepository, QueryResultRepository, RouteFactory,
 };
 use std::{env::var, sync::Arc};
 use tokio::runtime::Runtime;
@@ -52,6 +52,7 @@ fn create_bitcoin_routes(
     let block_query_repository = Arc::new(InMemoryQueryRepository::<BitcoinBlockQuery>::default());
     let transaction_query_result_repository = Arc::new(InMemoryQueryResultRepository::default());
     let block_query_result_repository = Arc::new(InMemoryQueryResultRepository::default());
+    let latest_block_repository = Arc::new(LatestBlockRepository::default());
 
     let bitcoin_rpc_client = bitcoin_rpc_client::BitcoinCoreClient::new(
         settings.node_url.as_str(),
@@ -65,16 +66,19 @@ fn create_bitcoin_routes(
         transaction_query_repository.clone(),
         block_query_repository.clone(),
         transaction_query_result_repository.clone(),
+        block_query_result_repository.clone(),
     );
 
     {
         let transaction_query_result_repository = transaction_query_result_repository.clone();
         let block_query_result_repository = block_query_result_repository.clone();
+        let latest_block_repository = latest_block_repository.clone();
 
         let bitcoin_blocks =
             ledger_query_service::bitcoin_block_listener(settings.zmq_endpoint.as_str())
                 .expect("Should return a Bitcoind received for MinedBlocks");
         let bitcoin_processor = bitcoin_blocks
+            .inspect(move |block| latest_block_repository.update(LatestBlock::from(block)))
             .and_then(move |block| transaction_processor.process(block))
             .for_each(move |(block_results, transaction_results)| {
                 for (id, block_id) in block_results {
@@ -106,7 +110,13 @@ fn create_bitcoin_routes(
         ledger_name,
     );
 
-    transaction_routes.or(block_routes).boxed()
+    let latest_block_route =
+        route_factory.create_latest_block_route(latest_block_repository, ledger_name);
+
+    transaction_routes
+        .or(block_routes)
+        .or(latest_block_route)
+        .boxed()
 }
 
 fn create_ethereum_routes(
@@ -126,6 +136,7 @@ fn create_ethereum_routes(
         transaction_query_repository.clone(),
         block_query_repository.clone(),
         transaction_query_result_repository.clone(),
+        block_query_result_repository.clone(),
     );
 
     let (event_loop, transport) =
@@ -1,6 +1,7 @@
 use crate::{query_repository::QueryRepository, query_result_repository::QueryResultRepository};
 use futures::{future::join_all, Future};
 use std::{
+    collections::HashMap,
     fmt::Debug,
     marker::PhantomData,
     sync::{Arc, Mutex},
@@ -59,9 +60,18 @@ impl QueryMatchResult {
 pub struct PendingTransaction {
     matching_query_id: u32,
     tx_id: String,
+    blockhash: String,
     pending_confirmations: u32,
 }
 
+/// The results that were reported because of a block, so that they can be
+/// invalidated when the block gets orphaned
+#[derive(Debug, Default)]
+struct BlockMatches {
+    blocks: Vec<QueryMatch>,
+    transactions: Vec<QueryMatch>,
+}
+
 type ArcQueryRepository<Q> = Arc<dyn QueryRepository<Q>>;
 type ArcQueryResultRepository<Q> = Arc<dyn QueryResultRepository<Q>>;
 
@@ -73,7 +83,10 @@ pub struct DefaultBlockProcessor<T, B, TQ, BQ> {
     block_queries: ArcQueryRepository<BQ>,
     #[debug_stub = "Results"]
     transaction_results: ArcQueryResultRepository<TQ>,
+    #[debug_stub = "Results"]
+    block_results: ArcQueryResultRepository<BQ>,
     pending_transactions: Arc<Mutex<Vec<PendingTransaction>>>,
+    matches: Arc<Mutex<HashMap<String, BlockMatches>>>,
     blockhashes: Vec<String>,
     tx_type: PhantomData<T>,
     block_type: PhantomData<B>,
@@ -87,21 +100,31 @@ impl<T: Transaction, B: Block<Transaction = T>, TQ: Query<T> + 'static, BQ: Quer
         block: B,
     ) -> Box<dyn Future<Item = (Vec<QueryMatch>, Vec<QueryMatch>), Error = ()> + Send> {
         trace!("New block received: {:?}", block);
-        // for now work on the assumption that there is one blockchain, but warn
-        // every time that assumption doesn't hold, by comparing the previous
-        // blockhash to the most recent member of a list of ordered blockhashes
-        if let Some(last_blockhash) = self.blockhashes.last() {
-            if *last_blockhash != block.prev_blockhash() {
-                warn!(
-                    "Block {} lists {} as previous block but last processed block was {}",
-                    block.blockhash(),
-                    block.prev_blockhash(),
-                    last_blockhash
-                );
+        // A block that doesn't build on the last processed block but on an
+        // earlier one orphans all blocks after its parent. If the parent is
+        // unknown we can only warn that the blocks no longer form a chain.
+        if let Some(last_blockhash) = self.blockhashes.last().cloned() {
+            let prev_blockhash = block.prev_blockhash();
+
+            if last_blockhash != prev_blockhash {
+                match self
+                    .blockhashes
+                    .iter()
+                    .rposition(|hash| *hash == prev_blockhash)
+                {
+                    Some(fork_point) => self.orphan_blocks_after(fork_point),
+                    None => warn!(
+                        "Block {} lists {} as previous block but last processed block was {}",
+                        block.blockhash(),
+                        prev_blockhash,
+                        last_blockhash
+                    ),
+                }
             }
         }
 
-        self.blockhashes.push(block.blockhash());
+        let blockhash = block.blockhash();
+        self.blockhashes.push(blockhash.clone());
         self.update_pending_transactions();
 
         let block_results = Self::process_new_block(Arc::clone(&self.block_queries), &block);
@@ -111,6 +134,7 @@ impl<T: Transaction, B: Block<Transaction = T>, TQ: Query<T> + 'static, BQ: Quer
             tx_result_vecs.push(Self::process_new_transaction(
                 Arc::clone(&self.transaction_queries),
                 Arc::clone(&self.pending_transactions),
+                &blockhash,
                 tx,
             ))
         }
@@ -118,7 +142,21 @@ impl<T: Transaction, B: Block<Transaction = T>, TQ: Query<T> + 'static, BQ: Quer
         let tx_results = join_all(tx_result_vecs)
             .map(|tx_result_vec| tx_result_vec.into_iter().flatten().collect());
 
-        Box::new(block_results.join(tx_results))
+        let matches = Arc::clone(&self.matches);
+
+        Box::new(
+            block_results
+                .join(tx_results)
+                .inspect(move |(block_results, tx_results)| {
+                    let mut matches = matches.lock().unwrap();
+                    let block_matches = matches.entry(blockhash).or_default();
+
+                    block_matches.blocks.extend(block_results.iter().cloned());
+                    block_matches
+                        .transactions
+                        .extend(tx_results.iter().cloned());
+                }),
+        )
     }
 }
 
@@ -151,6 +189,36 @@ impl<T: Transaction, B: Block<Transaction = T>, TQ: Query<T> + 'static, BQ: Quer
         join_all(query_match_futures).map(|results| results.into_iter().filter_map(|x| x).collect())
     }
 
+    fn orphan_blocks_after(&mut self, fork_point: usize) {
+        let orphaned_blockhashes = self.blockhashes.split_off(fork_point + 1);
+
+        self.pending_transactions
+            .lock()
+            .unwrap()
+            .retain(|utx| !orphaned_blockhashes.contains(&utx.blockhash));
+
+        let mut matches = self.matches.lock().unwrap();
+
+        for blockhash in orphaned_blockhashes.iter().rev() {
+            warn!("Block {} is no longer part of the blockchain", blockhash);
+
+            if let Some(block_matches) = matches.remove(blockhash) {
+                for (query_id, block_id) in block_matches.blocks {
+                    trace!("Invalidating block {} for Query-ID: {}", block_id, query_id);
+                    self.block_results.invalidate_result(query_id, block_id);
+                }
+                for (query_id, tx_id) in block_matches.transactions {
+                    trace!(
+                        "Invalidating transaction {} for Query-ID: {}",
+                        tx_id,
+                        query_id
+                    );
+                    self.transaction_results.invalidate_result(query_id, tx_id);
+                }
+            }
+        }
+    }
+
     fn update_pending_transactions(&mut self) {
         trace!("Updating pending matching transactions");
         let mut pending_transactions = self.pending_transactions.lock().unwrap();
@@ -166,7 +234,14 @@ impl<T: Transaction, B: Block<Transaction = T>, TQ: Query<T> + 'static, BQ: Quer
                     confirmed_tx_id
                 );
                 self.transaction_results
-                    .add_result(utx.matching_query_id, confirmed_tx_id.clone())
+                    .add_result(utx.matching_query_id, confirmed_tx_id.clone());
+                self.matches
+                    .lock()
+                    .unwrap()
+                    .entry(utx.blockhash.clone())
+                    .or_default()
+                    .transactions
+                    .push((utx.matching_query_id, confirmed_tx_id.clone()));
             }
         });
 
@@ -176,6 +251,7 @@ impl<T: Transaction, B: Block<Transaction = T>, TQ: Query<T> + 'static, BQ: Quer
     fn process_new_transaction(
         transaction_queries: ArcQueryRepository<TQ>,
         pending_transactions: Arc<Mutex<Vec<PendingTransaction>>>,
+        blockhash: &str,
         transaction: &T,
     ) -> impl Future<Item = Vec<QueryMatch>, Error = ()> + Send {
         trace!("Processing {:?}", transaction);
@@ -189,6 +265,7 @@ impl<T: Transaction, B: Block<Transaction = T>, TQ: Query<T> + 'static, BQ: Quer
             );
 
             let tx_id = transaction.transaction_id();
+            let blockhash = blockhash.to_string();
             let pending_transactions = Arc::clone(&pending_transactions);
 
             let result_future =
@@ -218,6 +295,7 @@ impl<T: Transaction, B: Block<Transaction = T>, TQ: Query<T> + 'static, BQ: Quer
                             let pending_tx = PendingTransaction {
                                 matching_query_id: query_id,
                                 tx_id,
+                                blockhash,
                                 pending_confirmations: confirmations_needed - 1,
                             };
                             let mut pending_transactions = pending_transactions.lock().unwrap();
@@ -239,12 +317,15 @@ impl<T, B, TQ, BQ> DefaultBlockProcessor<T, B, TQ, BQ> {
         transaction_query_repository: Arc<dyn QueryRepository<TQ>>,
         block_query_repository: Arc<dyn QueryRepository<BQ>>,
         transaction_query_result_repository: Arc<dyn QueryResultRepository<TQ>>,
+        block_query_result_repository: Arc<dyn QueryResultRepository<BQ>>,
     ) -> Self {
         Self {
             transaction_queries: transaction_query_repository,
             block_queries: block_query_repository,
             transaction_results: transaction_query_result_repository,
+            block_results: block_query_result_repository,
             pending_transactions: Arc::new(Mutex::new(Vec::new())),
+            matches: Arc::new(Mutex::new(HashMap::new())),
             blockhashes: Vec::new(),
             tx_type: PhantomData,
             block_type: PhantomData,
@@ -348,6 +429,9 @@ mod tests {
             GenericBlockQuery,
         >,
         first_block: GenericBlock,
+        transaction_query_result_repository:
+            Arc<InMemoryQueryResultRepository<GenericTransactionQuery>>,
+        block_query_result_repository: Arc<InMemoryQueryResultRepository<GenericBlockQuery>>,
     }
 
     impl Setup {
@@ -362,11 +446,13 @@ mod tests {
             let transaction_query_result_repository =
                 Arc::new(InMemoryQueryResultRepository::default());
             let block_query_repository = Arc::new(InMemoryQueryRepository::default());
+            let block_query_result_repository = Arc::new(InMemoryQueryResultRepository::default());
 
             let block_processor = DefaultBlockProcessor::new(
                 transaction_query_repository.clone(),
                 block_query_repository.clone(),
                 transaction_query_result_repository.clone(),
+                block_query_result_repository.clone(),
             );
 
             let first_transaction_query = GenericTransactionQuery {
@@ -395,6 +481,8 @@ mod tests {
             Self {
                 block_processor,
                 first_block,
+                transaction_query_result_repository,
+                block_query_result_repository,
             }
         }
     }
@@ -460,6 +548,48 @@ mod tests {
         assert_that(&transactions).named(&"found txs").has_length(0);
     }
 
+    #[test]
+    fn given_matching_transaction_when_its_block_is_orphaned_then_results_are_invalidated() {
+        let harness = Setup::new(1, 1, 1, 0, 0);
+        let mut block_processor = harness.block_processor;
+        let transaction_results = harness.transaction_query_result_repository;
+        let block_results = harness.block_query_result_repository;
+
+        let add_results = |(blocks, transactions): (Vec<QueryMatch>, Vec<QueryMatch>)| {
+            for (id, block_id) in blocks {
+                block_results.add_result(id, block_id);
+            }
+            for (id, tx_id) in transactions {
+                transaction_results.add_result(id, tx_id);
+            }
+        };
+
+        let parent_block = GenericBlock::default();
+        let orphaned_block = GenericBlock {
+            id: 1,
+            parent_id: 0,
+            transaction_list: vec![GenericTransaction { id: 1 }],
+            ..GenericBlock::default()
+        };
+        let competing_block = GenericBlock {
+            id: 2,
+            parent_id: 0,
+            ..GenericBlock::default()
+        };
+
+        add_results(process_results(block_processor.process(parent_block)));
+        add_results(process_results(block_processor.process(orphaned_block)));
+        add_results(process_results(block_processor.process(competing_block)));
+
+        let transaction_result = transaction_results.get(1).unwrap();
+        assert_that(&transaction_result.matches).is_empty();
+        assert_that(&transaction_result.invalidated).is_equal_to(vec![String::from("1")]);
+
+        let block_result = block_results.get(1).unwrap();
+        assert_that(&block_result.matches).is_equal_to(vec![String::from("0"), String::from("2")]);
+        assert_that(&block_result.invalidated).is_equal_to(vec![String::from("1")]);
+    }
+
     fn process_results(
         processing_future: Box<
             dyn Future<Item = (Vec<QueryMatch>, Vec<QueryMatch>), Error = ()> + Send,
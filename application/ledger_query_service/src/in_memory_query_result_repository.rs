@@ -21,11 +21,27 @@ impl<Q: Send + Sync + Clone + 'static> QueryResultRepository<Q>
 
         let mut query_result = storage.remove(&id).unwrap_or_default();
 
-        query_result.0.push(tx_id);
+        query_result
+            .invalidated
+            .retain(|invalidated| *invalidated != tx_id);
+        query_result.matches.push(tx_id);
 
         storage.insert(id, query_result);
     }
 
+    fn invalidate_result(&self, id: u32, tx_id: String) {
+        let mut storage = self.storage.write().unwrap();
+
+        if let Some(query_result) = storage.get_mut(&id) {
+            let matches_before = query_result.matches.len();
+            query_result.matches.retain(|tx| *tx != tx_id);
+
+            if query_result.matches.len() < matches_before {
+                query_result.invalidated.push(tx_id);
+            }
+        }
+    }
+
     fn delete(&self, id: u32) {
         let mut storage = self.storage.write().unwrap();
 
@@ -48,7 +64,7 @@ mod tests {
 
         assert_that(&repository.get(1))
             .is_some()
-            .map(|r| &r.0)
+            .map(|r| &r.matches)
             .contains(String::from("foobar"));
     }
 
@@ -61,9 +77,37 @@ mod tests {
 
         let result = repository.get(1);
 
-        let mut query_results = assert_that(&result).is_some().map(|r| &r.0);
+        let mut query_results = assert_that(&result).is_some().map(|r| &r.matches);
 
         query_results.contains(String::from("foobar"));
         query_results.contains(String::from("baz"));
     }
+
+    #[test]
+    fn given_invalidated_result_moves_it_out_of_matches() {
+        let repository = InMemoryQueryResultRepository::<()>::default();
+
+        repository.add_result(1, String::from("foobar"));
+        repository.add_result(1, String::from("baz"));
+        repository.invalidate_result(1, String::from("foobar"));
+
+        let result = repository.get(1).unwrap();
+
+        assert_that(&result.matches).is_equal_to(vec![String::from("baz")]);
+        assert_that(&result.invalidated).is_equal_to(vec![String::from("foobar")]);
+    }
+
+    #[test]
+    fn given_invalidated_result_is_matched_again_then_it_is_no_longer_invalidated() {
+        let repository = InMemoryQueryResultRepository::<()>::default();
+
+        repository.add_result(1, String::from("foobar"));
+        repository.invalidate_result(1, String::from("foobar"));
+        repository.add_result(1, String::from("foobar"));
+
+        let result = repository.get(1).unwrap();
+
+        assert_that(&result.matches).is_equal_to(vec![String::from("foobar")]);
+        assert_that(&result.invalidated).is_empty();
+    }
 }
@@ -0,0 +1,68 @@
+use bitcoin_support::MinedBlock;
+use std::sync::RwLock;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct LatestBlock {
+    pub height: u32,
+    pub timestamp_secs: u32,
+}
+
+impl<'a> From<&'a MinedBlock> for LatestBlock {
+    fn from(block: &'a MinedBlock) -> Self {
+        LatestBlock {
+            height: block.height,
+            timestamp_secs: block.as_ref().header.time,
+        }
+    }
+}
+
+/// Remembers the block that was processed last, which after a chain
+/// reorganisation may be lower than the one before
+#[derive(Debug, Default)]
+pub struct LatestBlockRepository {
+    latest: RwLock<Option<LatestBlock>>,
+}
+
+impl LatestBlockRepository {
+    pub fn get(&self) -> Option<LatestBlock> {
+        *self.latest.read().unwrap()
+    }
+
+    pub fn update(&self, block: LatestBlock) {
+        *self.latest.write().unwrap() = Some(block);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn given_no_block_was_processed_then_latest_block_is_unknown() {
+        let repository = LatestBlockRepository::default();
+
+        assert_that(&repository.get()).is_none();
+    }
+
+    #[test]
+    fn given_reorganised_chain_then_latest_block_can_be_lower() {
+        let repository = LatestBlockRepository::default();
+
+        repository.update(LatestBlock {
+            height: 101,
+            timestamp_secs: 1_550_000_600,
+        });
+        repository.update(LatestBlock {
+            height: 100,
+            timestamp_secs: 1_550_000_000,
+        });
+
+        assert_that(&repository.get())
+            .is_some()
+            .is_equal_to(LatestBlock {
+                height: 100,
+                timestamp_secs: 1_550_000_000,
+            });
+    }
+}
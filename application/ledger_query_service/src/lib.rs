@@ -12,6 +12,7 @@ mod block_processor;
 mod connectors;
 mod in_memory_query_repository;
 mod in_memory_query_result_repository;
+mod latest_block_repository;
 mod queries;
 mod query_repository;
 mod query_result_repository;
@@ -24,6 +25,7 @@ pub use crate::{
     connectors::{bitcoind_zmq_listener::*, ethereum_web3_block_poller::*},
     in_memory_query_repository::*,
     in_memory_query_result_repository::*,
+    latest_block_repository::*,
     queries::*,
     query_repository::*,
     query_result_repository::*,
@@ -41,7 +41,7 @@ impl ExpandResult for BitcoinTransactionQuery {
         client: Arc<BitcoinCoreClient>,
     ) -> Result<Vec<BitcoinTransaction>, Error> {
         let mut expanded_result: Vec<BitcoinTransaction> = Vec::new();
-        for tx_id in result.clone().0 {
+        for tx_id in result.clone().matches {
             let tx_id = TransactionId::from_hex(tx_id.as_str()).map_err(|_| Error::InvalidHex)?;
 
             let transaction = client
@@ -126,6 +126,7 @@ impl Block for BitcoinBlock {
 #[derive(Serialize, Deserialize, Clone, Default, Debug)]
 pub struct BitcoinBlockQuery {
     pub min_height: Option<u32>,
+    pub min_timestamp_secs: Option<u32>,
 }
 
 impl QueryType for BitcoinBlockQuery {
@@ -154,23 +155,29 @@ impl Query<BitcoinBlock> for BitcoinBlockQuery {
         &self,
         block: &BitcoinBlock,
     ) -> Box<dyn Future<Item = QueryMatchResult, Error = ()> + Send> {
-        Box::new(futures::future::ok(match self.min_height {
-            Some(height) => {
-                if height <= block.height {
-                    QueryMatchResult::yes()
-                } else {
-                    QueryMatchResult::no()
-                }
-            }
-            None => {
-                warn!("min_height not set, nothing to compare");
+        if self.is_empty() {
+            warn!("min_height and min_timestamp_secs not set, nothing to compare");
+            return Box::new(futures::future::ok(QueryMatchResult::no()));
+        }
+
+        let height_matches = self
+            .min_height
+            .map_or(true, |min_height| min_height <= block.height);
+        let timestamp_matches = self.min_timestamp_secs.map_or(true, |min_timestamp_secs| {
+            min_timestamp_secs <= block.as_ref().header.time
+        });
+
+        Box::new(futures::future::ok(
+            if height_matches && timestamp_matches {
+                QueryMatchResult::yes()
+            } else {
                 QueryMatchResult::no()
-            }
-        }))
+            },
+        ))
     }
 
     fn is_empty(&self) -> bool {
-        self.min_height.is_none()
+        self.min_height.is_none() && self.min_timestamp_secs.is_none()
     }
 }
 
@@ -223,6 +230,7 @@ mod tests {
 
         let query = BitcoinBlockQuery {
             min_height: Some(42),
+            min_timestamp_secs: None,
         };
 
         let result = exec_future(query.matches(&block));
@@ -250,6 +258,7 @@ mod tests {
 
         let query = BitcoinBlockQuery {
             min_height: Some(42),
+            min_timestamp_secs: None,
         };
 
         let result = exec_future(query.matches(&block));
@@ -277,12 +286,43 @@ mod tests {
 
         let query = BitcoinBlockQuery {
             min_height: Some(42),
+            min_timestamp_secs: None,
         };
 
         let result = exec_future(query.matches(&block));
         assert_that(&result).is_equal_to(QueryMatchResult::yes());
     }
 
+    #[test]
+    fn given_query_min_timestamp_then_only_later_blocks_match() {
+        let block_at = |time| {
+            MinedBlock::new(
+                Block {
+                    header: BlockHeader {
+                        version: 1,
+                        prev_blockhash: Sha256dHash::default(),
+                        merkle_root: Sha256dHash::default(),
+                        time,
+                        bits: 1,
+                        nonce: 0,
+                    },
+                    txdata: vec![],
+                },
+                45,
+            )
+        };
+
+        let query = BitcoinBlockQuery {
+            min_height: None,
+            min_timestamp_secs: Some(1_550_000_000),
+        };
+
+        let result = exec_future(query.matches(&block_at(1_549_999_999)));
+        assert_that(&result).is_equal_to(QueryMatchResult::no());
+        let result = exec_future(query.matches(&block_at(1_550_000_000)));
+        assert_that(&result).is_equal_to(QueryMatchResult::yes());
+    }
+
     #[test]
     fn given_transaction_with_to_then_to_address_query_matches() {
         let tx = parse_raw_tx(WITNESS_TX);
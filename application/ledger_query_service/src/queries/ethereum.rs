@@ -55,7 +55,7 @@ impl ExpandResult for EthereumTransactionQuery {
         client: Arc<Web3<Http>>,
     ) -> Result<Vec<Self::Item>, Error> {
         let futures: Vec<_> = result
-            .0
+            .matches
             .iter()
             .filter_map(|tx_id| match hex::decode(clean_0x(tx_id)) {
                 Ok(bytes) => Some(bytes),
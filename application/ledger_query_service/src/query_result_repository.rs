@@ -1,8 +1,14 @@
 #[derive(Debug, Serialize, Clone, Default)]
-pub struct QueryResult(pub Vec<String>);
+pub struct QueryResult {
+    pub matches: Vec<String>,
+    /// Matches that were reported before but are no longer part of the
+    /// blockchain because of a reorganisation
+    pub invalidated: Vec<String>,
+}
 
 pub trait QueryResultRepository<T>: Send + Sync + 'static {
     fn get(&self, id: u32) -> Option<QueryResult>;
     fn add_result(&self, id: u32, tx_id: String);
+    fn invalidate_result(&self, id: u32, tx_id: String);
     fn delete(&self, id: u32);
 }
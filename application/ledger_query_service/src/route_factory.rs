@@ -1,5 +1,6 @@
 use crate::{
     block_processor::Query,
+    latest_block_repository::LatestBlockRepository,
     query_repository::QueryRepository,
     query_result_repository::{QueryResult, QueryResultRepository},
     routes, web3,
@@ -110,4 +111,22 @@ impl RouteFactory {
             .recover(routes::customize_error)
             .boxed()
     }
+
+    pub fn create_latest_block_route(
+        &self,
+        latest_block_repository: Arc<LatestBlockRepository>,
+        ledger_name: &'static str,
+    ) -> BoxedFilter<(impl Reply,)> {
+        let latest_block_repository = warp::any().map(move || latest_block_repository.clone());
+
+        warp::get2()
+            .and(warp::path("blocks"))
+            .and(warp::path(ledger_name))
+            .and(warp::path("latest"))
+            .and(warp::path::end())
+            .and(latest_block_repository)
+            .and_then(routes::retrieve_latest_block)
+            .recover(routes::customize_error)
+            .boxed()
+    }
 }
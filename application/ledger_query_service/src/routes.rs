@@ -1,5 +1,6 @@
 use crate::{
     block_processor::Query,
+    latest_block_repository::LatestBlockRepository,
     query_repository::QueryRepository,
     query_result_repository::QueryResultRepository,
     route_factory::{ExpandResult, QueryParams, ShouldExpand},
@@ -18,6 +19,7 @@ pub enum Error {
     DataExpansion,
     MissingClient,
     QueryNotFound,
+    NoBlockProcessed,
 }
 
 #[derive(Debug)]
@@ -53,6 +55,9 @@ impl From<Error> for HttpApiProblem {
             QueryNotFound => HttpApiProblem::new("query-not-found")
                 .set_status(404)
                 .set_detail("The requested query does not exist"),
+            NoBlockProcessed => HttpApiProblem::new("latest-block-unknown")
+                .set_status(404)
+                .set_detail("No block has been processed yet"),
         }
     }
 }
@@ -129,7 +134,7 @@ pub fn retrieve_query<
     match query {
         Ok(query) => {
             let query_result = query_result_repository.get(id).unwrap_or_default();
-            let mut result = ResponsePayload::TransactionIds(query_result.0.clone());
+            let mut result = ResponsePayload::TransactionIds(query_result.matches.clone());
 
             if Q::should_expand(&query_params) {
                 match client {
@@ -156,6 +161,7 @@ pub fn retrieve_query<
             Ok(warp::reply::json(&RetrieveQueryResponse {
                 query,
                 matches: result,
+                invalidated_matches: query_result.invalidated,
             }))
         }
         Err(e) => Err(e),
@@ -182,6 +188,20 @@ pub fn delete_query<
     ))
 }
 
+#[allow(clippy::needless_pass_by_value)]
+pub fn retrieve_latest_block(
+    latest_block_repository: Arc<LatestBlockRepository>,
+) -> Result<impl Reply, Rejection> {
+    latest_block_repository
+        .get()
+        .map(|latest_block| warp::reply::json(&latest_block))
+        .ok_or_else(|| {
+            warp::reject::custom(HttpApiProblemStdError {
+                http_api_problem: Error::NoBlockProcessed.into(),
+            })
+        })
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(untagged)]
 enum ResponsePayload<T> {
@@ -199,4 +219,5 @@ impl<T> Default for ResponsePayload<T> {
 pub struct RetrieveQueryResponse<Q, T> {
     query: Q,
     matches: ResponsePayload<T>,
+    invalidated_matches: Vec<String>,
 }